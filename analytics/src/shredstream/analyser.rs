@@ -1,13 +1,14 @@
 use std::{collections::HashMap, fs::OpenOptions, io::Write, mem, str::FromStr, thread, time::{self, Instant, SystemTime, UNIX_EPOCH}};
+use bot::jito::tip_oracle::TipOracle;
+use bot::solana::fee_estimator::PriorityFeeEstimator;
+use bot::solana::idl::{IdlProgram, IdlValue};
 use bot::solana::transaction::{message::TransactionStatusMeta, DecodedInstruction, PFCreateInstruction};
 use chrono::{Utc, DateTime, NaiveDateTime};
 use lazy_static::lazy_static;
 use prost_types::Any;
-use sha2::{Digest, Sha256};
 use solana_sdk::{clock::Slot, instruction, pubkey::Pubkey, signature::Signature, transaction::VersionedTransaction};
 use std::sync::{Arc, Mutex};
 use num_cpus;
-use borsh::{BorshDeserialize};
 
 use gethostname::gethostname;
 
@@ -33,6 +34,9 @@ const SHREDSTREAM_TOOL_ID: i64 = 1;
 lazy_static! {
     static ref RUNNING_THREADS: Arc<Mutex<usize>> = Arc::new(Mutex::new(0));
     static ref DB: Mutex<Database> = Mutex::new(Database::new(DB_PATH).expect("Failed to initialize database"));
+    // Tip accounts are set at startup from the block engine
+    pub static ref TIP_ORACLE: TipOracle = TipOracle::default();
+    pub static ref FEE_ESTIMATOR: PriorityFeeEstimator = PriorityFeeEstimator::default();
 }
 
 #[derive(Clone)]
@@ -291,6 +295,11 @@ fn decode_shred_payload(
 }
 
 fn pumpfun_decompile(entries: &Vec<Entry>, slot: Slot ) {
+    for transaction in entries.iter().flat_map(|entry| entry.transactions.iter()) {
+        TIP_ORACLE.observe_transaction(slot, transaction);
+        FEE_ESTIMATOR.observe_transaction(slot, transaction);
+    }
+
     // Check if any transaction contains a Pumpfun instruction
     let entries_cloned = entries.clone();
     let contains_pumpfun = entries_cloned.iter().any(|entry| {
@@ -301,18 +310,19 @@ fn pumpfun_decompile(entries: &Vec<Entry>, slot: Slot ) {
 
     log_info!(LOG_FILE, "Contains Pumpfun: {}", contains_pumpfun);
 
-    let instructions = ["buy", "sell", "create", "setparams", "initialize", "withdraw"];
+    let pumpfun = IdlProgram::pumpfun();
 
     if contains_pumpfun {
         for entry in entries_cloned {
             for transaction in entry.transactions.iter() {
                 let signature = transaction.signatures[0];
+                let account_keys = transaction.message.static_account_keys();
                 for instruction in transaction.message.instructions() {
                     let program_id_index = instruction.program_id_index as usize;
-                    if program_id_index >= transaction.message.static_account_keys().len() {
+                    if program_id_index >= account_keys.len() {
                         continue;
                     }
-                    let program_id = transaction.message.static_account_keys()[program_id_index];
+                    let program_id = account_keys[program_id_index];
                     let start = SystemTime::now();
                     let since_the_epoch = start
                         .duration_since(UNIX_EPOCH)
@@ -320,67 +330,73 @@ fn pumpfun_decompile(entries: &Vec<Entry>, slot: Slot ) {
 
                     
                     if program_id.to_string() == PUMPFUN_PROGRAM_ID {
-                        // Try to find a matching discriminator in the data
-                        let idl_instruction = instructions.iter().find(|&idl_instr| {
-                            let discriminator = get_discriminator(idl_instr, None);
-                            instruction.data.starts_with(&discriminator)
-                        });
-                        
-                        log_info!(LOG_FILE, "Pumpfun idle instruction {:?}", idl_instruction.unwrap());
+                        // Accounts loaded from lookup tables are not in the entry, so name only the static ones
+                        let accounts: Vec<Pubkey> = instruction.accounts.iter()
+                            .map_while(|&index| account_keys.get(index as usize).copied())
+                            .collect();
+                        let idl_instruction = match pumpfun.decode_instruction(&instruction.data, &accounts) {
+                            Ok(decoded) => decoded,
+                            Err(error) => {
+                                log_info!(LOG_FILE, "Failed to decode Pumpfun instruction: {:?}", error);
+                                continue;
+                            }
+                        };
+
+                        log_info!(LOG_FILE, "Pumpfun idle instruction {:?}", idl_instruction.as_ref().map(|decoded| decoded.name.as_str()));
                         log_info!(LOG_FILE, "Pumpfun instruction found");
                         log_info!(LOG_FILE, "Transaction signature: {:?}", signature);
                         log_info!(LOG_FILE, "{}", program_id.to_string());
       
                         
                         match idl_instruction {
-                            Some(&"buy") => {
+                            Some(decoded) if decoded.name == "buy" => {
                                 log_info!(LOG_FILE, "Pumpfun Buy instruction");
-                                let (amount, max_sol_cost): (u64, u64) = bincode::deserialize(&instruction.data[8..]).expect("Failed to Deserialize");
+                                let amount = decoded.arg("amount").and_then(IdlValue::as_u64);
+                                let max_sol_cost = decoded.arg("maxSolCost").and_then(IdlValue::as_u64);
                                 log_info!(LOG_FILE, "Amount: {:?} - Max sol count: {:?}", amount, max_sol_cost);
                             },
-                            Some(&"create") => {
-                                match <(String, String, String)>::try_from_slice(&instruction.data[8..]) {
-                                    Ok(data) => {
-                                        let now = Utc::now();
-                                        let mut file = OpenOptions::new()
-                                            .append(true)
-                                            .create(true)
-                                            .open("pumpfun_token_creation.txt");
-                                        writeln!(file.unwrap(), "{}, {}, {}, {}, {}, {}, {}",
-                                            gethostname().into_string().unwrap(),
-                                            since_the_epoch.as_millis().to_string(),
-                                            now.format("%Y-%m-%d %H:%M:%S%.3f"),
-                                            data.0,
-                                            slot,
-                                            signature,
-                                            transaction.message.static_account_keys()[instruction.accounts[0] as usize],
-                                        );
-                                        log_info!(LOG_FILE, "Pumpfun Create instruction: {}, {}, {}, {}, {}",
-                                            now.format("%Y-%m-%d %H:%M:%S%.3f"),
-                                            data.0,
-                                            data.1,
-                                            data.2,
-                                            transaction.message.static_account_keys()[instruction.accounts[0] as usize]
-                                        );
-
-                                        let mint = transaction.message.static_account_keys()[1].to_string();
-                                        let mint_str: &str = &mint;
-                                        
-                                        // Use async database insertion
-                                        if let Ok(db) = DB.lock() {
-                                            db.insert_token_creation_async(
-                                                data.0.clone(),  // name
-                                                mint.clone(),    // mint
-                                                signature.to_string(), // transaction_signature
-                                                SHREDSTREAM_TOOL_ID, // detect_tool_id for shredstream
-                                            );
-                                            log_info!(LOG_FILE, "Queued token creation for async database insertion");
-                                        }
-
-                                        // tokio::runtime::Runtime::new().unwrap().block_on(bot::solana::transaction::buy(0.0001, 0.000011, mint_str, 6, true, Some(transaction.message.recent_blockhash())));
-                                    }
-                                    Err(_) => {}
+                            Some(decoded) if decoded.name == "create" => {
+                                let name = decoded.arg("name").and_then(IdlValue::as_str).unwrap_or_default();
+                                let symbol = decoded.arg("symbol").and_then(IdlValue::as_str).unwrap_or_default();
+                                let uri = decoded.arg("uri").and_then(IdlValue::as_str).unwrap_or_default();
+                                let Some(mint) = decoded.account("mint") else {
+                                    continue;
+                                };
+
+                                let now = Utc::now();
+                                let mut file = OpenOptions::new()
+                                    .append(true)
+                                    .create(true)
+                                    .open("pumpfun_token_creation.txt");
+                                writeln!(file.unwrap(), "{}, {}, {}, {}, {}, {}, {}",
+                                    gethostname().into_string().unwrap(),
+                                    since_the_epoch.as_millis().to_string(),
+                                    now.format("%Y-%m-%d %H:%M:%S%.3f"),
+                                    name,
+                                    slot,
+                                    signature,
+                                    mint,
+                                );
+                                log_info!(LOG_FILE, "Pumpfun Create instruction: {}, {}, {}, {}, {}",
+                                    now.format("%Y-%m-%d %H:%M:%S%.3f"),
+                                    name,
+                                    symbol,
+                                    uri,
+                                    mint
+                                );
+
+                                // Use async database insertion
+                                if let Ok(db) = DB.lock() {
+                                    db.insert_token_creation_async(
+                                        name.to_string(),  // name
+                                        mint.to_string(),    // mint
+                                        signature.to_string(), // transaction_signature
+                                        SHREDSTREAM_TOOL_ID, // detect_tool_id for shredstream
+                                    );
+                                    log_info!(LOG_FILE, "Queued token creation for async database insertion");
                                 }
+
+                                // tokio::runtime::Runtime::new().unwrap().block_on(bot::solana::transaction::buy(0.0001, 0.000011, &mint.to_string(), 6, true, Some(transaction.message.recent_blockhash())));
                             }
                             _ => {
                                 log_info!(LOG_FILE, "Pumpfun instruction not found");
@@ -393,17 +409,6 @@ fn pumpfun_decompile(entries: &Vec<Entry>, slot: Slot ) {
     }
 }
 
-fn get_discriminator(instruction_name: &str, param:Option<&str>) -> Vec<u8> {
-    let mut hasher = Sha256::new();
-    let input = match param {
-        Some(p) => format!("global:{p}:{instruction_name}"),
-        None => format!("global:{instruction_name}"),
-    };
-    hasher.update(input);
-    let hash = hasher.finalize();
-    hash[..8].to_vec() // First 8 bytes of the SHA-256 hash as the discriminator
-}
-
 fn calculate_hashmap_size(hashmap: &HashMap<(u64, u32), ShredEntry>) -> usize {
     let mut total_size = mem::size_of::<HashMap<(u64, u32), ShredEntry>>(); // HashMap overhead
 
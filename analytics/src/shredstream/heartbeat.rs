@@ -12,6 +12,7 @@ use crossbeam_channel::Receiver;
 use jito_protos::{
     auth::{auth_service_client::AuthServiceClient, Role},
     shredstream::{shredstream_client::ShredstreamClient, Heartbeat},
+    token_authenticator::{create_grpc_channel, ClientInterceptor},
 };
 use log::{info, warn};
 use solana_metrics::{datapoint_info, datapoint_warn};
@@ -19,8 +20,6 @@ use solana_sdk::signature::Keypair;
 use tokio::runtime::Runtime;
 use tonic::{codegen::InterceptedService, transport::Channel, Code};
 
-use crate::shredstream::token_authenticator::{create_grpc_channel, ClientInterceptor};
-
 use super::main::ShredstreamProxyError;
 
 #[allow(clippy::too_many_arguments)]
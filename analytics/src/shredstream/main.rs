@@ -15,20 +15,22 @@ use std::{
 };
 
 use arc_swap::ArcSwap;
+use bot::jito::bundle_client::BundleClient;
 use crossbeam_channel::{Receiver, RecvError, Sender};
+use jito_protos::token_authenticator::BlockEngineConnectionError;
 use log::*;
 use signal_hook::consts::{SIGINT, SIGTERM};
 use solana_client::client_error::{reqwest, ClientError};
 use solana_metrics::set_host_id;
 use solana_perf::deduper::Deduper;
-use solana_sdk::signature::read_keypair_file;
+use solana_sdk::{pubkey::Pubkey, signature::read_keypair_file};
 use thiserror::Error;
 use tonic::Status;
 use tokio::runtime::Runtime;
 
 use crate::shredstream::{
+    analyser,
     forwarder::{ShredMetrics, self},
-    token_authenticator::BlockEngineConnectionError,
     logger::{self, LogMode},
     heartbeat,
 };
@@ -152,6 +154,13 @@ pub fn run_shredstream(config: ShredstreamConfig) -> Result<(), ShredstreamProxy
     set_host_id(hostname::get()?.into_string().unwrap());
 
     let exit = Arc::new(AtomicBool::new(false));
+
+    // Tip transfers are only recognised once the oracle knows the block engine's tip accounts
+    match runtime.block_on(fetch_tip_accounts(&config, exit.clone())) {
+        Ok(tip_accounts) => analyser::TIP_ORACLE.set_tip_accounts(tip_accounts),
+        Err(e) => warn!("Failed to fetch Jito tip accounts, tips won't be tracked: {e}"),
+    }
+
     let (shutdown_sender, shutdown_receiver) =
         shutdown_notifier(exit.clone()).expect("Failed to set up signal handler");
     let panic_hook = panic::take_hook();
@@ -239,6 +248,20 @@ pub fn run_shredstream(config: ShredstreamConfig) -> Result<(), ShredstreamProxy
     Ok(())
 }
 
+// Asks the block engine for its tip accounts once, as a searcher authenticated with the auth keypair
+async fn fetch_tip_accounts(
+    config: &ShredstreamConfig,
+    exit: Arc<AtomicBool>,
+) -> Result<Vec<Pubkey>, Box<dyn std::error::Error>> {
+    let auth_keypair = Arc::new(read_keypair_file(Path::new(&config.auth_keypair))?);
+    let auth_url = config.auth_url.clone().unwrap_or(config.block_engine_url.clone());
+    let (bundle_client, refresh_handle) =
+        BundleClient::connect(config.block_engine_url.clone(), auth_url, auth_keypair, exit).await?;
+    let tip_accounts = bundle_client.tip_accounts().await;
+    refresh_handle.abort();
+    Ok(tip_accounts?)
+}
+
 fn start_heartbeat(
     config: &ShredstreamConfig,
     exit: &Arc<AtomicBool>,
@@ -1,6 +1,5 @@
 pub mod main;
 pub mod analyser;
 pub mod logger;
-pub mod token_authenticator;
 pub mod heartbeat;
 pub mod forwarder;
\ No newline at end of file
@@ -1,20 +1,39 @@
 //use bot::solana::transaction::{self, BuyInstruction};
-use log::warn;
+use log::{info, warn};
 use rdkafka::config::ClientConfig;
 use rdkafka::consumer::{Consumer, StreamConsumer};
 use rdkafka::message::{Headers, Message};
 use solana_sdk::account::create_is_signer_account_infos;
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 mod program_pumpfun;
 use base58::ToBase58;
+use bot::financial_services::curve_cache::CurveCache;
+use bot::solana::account_mirror::{AccountFilter, AccountMirror};
+use bot::jito::bundle_client::BundleClient;
+use bot::jito::bundle_results::BundleResultTracker;
+use bot::jito::leader_schedule::{block_engine_url, LeaderScheduler};
+use bot::jito::tip_oracle::{TipOracle, TipPolicy};
 use bot::solana::position_tracker::PositionTracker;
-use bot::solana::address_table_cache::AddressTableCache;
+use bot::solana::fork_tracker::ForkAwarePositions;
+use bot::solana::address_table_cache::{AccountFetcher, AddressTableCache, RpcAccountFetcher};
+use bot::solana::blockhash::{BlockhashCache, RpcBlockhashSource};
+use bot::solana::broadcaster::{BroadcastConfig, Broadcaster};
+use bot::solana::decoder::ProgramDecoderRegistry;
+use bot::solana::executor::{LiveExecutor, LiveExecutorConfig};
+use bot::solana::fee_estimator::PriorityFeeEstimator;
+use bot::solana::order::{Commitment, OrderBook, ResubmitPolicy, RpcSignatureStatusSource};
+use bot::solana::signer::signer_from_env;
 use bot::solana::transaction;
+use bot::solana::transaction::message::{SlotStatusEvent, UpdateAccountEvent};
+use prost::Message as ProstMessage;
 use colored::*;
 use dotenv::dotenv;
 use solana_sdk::pubkey::Pubkey;
-use solana_sdk::signature::Signer;
-use solana_client::rpc_client::RpcClient;
+use solana_sdk::signature::{read_keypair_file, Signature, Signer};
+use solana_client::nonblocking::rpc_client::RpcClient;
 use std::env;
 use std::process;
 use chrono::Utc; 
@@ -29,10 +48,125 @@ async fn main() {
     dotenv().ok();
 
     //Setup Cache System
-    let rpc_client = RpcClient::new("https://api.mainnet-beta.solana.com");
+    let rpc_client = RpcClient::new("https://api.mainnet-beta.solana.com".to_string());
+
+    // Initialize AddressTableCache, optionally prewarmed with known lookup tables
+    let fetcher: Arc<dyn AccountFetcher> = Arc::new(RpcAccountFetcher::new(rpc_client));
+    let address_table_cache = Arc::new(AddressTableCache::new(Arc::clone(&fetcher)));
+    if let Ok(path) = env::var("ADDRESS_TABLES_FILE") {
+        if let Err(e) = address_table_cache.prewarm_from_file(&path).await {
+            println!("Failed to prewarm address tables from {}: {}", path, e);
+        }
+    }
 
-    // Initialize AddressTableCache with a reference to the RpcClient
-    let mut address_table_cache = AddressTableCache::new(&rpc_client);
+    // Decoders for every program we understand, keyed by program id.
+    // Additional Anchor programs can be decoded from their IDL files.
+    let mut decoder_registry = ProgramDecoderRegistry::default();
+    if let Ok(idl_files) = env::var("ANCHOR_IDL_FILES") {
+        for idl_file in idl_files.split(',').map(str::trim).filter(|path| !path.is_empty()) {
+            if let Err(e) = decoder_registry.register_idl(idl_file) {
+                println!("Failed to load IDL {}: {}", idl_file, e);
+            }
+        }
+    }
+
+    // Unlocked or connected once; every trade signs through it
+    let signer = signer_from_env().expect("Failed to load the trade signer");
+    // Trades go out to every BROADCAST_ENDPOINTS endpoint at once
+    let broadcaster = Arc::new(
+        Broadcaster::new(BroadcastConfig::from_env().expect("Failed to read the broadcast config"))
+            .expect("Failed to create the broadcaster"),
+    );
+
+    // Fresh blockhashes for trades, polled in the background
+    let blockhash_cache = Arc::new(BlockhashCache::new());
+    blockhash_cache.spawn_polling(
+        Arc::new(RpcBlockhashSource::new(RpcClient::new("https://api.mainnet-beta.solana.com".to_string()))),
+        Duration::from_millis(400),
+    );
+    // Orders placed by this process, confirmed by polling their signature statuses
+    let order_book = Arc::new(OrderBook::new(ResubmitPolicy::from_env().expect("Failed to read the resubmit policy")));
+    order_book.spawn_status_polling(
+        Arc::new(RpcSignatureStatusSource::new(RpcClient::new("https://api.mainnet-beta.solana.com".to_string()))),
+        Duration::from_secs(1),
+    );
+    // Priority fees other Pump.fun and Raydium traders pay, fed from every decoded transaction
+    let fee_estimator = Arc::new(PriorityFeeEstimator::default());
+    // Pump.fun curve reserves per mint, kept current from trade and create events
+    let curve_cache = Arc::new(CurveCache::default());
+    // Watched accounts from the account-update topic; Pump.fun bonding curve writes go to the curve cache
+    let account_mirror = AccountMirror::new();
+    let mut bonding_curve_updates = account_mirror.subscribe(
+        AccountFilter::Owner(transaction::PUMPFUN_PROGRAM_ID.parse().unwrap()),
+        Commitment::Processed,
+        |version| Some(version.data.clone()),
+    );
+    let account_topic = env::var("KAFKA_ACCOUNT_TOPIC").unwrap_or_else(|_| "solana.mainnet.accounts".to_string());
+    let slot_topic = env::var("KAFKA_SLOT_TOPIC").unwrap_or_else(|_| "solana.mainnet.slots".to_string());
+    // Tips that landed, fed from every decoded transaction once the tip accounts are known
+    let tip_oracle = Arc::new(TipOracle::default());
+    // A percentile of those tips per JITO_TIP_PERCENTILE, or a fixed JITO_TIP_AMOUNT
+    let tip_policy = TipPolicy::from_env().expect("Failed to read the tip policy");
+    // Bundles go to BLOCK_ENGINE_URL when set, authenticated with the JITO_AUTH_KEYPAIR file;
+    // the block engine's tip accounts come from the same connection
+    let bundle_exit = Arc::new(AtomicBool::new(false));
+    let bundle_client = match env::var("BLOCK_ENGINE_URL") {
+        Ok(block_engine_url) => {
+            let auth_url = env::var("BLOCK_ENGINE_AUTH_URL").unwrap_or_else(|_| block_engine_url.clone());
+            let auth_keypair = Arc::new(
+                read_keypair_file(env::var("JITO_AUTH_KEYPAIR").expect("JITO_AUTH_KEYPAIR not set in environment"))
+                    .expect("Failed to read JITO_AUTH_KEYPAIR"),
+            );
+            let (client, _refresh_handle) = BundleClient::connect(block_engine_url, auth_url, Arc::clone(&auth_keypair), bundle_exit.clone())
+                .await
+                .expect("Failed to connect to the block engine");
+            Some((Arc::new(client), auth_keypair))
+        }
+        Err(_) => None,
+    };
+    // Every trade becomes an order in the order book; expired ones are rebuilt per RESUBMIT_* settings
+    // Compute budgets follow the fee estimator, with COMPUTE_PRICE and COMPUTE_LIMIT until it has data
+    let mut executor = LiveExecutor::new(
+        signer,
+        LiveExecutorConfig::from_env(tip_policy).expect("Failed to read the executor config"),
+        Arc::clone(&curve_cache),
+        fetcher,
+        Arc::clone(&blockhash_cache),
+        Arc::clone(&tip_oracle),
+        Arc::clone(&order_book),
+        Arc::clone(&broadcaster),
+    )
+    .with_fee_estimator(Arc::clone(&fee_estimator));
+    if let Some((bundle_client, auth_keypair)) = &bundle_client {
+        match bundle_client.tip_accounts().await {
+            Ok(tip_accounts) => tip_oracle.set_tip_accounts(tip_accounts),
+            Err(e) => println!("Failed to fetch the Jito tip accounts: {}", e),
+        }
+        // Bundle results from SubscribeBundleResults move each bundled order along
+        let bundle_results = Arc::new(BundleResultTracker::new());
+        bundle_results.spawn(bundle_client.searcher_client());
+        // Bundles wait for a Jito leader in one of DESIRED_REGIONS and go to the block engine of its region
+        let regions: Vec<String> = env::var("DESIRED_REGIONS").unwrap_or_else(|_| "ny".to_string())
+            .split(',')
+            .map(|region| region.trim().to_string())
+            .filter(|region| !region.is_empty())
+            .collect();
+        let leader_scheduler = Arc::new(LeaderScheduler::new(bundle_client.searcher_client(), regions.clone()));
+        leader_scheduler.spawn(Duration::from_millis(400));
+        executor = executor
+            .with_bundle_client(Arc::clone(bundle_client))
+            .with_bundle_results(bundle_results)
+            .with_leader_scheduler(leader_scheduler);
+        for region in regions {
+            let url = block_engine_url(&region);
+            match BundleClient::connect(url.clone(), url.clone(), Arc::clone(auth_keypair), bundle_exit.clone()).await {
+                Ok((client, _refresh_handle)) => executor = executor.with_regional_bundle_client(url, Arc::new(client)),
+                Err(e) => println!("Failed to connect to the {} block engine: {}", region, e),
+            }
+        }
+    }
+    let executor = Arc::new(executor);
+    executor.spawn_resubmission(Duration::from_secs(1));
 
     println!("Starting.............");
     // Step 1: Get the environment variable
@@ -57,22 +191,21 @@ async fn main() {
         .expect("Consumer creation failed");
     println!("Subscribing.............");
     consumer
-        .subscribe(&["solana.mainnet.transactions"])
+        .subscribe(&["solana.mainnet.transactions", &account_topic, &slot_topic])
         .expect("Failed to subscribe to topic");
 
     let mut message_count = 0;
     let mut all_count = 0;
     let mut total_message_count = 0;
     let mut start_time = Instant::now();
+    let mut decode_error_counts: HashMap<&'static str, u64> = HashMap::new();
 
-    // Base58-encoded private key (replace with actual private key)
-    // let private_key_base58 = env::var("PRIVATE_KEY").expect("PRIVATE_KEY not set in environment");
-    // let keypair = bot::solana::transaction::keypair_from_base58(&private_key_base58);
-    // let user_pubkey = keypair.pubkey();
-
-    // pubkeys.push(user_pubkey);
-
-    let mut position_tracker = PositionTracker::new_with_accounts(pubkeys.clone());
+    // Positions follow forks through the slot-status topic and are applied at POSITION_COMMITMENT
+    let position_commitment: Commitment = env::var("POSITION_COMMITMENT")
+        .unwrap_or_else(|_| "processed".to_string())
+        .parse()
+        .expect("Failed to parse POSITION_COMMITMENT");
+    let mut positions = ForkAwarePositions::new(PositionTracker::new_with_accounts(pubkeys.clone()), position_commitment);
 
     /*bot::solana::transaction::raydium_swap_base_out("So11111111111111111111111111111111111111112","D1RCcauTVCt4o31KrFXiTUTRRwATLUWC3Z56pFYsVACb", 1.0, 3.0, 9, 6).await;
     bot::solana::transaction::raydium_swap_base_in("D1RCcauTVCt4o31KrFXiTUTRRwATLUWC3Z56pFYsVACb","So11111111111111111111111111111111111111112", 3.0, 0.0, 9, 6).await;
@@ -151,18 +284,69 @@ async fn main() {
                     None => String::new(),
                 };
 
+                if m.topic() == account_topic || m.topic() == slot_topic {
+                    let applied = match m.payload() {
+                        Some(payload) if m.topic() == account_topic => UpdateAccountEvent::decode(payload)
+                            .map_err(|e| e.to_string())
+                            .and_then(|event| account_mirror.apply_update(event).map_err(|e| e.to_string()).map(|_| ())),
+                        Some(payload) => SlotStatusEvent::decode(payload)
+                            .map_err(|e| e.to_string())
+                            .and_then(|event| {
+                                // Both views follow the same slot, so one failing must not starve the other
+                                let positions_applied = positions.observe_slot_status(&event);
+                                let mirror_applied = account_mirror.apply_slot_status(&event);
+                                if let Err(e) = mirror_applied {
+                                    println!("Failed to apply slot {} to the account mirror: {}", event.slot, e);
+                                }
+                                positions_applied.map_err(|e| e.to_string())
+                            }),
+                        None => Ok(()),
+                    };
+                    if let Err(e) = applied {
+                        println!("Failed to apply {} message: {}", m.topic(), e);
+                    }
+                    while let Ok(update) = bonding_curve_updates.try_recv() {
+                        if let Err(e) = curve_cache.apply_account(&update.pubkey, update.slot, &update.value) {
+                            println!("Failed to decode bonding curve {}: {}", update.pubkey, e);
+                        }
+                    }
+                    continue;
+                }
+
                 if let Some(payload) = m.payload() {
-                    let transaction = bot::solana::transaction::decode(payload, &mut address_table_cache);
+                    let transaction = bot::solana::transaction::decode(payload, &address_table_cache, &decoder_registry).await;
                     
                     match transaction {
                         Ok(ref decoded_tx) => {
-                            position_tracker.update_by_transaction(decoded_tx);
+                            for &kind in &decoded_tx.instruction_errors {
+                                *decode_error_counts.entry(kind).or_insert(0) += 1;
+                            }
+                            fee_estimator.observe_decoded(decoded_tx);
+                            curve_cache.observe_decoded(decoded_tx);
+                            tip_oracle.observe_decoded(decoded_tx);
                         }
                         Err(ref e) => {
+                            *decode_error_counts.entry(e.kind()).or_insert(0) += 1;
                             println!("Failed to decode transaction: {}", e);
                         }
                     }
-                    if let Ok(tx) = transaction {
+                    // Our own orders' transactions are held by the order book until confirmed.
+                    // Those are the ones we broadcast, so the endpoint that delivered them is credited.
+                    let transaction = transaction.map(|decoded_tx| {
+                        let signature = decoded_tx.signatures.first().and_then(|signature| Signature::try_from(signature.as_slice()).ok());
+                        let unmatched = order_book.observe_transaction(decoded_tx);
+                        if let (None, Some(signature)) = (&unmatched, signature) {
+                            broadcaster.record_landed(&signature);
+                        }
+                        unmatched
+                    });
+                    for fill in order_book.drain_confirmed_fills() {
+                        positions.observe_transaction(fill);
+                    }
+                    if let Ok(Some(ref decoded_tx)) = transaction {
+                        positions.observe_transaction(decoded_tx.clone());
+                    }
+                    if let Ok(Some(tx)) = transaction {
 
 //                         println!("TX tx: {}", tx.signatures[0].to_base58().to_string());
 //                         if tx.signatures[0].to_base58().to_string() == "62bU7jkQ9amhoLEtuHRsQfHxfTJYgqPxzw9haTuNa1BUSddJvWGyCgkbATNRLd4Ljrd3W8FuDzBPAxjXrCbZuqoy" {
@@ -237,6 +421,7 @@ async fn main() {
 
                                              println!("{:?} - {} {}", Utc::now().timestamp_millis(), Utc::now().to_rfc3339(), tx.slot);
                                              let test3 = bot::solana::transaction::buy(
+                                                 &executor,
                                                  1000000.0,
                                                  0.03,
                                                  &pubkey_string,
@@ -244,7 +429,10 @@ async fn main() {
                                                  true,
                                              )
                                              .await;
-                                             println!("{:?} {}", test3, message_age);
+                                             match &test3 {
+                                                 Ok(execution) => info!("Sent order {:?}: {} (message age {} ms)", execution.order_id, execution.signature, message_age),
+                                                 Err(e) => warn!("Failed to buy {}: {}", pubkey_string, e),
+                                             }
                                              println!("{:?} - {}", Utc::now().timestamp_millis(), Utc::now().to_rfc3339());
                                              process::exit(0);
                                          // }
@@ -310,6 +498,15 @@ async fn main() {
                                 ) => {
                                     //println!("Token swap from_mint:{} to_mint:{} amount_in:{} max_amount_in:{} amount_out:{} user:{}", swap_instruction.from_mint, swap_instruction.to_mint, swap_instruction.amount_in, swap_instruction.max_amount_in, swap_instruction.amount_out, swap_instruction.user);
                                 }
+                                transaction::DecodedInstruction::PFTradeEvent(_) => {}
+                                transaction::DecodedInstruction::PFCreateEvent(_) => {}
+                                transaction::DecodedInstruction::ComputeUnitLimit(_) => {}
+                                transaction::DecodedInstruction::ComputeUnitPrice(_) => {}
+                                transaction::DecodedInstruction::Anchor(_) => {}
+                                transaction::DecodedInstruction::AnchorEvent(_) => {}
+                                transaction::DecodedInstruction::RaydiumInitialize2(_) => {}
+                                transaction::DecodedInstruction::RaydiumDeposit(_) => {}
+                                transaction::DecodedInstruction::RaydiumWithdraw(_) => {}
                             }
                         }
                     }
@@ -337,6 +534,14 @@ async fn main() {
                     );
                     message_count = 0; // Reset count for the next 10 seconds
 
+                    if !decode_error_counts.is_empty() {
+                        println!(
+                            "{} {:?}",
+                            "Decode errors by kind:".bold().red(),
+                            decode_error_counts
+                        );
+                    }
+
                     // match position_tracker.get_all_position_elapsed_for_user("12BRrNxzJYMx7cRhuBdhA71AchuxWRcvGydNnDoZpump") {
                     //     Ok(elapsed) => println!("{:#?}", elapsed),
                     //     Err(e) => eprintln!("Error: {}", e),
@@ -344,7 +549,7 @@ async fn main() {
 
                     // Ensure mutable borrow only happens after collecting accounts
                     for tracked_account in pubkeys.iter() {
-                        position_tracker.print_position(tracked_account);
+                        positions.tracker_mut().print_position(tracked_account);
                     }
                     start_time = Instant::now();
                 }
@@ -0,0 +1,43 @@
+use std::env;
+use std::fs;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::os::unix::net::UnixListener;
+use std::sync::Arc;
+use dotenv::dotenv;
+use simplelog::{Config, LevelFilter, SimpleLogger};
+use zeroize::Zeroizing;
+use bot::solana::signer_daemon::{SignerDaemon, SignerPolicy};
+use bot::solana::wallet::{self, Unlock};
+
+const DEFAULT_SOCKET: &str = "/tmp/bot-signer.sock";
+
+// Unlocks the keystore at KEYSTORE_PATH and signs for trading processes connecting to SIGNER_SOCKET,
+// within the limits of `SignerPolicy::from_env`
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    dotenv().ok();
+    SimpleLogger::init(LevelFilter::Info, Config::default())?;
+
+    let keystore_path = env::var("KEYSTORE_PATH").map_err(|_| "KEYSTORE_PATH not set in environment")?;
+    let unlock = match Unlock::from_env() {
+        Ok(unlock) => unlock,
+        Err(_) => Unlock::Passphrase(Zeroizing::new(rpassword::prompt_password("Keystore passphrase: ")?)),
+    };
+    let keypair = wallet::load_keypair(&keystore_path, &unlock)?;
+    drop(unlock);
+    let policy = SignerPolicy::from_env()?;
+
+    let socket_path = env::var("SIGNER_SOCKET").unwrap_or_else(|_| DEFAULT_SOCKET.to_string());
+    // Replace a socket left behind by a previous run, but nothing else
+    if let Ok(metadata) = fs::symlink_metadata(&socket_path) {
+        if !metadata.file_type().is_socket() {
+            return Err(format!("{} exists and isn't a socket", socket_path).into());
+        }
+        fs::remove_file(&socket_path)?;
+    }
+    let listener = UnixListener::bind(&socket_path)?;
+    // Only processes of the same user may ask for signatures
+    fs::set_permissions(&socket_path, fs::Permissions::from_mode(0o600))?;
+
+    Arc::new(SignerDaemon::new(keypair, policy)).serve(listener)?;
+    Ok(())
+}
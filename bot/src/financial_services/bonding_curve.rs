@@ -0,0 +1,539 @@
+use borsh::BorshDeserialize;
+use thiserror::Error;
+use crate::solana::idl::discriminator;
+use crate::solana::transaction::PFSetParamsInstruction;
+
+// Pump.fun global parameters at the time of writing, used until a SetParams instruction is seen
+pub const INITIAL_VIRTUAL_TOKEN_RESERVES: u64 = 1_073_000_000_000_000;
+pub const INITIAL_VIRTUAL_SOL_RESERVES: u64 = 30_000_000_000;
+pub const INITIAL_REAL_TOKEN_RESERVES: u64 = 793_100_000_000_000;
+pub const TOKEN_TOTAL_SUPPLY: u64 = 1_000_000_000_000_000;
+pub const FEE_BASIS_POINTS: u64 = 100;
+
+const BPS: u128 = 10_000;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum CurveError {
+    #[error("Bonding curve is complete")]
+    Complete,
+    #[error("Not enough liquidity on the curve")]
+    InsufficientLiquidity,
+    #[error("Arithmetic overflow")]
+    Overflow,
+    #[error("Invalid bonding curve account: {0}")]
+    InvalidAccount(String),
+    #[error("Trade amount rounds to zero")]
+    ZeroAmount,
+    #[error("Price impact of {impact_bps} bps exceeds the limit of {max_bps} bps")]
+    PriceImpactTooHigh { impact_bps: u64, max_bps: u64 },
+}
+
+// On-chain layout of the BondingCurve account after its discriminator. Newer accounts carry
+// extra trailing fields, which are ignored.
+#[derive(BorshDeserialize)]
+struct BondingCurveAccount {
+    virtual_token_reserves: u64,
+    virtual_sol_reserves: u64,
+    real_token_reserves: u64,
+    real_sol_reserves: u64,
+    token_total_supply: u64,
+    complete: bool,
+}
+
+/// A quote for buying from the curve. `sol_cost` goes to the curve, `fee` to the fee recipient.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuyQuote {
+    pub tokens_out: u64,
+    pub sol_cost: u64,
+    pub fee: u64,
+}
+
+impl BuyQuote {
+    /// Lamports leaving the buyer's wallet.
+    pub fn total(&self) -> u64 {
+        self.sol_cost.saturating_add(self.fee)
+    }
+}
+
+/// A quote for selling to the curve. `sol_out` leaves the curve, of which `fee` goes to the fee recipient.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SellQuote {
+    pub tokens_in: u64,
+    pub sol_out: u64,
+    pub fee: u64,
+}
+
+impl SellQuote {
+    /// Lamports reaching the seller's wallet.
+    pub fn net(&self) -> u64 {
+        self.sol_out.saturating_sub(self.fee)
+    }
+}
+
+/// How much of a holding to sell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SellAmount {
+    /// Raw token amount
+    Tokens(u64),
+    /// Percentage (0-100) of the holding
+    Percent(u8),
+}
+
+impl SellAmount {
+    pub fn resolve(&self, balance: u64) -> u64 {
+        match *self {
+            SellAmount::Tokens(tokens) => tokens.min(balance),
+            SellAmount::Percent(percent) => (balance as u128 * percent.min(100) as u128 / 100) as u64,
+        }
+    }
+}
+
+/// Arguments of a Pump.fun buy instruction, with the quote they were derived from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuyOrder {
+    pub amount: u64,
+    pub max_sol_cost: u64,
+    pub quote: BuyQuote,
+    pub price_impact_bps: u64,
+}
+
+/// Arguments of a Pump.fun sell instruction, with the quote they were derived from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SellOrder {
+    pub amount: u64,
+    pub min_sol_output: u64,
+    pub quote: SellQuote,
+    pub price_impact_bps: u64,
+}
+
+/// Constant-product Pump.fun bonding curve over the virtual reserves.
+///
+/// All quotes are exact integer lamport and token amounts. Rounding always favours the curve:
+/// costs and fees round up, proceeds round down, so the product of the virtual reserves never
+/// decreases across a trade.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BondingCurve {
+    pub virtual_sol_reserves: u64,
+    pub virtual_token_reserves: u64,
+    pub real_sol_reserves: u64,
+    pub real_token_reserves: u64,
+    pub token_total_supply: u64,
+    pub fee_basis_points: u64,
+    /// Real token reserves the curve started with, the denominator of `progress_bps`
+    pub initial_real_token_reserves: u64,
+    pub complete: bool,
+}
+
+impl Default for BondingCurve {
+    fn default() -> Self {
+        BondingCurve {
+            virtual_sol_reserves: INITIAL_VIRTUAL_SOL_RESERVES,
+            virtual_token_reserves: INITIAL_VIRTUAL_TOKEN_RESERVES,
+            real_sol_reserves: 0,
+            real_token_reserves: INITIAL_REAL_TOKEN_RESERVES,
+            token_total_supply: TOKEN_TOTAL_SUPPLY,
+            fee_basis_points: FEE_BASIS_POINTS,
+            initial_real_token_reserves: INITIAL_REAL_TOKEN_RESERVES,
+            complete: false,
+        }
+    }
+}
+
+impl BondingCurve {
+    /// A freshly created curve under the given global parameters.
+    pub fn from_params(params: &PFSetParamsInstruction) -> Self {
+        BondingCurve {
+            virtual_sol_reserves: params.initial_virtual_sol_reserves,
+            virtual_token_reserves: params.initial_virtual_token_reserves,
+            real_sol_reserves: 0,
+            real_token_reserves: params.initial_real_token_reserves,
+            token_total_supply: params.token_total_supply,
+            fee_basis_points: params.fee_basis_points,
+            initial_real_token_reserves: params.initial_real_token_reserves,
+            complete: false,
+        }
+    }
+
+    /// Decodes a BondingCurve account. The account holds no fee or initial reserves, so those are
+    /// taken from `template`, usually a curve built by `from_params`.
+    pub fn decode_account(data: &[u8], template: &BondingCurve) -> Result<Self, CurveError> {
+        let (account_discriminator, mut fields) = data.split_at_checked(8)
+            .ok_or_else(|| CurveError::InvalidAccount(format!("{} bytes", data.len())))?;
+        if account_discriminator != discriminator("account", "BondingCurve") {
+            return Err(CurveError::InvalidAccount("wrong discriminator".to_string()));
+        }
+        let account = BondingCurveAccount::deserialize(&mut fields)
+            .map_err(|e| CurveError::InvalidAccount(e.to_string()))?;
+        Ok(BondingCurve {
+            virtual_sol_reserves: account.virtual_sol_reserves,
+            virtual_token_reserves: account.virtual_token_reserves,
+            real_sol_reserves: account.real_sol_reserves,
+            real_token_reserves: account.real_token_reserves,
+            token_total_supply: account.token_total_supply,
+            complete: account.complete,
+            ..*template
+        })
+    }
+
+    /// The curve after trades moved its virtual reserves to the given values, as reported by a
+    /// trade event. Real reserves move by the same amounts as the virtual ones.
+    pub fn with_virtual_reserves(&self, virtual_sol_reserves: u64, virtual_token_reserves: u64) -> Self {
+        let shift = |real: u64, from: u64, to: u64| (real as i128 + to as i128 - from as i128).max(0) as u64;
+        let real_token_reserves = shift(self.real_token_reserves, self.virtual_token_reserves, virtual_token_reserves);
+        BondingCurve {
+            virtual_sol_reserves,
+            virtual_token_reserves,
+            real_sol_reserves: shift(self.real_sol_reserves, self.virtual_sol_reserves, virtual_sol_reserves),
+            real_token_reserves,
+            complete: self.complete || real_token_reserves == 0,
+            ..*self
+        }
+    }
+
+    /// Most tokens `sol_in` lamports buy, fee included.
+    pub fn quote_buy(&self, sol_in: u64) -> Result<BuyQuote, CurveError> {
+        self.check_active()?;
+        // Largest cost whose fee still fits in sol_in; the ceiling fee makes the first guess at most one too high
+        let mut sol_cost = (sol_in as u128 * BPS / (BPS + self.fee_basis_points as u128)) as u64;
+        while sol_cost > 0 && sol_cost.checked_add(self.fee(sol_cost)).ok_or(CurveError::Overflow)? > sol_in {
+            sol_cost -= 1;
+        }
+        let (sol, tokens) = (self.virtual_sol_reserves as u128, self.virtual_token_reserves as u128);
+        let mut tokens_out = (tokens * sol_cost as u128 / (sol + sol_cost as u128)) as u64;
+        tokens_out = tokens_out.min(self.real_token_reserves);
+        self.quote_buy_exact_tokens(tokens_out)
+    }
+
+    /// What buying exactly `tokens` costs.
+    pub fn quote_buy_exact_tokens(&self, tokens: u64) -> Result<BuyQuote, CurveError> {
+        self.check_active()?;
+        if tokens > self.real_token_reserves || tokens >= self.virtual_token_reserves {
+            return Err(CurveError::InsufficientLiquidity);
+        }
+        let (sol, virtual_tokens) = (self.virtual_sol_reserves as u128, self.virtual_token_reserves as u128);
+        let sol_cost = (tokens as u128 * sol).div_ceil(virtual_tokens - tokens as u128);
+        let sol_cost = u64::try_from(sol_cost).map_err(|_| CurveError::Overflow)?;
+        self.virtual_sol_reserves.checked_add(sol_cost).ok_or(CurveError::Overflow)?;
+        Ok(BuyQuote { tokens_out: tokens, sol_cost, fee: self.fee(sol_cost) })
+    }
+
+    /// What selling `tokens` pays out.
+    pub fn quote_sell(&self, tokens: u64) -> Result<SellQuote, CurveError> {
+        self.check_active()?;
+        let (sol, virtual_tokens) = (self.virtual_sol_reserves as u128, self.virtual_token_reserves as u128);
+        let sol_out = (tokens as u128 * sol / (virtual_tokens + tokens as u128)) as u64;
+        if sol_out > self.real_sol_reserves {
+            return Err(CurveError::InsufficientLiquidity);
+        }
+        Ok(SellQuote { tokens_in: tokens, sol_out, fee: self.fee(sol_out) })
+    }
+
+    /// A buy spending at most `sol_budget` lamports, fee included. The token amount is quoted for
+    /// the budget less `slippage_bps`, so the order still fills if the price rises by that much
+    /// before it lands, and the budget itself is the instruction's `max_sol_cost`.
+    pub fn buy_order(&self, sol_budget: u64, slippage_bps: u64, max_impact_bps: u64) -> Result<BuyOrder, CurveError> {
+        let quoted_sol = (sol_budget as u128 * BPS / (BPS + slippage_bps as u128)) as u64;
+        let quote = self.quote_buy(quoted_sol)?;
+        if quote.tokens_out == 0 {
+            return Err(CurveError::ZeroAmount);
+        }
+        let price_impact_bps = self.buy_price_impact_bps(&quote)?;
+        if price_impact_bps > max_impact_bps {
+            return Err(CurveError::PriceImpactTooHigh { impact_bps: price_impact_bps, max_bps: max_impact_bps });
+        }
+        Ok(BuyOrder { amount: quote.tokens_out, max_sol_cost: sol_budget, quote, price_impact_bps })
+    }
+
+    /// A sell of `tokens` accepting up to `slippage_bps` less than the quoted proceeds after fees.
+    pub fn sell_order(&self, tokens: u64, slippage_bps: u64, max_impact_bps: u64) -> Result<SellOrder, CurveError> {
+        if tokens == 0 {
+            return Err(CurveError::ZeroAmount);
+        }
+        let quote = self.quote_sell(tokens)?;
+        let price_impact_bps = self.sell_price_impact_bps(&quote)?;
+        if price_impact_bps > max_impact_bps {
+            return Err(CurveError::PriceImpactTooHigh { impact_bps: price_impact_bps, max_bps: max_impact_bps });
+        }
+        let min_sol_output = (quote.net() as u128 * BPS.saturating_sub(slippage_bps as u128) / BPS) as u64;
+        Ok(SellOrder { amount: tokens, min_sol_output, quote, price_impact_bps })
+    }
+
+    // A quote from another curve state can ask for more than the reserves hold; the curve is left untouched then
+    pub fn apply_buy(&mut self, quote: &BuyQuote) -> Result<(), CurveError> {
+        let virtual_sol_reserves = self.virtual_sol_reserves.checked_add(quote.sol_cost).ok_or(CurveError::Overflow)?;
+        let real_sol_reserves = self.real_sol_reserves.checked_add(quote.sol_cost).ok_or(CurveError::Overflow)?;
+        let virtual_token_reserves = self.virtual_token_reserves.checked_sub(quote.tokens_out).ok_or(CurveError::InsufficientLiquidity)?;
+        let real_token_reserves = self.real_token_reserves.checked_sub(quote.tokens_out).ok_or(CurveError::InsufficientLiquidity)?;
+        self.virtual_sol_reserves = virtual_sol_reserves;
+        self.real_sol_reserves = real_sol_reserves;
+        self.virtual_token_reserves = virtual_token_reserves;
+        self.real_token_reserves = real_token_reserves;
+        self.complete = self.real_token_reserves == 0;
+        Ok(())
+    }
+
+    pub fn apply_sell(&mut self, quote: &SellQuote) -> Result<(), CurveError> {
+        let virtual_sol_reserves = self.virtual_sol_reserves.checked_sub(quote.sol_out).ok_or(CurveError::InsufficientLiquidity)?;
+        let real_sol_reserves = self.real_sol_reserves.checked_sub(quote.sol_out).ok_or(CurveError::InsufficientLiquidity)?;
+        let virtual_token_reserves = self.virtual_token_reserves.checked_add(quote.tokens_in).ok_or(CurveError::Overflow)?;
+        let real_token_reserves = self.real_token_reserves.checked_add(quote.tokens_in).ok_or(CurveError::Overflow)?;
+        self.virtual_sol_reserves = virtual_sol_reserves;
+        self.real_sol_reserves = real_sol_reserves;
+        self.virtual_token_reserves = virtual_token_reserves;
+        self.real_token_reserves = real_token_reserves;
+        Ok(())
+    }
+
+    /// How much worse than the spot price a buy executes, in basis points.
+    /// A curve without virtual SOL reserves has no spot price.
+    pub fn buy_price_impact_bps(&self, quote: &BuyQuote) -> Result<u64, CurveError> {
+        if quote.tokens_out == 0 {
+            return Ok(0);
+        }
+        // (sol_cost / tokens_out) / (virtual_sol / virtual_tokens) - 1
+        let paid = quote.sol_cost as u128 * self.virtual_token_reserves as u128 * BPS;
+        let spot = quote.tokens_out as u128 * self.virtual_sol_reserves as u128;
+        if spot == 0 {
+            return Err(CurveError::InsufficientLiquidity);
+        }
+        Ok(paid.div_ceil(spot).saturating_sub(BPS).min(u64::MAX as u128) as u64)
+    }
+
+    /// How much worse than the spot price a sell executes, in basis points, fee excluded.
+    pub fn sell_price_impact_bps(&self, quote: &SellQuote) -> Result<u64, CurveError> {
+        if quote.tokens_in == 0 {
+            return Ok(0);
+        }
+        let received = quote.sol_out as u128 * self.virtual_token_reserves as u128 * BPS;
+        let spot = quote.tokens_in as u128 * self.virtual_sol_reserves as u128;
+        if spot == 0 {
+            return Err(CurveError::InsufficientLiquidity);
+        }
+        Ok(BPS.saturating_sub(received / spot) as u64)
+    }
+
+    /// Total supply valued at the spot price, in lamports.
+    pub fn market_cap_lamports(&self) -> u64 {
+        if self.virtual_token_reserves == 0 {
+            return 0;
+        }
+        let market_cap = self.token_total_supply as u128 * self.virtual_sol_reserves as u128 / self.virtual_token_reserves as u128;
+        market_cap.min(u64::MAX as u128) as u64
+    }
+
+    /// Share of the initial real token reserves sold, in basis points; 10000 once complete.
+    pub fn progress_bps(&self) -> u64 {
+        if self.complete || self.initial_real_token_reserves == 0 {
+            return BPS as u64;
+        }
+        let sold = self.initial_real_token_reserves.saturating_sub(self.real_token_reserves);
+        (sold as u128 * BPS / self.initial_real_token_reserves as u128) as u64
+    }
+
+    /// A fresh curve after a single buy of `sol_in` lamports.
+    #[cfg(test)]
+    pub(crate) fn traded(sol_in: u64) -> Self {
+        let mut curve = BondingCurve::default();
+        curve.apply_buy(&curve.quote_buy(sol_in).unwrap()).unwrap();
+        curve
+    }
+
+    fn fee(&self, lamports: u64) -> u64 {
+        (lamports as u128 * self.fee_basis_points as u128).div_ceil(BPS) as u64
+    }
+
+    fn check_active(&self) -> Result<(), CurveError> {
+        if self.complete {
+            return Err(CurveError::Complete);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    const CASES: usize = 2_000;
+
+    fn k(curve: &BondingCurve) -> u128 {
+        curve.virtual_sol_reserves as u128 * curve.virtual_token_reserves as u128
+    }
+
+    // A curve somewhere along its way to completion
+    fn random_curve(rng: &mut StdRng) -> BondingCurve {
+        let mut curve = BondingCurve::default();
+        let sol_in = rng.gen_range(0..80_000_000_000);
+        let quote = curve.quote_buy(sol_in).unwrap();
+        curve.apply_buy(&quote).unwrap();
+        curve.complete = false;
+        curve
+    }
+
+    #[test]
+    fn test_fresh_curve() {
+        let curve = BondingCurve::default();
+
+        assert_eq!(curve.progress_bps(), 0);
+        // About 28 SOL at launch
+        assert_eq!(curve.market_cap_lamports(), 27_958_993_476);
+        let quote = curve.quote_buy(1_010_000_000).unwrap();
+        assert_eq!(quote.total(), 1_010_000_000);
+        assert_eq!(quote.fee, 10_000_000);
+        assert_eq!(quote.tokens_out, 34_612_903_225_806);
+    }
+
+    #[test]
+    fn test_buy_invariants() {
+        let mut rng = StdRng::seed_from_u64(18);
+        for _ in 0..CASES {
+            let curve = random_curve(&mut rng);
+            let sol_in = rng.gen_range(0..100_000_000_000);
+            let quote = curve.quote_buy(sol_in).unwrap();
+
+            assert!(quote.total() <= sol_in);
+            assert!(quote.tokens_out <= curve.real_token_reserves);
+            assert_eq!(curve.quote_buy_exact_tokens(quote.tokens_out).unwrap(), quote);
+            // One more token wouldn't have been affordable
+            if quote.tokens_out < curve.real_token_reserves {
+                assert!(curve.quote_buy_exact_tokens(quote.tokens_out + 1).unwrap().total() > sol_in);
+            }
+            // More SOL never buys fewer tokens
+            assert!(curve.quote_buy(sol_in + rng.gen_range(0..1_000_000)).unwrap().tokens_out >= quote.tokens_out);
+
+            let mut after = curve;
+            after.apply_buy(&quote).unwrap();
+            assert!(k(&after) >= k(&curve));
+            assert!(after.progress_bps() >= curve.progress_bps());
+            assert!(after.market_cap_lamports() >= curve.market_cap_lamports());
+        }
+    }
+
+    #[test]
+    fn test_sell_invariants() {
+        let mut rng = StdRng::seed_from_u64(180);
+        for _ in 0..CASES {
+            let curve = random_curve(&mut rng);
+            let sold = curve.initial_real_token_reserves - curve.real_token_reserves;
+            let tokens = rng.gen_range(0..=sold);
+            let quote = curve.quote_sell(tokens).unwrap();
+
+            assert!(quote.net() <= quote.sol_out);
+            assert!(quote.sol_out <= curve.real_sol_reserves);
+            assert!(curve.quote_sell(tokens / 2).unwrap().sol_out <= quote.sol_out);
+
+            let mut after = curve;
+            after.apply_sell(&quote).unwrap();
+            assert!(k(&after) >= k(&curve));
+            assert!(after.market_cap_lamports() <= curve.market_cap_lamports());
+        }
+    }
+
+    #[test]
+    fn test_round_trip_never_profits() {
+        let mut rng = StdRng::seed_from_u64(1_800);
+        for _ in 0..CASES {
+            let mut curve = random_curve(&mut rng);
+            let buy = curve.quote_buy(rng.gen_range(1..50_000_000_000)).unwrap();
+            curve.apply_buy(&buy).unwrap();
+            curve.complete = false;
+            let sell = curve.quote_sell(buy.tokens_out).unwrap();
+
+            assert!(sell.net() <= buy.total());
+            assert!(sell.sol_out <= buy.sol_cost);
+        }
+    }
+
+    #[test]
+    fn test_price_impact_grows_with_size() {
+        let mut rng = StdRng::seed_from_u64(18_000);
+        for _ in 0..CASES {
+            let curve = random_curve(&mut rng);
+            let small = curve.quote_buy(rng.gen_range(1_000_000..1_000_000_000)).unwrap();
+            let large = curve.quote_buy(small.total() * 10).unwrap();
+
+            assert!(curve.buy_price_impact_bps(&large).unwrap() >= curve.buy_price_impact_bps(&small).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_orders_respect_bounds() {
+        let mut rng = StdRng::seed_from_u64(20);
+        for _ in 0..CASES {
+            let curve = random_curve(&mut rng);
+            let sol_budget = rng.gen_range(1_000_000..5_000_000_000);
+            let slippage_bps = rng.gen_range(0..2_000);
+            let Ok(buy) = curve.buy_order(sol_budget, slippage_bps, u64::MAX) else {
+                continue;
+            };
+
+            assert_eq!(buy.max_sol_cost, sol_budget);
+            assert!(buy.quote.total() <= sol_budget);
+            // The budget covers the order at the full quote
+            assert!(buy.quote.total() as u128 * (BPS + slippage_bps as u128) <= sol_budget as u128 * BPS);
+
+            let mut after = curve;
+            after.apply_buy(&buy.quote).unwrap();
+            after.complete = false;
+            let sell = after.sell_order(buy.amount, slippage_bps, u64::MAX).unwrap();
+            assert!(sell.min_sol_output <= sell.quote.net());
+        }
+    }
+
+    #[test]
+    fn test_impact_limit() {
+        let curve = BondingCurve::default();
+        let small = curve.buy_order(100_000_000, 100, 100).unwrap();
+        assert!(small.price_impact_bps <= 100);
+
+        assert!(matches!(
+            curve.buy_order(10_000_000_000, 100, 100),
+            Err(CurveError::PriceImpactTooHigh { max_bps: 100, .. })
+        ));
+        assert_eq!(curve.buy_order(0, 100, 100), Err(CurveError::ZeroAmount));
+        assert_eq!(SellAmount::Percent(25).resolve(1_001), 250);
+        assert_eq!(SellAmount::Tokens(5_000).resolve(1_001), 1_001);
+    }
+
+    #[test]
+    fn test_complete_curve_refuses_quotes() {
+        let mut curve = BondingCurve::default();
+        let quote = curve.quote_buy(u64::MAX / 2).unwrap();
+        assert_eq!(quote.tokens_out, INITIAL_REAL_TOKEN_RESERVES);
+        curve.apply_buy(&quote).unwrap();
+
+        assert!(curve.complete);
+        assert_eq!(curve.progress_bps(), 10_000);
+        assert_eq!(curve.quote_buy(1), Err(CurveError::Complete));
+        assert_eq!(curve.quote_sell(1), Err(CurveError::Complete));
+    }
+
+    #[test]
+    fn test_quote_totals_do_not_wrap() {
+        let sell = SellQuote { tokens_in: 1, sol_out: 1, fee: 2 };
+        assert_eq!(sell.net(), 0);
+        let buy = BuyQuote { tokens_out: 1, sol_cost: u64::MAX, fee: 1 };
+        assert_eq!(buy.total(), u64::MAX);
+    }
+
+    #[test]
+    fn test_inconsistent_quotes_are_refused() {
+        let curve = BondingCurve::traded(1_000_000_000);
+        let mut after = curve;
+        let oversold = SellQuote { tokens_in: 1, sol_out: curve.real_sol_reserves + 1, fee: 0 };
+        assert_eq!(after.apply_sell(&oversold), Err(CurveError::InsufficientLiquidity));
+        let overbought = BuyQuote { tokens_out: curve.real_token_reserves + 1, sol_cost: 1, fee: 0 };
+        assert_eq!(after.apply_buy(&overbought), Err(CurveError::InsufficientLiquidity));
+        let overflowing = BuyQuote { tokens_out: 1, sol_cost: u64::MAX, fee: 0 };
+        assert_eq!(after.apply_buy(&overflowing), Err(CurveError::Overflow));
+        assert_eq!(after, curve);
+
+        let drained = BondingCurve { virtual_sol_reserves: 0, ..curve };
+        let buy = BuyQuote { tokens_out: 1, sol_cost: 1, fee: 0 };
+        assert_eq!(drained.buy_price_impact_bps(&buy), Err(CurveError::InsufficientLiquidity));
+        let sell = SellQuote { tokens_in: 1, sol_out: 0, fee: 0 };
+        assert_eq!(drained.sell_price_impact_bps(&sell), Err(CurveError::InsufficientLiquidity));
+    }
+}
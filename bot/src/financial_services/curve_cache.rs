@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+use solana_sdk::pubkey::Pubkey;
+use crate::financial_services::bonding_curve::{BondingCurve, CurveError};
+use crate::solana::address_table_cache::{AccountFetcher, AddressTableError};
+use crate::solana::transaction::{get_bonding_curve_account, DecodedInstruction, DecodedTransaction, PFSetParamsInstruction};
+
+#[derive(Debug, thiserror::Error)]
+pub enum CurveCacheError {
+    #[error("FetchError {0}")]
+    Fetch(#[from] AddressTableError),
+    #[error("CurveError {0}")]
+    Curve(#[from] CurveError),
+}
+
+/// A mint's curve as of the slot it was last updated at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CachedCurve {
+    pub curve: BondingCurve,
+    pub slot: u64,
+}
+
+/// Current Pump.fun curve state per mint, shared between tasks behind an `Arc`.
+///
+/// Seeded over RPC with `seed`, then kept current from decoded trade and create events with
+/// `observe_decoded` and from BondingCurve account updates with `apply_account`. Updates older
+/// than the cached slot are ignored.
+pub struct CurveCache {
+    // Fresh curve under the current global parameters; supplies fees and initial reserves
+    template: RwLock<BondingCurve>,
+    curves: RwLock<HashMap<Pubkey, CachedCurve>>,
+    // BondingCurve account to mint, for account updates
+    mints: RwLock<HashMap<Pubkey, Pubkey>>,
+}
+
+impl Default for CurveCache {
+    fn default() -> Self {
+        Self::new(BondingCurve::default())
+    }
+}
+
+impl CurveCache {
+    pub fn new(template: BondingCurve) -> Self {
+        CurveCache {
+            template: RwLock::new(template),
+            curves: RwLock::new(HashMap::new()),
+            mints: RwLock::new(HashMap::new()),
+        }
+    }
+
+    // New global parameters apply to curves created from now on; the fee applies to every curve
+    pub fn set_params(&self, params: &PFSetParamsInstruction) {
+        *self.template.write().unwrap() = BondingCurve::from_params(params);
+        for cached in self.curves.write().unwrap().values_mut() {
+            cached.curve.fee_basis_points = params.fee_basis_points;
+        }
+    }
+
+    pub fn get(&self, mint: &Pubkey) -> Option<BondingCurve> {
+        self.get_cached(mint).map(|cached| cached.curve)
+    }
+
+    pub fn get_cached(&self, mint: &Pubkey) -> Option<CachedCurve> {
+        self.curves.read().unwrap().get(mint).copied()
+    }
+
+    pub fn mint_for_account(&self, bonding_curve: &Pubkey) -> Option<Pubkey> {
+        self.mints.read().unwrap().get(bonding_curve).copied()
+    }
+
+    pub fn remove(&self, mint: &Pubkey) {
+        self.curves.write().unwrap().remove(mint);
+        self.mints.write().unwrap().retain(|_, cached_mint| cached_mint != mint);
+    }
+
+    /// Caches `curve` for `mint` unless a newer slot is already cached. Returns whether it was stored.
+    pub fn insert(&self, mint: Pubkey, slot: u64, curve: BondingCurve) -> bool {
+        self.update(mint, slot, None, |_| curve)
+    }
+
+    /// Applies a BondingCurve account update. Accounts of mints never seen are ignored.
+    pub fn apply_account(&self, bonding_curve: &Pubkey, slot: u64, data: &[u8]) -> Result<bool, CurveError> {
+        let Some(mint) = self.mint_for_account(bonding_curve) else {
+            return Ok(false);
+        };
+        let curve = BondingCurve::decode_account(data, &self.template.read().unwrap())?;
+        Ok(self.insert(mint, slot, curve))
+    }
+
+    // Trade events carry the virtual reserves after the trade, so a mint first seen through one
+    // is reconstructed from a fresh curve
+    pub fn observe_decoded(&self, transaction: &DecodedTransaction) {
+        if transaction.error.is_some() {
+            return;
+        }
+        for instruction in &transaction.instructions {
+            match instruction {
+                DecodedInstruction::PFSetParams(params) => self.set_params(params),
+                DecodedInstruction::PFCreateEvent(event) => {
+                    let template = *self.template.read().unwrap();
+                    self.update(event.mint, transaction.slot, Some(event.bonding_curve), |_| template);
+                }
+                DecodedInstruction::PFTradeEvent(event) => {
+                    let template = *self.template.read().unwrap();
+                    self.update(event.mint, transaction.slot, None, |cached| {
+                        cached.unwrap_or(template).with_virtual_reserves(event.virtual_sol_reserves, event.virtual_token_reserves)
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Fetches and caches the curve of `mint`.
+    pub async fn seed(&self, fetcher: &dyn AccountFetcher, mint: &Pubkey) -> Result<BondingCurve, CurveCacheError> {
+        let bonding_curve = get_bonding_curve_account(mint);
+        let account = fetcher.fetch_account(&bonding_curve).await?;
+        let curve = BondingCurve::decode_account(&account.data, &self.template.read().unwrap())?;
+        self.update(*mint, account.slot, Some(bonding_curve), |_| curve);
+        // A newer update may have landed while fetching
+        Ok(self.get(mint).unwrap_or(curve))
+    }
+
+    pub async fn get_or_seed(&self, fetcher: &dyn AccountFetcher, mint: &Pubkey) -> Result<BondingCurve, CurveCacheError> {
+        match self.get(mint) {
+            Some(curve) => Ok(curve),
+            None => self.seed(fetcher, mint).await,
+        }
+    }
+
+    fn update(
+        &self,
+        mint: Pubkey,
+        slot: u64,
+        bonding_curve: Option<Pubkey>,
+        apply: impl FnOnce(Option<BondingCurve>) -> BondingCurve,
+    ) -> bool {
+        let mut curves = self.curves.write().unwrap();
+        let cached = curves.get(&mint).copied();
+        if cached.is_some_and(|cached| cached.slot > slot) {
+            return false;
+        }
+        if cached.is_none() {
+            let bonding_curve = bonding_curve.unwrap_or_else(|| get_bonding_curve_account(&mint));
+            self.mints.write().unwrap().insert(bonding_curve, mint);
+        }
+        curves.insert(mint, CachedCurve { curve: apply(cached.map(|cached| cached.curve)), slot });
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use crate::solana::address_table_cache::FetchedAccount;
+    use crate::solana::idl::discriminator;
+    use crate::solana::transaction::{PFCreateEvent, PFTradeEvent};
+
+    fn account_data(curve: &BondingCurve) -> Vec<u8> {
+        let mut data = discriminator("account", "BondingCurve").to_vec();
+        for field in [
+            curve.virtual_token_reserves,
+            curve.virtual_sol_reserves,
+            curve.real_token_reserves,
+            curve.real_sol_reserves,
+            curve.token_total_supply,
+        ] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+        data.push(curve.complete as u8);
+        // Creator, present on newer accounts
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data
+    }
+
+    fn trade_event(slot: u64, mint: Pubkey, curve: &BondingCurve) -> DecodedTransaction {
+        let mut transaction = DecodedTransaction::new_empty();
+        transaction.slot = slot;
+        transaction.instructions = vec![DecodedInstruction::PFTradeEvent(PFTradeEvent {
+            mint,
+            sol_amount: 0,
+            token_amount: 0,
+            is_buy: true,
+            user: Pubkey::new_unique(),
+            timestamp: 0,
+            virtual_sol_reserves: curve.virtual_sol_reserves,
+            virtual_token_reserves: curve.virtual_token_reserves,
+        })];
+        transaction
+    }
+
+    struct MockAccountFetcher(FetchedAccount);
+
+    #[async_trait]
+    impl AccountFetcher for MockAccountFetcher {
+        async fn fetch_account(&self, _account_key: &Pubkey) -> Result<FetchedAccount, AddressTableError> {
+            Ok(FetchedAccount { slot: self.0.slot, data: self.0.data.clone() })
+        }
+    }
+
+    #[test]
+    fn test_decode_account() {
+        let curve = BondingCurve::traded(5_000_000_000);
+
+        assert_eq!(BondingCurve::decode_account(&account_data(&curve), &BondingCurve::default()), Ok(curve));
+        assert!(matches!(
+            BondingCurve::decode_account(&[0; 16], &BondingCurve::default()),
+            Err(CurveError::InvalidAccount(_))
+        ));
+    }
+
+    #[test]
+    fn test_trade_events_reconstruct_real_reserves() {
+        let cache = CurveCache::default();
+        let mint = Pubkey::new_unique();
+        let after_first = BondingCurve::traded(2_000_000_000);
+        let mut after_second = after_first;
+        after_second.apply_buy(&after_second.quote_buy(3_000_000_000).unwrap()).unwrap();
+
+        cache.observe_decoded(&trade_event(10, mint, &after_first));
+        assert_eq!(cache.get(&mint), Some(after_first));
+        cache.observe_decoded(&trade_event(12, mint, &after_second));
+        // An older event arriving late is ignored
+        cache.observe_decoded(&trade_event(11, mint, &after_first));
+        assert_eq!(cache.get_cached(&mint), Some(CachedCurve { curve: after_second, slot: 12 }));
+    }
+
+    #[test]
+    fn test_account_updates_follow_created_mints() {
+        let cache = CurveCache::default();
+        let (mint, bonding_curve) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut create = DecodedTransaction::new_empty();
+        create.slot = 5;
+        create.instructions = vec![DecodedInstruction::PFCreateEvent(PFCreateEvent {
+            name: String::new(),
+            symbol: String::new(),
+            uri: String::new(),
+            mint,
+            bonding_curve,
+            user: Pubkey::new_unique(),
+        })];
+        let curve = BondingCurve::traded(1_000_000_000);
+
+        assert_eq!(cache.apply_account(&bonding_curve, 6, &account_data(&curve)), Ok(false));
+        cache.observe_decoded(&create);
+        assert_eq!(cache.get(&mint), Some(BondingCurve::default()));
+        assert_eq!(cache.apply_account(&bonding_curve, 6, &account_data(&curve)), Ok(true));
+        assert_eq!(cache.get(&mint), Some(curve));
+    }
+
+    #[tokio::test]
+    async fn test_seed_over_rpc() {
+        let cache = CurveCache::default();
+        let mint = Pubkey::new_unique();
+        let curve = BondingCurve::traded(7_000_000_000);
+        let fetcher = MockAccountFetcher(FetchedAccount { slot: 20, data: account_data(&curve) });
+
+        assert_eq!(cache.get_or_seed(&fetcher, &mint).await.unwrap(), curve);
+        assert_eq!(cache.mint_for_account(&get_bonding_curve_account(&mint)), Some(mint));
+        // Newer state from events isn't overwritten by a stale fetch
+        let newer = BondingCurve::traded(9_000_000_000);
+        cache.observe_decoded(&trade_event(21, mint, &newer));
+        assert_eq!(cache.seed(&fetcher, &mint).await.unwrap(), newer);
+    }
+}
@@ -1 +1,3 @@
-pub mod buy_recommendation;
\ No newline at end of file
+pub mod bonding_curve;
+pub mod buy_recommendation;
+pub mod curve_cache;
@@ -0,0 +1,390 @@
+use std::str::FromStr;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use jito_protos::auth::{auth_service_client::AuthServiceClient, Role};
+use jito_protos::bundle::Bundle;
+use jito_protos::packet::{Meta, Packet};
+use jito_protos::searcher::{searcher_service_client::SearcherServiceClient, GetTipAccountsRequest, SendBundleRequest};
+use rand::seq::SliceRandom;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Keypair;
+use solana_sdk::transaction::VersionedTransaction;
+use tokio::task::JoinHandle;
+use tonic::codegen::InterceptedService;
+use tonic::transport::Channel;
+use jito_protos::token_authenticator::{create_grpc_channel, BlockEngineConnectionError, ClientInterceptor};
+
+/// The block engine rejects bundles with more transactions than this.
+pub const MAX_BUNDLE_TRANSACTIONS: usize = 5;
+// Tip accounts rarely change, so they are refreshed lazily after this long
+const DEFAULT_TIP_ACCOUNTS_TTL: Duration = Duration::from_secs(10 * 60);
+
+pub type SearcherClient = SearcherServiceClient<InterceptedService<Channel, ClientInterceptor>>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BundleError {
+    #[error("ConnectionError {0}")]
+    Connection(Box<BlockEngineConnectionError>),
+    #[error("StatusError {0}")]
+    Status(Box<tonic::Status>),
+    #[error("SerializeError {0}")]
+    Serialize(#[from] bincode::Error),
+    #[error("bundle has no transactions")]
+    Empty,
+    #[error("bundle has {0} transactions, at most {MAX_BUNDLE_TRANSACTIONS} are allowed")]
+    TooManyTransactions(usize),
+    #[error("block engine returned no tip accounts")]
+    NoTipAccounts,
+    #[error("InvalidTipAccount {0}")]
+    InvalidTipAccount(String),
+}
+
+// tonic's Status is large, so both are boxed to keep results small
+impl From<BlockEngineConnectionError> for BundleError {
+    fn from(error: BlockEngineConnectionError) -> Self {
+        BundleError::Connection(Box::new(error))
+    }
+}
+
+impl From<tonic::Status> for BundleError {
+    fn from(status: tonic::Status) -> Self {
+        BundleError::Status(Box::new(status))
+    }
+}
+
+struct CachedTipAccounts {
+    accounts: Vec<Pubkey>,
+    fetched_at: Instant,
+}
+
+/// Sends bundles to the Jito block engine over the authenticated SearcherService.
+pub struct BundleClient {
+    client: SearcherClient,
+    tip_accounts: Mutex<Option<CachedTipAccounts>>,
+    tip_accounts_ttl: Duration,
+}
+
+impl BundleClient {
+    /// Authenticates as a searcher with `auth_keypair` and connects to the block engine.
+    /// The returned handle refreshes the access token until `exit` is set.
+    pub async fn connect(
+        block_engine_url: String,
+        auth_url: String,
+        auth_keypair: Arc<Keypair>,
+        exit: Arc<AtomicBool>,
+    ) -> Result<(Self, JoinHandle<()>), BundleError> {
+        let auth_channel = create_grpc_channel(auth_url).await?;
+        let searcher_channel = create_grpc_channel(block_engine_url).await?;
+        let (client_interceptor, refresh_handle) = ClientInterceptor::new(
+            AuthServiceClient::new(auth_channel),
+            auth_keypair,
+            Role::Searcher,
+            "bundle_client".to_string(),
+            exit,
+        )
+        .await?;
+        let client = SearcherServiceClient::with_interceptor(searcher_channel, client_interceptor);
+        Ok((Self::new(client), refresh_handle))
+    }
+
+    pub fn new(client: SearcherClient) -> Self {
+        BundleClient {
+            client,
+            tip_accounts: Mutex::new(None),
+            tip_accounts_ttl: DEFAULT_TIP_ACCOUNTS_TTL,
+        }
+    }
+
+    pub fn with_tip_accounts_ttl(mut self, ttl: Duration) -> Self {
+        self.tip_accounts_ttl = ttl;
+        self
+    }
+
+    // A handle to the underlying SearcherService client for the other searcher RPCs
+    pub fn searcher_client(&self) -> SearcherClient {
+        self.client.clone()
+    }
+
+    /// Sends the transactions as one atomic bundle and returns the bundle's uuid.
+    pub async fn send_bundle(&self, transactions: &[VersionedTransaction]) -> Result<String, BundleError> {
+        if transactions.is_empty() {
+            return Err(BundleError::Empty);
+        }
+        if transactions.len() > MAX_BUNDLE_TRANSACTIONS {
+            return Err(BundleError::TooManyTransactions(transactions.len()));
+        }
+
+        let packets = transactions.iter()
+            .map(|transaction| {
+                let data = bincode::serialize(transaction)?;
+                Ok(Packet {
+                    meta: Some(Meta { size: data.len() as u64, ..Default::default() }),
+                    data,
+                })
+            })
+            .collect::<Result<Vec<_>, BundleError>>()?;
+
+        let response = self.client.clone()
+            .send_bundle(SendBundleRequest { bundle: Some(Bundle { header: None, packets }) })
+            .await?
+            .into_inner();
+        Ok(response.uuid)
+    }
+
+    /// The block engine's tip accounts, fetched with `GetTipAccounts` at most once per TTL.
+    pub async fn tip_accounts(&self) -> Result<Vec<Pubkey>, BundleError> {
+        if let Some(cached) = self.tip_accounts.lock().unwrap().as_ref() {
+            if cached.fetched_at.elapsed() < self.tip_accounts_ttl {
+                return Ok(cached.accounts.clone());
+            }
+        }
+
+        let response = self.client.clone()
+            .get_tip_accounts(GetTipAccountsRequest {})
+            .await?
+            .into_inner();
+        let accounts = response.accounts.iter()
+            .map(|account| Pubkey::from_str(account).map_err(|_| BundleError::InvalidTipAccount(account.clone())))
+            .collect::<Result<Vec<_>, _>>()?;
+        if accounts.is_empty() {
+            return Err(BundleError::NoTipAccounts);
+        }
+
+        *self.tip_accounts.lock().unwrap() = Some(CachedTipAccounts {
+            accounts: accounts.clone(),
+            fetched_at: Instant::now(),
+        });
+        Ok(accounts)
+    }
+
+    // Spreads tips across the accounts to avoid write-lock contention on any single one
+    pub async fn random_tip_account(&self) -> Result<Pubkey, BundleError> {
+        let accounts = self.tip_accounts().await?;
+        accounts.choose(&mut rand::thread_rng()).copied().ok_or(BundleError::NoTipAccounts)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::SystemTime;
+    use jito_protos::auth::auth_service_server::{AuthService, AuthServiceServer};
+    use jito_protos::auth::{
+        GenerateAuthChallengeRequest, GenerateAuthChallengeResponse, GenerateAuthTokensRequest,
+        GenerateAuthTokensResponse, RefreshAccessTokenRequest, RefreshAccessTokenResponse, Token,
+    };
+    use jito_protos::bundle::BundleResult;
+    use jito_protos::searcher::searcher_service_server::{SearcherService, SearcherServiceServer};
+    use jito_protos::searcher::*;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::signature::Signer;
+    use solana_sdk::system_transaction;
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tokio_stream::Stream;
+    use tonic::{Request, Response, Status};
+
+    const ACCESS_TOKEN: &str = "access-token";
+
+    struct MockAuth;
+
+    fn token(value: &str) -> Option<Token> {
+        let expires_at = SystemTime::now() + Duration::from_secs(60 * 60);
+        Some(Token { value: value.to_string(), expires_at_utc: Some(expires_at.into()) })
+    }
+
+    #[tonic::async_trait]
+    impl AuthService for MockAuth {
+        async fn generate_auth_challenge(
+            &self,
+            request: Request<GenerateAuthChallengeRequest>,
+        ) -> Result<Response<GenerateAuthChallengeResponse>, Status> {
+            assert_eq!(request.into_inner().role, Role::Searcher as i32);
+            Ok(Response::new(GenerateAuthChallengeResponse { challenge: "challenge".to_string() }))
+        }
+
+        async fn generate_auth_tokens(
+            &self,
+            request: Request<GenerateAuthTokensRequest>,
+        ) -> Result<Response<GenerateAuthTokensResponse>, Status> {
+            let request = request.into_inner();
+            let pubkey = Pubkey::try_from(request.client_pubkey.as_slice()).unwrap();
+            let signature = solana_sdk::signature::Signature::try_from(request.signed_challenge.as_slice()).unwrap();
+            if !signature.verify(pubkey.as_ref(), request.challenge.as_bytes()) {
+                return Err(Status::unauthenticated("bad signature"));
+            }
+            Ok(Response::new(GenerateAuthTokensResponse {
+                access_token: token(ACCESS_TOKEN),
+                refresh_token: token("refresh-token"),
+            }))
+        }
+
+        async fn refresh_access_token(
+            &self,
+            _request: Request<RefreshAccessTokenRequest>,
+        ) -> Result<Response<RefreshAccessTokenResponse>, Status> {
+            Ok(Response::new(RefreshAccessTokenResponse { access_token: token(ACCESS_TOKEN) }))
+        }
+    }
+
+    #[derive(Default)]
+    pub(crate) struct MockSearcher {
+        pub(crate) bundles: Mutex<Vec<Bundle>>,
+        pub(crate) tip_account_calls: AtomicUsize,
+        pub(crate) tip_accounts: Vec<String>,
+        pub(crate) regions: GetRegionsResponse,
+        pub(crate) next_leader: NextScheduledLeaderResponse,
+    }
+
+    // Mirrors the service methods, which have to return a bare Status
+    #[allow(clippy::result_large_err)]
+    fn authorize<T>(request: &Request<T>) -> Result<(), Status> {
+        let expected = format!("Bearer {ACCESS_TOKEN}");
+        match request.metadata().get("authorization") {
+            Some(value) if value == expected.as_str() => Ok(()),
+            _ => Err(Status::unauthenticated("missing access token")),
+        }
+    }
+
+    #[tonic::async_trait]
+    impl SearcherService for MockSearcher {
+        type SubscribeBundleResultsStream = Pin<Box<dyn Stream<Item = Result<BundleResult, Status>> + Send>>;
+
+        async fn subscribe_bundle_results(
+            &self,
+            _request: Request<SubscribeBundleResultsRequest>,
+        ) -> Result<Response<Self::SubscribeBundleResultsStream>, Status> {
+            Err(Status::unimplemented("not mocked"))
+        }
+
+        async fn send_bundle(&self, request: Request<SendBundleRequest>) -> Result<Response<SendBundleResponse>, Status> {
+            authorize(&request)?;
+            let bundle = request.into_inner().bundle.ok_or_else(|| Status::invalid_argument("missing bundle"))?;
+            let mut bundles = self.bundles.lock().unwrap();
+            bundles.push(bundle);
+            Ok(Response::new(SendBundleResponse { uuid: format!("bundle-{}", bundles.len()) }))
+        }
+
+        async fn get_next_scheduled_leader(
+            &self,
+            request: Request<NextScheduledLeaderRequest>,
+        ) -> Result<Response<NextScheduledLeaderResponse>, Status> {
+            authorize(&request)?;
+            Ok(Response::new(self.next_leader.clone()))
+        }
+
+        async fn get_connected_leaders(
+            &self,
+            _request: Request<ConnectedLeadersRequest>,
+        ) -> Result<Response<ConnectedLeadersResponse>, Status> {
+            Err(Status::unimplemented("not mocked"))
+        }
+
+        async fn get_connected_leaders_regioned(
+            &self,
+            request: Request<ConnectedLeadersRegionedRequest>,
+        ) -> Result<Response<ConnectedLeadersRegionedResponse>, Status> {
+            authorize(&request)?;
+            Ok(Response::new(ConnectedLeadersRegionedResponse::default()))
+        }
+
+        async fn get_tip_accounts(
+            &self,
+            request: Request<GetTipAccountsRequest>,
+        ) -> Result<Response<GetTipAccountsResponse>, Status> {
+            authorize(&request)?;
+            self.tip_account_calls.fetch_add(1, Ordering::SeqCst);
+            Ok(Response::new(GetTipAccountsResponse { accounts: self.tip_accounts.clone() }))
+        }
+
+        async fn get_regions(&self, request: Request<GetRegionsRequest>) -> Result<Response<GetRegionsResponse>, Status> {
+            authorize(&request)?;
+            Ok(Response::new(self.regions.clone()))
+        }
+    }
+
+    // Serves the mock auth and searcher services on one ephemeral local port
+    pub(crate) async fn serve(searcher: Arc<MockSearcher>) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(AuthServiceServer::new(MockAuth))
+                .add_service(SearcherServiceServer::from_arc(searcher))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        addr
+    }
+
+    pub(crate) async fn connect(addr: SocketAddr) -> (BundleClient, Arc<AtomicBool>) {
+        let exit = Arc::new(AtomicBool::new(false));
+        let url = format!("http://{addr}");
+        let (client, _refresh_handle) = BundleClient::connect(url.clone(), url, Arc::new(Keypair::new()), exit.clone())
+            .await
+            .unwrap();
+        (client, exit)
+    }
+
+    #[tokio::test]
+    async fn test_send_bundle_packs_every_transaction() {
+        let searcher = Arc::new(MockSearcher::default());
+        let (client, exit) = connect(serve(searcher.clone()).await).await;
+
+        let payer = Keypair::new();
+        let transactions: Vec<VersionedTransaction> = (1..=3)
+            .map(|lamports| system_transaction::transfer(&payer, &Pubkey::new_unique(), lamports, Hash::new_unique()).into())
+            .collect();
+
+        let uuid = client.send_bundle(&transactions).await.unwrap();
+
+        assert_eq!(uuid, "bundle-1");
+        let bundles = searcher.bundles.lock().unwrap();
+        let packets = &bundles[0].packets;
+        assert_eq!(packets.len(), 3);
+        for (packet, transaction) in packets.iter().zip(&transactions) {
+            assert_eq!(packet.data, bincode::serialize(transaction).unwrap());
+            assert_eq!(packet.meta.as_ref().unwrap().size, packet.data.len() as u64);
+        }
+        exit.store(true, Ordering::Relaxed);
+    }
+
+    #[tokio::test]
+    async fn test_bundle_size_is_checked_before_sending() {
+        let searcher = Arc::new(MockSearcher::default());
+        let (client, exit) = connect(serve(searcher.clone()).await).await;
+        let payer = Keypair::new();
+        let transaction: VersionedTransaction =
+            system_transaction::transfer(&payer, &payer.pubkey(), 1, Hash::new_unique()).into();
+
+        assert!(matches!(client.send_bundle(&[]).await, Err(BundleError::Empty)));
+        assert!(matches!(
+            client.send_bundle(&vec![transaction; MAX_BUNDLE_TRANSACTIONS + 1]).await,
+            Err(BundleError::TooManyTransactions(6))
+        ));
+        assert!(searcher.bundles.lock().unwrap().is_empty());
+        exit.store(true, Ordering::Relaxed);
+    }
+
+    #[tokio::test]
+    async fn test_tip_accounts_are_cached() {
+        let tip_accounts: Vec<Pubkey> = (0..8).map(|_| Pubkey::new_unique()).collect();
+        let searcher = Arc::new(MockSearcher {
+            tip_accounts: tip_accounts.iter().map(Pubkey::to_string).collect(),
+            ..Default::default()
+        });
+        let (client, exit) = connect(serve(searcher.clone()).await).await;
+
+        assert_eq!(client.tip_accounts().await.unwrap(), tip_accounts);
+        assert!(tip_accounts.contains(&client.random_tip_account().await.unwrap()));
+        assert_eq!(searcher.tip_account_calls.load(Ordering::SeqCst), 1);
+
+        let client = client.with_tip_accounts_ttl(Duration::ZERO);
+        client.tip_accounts().await.unwrap();
+        assert_eq!(searcher.tip_account_calls.load(Ordering::SeqCst), 2);
+        exit.store(true, Ordering::Relaxed);
+    }
+}
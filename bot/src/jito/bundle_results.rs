@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use jito_protos::bundle::{self, bundle_result, rejected, BundleResult};
+use jito_protos::searcher::SubscribeBundleResultsRequest;
+use log::{error, warn};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use super::bundle_client::{BundleError, SearcherClient};
+
+/// Identifies the order a bundle was sent for.
+pub type OrderId = u64;
+
+// Results can arrive before `track` learns the bundle's uuid; this many are held back for it
+const MAX_UNMATCHED_RESULTS: usize = 1024;
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RejectReason {
+    /// The bid was too low to win its state auction
+    StateAuctionBidRejected { auction_id: String, simulated_bid_lamports: u64, msg: Option<String> },
+    /// The bid won its auction but not a place in the forwarded batch
+    WinningBatchBidRejected { auction_id: String, simulated_bid_lamports: u64, msg: Option<String> },
+    SimulationFailure { tx_signature: String, msg: Option<String> },
+    InternalError(String),
+    DroppedBundle(String),
+    Unspecified,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DropReason {
+    BlockhashExpired,
+    PartiallyProcessed,
+    NotFinalized,
+}
+
+/// Where an order's bundle is, as reported by the block engine.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BundleStatus {
+    Pending,
+    Accepted { slot: u64, validator_identity: String },
+    Processed { slot: u64, validator_identity: String, bundle_index: u64 },
+    Finalized,
+    Rejected(RejectReason),
+    Dropped(DropReason),
+}
+
+impl BundleStatus {
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, BundleStatus::Finalized | BundleStatus::Rejected(_) | BundleStatus::Dropped(_))
+    }
+
+    // Accepted can be reported once per validator the bundle was forwarded to,
+    // so it never replaces a later stage
+    fn supersedes(&self, current: &BundleStatus) -> bool {
+        match (current, self) {
+            (current, _) if current.is_terminal() => false,
+            (BundleStatus::Processed { .. }, BundleStatus::Accepted { .. }) => false,
+            _ => true,
+        }
+    }
+}
+
+impl From<BundleResult> for BundleStatus {
+    fn from(result: BundleResult) -> Self {
+        match result.result {
+            Some(bundle_result::Result::Accepted(accepted)) => BundleStatus::Accepted {
+                slot: accepted.slot,
+                validator_identity: accepted.validator_identity,
+            },
+            Some(bundle_result::Result::Processed(processed)) => BundleStatus::Processed {
+                slot: processed.slot,
+                validator_identity: processed.validator_identity,
+                bundle_index: processed.bundle_index,
+            },
+            Some(bundle_result::Result::Finalized(_)) => BundleStatus::Finalized,
+            Some(bundle_result::Result::Rejected(rejected)) => BundleStatus::Rejected(match rejected.reason {
+                Some(rejected::Reason::StateAuctionBidRejected(reason)) => RejectReason::StateAuctionBidRejected {
+                    auction_id: reason.auction_id,
+                    simulated_bid_lamports: reason.simulated_bid_lamports,
+                    msg: reason.msg,
+                },
+                Some(rejected::Reason::WinningBatchBidRejected(reason)) => RejectReason::WinningBatchBidRejected {
+                    auction_id: reason.auction_id,
+                    simulated_bid_lamports: reason.simulated_bid_lamports,
+                    msg: reason.msg,
+                },
+                Some(rejected::Reason::SimulationFailure(reason)) => RejectReason::SimulationFailure {
+                    tx_signature: reason.tx_signature,
+                    msg: reason.msg,
+                },
+                Some(rejected::Reason::InternalError(reason)) => RejectReason::InternalError(reason.msg),
+                Some(rejected::Reason::DroppedBundle(reason)) => RejectReason::DroppedBundle(reason.msg),
+                None => RejectReason::Unspecified,
+            }),
+            Some(bundle_result::Result::Dropped(dropped)) => BundleStatus::Dropped(
+                match bundle::DroppedReason::try_from(dropped.reason) {
+                    Ok(bundle::DroppedReason::PartiallyProcessed) => DropReason::PartiallyProcessed,
+                    Ok(bundle::DroppedReason::NotFinalized) => DropReason::NotFinalized,
+                    _ => DropReason::BlockhashExpired,
+                },
+            ),
+            None => BundleStatus::Pending,
+        }
+    }
+}
+
+/// A bundle result as it was received, kept for post-trade analysis.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BundleEvent {
+    pub received_at: SystemTime,
+    pub status: BundleStatus,
+}
+
+struct TrackedOrder {
+    bundle_id: String,
+    status: watch::Sender<BundleStatus>,
+    events: Vec<BundleEvent>,
+}
+
+#[derive(Default)]
+struct TrackerState {
+    bundle_orders: HashMap<String, OrderId>,
+    orders: HashMap<OrderId, TrackedOrder>,
+    unmatched: HashMap<String, Vec<BundleEvent>>,
+    unmatched_count: usize,
+}
+
+/// Follows `SubscribeBundleResults` and maps every result back to the order that sent the bundle.
+#[derive(Default)]
+pub struct BundleResultTracker {
+    state: Mutex<TrackerState>,
+}
+
+/// The live bundle status of one order.
+#[derive(Clone)]
+pub struct OrderStatus {
+    receiver: watch::Receiver<BundleStatus>,
+}
+
+impl OrderStatus {
+    pub fn current(&self) -> BundleStatus {
+        self.receiver.borrow().clone()
+    }
+
+    /// Waits for the next status change and returns it.
+    /// Returns `None` once the tracker stops tracking the order.
+    pub async fn changed(&mut self) -> Option<BundleStatus> {
+        self.receiver.changed().await.ok()?;
+        Some(self.current())
+    }
+
+    /// Waits until the bundle is finalized, rejected or dropped.
+    pub async fn terminal(&mut self) -> Option<BundleStatus> {
+        self.receiver.wait_for(BundleStatus::is_terminal).await.ok().map(|status| status.clone())
+    }
+}
+
+impl BundleResultTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts tracking the bundle `bundle_id` sent for `order_id`.
+    /// Results that arrived before this call are applied right away.
+    pub fn track(&self, order_id: OrderId, bundle_id: String) -> OrderStatus {
+        let mut state = self.state.lock().unwrap();
+        let (sender, receiver) = watch::channel(BundleStatus::Pending);
+        let mut order = TrackedOrder { bundle_id: bundle_id.clone(), status: sender, events: Vec::new() };
+
+        if let Some(events) = state.unmatched.remove(&bundle_id) {
+            state.unmatched_count -= events.len();
+            for event in events {
+                apply(&mut order, event);
+            }
+        }
+        if let Some(previous) = state.orders.insert(order_id, order) {
+            state.bundle_orders.remove(&previous.bundle_id);
+        }
+        state.bundle_orders.insert(bundle_id, order_id);
+        OrderStatus { receiver }
+    }
+
+    /// Stops tracking the order, waking its waiters with `None`.
+    pub fn untrack(&self, order_id: OrderId) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(order) = state.orders.remove(&order_id) {
+            state.bundle_orders.remove(&order.bundle_id);
+        }
+    }
+
+    pub fn status(&self, order_id: OrderId) -> Option<OrderStatus> {
+        let state = self.state.lock().unwrap();
+        state.orders.get(&order_id).map(|order| OrderStatus { receiver: order.status.subscribe() })
+    }
+
+    pub fn events(&self, order_id: OrderId) -> Vec<BundleEvent> {
+        let state = self.state.lock().unwrap();
+        state.orders.get(&order_id).map(|order| order.events.clone()).unwrap_or_default()
+    }
+
+    pub fn handle_result(&self, result: BundleResult) {
+        let bundle_id = result.bundle_id.clone();
+        let event = BundleEvent { received_at: SystemTime::now(), status: result.into() };
+
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        match state.bundle_orders.get(&bundle_id).and_then(|order_id| state.orders.get_mut(order_id)) {
+            Some(order) => apply(order, event),
+            None => {
+                // Bundles sent by other processes sharing the auth key also show up here
+                if state.unmatched_count >= MAX_UNMATCHED_RESULTS {
+                    state.unmatched.clear();
+                    state.unmatched_count = 0;
+                }
+                state.unmatched.entry(bundle_id).or_default().push(event);
+                state.unmatched_count += 1;
+            }
+        }
+    }
+
+    /// Consumes the result stream until it ends or fails.
+    pub async fn subscribe(&self, mut client: SearcherClient) -> Result<(), BundleError> {
+        let mut stream = client
+            .subscribe_bundle_results(SubscribeBundleResultsRequest {})
+            .await?
+            .into_inner();
+        while let Some(result) = stream.message().await? {
+            self.handle_result(result);
+        }
+        Ok(())
+    }
+
+    // Keeps a subscription open, resubscribing whenever the stream drops
+    pub fn spawn(self: &Arc<Self>, client: SearcherClient) -> JoinHandle<()> {
+        let tracker = Arc::clone(self);
+        tokio::spawn(async move {
+            loop {
+                match tracker.subscribe(client.clone()).await {
+                    Ok(()) => warn!("Bundle result stream ended, resubscribing"),
+                    Err(e) => error!("Bundle result stream failed: {}", e),
+                }
+                tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+            }
+        })
+    }
+}
+
+fn apply(order: &mut TrackedOrder, event: BundleEvent) {
+    let status = event.status.clone();
+    order.events.push(event);
+    order.status.send_if_modified(|current| {
+        if status.supersedes(current) && *current != status {
+            *current = status;
+            true
+        } else {
+            false
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jito_protos::bundle::{Accepted, Dropped, Finalized, Processed, Rejected, SimulationFailure};
+
+    fn result(bundle_id: &str, result: bundle_result::Result) -> BundleResult {
+        BundleResult { bundle_id: bundle_id.to_string(), result: Some(result) }
+    }
+
+    fn accepted(slot: u64) -> bundle_result::Result {
+        bundle_result::Result::Accepted(Accepted { slot, validator_identity: "validator".to_string() })
+    }
+
+    #[tokio::test]
+    async fn test_results_drive_order_status() {
+        let tracker = BundleResultTracker::new();
+        let mut status = tracker.track(7, "bundle-a".to_string());
+
+        tracker.handle_result(result("bundle-a", accepted(100)));
+        assert_eq!(status.current(), BundleStatus::Accepted { slot: 100, validator_identity: "validator".to_string() });
+
+        tracker.handle_result(result("bundle-a", bundle_result::Result::Processed(Processed {
+            validator_identity: "validator".to_string(),
+            slot: 101,
+            bundle_index: 2,
+        })));
+        // A late accept from a second validator doesn't undo processing
+        tracker.handle_result(result("bundle-a", accepted(101)));
+        assert!(matches!(status.current(), BundleStatus::Processed { slot: 101, bundle_index: 2, .. }));
+
+        tracker.handle_result(result("bundle-a", bundle_result::Result::Finalized(Finalized {})));
+        assert_eq!(status.terminal().await, Some(BundleStatus::Finalized));
+        assert_eq!(tracker.events(7).len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_result_before_track_is_replayed() {
+        let tracker = BundleResultTracker::new();
+        tracker.handle_result(result("bundle-b", bundle_result::Result::Rejected(Rejected {
+            reason: Some(rejected::Reason::SimulationFailure(SimulationFailure {
+                tx_signature: "sig".to_string(),
+                msg: Some("custom program error: 0x1772".to_string()),
+            })),
+        })));
+
+        let mut status = tracker.track(8, "bundle-b".to_string());
+
+        assert_eq!(status.terminal().await, Some(BundleStatus::Rejected(RejectReason::SimulationFailure {
+            tx_signature: "sig".to_string(),
+            msg: Some("custom program error: 0x1772".to_string()),
+        })));
+    }
+
+    #[tokio::test]
+    async fn test_waiters_see_drop_reason() {
+        let tracker = Arc::new(BundleResultTracker::new());
+        let mut status = tracker.track(9, "bundle-c".to_string());
+
+        let waiter = tokio::spawn(async move { status.terminal().await });
+        tracker.handle_result(result("bundle-c", bundle_result::Result::Dropped(Dropped {
+            reason: bundle::DroppedReason::BlockhashExpired as i32,
+        })));
+
+        assert_eq!(waiter.await.unwrap(), Some(BundleStatus::Dropped(DropReason::BlockhashExpired)));
+        // Other bundles are ignored
+        tracker.handle_result(result("bundle-unknown", accepted(1)));
+        assert_eq!(tracker.events(9).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_untrack_wakes_waiters() {
+        let tracker = BundleResultTracker::new();
+        let mut status = tracker.track(10, "bundle-d".to_string());
+
+        tracker.untrack(10);
+
+        assert_eq!(status.terminal().await, None);
+        assert!(tracker.status(10).is_none());
+    }
+}
@@ -0,0 +1,317 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use jito_protos::searcher::{
+    ConnectedLeadersRegionedRequest, ConnectedLeadersRegionedResponse, GetRegionsRequest, GetRegionsResponse,
+    NextScheduledLeaderRequest, NextScheduledLeaderResponse,
+};
+use log::error;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use super::bundle_client::{BundleError, SearcherClient};
+
+// Bundles for a leader further away than this are held rather than sent
+const DEFAULT_MAX_SLOTS_AHEAD: u64 = 8;
+// The connected leader schedule covers the whole epoch, so it's refreshed far less often than the next leader
+const SCHEDULE_REFRESH_EVERY: u32 = 150;
+
+/// Builds the block engine URL serving `region`, e.g. `ny` or `frankfurt`.
+pub fn block_engine_url(region: &str) -> String {
+    format!("https://{}.mainnet.block-engine.jito.wtf", region)
+}
+
+/// A leader slot of a validator running the Jito client.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JitoLeader {
+    pub slot: u64,
+    pub identity: String,
+    /// Block engine region the validator is connected through
+    pub region: String,
+}
+
+/// What a strategy should do with a bundle right now.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubmissionDecision {
+    /// The upcoming Jito leader is reachable through the current region
+    Send { leader: JitoLeader },
+    /// The upcoming Jito leader is connected through another region; send there instead
+    Redirect { leader: JitoLeader, block_engine_url: String },
+    /// No Jito leader within the window; `next` is the first one known after it, if any
+    Hold { next: Option<JitoLeader> },
+}
+
+/// What the scheduler knows about upcoming Jito leaders.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LeaderSchedule {
+    pub current_slot: u64,
+    pub current_region: Option<String>,
+    pub available_regions: Vec<String>,
+    // Jito leader slots of the epoch, keyed by slot
+    leaders: BTreeMap<u64, JitoLeader>,
+}
+
+impl LeaderSchedule {
+    pub fn observe_slot(&mut self, slot: u64) {
+        self.current_slot = self.current_slot.max(slot);
+        // Past leader slots are of no further use
+        self.leaders = self.leaders.split_off(&self.current_slot);
+    }
+
+    pub fn apply_regions(&mut self, response: GetRegionsResponse) {
+        self.current_region = Some(response.current_region).filter(|region| !region.is_empty());
+        self.available_regions = response.available_regions;
+    }
+
+    pub fn apply_connected_leaders(&mut self, response: ConnectedLeadersRegionedResponse) {
+        self.leaders.clear();
+        for (region, connected) in response.connected_validators {
+            for (identity, slot_list) in connected.connected_validators {
+                for slot in slot_list.slots.into_iter().filter(|&slot| slot >= self.current_slot) {
+                    self.leaders.insert(slot, JitoLeader { slot, identity: identity.clone(), region: region.clone() });
+                }
+            }
+        }
+    }
+
+    pub fn apply_next_leader(&mut self, response: NextScheduledLeaderResponse) {
+        self.observe_slot(response.current_slot);
+        if response.next_leader_identity.is_empty() {
+            return;
+        }
+        let region = if response.next_leader_region.is_empty() {
+            self.current_region.clone().unwrap_or_default()
+        } else {
+            response.next_leader_region
+        };
+        self.leaders.insert(response.next_leader_slot, JitoLeader {
+            slot: response.next_leader_slot,
+            identity: response.next_leader_identity,
+            region,
+        });
+    }
+
+    /// The first Jito leader slot at or after the current slot.
+    pub fn next_leader(&self) -> Option<&JitoLeader> {
+        self.leaders.range(self.current_slot..).next().map(|(_, leader)| leader)
+    }
+
+    pub fn is_jito_leader(&self, slot: u64) -> bool {
+        self.leaders.contains_key(&slot)
+    }
+
+    pub fn decision(&self, max_slots_ahead: u64) -> SubmissionDecision {
+        let Some(leader) = self.next_leader().cloned() else {
+            return SubmissionDecision::Hold { next: None };
+        };
+        if leader.slot > self.current_slot + max_slots_ahead {
+            return SubmissionDecision::Hold { next: Some(leader) };
+        }
+        let reachable_here = self.current_region.as_ref().is_none_or(|region| *region == leader.region);
+        // Only redirect to regions the block engine says are up
+        let region_available = self.available_regions.is_empty() || self.available_regions.contains(&leader.region);
+        if reachable_here || !region_available {
+            SubmissionDecision::Send { leader }
+        } else {
+            let block_engine_url = block_engine_url(&leader.region);
+            SubmissionDecision::Redirect { leader, block_engine_url }
+        }
+    }
+}
+
+/// Tracks upcoming Jito leaders through the SearcherService and tells strategies when to submit.
+///
+/// `regions` is the same region list given to shredstream as `desired_regions`;
+/// leaders are only looked up in those regions.
+pub struct LeaderScheduler {
+    client: SearcherClient,
+    regions: Vec<String>,
+    max_slots_ahead: u64,
+    schedule: RwLock<LeaderSchedule>,
+    decisions: watch::Sender<SubmissionDecision>,
+}
+
+impl LeaderScheduler {
+    pub fn new(client: SearcherClient, regions: Vec<String>) -> Self {
+        LeaderScheduler {
+            client,
+            regions,
+            max_slots_ahead: DEFAULT_MAX_SLOTS_AHEAD,
+            schedule: RwLock::new(LeaderSchedule::default()),
+            decisions: watch::channel(SubmissionDecision::Hold { next: None }).0,
+        }
+    }
+
+    pub fn with_max_slots_ahead(mut self, max_slots_ahead: u64) -> Self {
+        self.max_slots_ahead = max_slots_ahead;
+        self
+    }
+
+    pub fn schedule(&self) -> LeaderSchedule {
+        self.schedule.read().unwrap().clone()
+    }
+
+    pub fn decision(&self) -> SubmissionDecision {
+        self.decisions.borrow().clone()
+    }
+
+    /// Notified whenever the submission decision changes.
+    pub fn subscribe(&self) -> watch::Receiver<SubmissionDecision> {
+        self.decisions.subscribe()
+    }
+
+    /// Waits until a Jito leader is within reach and returns where to send.
+    pub async fn wait_for_leader(&self) -> SubmissionDecision {
+        let mut decisions = self.subscribe();
+        // The sender lives as long as self, so this can't fail
+        let decision = decisions
+            .wait_for(|decision| !matches!(decision, SubmissionDecision::Hold { .. }))
+            .await
+            .expect("scheduler dropped");
+        decision.clone()
+    }
+
+    // Advances the current slot from a faster source such as shredstream entries
+    pub fn observe_slot(&self, slot: u64) {
+        self.update(|schedule| schedule.observe_slot(slot));
+    }
+
+    pub async fn refresh_schedule(&self) -> Result<(), BundleError> {
+        let mut client = self.client.clone();
+        let regions = client.get_regions(GetRegionsRequest {}).await?.into_inner();
+        let leaders = client
+            .get_connected_leaders_regioned(ConnectedLeadersRegionedRequest { regions: self.regions.clone() })
+            .await?
+            .into_inner();
+        self.update(|schedule| {
+            schedule.apply_regions(regions);
+            schedule.apply_connected_leaders(leaders);
+        });
+        Ok(())
+    }
+
+    pub async fn refresh_next_leader(&self) -> Result<(), BundleError> {
+        let next_leader = self.client.clone()
+            .get_next_scheduled_leader(NextScheduledLeaderRequest { regions: self.regions.clone() })
+            .await?
+            .into_inner();
+        self.update(|schedule| schedule.apply_next_leader(next_leader));
+        Ok(())
+    }
+
+    // Polls the next leader every `interval` and the full schedule every few hundred polls
+    pub fn spawn(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let scheduler = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            let mut polls = 0;
+            loop {
+                ticker.tick().await;
+                if polls % SCHEDULE_REFRESH_EVERY == 0 {
+                    if let Err(e) = scheduler.refresh_schedule().await {
+                        error!("Failed to refresh Jito leader schedule: {}", e);
+                    }
+                }
+                polls = polls.wrapping_add(1);
+                if let Err(e) = scheduler.refresh_next_leader().await {
+                    error!("Failed to fetch next Jito leader: {}", e);
+                }
+            }
+        })
+    }
+
+    fn update(&self, apply: impl FnOnce(&mut LeaderSchedule)) {
+        let decision = {
+            let mut schedule = self.schedule.write().unwrap();
+            apply(&mut schedule);
+            schedule.decision(self.max_slots_ahead)
+        };
+        self.decisions.send_if_modified(|current| {
+            let changed = *current != decision;
+            *current = decision;
+            changed
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use jito_protos::searcher::{ConnectedLeadersResponse, SlotList};
+
+    fn connected(regions: &[(&str, &str, &[u64])]) -> ConnectedLeadersRegionedResponse {
+        let mut connected_validators: HashMap<String, ConnectedLeadersResponse> = HashMap::new();
+        for (region, identity, slots) in regions {
+            connected_validators.entry(region.to_string()).or_default()
+                .connected_validators
+                .insert(identity.to_string(), SlotList { slots: slots.to_vec() });
+        }
+        ConnectedLeadersRegionedResponse { connected_validators }
+    }
+
+    fn schedule() -> LeaderSchedule {
+        let mut schedule = LeaderSchedule::default();
+        schedule.apply_regions(GetRegionsResponse {
+            current_region: "ny".to_string(),
+            available_regions: vec!["ny".to_string(), "frankfurt".to_string()],
+        });
+        schedule.observe_slot(100);
+        schedule.apply_connected_leaders(connected(&[
+            ("ny", "ny-validator", &[96, 104, 105, 106, 107]),
+            ("frankfurt", "fra-validator", &[120, 121, 122, 123]),
+            ("tokyo", "tyo-validator", &[112]),
+        ]));
+        schedule
+    }
+
+    #[test]
+    fn test_send_when_leader_is_near_and_local() {
+        let schedule = schedule();
+
+        assert!(!schedule.is_jito_leader(96));
+        assert!(schedule.is_jito_leader(104));
+        assert!(matches!(
+            schedule.decision(8),
+            SubmissionDecision::Send { leader } if leader.slot == 104 && leader.identity == "ny-validator"
+        ));
+        assert!(matches!(schedule.decision(3), SubmissionDecision::Hold { next: Some(leader) } if leader.slot == 104));
+    }
+
+    #[test]
+    fn test_redirect_to_leader_region() {
+        let mut schedule = schedule();
+        schedule.observe_slot(115);
+
+        assert_eq!(schedule.decision(8), SubmissionDecision::Redirect {
+            leader: JitoLeader { slot: 120, identity: "fra-validator".to_string(), region: "frankfurt".to_string() },
+            block_engine_url: "https://frankfurt.mainnet.block-engine.jito.wtf".to_string(),
+        });
+    }
+
+    #[test]
+    fn test_unavailable_region_falls_back_to_current() {
+        let mut schedule = schedule();
+        schedule.observe_slot(108);
+
+        // Tokyo isn't an available region
+        assert!(matches!(schedule.decision(8), SubmissionDecision::Send { leader } if leader.region == "tokyo"));
+    }
+
+    #[test]
+    fn test_next_leader_response_extends_schedule() {
+        let mut schedule = schedule();
+        schedule.apply_next_leader(NextScheduledLeaderResponse {
+            current_slot: 124,
+            next_leader_slot: 200,
+            next_leader_identity: "late-validator".to_string(),
+            next_leader_region: String::new(),
+        });
+
+        assert_eq!(schedule.current_slot, 124);
+        assert_eq!(schedule.next_leader().unwrap().region, "ny");
+        assert!(matches!(schedule.decision(8), SubmissionDecision::Hold { next: Some(leader) } if leader.slot == 200));
+
+        schedule.observe_slot(201);
+        assert_eq!(schedule.decision(8), SubmissionDecision::Hold { next: None });
+    }
+}
@@ -0,0 +1,4 @@
+pub mod bundle_client;
+pub mod bundle_results;
+pub mod leader_schedule;
+pub mod tip_oracle;
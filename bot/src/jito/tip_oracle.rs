@@ -0,0 +1,276 @@
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::sync::RwLock;
+use dotenv::dotenv;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::system_program;
+use solana_sdk::transaction::VersionedTransaction;
+use crate::solana::transaction::{DecodedInstruction, DecodedTransaction};
+
+// Slots of history kept for percentile queries, a little over ten minutes
+const DEFAULT_MAX_SLOTS: u64 = 1_500;
+// SystemInstruction::Transfer
+const SYSTEM_TRANSFER: u32 = 2;
+// Used when JITO_TIP_SLOTS is unset, about a minute of tips
+const DEFAULT_TIP_SLOTS: u64 = 150;
+
+/// How much to tip the block engine on a trade.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TipPolicy {
+    Fixed(u64),
+    /// Tip the given percentile of tips that landed over the last `slots` slots
+    Percentile { percentile: u8, slots: u64 },
+    /// Like `Percentile`, clamped to `[min, max]`; `min` is also used before any tips were observed
+    CappedPercentile { percentile: u8, slots: u64, min: u64, max: u64 },
+}
+
+impl TipPolicy {
+    // JITO_TIP_PERCENTILE tips that percentile over the last JITO_TIP_SLOTS slots, capped to
+    // JITO_TIP_MIN..=JITO_TIP_MAX when JITO_TIP_MAX is set; otherwise JITO_TIP_AMOUNT is tipped
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        dotenv().ok();
+        let Ok(percentile) = env::var("JITO_TIP_PERCENTILE") else {
+            let lamports = env::var("JITO_TIP_AMOUNT").map_err(|_| "JITO_TIP_AMOUNT not set in environment")?.parse()?;
+            return Ok(TipPolicy::Fixed(lamports));
+        };
+        let percentile = percentile.parse()?;
+        let slots = match env::var("JITO_TIP_SLOTS") {
+            Ok(slots) => slots.parse()?,
+            Err(_) => DEFAULT_TIP_SLOTS,
+        };
+        match env::var("JITO_TIP_MAX") {
+            Ok(max) => {
+                let min = env::var("JITO_TIP_MIN").map_err(|_| "JITO_TIP_MIN not set in environment")?.parse()?;
+                Ok(TipPolicy::capped_percentile(percentile, slots, min, max.parse()?)?)
+            }
+            Err(_) => Ok(TipPolicy::Percentile { percentile, slots }),
+        }
+    }
+
+    /// Percentile tip capped to `min..=max`, refused when `min` exceeds `max`.
+    pub fn capped_percentile(percentile: u8, slots: u64, min: u64, max: u64) -> Result<Self, String> {
+        if min > max {
+            return Err(format!("JITO_TIP_MIN {} exceeds JITO_TIP_MAX {}", min, max));
+        }
+        Ok(TipPolicy::CappedPercentile { percentile, slots, min, max })
+    }
+
+    /// The tip in lamports, or `None` when a percentile is asked for and no tips were observed.
+    pub fn resolve(&self, oracle: &TipOracle) -> Option<u64> {
+        match *self {
+            TipPolicy::Fixed(lamports) => Some(lamports),
+            TipPolicy::Percentile { percentile, slots } => oracle.percentile(percentile, slots),
+            TipPolicy::CappedPercentile { percentile, slots, min, max } => {
+                Some(oracle.percentile(percentile, slots).map_or(min, |lamports| lamports.clamp(min, max)))
+            }
+        }
+    }
+}
+
+/// Rolling record of tips that landed, fed from shredstream entries, decoded Kafka transactions or
+/// any other transaction source.
+///
+/// The block engine has eight tip accounts; they have to be set from `BundleClient::tip_accounts`
+/// before any tips are recognised.
+pub struct TipOracle {
+    tip_accounts: RwLock<HashSet<Pubkey>>,
+    max_slots: u64,
+    // Tip paid by each tipping transaction, keyed by slot
+    tips: RwLock<BTreeMap<u64, Vec<u64>>>,
+}
+
+impl Default for TipOracle {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_SLOTS)
+    }
+}
+
+impl TipOracle {
+    pub fn new(max_slots: u64) -> Self {
+        TipOracle {
+            tip_accounts: RwLock::new(HashSet::new()),
+            max_slots,
+            tips: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn set_tip_accounts(&self, tip_accounts: impl IntoIterator<Item = Pubkey>) {
+        *self.tip_accounts.write().unwrap() = tip_accounts.into_iter().collect();
+    }
+
+    // Records the total of the transaction's system transfers to tip accounts, if it has any.
+    // Only static account keys are checked; tip accounts aren't expected in lookup tables.
+    pub fn observe_transaction(&self, slot: u64, transaction: &VersionedTransaction) {
+        let account_keys = transaction.message.static_account_keys();
+        let tip_accounts = self.tip_accounts.read().unwrap();
+        let tip: u64 = transaction.message.instructions().iter()
+            .filter(|instruction| account_keys.get(instruction.program_id_index as usize) == Some(&system_program::id()))
+            .filter_map(|instruction| {
+                let destination = account_keys.get(*instruction.accounts.get(1)? as usize)?;
+                if !tip_accounts.contains(destination) {
+                    return None;
+                }
+                system_transfer_lamports(&instruction.data)
+            })
+            .sum();
+        drop(tip_accounts);
+        if tip > 0 {
+            self.record(slot, tip);
+        }
+    }
+
+    // Records the total of an executed transaction's system transfers to tip accounts, inner ones included.
+    // Failed transactions are skipped, their transfers were rolled back.
+    pub fn observe_decoded(&self, transaction: &DecodedTransaction) {
+        if transaction.error.is_some() {
+            return;
+        }
+        let tip_accounts = self.tip_accounts.read().unwrap();
+        let tip: u64 = transaction.instructions.iter()
+            .filter_map(|instruction| match instruction {
+                DecodedInstruction::SystemTransfer(transfer) if tip_accounts.contains(&transfer.to_pubkey) => Some(transfer.lamports),
+                _ => None,
+            })
+            .sum();
+        drop(tip_accounts);
+        if tip > 0 {
+            self.record(transaction.slot, tip);
+        }
+    }
+
+    pub fn record(&self, slot: u64, lamports: u64) {
+        let mut tips = self.tips.write().unwrap();
+        tips.entry(slot).or_default().push(lamports);
+        let newest = *tips.keys().next_back().unwrap();
+        if newest >= self.max_slots {
+            *tips = tips.split_off(&(newest - self.max_slots + 1));
+        }
+    }
+
+    pub fn newest_slot(&self) -> Option<u64> {
+        self.tips.read().unwrap().keys().next_back().copied()
+    }
+
+    /// Nearest-rank `percentile` (0-100) of tips landed in the last `slots` slots up to the newest one seen.
+    pub fn percentile(&self, percentile: u8, slots: u64) -> Option<u64> {
+        if slots == 0 {
+            return None;
+        }
+        let tips = self.tips.read().unwrap();
+        let newest = *tips.keys().next_back()?;
+        let oldest = newest.saturating_sub(slots - 1);
+        let mut window: Vec<u64> = tips.range(oldest..=newest).flat_map(|(_, tips)| tips.iter().copied()).collect();
+        if window.is_empty() {
+            return None;
+        }
+        window.sort_unstable();
+        let rank = (percentile.min(100) as usize * window.len()).div_ceil(100);
+        Some(window[rank.saturating_sub(1)])
+    }
+}
+
+fn system_transfer_lamports(data: &[u8]) -> Option<u64> {
+    if data.len() != 12 || u32::from_le_bytes(data[..4].try_into().ok()?) != SYSTEM_TRANSFER {
+        return None;
+    }
+    Some(u64::from_le_bytes(data[4..12].try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::{Keypair, Signer};
+    use solana_sdk::system_instruction;
+    use crate::solana::test_utils::signed_transaction;
+
+    fn transfers(transfers: &[(Pubkey, u64)]) -> VersionedTransaction {
+        let payer = Keypair::new();
+        let instructions: Vec<_> = transfers.iter()
+            .map(|(to, lamports)| system_instruction::transfer(&payer.pubkey(), to, *lamports))
+            .collect();
+        signed_transaction(&payer, &instructions)
+    }
+
+    #[test]
+    fn test_only_transfers_to_tip_accounts_count() {
+        let oracle = TipOracle::default();
+        let tip_accounts: Vec<Pubkey> = (0..8).map(|_| Pubkey::new_unique()).collect();
+        let transaction = transfers(&[(tip_accounts[0], 10_000), (tip_accounts[7], 2_000), (Pubkey::new_unique(), 1)]);
+
+        oracle.observe_transaction(10, &transaction);
+        oracle.observe_transaction(10, &transfers(&[(Pubkey::new_unique(), 5_000_000)]));
+        assert_eq!(oracle.newest_slot(), None);
+
+        oracle.set_tip_accounts(tip_accounts);
+        oracle.observe_transaction(10, &transaction);
+        assert_eq!(oracle.percentile(100, 1), Some(12_000));
+    }
+
+    #[test]
+    fn test_decoded_transfers_to_tip_accounts_count() {
+        use crate::solana::transaction::SystemTransferInstruction;
+        let oracle = TipOracle::default();
+        let tip_account = Pubkey::new_unique();
+        oracle.set_tip_accounts([tip_account]);
+        let transfer = |to_pubkey, lamports| DecodedInstruction::SystemTransfer(SystemTransferInstruction {
+            from_pubkey: Pubkey::new_unique(),
+            to_pubkey,
+            lamports,
+        });
+        let mut transaction = DecodedTransaction::new_empty();
+        transaction.slot = 10;
+        transaction.instructions = vec![transfer(tip_account, 10_000), transfer(Pubkey::new_unique(), 1), transfer(tip_account, 2_000)];
+
+        transaction.error = Some("custom program error: 0x1".to_string());
+        oracle.observe_decoded(&transaction);
+        assert_eq!(oracle.newest_slot(), None);
+
+        transaction.error = None;
+        oracle.observe_decoded(&transaction);
+        assert_eq!(oracle.percentile(100, 1), Some(12_000));
+    }
+
+    #[test]
+    fn test_percentiles_over_slot_window() {
+        let oracle = TipOracle::default();
+        for (slot, lamports) in [(1, 100), (2, 1_000), (2, 2_000), (3, 3_000), (3, 4_000)] {
+            oracle.record(slot, lamports);
+        }
+
+        assert_eq!(oracle.percentile(50, 2), Some(2_000));
+        assert_eq!(oracle.percentile(75, 2), Some(3_000));
+        assert_eq!(oracle.percentile(0, 3), Some(100));
+        assert_eq!(oracle.percentile(100, 1), Some(4_000));
+        assert_eq!(oracle.percentile(50, 0), None);
+    }
+
+    #[test]
+    fn test_old_slots_are_pruned() {
+        let oracle = TipOracle::new(10);
+        oracle.record(1, 1_000_000);
+        oracle.record(11, 1_000);
+
+        assert_eq!(oracle.percentile(100, 100), Some(1_000));
+    }
+
+    #[test]
+    fn test_policies() {
+        let oracle = TipOracle::default();
+        let capped = TipPolicy::capped_percentile(75, 10, 5_000, 50_000).unwrap();
+        let percentile = TipPolicy::Percentile { percentile: 75, slots: 10 };
+
+        assert_eq!(TipPolicy::Fixed(1_000).resolve(&oracle), Some(1_000));
+        assert_eq!(percentile.resolve(&oracle), None);
+        assert_eq!(capped.resolve(&oracle), Some(5_000));
+
+        oracle.record(5, 1_000_000);
+        assert_eq!(percentile.resolve(&oracle), Some(1_000_000));
+        assert_eq!(capped.resolve(&oracle), Some(50_000));
+    }
+
+    #[test]
+    fn test_inverted_cap_is_refused() {
+        assert!(TipPolicy::capped_percentile(75, 10, 50_000, 5_000).is_err());
+        assert!(TipPolicy::capped_percentile(75, 10, 5_000, 5_000).is_ok());
+    }
+}
@@ -1,5 +1,6 @@
 pub mod solana;
 pub mod financial_services;
+pub mod jito;
 
 pub fn hello() {
     println!("Hello from the library!");
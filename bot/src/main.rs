@@ -1,4 +1,4 @@
-mod solana;
+use bot::solana;
 
 fn main() {
     solana::wallet::main();
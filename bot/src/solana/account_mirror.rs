@@ -0,0 +1,441 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Mutex;
+use solana_sdk::pubkey::Pubkey;
+use tokio::sync::mpsc;
+use crate::solana::order::Commitment;
+use crate::solana::transaction::message::{SlotStatus, SlotStatusEvent, UpdateAccountEvent};
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum AccountMirrorError {
+    #[error("Account key has {0} bytes, expected 32")]
+    InvalidPubkey(usize),
+    #[error("Unknown slot status {0}")]
+    UnknownSlotStatus(i32),
+}
+
+/// Maps a Kafka slot status to the commitment it grants; rooted slots are finalized.
+pub fn slot_commitment(event: &SlotStatusEvent) -> Result<Commitment, AccountMirrorError> {
+    match SlotStatus::from_i32(event.status) {
+        Some(SlotStatus::Processed) => Ok(Commitment::Processed),
+        Some(SlotStatus::Confirmed) => Ok(Commitment::Confirmed),
+        Some(SlotStatus::Rooted) => Ok(Commitment::Finalized),
+        None => Err(AccountMirrorError::UnknownSlotStatus(event.status)),
+    }
+}
+
+/// One write of an account, as streamed by the Kafka plugin.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountVersion {
+    pub pubkey: Pubkey,
+    pub slot: u64,
+    pub write_version: u64,
+    pub lamports: u64,
+    pub owner: Pubkey,
+    pub data: Vec<u8>,
+}
+
+impl TryFrom<UpdateAccountEvent> for AccountVersion {
+    type Error = AccountMirrorError;
+
+    fn try_from(event: UpdateAccountEvent) -> Result<Self, Self::Error> {
+        let pubkey = |bytes: &[u8]| Pubkey::try_from(bytes).map_err(|_| AccountMirrorError::InvalidPubkey(bytes.len()));
+        Ok(AccountVersion {
+            pubkey: pubkey(&event.pubkey)?,
+            slot: event.slot,
+            write_version: event.write_version,
+            lamports: event.lamports,
+            owner: pubkey(&event.owner)?,
+            data: event.data,
+        })
+    }
+}
+
+/// Which accounts a subscription covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AccountFilter {
+    Account(Pubkey),
+    /// Every account owned by the program
+    Owner(Pubkey),
+}
+
+impl AccountFilter {
+    fn matches(&self, version: &AccountVersion) -> bool {
+        match self {
+            AccountFilter::Account(pubkey) => version.pubkey == *pubkey,
+            AccountFilter::Owner(owner) => version.owner == *owner,
+        }
+    }
+}
+
+/// A decoded account version delivered to a subscriber once it reached the subscribed commitment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountUpdate<T> {
+    pub pubkey: Pubkey,
+    pub slot: u64,
+    pub write_version: u64,
+    pub value: T,
+}
+
+struct Subscriber {
+    filter: AccountFilter,
+    commitment: Commitment,
+    // Decodes and sends, returning false once the receiver is gone
+    deliver: Box<dyn FnMut(&AccountVersion) -> bool + Send>,
+}
+
+#[derive(Default)]
+struct MirrorState {
+    watched: HashSet<AccountFilter>,
+    // Newest write of each account per slot; several forks may hold versions at once
+    accounts: HashMap<Pubkey, BTreeMap<u64, AccountVersion>>,
+    slot_accounts: BTreeMap<u64, HashSet<Pubkey>>,
+    slot_status: BTreeMap<u64, Commitment>,
+    root: u64,
+    subscribers: Vec<Subscriber>,
+}
+
+impl MirrorState {
+    fn is_watched(&self, version: &AccountVersion) -> bool {
+        self.watched.iter().any(|filter| filter.matches(version))
+    }
+
+    fn status(&self, slot: u64) -> Commitment {
+        // Versions at or below the root that survived pruning are on the rooted fork
+        if slot <= self.root {
+            return Commitment::Finalized;
+        }
+        self.slot_status.get(&slot).copied().unwrap_or(Commitment::Processed)
+    }
+
+    fn newest(&self, pubkey: &Pubkey, commitment: Commitment) -> Option<&AccountVersion> {
+        self.accounts.get(pubkey)?
+            .values()
+            .rev()
+            .find(|version| self.status(version.slot) >= commitment)
+    }
+
+    fn notify(&mut self, version: &AccountVersion, commitment: Commitment) {
+        self.subscribers.retain_mut(|subscriber| {
+            if subscriber.commitment != commitment || !subscriber.filter.matches(version) {
+                return true;
+            }
+            (subscriber.deliver)(version)
+        });
+    }
+
+    // Drops versions from slots at or below the root that never became confirmed, which were on
+    // abandoned forks, and everything the newest surviving version at or below the root supersedes
+    fn prune(&mut self, root: u64) {
+        let (slot_status, previous_root) = (&self.slot_status, self.root);
+        let on_rooted_fork = |slot: u64| {
+            slot <= previous_root || slot_status.get(&slot).is_some_and(|status| *status >= Commitment::Confirmed)
+        };
+        self.accounts.retain(|_, versions| {
+            let newer = versions.split_off(&(root + 1));
+            let rooted = std::mem::take(versions).into_iter().rev().find(|(slot, _)| on_rooted_fork(*slot));
+            *versions = newer;
+            versions.extend(rooted);
+            !versions.is_empty()
+        });
+        self.slot_accounts = self.slot_accounts.split_off(&(root + 1));
+        self.slot_status = self.slot_status.split_off(&(root + 1));
+        self.root = root;
+    }
+}
+
+/// Mirror of watched accounts fed by the Kafka account-update and slot-status topics.
+///
+/// Every write of a watched account is kept per slot, ordered by `write_version` within a slot,
+/// until the slot is rooted or abandoned. Reads and subscriptions pick the newest version at the
+/// requested commitment, so processed state from a minority fork never shows up as confirmed.
+#[derive(Default)]
+pub struct AccountMirror {
+    state: Mutex<MirrorState>,
+}
+
+impl AccountMirror {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn watch(&self, filter: AccountFilter) {
+        self.state.lock().unwrap().watched.insert(filter);
+    }
+
+    pub fn unwatch(&self, filter: &AccountFilter) {
+        let mut state = self.state.lock().unwrap();
+        state.watched.remove(filter);
+        state.subscribers.retain(|subscriber| subscriber.filter != *filter);
+    }
+
+    pub fn root(&self) -> u64 {
+        self.state.lock().unwrap().root
+    }
+
+    /// Newest version of the account that reached `commitment`.
+    pub fn get(&self, pubkey: &Pubkey, commitment: Commitment) -> Option<AccountVersion> {
+        self.state.lock().unwrap().newest(pubkey, commitment).cloned()
+    }
+
+    pub fn get_decoded<T>(
+        &self,
+        pubkey: &Pubkey,
+        commitment: Commitment,
+        decode: impl Fn(&AccountVersion) -> Option<T>,
+    ) -> Option<T> {
+        self.state.lock().unwrap().newest(pubkey, commitment).and_then(decode)
+    }
+
+    /// Watches `filter` and sends every version that reaches `commitment` and that `decode` accepts.
+    /// Versions already mirrored at that commitment are sent straight away.
+    pub fn subscribe<T: Send + 'static>(
+        &self,
+        filter: AccountFilter,
+        commitment: Commitment,
+        decode: impl Fn(&AccountVersion) -> Option<T> + Send + 'static,
+    ) -> mpsc::UnboundedReceiver<AccountUpdate<T>> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let mut deliver = move |version: &AccountVersion| match decode(version) {
+            Some(value) => sender.send(AccountUpdate {
+                pubkey: version.pubkey,
+                slot: version.slot,
+                write_version: version.write_version,
+                value,
+            }).is_ok(),
+            None => !sender.is_closed(),
+        };
+
+        let mut state = self.state.lock().unwrap();
+        state.watched.insert(filter);
+        let current: Vec<AccountVersion> = state.accounts.keys()
+            .filter_map(|pubkey| state.newest(pubkey, commitment))
+            .filter(|version| filter.matches(version))
+            .cloned()
+            .collect();
+        if current.iter().all(&mut deliver) {
+            state.subscribers.push(Subscriber { filter, commitment, deliver: Box::new(deliver) });
+        }
+        receiver
+    }
+
+    /// Records an account write. Returns whether it was kept: writes of unwatched accounts, writes
+    /// at or below the root and writes older than the one already held for the slot are dropped.
+    pub fn apply_update(&self, event: UpdateAccountEvent) -> Result<bool, AccountMirrorError> {
+        let version = AccountVersion::try_from(event)?;
+        let mut state = self.state.lock().unwrap();
+        if version.slot <= state.root || !state.is_watched(&version) {
+            return Ok(false);
+        }
+        let versions = state.accounts.entry(version.pubkey).or_default();
+        if versions.get(&version.slot).is_some_and(|held| held.write_version >= version.write_version) {
+            return Ok(false);
+        }
+        versions.insert(version.slot, version.clone());
+        state.slot_accounts.entry(version.slot).or_default().insert(version.pubkey);
+
+        // A write can arrive after its slot was already confirmed
+        let status = state.status(version.slot);
+        for commitment in [Commitment::Processed, Commitment::Confirmed, Commitment::Finalized] {
+            if commitment <= status && state.newest(&version.pubkey, commitment) == Some(&version) {
+                state.notify(&version, commitment);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Advances a slot's commitment and notifies subscribers of the writes that reached theirs.
+    pub fn apply_slot_status(&self, event: &SlotStatusEvent) -> Result<(), AccountMirrorError> {
+        let commitment = slot_commitment(event)?;
+        let mut state = self.state.lock().unwrap();
+        if event.slot <= state.root || commitment == Commitment::Processed {
+            return Ok(());
+        }
+        let previous = state.status(event.slot);
+        if commitment <= previous {
+            return Ok(());
+        }
+        state.slot_status.insert(event.slot, commitment);
+
+        let pubkeys: Vec<Pubkey> = state.slot_accounts.get(&event.slot).into_iter().flatten().copied().collect();
+        for pubkey in pubkeys {
+            for level in [Commitment::Confirmed, Commitment::Finalized] {
+                if level <= previous || level > commitment {
+                    continue;
+                }
+                // Only if no newer write already reached this level
+                if let Some(version) = state.newest(&pubkey, level).filter(|version| version.slot == event.slot).cloned() {
+                    state.notify(&version, level);
+                }
+            }
+        }
+        if commitment == Commitment::Finalized {
+            state.prune(event.slot);
+        }
+        Ok(())
+    }
+}
+
+/// The parts of a Raydium AMM v4 pool needed to price it; the reserves are the vault balances
+/// less the pnl still owed to the pool owner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RaydiumAmmPool {
+    pub status: u64,
+    pub base_decimals: u64,
+    pub quote_decimals: u64,
+    pub swap_fee_numerator: u64,
+    pub swap_fee_denominator: u64,
+    pub base_need_take_pnl: u64,
+    pub quote_need_take_pnl: u64,
+    pub pool_open_time: u64,
+    pub base_vault: Pubkey,
+    pub quote_vault: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub lp_mint: Pubkey,
+    pub open_orders: Pubkey,
+    pub market_id: Pubkey,
+    pub lp_reserve: u64,
+}
+
+impl RaydiumAmmPool {
+    // AmmInfo: 32 u64 parameters, swap statistics up to byte 336, then 12 pubkeys and the lp reserve
+    const LEN: usize = 752;
+    const PUBKEYS_OFFSET: usize = 336;
+
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() != Self::LEN {
+            return None;
+        }
+        let u64_at = |index: usize| u64::from_le_bytes(data[index * 8..index * 8 + 8].try_into().unwrap());
+        let pubkey_at = |index: usize| {
+            let offset = Self::PUBKEYS_OFFSET + index * 32;
+            Pubkey::try_from(&data[offset..offset + 32]).unwrap()
+        };
+        Some(RaydiumAmmPool {
+            status: u64_at(0),
+            base_decimals: u64_at(4),
+            quote_decimals: u64_at(5),
+            swap_fee_numerator: u64_at(22),
+            swap_fee_denominator: u64_at(23),
+            base_need_take_pnl: u64_at(24),
+            quote_need_take_pnl: u64_at(25),
+            pool_open_time: u64_at(28),
+            base_vault: pubkey_at(0),
+            quote_vault: pubkey_at(1),
+            base_mint: pubkey_at(2),
+            quote_mint: pubkey_at(3),
+            lp_mint: pubkey_at(4),
+            open_orders: pubkey_at(5),
+            market_id: pubkey_at(6),
+            lp_reserve: u64_at((Self::PUBKEYS_OFFSET + 12 * 32) / 8),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana::transaction::TokenAccount;
+
+    fn update(pubkey: Pubkey, owner: Pubkey, slot: u64, write_version: u64, data: &[u8]) -> UpdateAccountEvent {
+        UpdateAccountEvent {
+            slot,
+            pubkey: pubkey.to_bytes().to_vec(),
+            lamports: 1,
+            owner: owner.to_bytes().to_vec(),
+            data: data.to_vec(),
+            write_version,
+            ..Default::default()
+        }
+    }
+
+    fn status(slot: u64, status: SlotStatus) -> SlotStatusEvent {
+        SlotStatusEvent { slot, parent: slot.saturating_sub(1), status: status as i32 }
+    }
+
+    fn data(mirror: &AccountMirror, pubkey: &Pubkey, commitment: Commitment) -> Option<Vec<u8>> {
+        mirror.get(pubkey, commitment).map(|version| version.data)
+    }
+
+    #[test]
+    fn test_versions_ordered_by_slot_and_write_version() {
+        let mirror = AccountMirror::new();
+        let (account, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        assert_eq!(mirror.apply_update(update(account, owner, 10, 1, &[1])), Ok(false));
+        mirror.watch(AccountFilter::Account(account));
+        assert_eq!(mirror.apply_update(update(account, owner, 10, 5, &[5])), Ok(true));
+        assert_eq!(mirror.apply_update(update(account, owner, 10, 4, &[4])), Ok(false));
+        assert_eq!(mirror.apply_update(update(account, owner, 9, 9, &[9])), Ok(true));
+
+        assert_eq!(data(&mirror, &account, Commitment::Processed), Some(vec![5]));
+        assert_eq!(data(&mirror, &account, Commitment::Confirmed), None);
+        assert!(matches!(
+            mirror.apply_update(UpdateAccountEvent { pubkey: vec![0; 5], ..Default::default() }),
+            Err(AccountMirrorError::InvalidPubkey(5))
+        ));
+    }
+
+    #[test]
+    fn test_commitment_levels_and_abandoned_forks() {
+        let mirror = AccountMirror::new();
+        let (account, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        mirror.watch(AccountFilter::Owner(owner));
+        mirror.apply_update(update(account, owner, 10, 1, &[10])).unwrap();
+        // Slot 11 is on a fork that gets abandoned
+        mirror.apply_update(update(account, owner, 11, 2, &[11])).unwrap();
+        mirror.apply_update(update(account, owner, 12, 3, &[12])).unwrap();
+
+        mirror.apply_slot_status(&status(10, SlotStatus::Confirmed)).unwrap();
+        assert_eq!(data(&mirror, &account, Commitment::Processed), Some(vec![12]));
+        assert_eq!(data(&mirror, &account, Commitment::Confirmed), Some(vec![10]));
+
+        mirror.apply_slot_status(&status(12, SlotStatus::Confirmed)).unwrap();
+        mirror.apply_slot_status(&status(12, SlotStatus::Rooted)).unwrap();
+        assert_eq!(mirror.root(), 12);
+        assert_eq!(data(&mirror, &account, Commitment::Finalized), Some(vec![12]));
+        // Writes at or below the root are stale
+        assert_eq!(mirror.apply_update(update(account, owner, 11, 4, &[0])), Ok(false));
+    }
+
+    #[test]
+    fn test_subscribers_get_decoded_updates_at_their_commitment() {
+        let mirror = AccountMirror::new();
+        let (mint, owner, token_account) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let token_data = |amount: u64| [mint.as_ref(), owner.as_ref(), &amount.to_le_bytes(), &[0; 93]].concat();
+        let filter = AccountFilter::Account(token_account);
+        let mut processed = mirror.subscribe(filter, Commitment::Processed, |version| TokenAccount::decode(&version.data));
+        let mut confirmed = mirror.subscribe(filter, Commitment::Confirmed, |version| TokenAccount::decode(&version.data));
+
+        mirror.apply_update(update(token_account, owner, 20, 1, &token_data(100))).unwrap();
+        mirror.apply_update(update(token_account, owner, 21, 2, &token_data(50))).unwrap();
+        assert_eq!(processed.try_recv().unwrap().value.amount, 100);
+        assert_eq!(processed.try_recv().unwrap().value.amount, 50);
+        assert!(confirmed.try_recv().is_err());
+
+        // Slot 20 confirming after slot 21 doesn't roll the confirmed view back past 21
+        mirror.apply_slot_status(&status(21, SlotStatus::Confirmed)).unwrap();
+        mirror.apply_slot_status(&status(20, SlotStatus::Confirmed)).unwrap();
+        let update = confirmed.try_recv().unwrap();
+        assert_eq!((update.slot, update.value.amount, update.value.mint), (21, 50, mint));
+        assert!(confirmed.try_recv().is_err());
+
+        // Late subscribers start from the current state
+        let mut late = mirror.subscribe(filter, Commitment::Confirmed, |version| TokenAccount::decode(&version.data));
+        assert_eq!(late.try_recv().unwrap().slot, 21);
+    }
+
+    #[test]
+    fn test_raydium_pool_layout() {
+        let mut data = vec![0u8; 752];
+        data[4 * 8..5 * 8].copy_from_slice(&9u64.to_le_bytes());
+        data[23 * 8..24 * 8].copy_from_slice(&10_000u64.to_le_bytes());
+        let base_mint = Pubkey::new_unique();
+        data[336 + 2 * 32..336 + 3 * 32].copy_from_slice(base_mint.as_ref());
+        data[720..728].copy_from_slice(&42u64.to_le_bytes());
+
+        let pool = RaydiumAmmPool::decode(&data).unwrap();
+        assert_eq!((pool.base_decimals, pool.swap_fee_denominator, pool.base_mint, pool.lp_reserve), (9, 10_000, base_mint, 42));
+        assert_eq!(RaydiumAmmPool::decode(&data[1..]), None);
+    }
+}
@@ -1,51 +1,497 @@
 use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use async_trait::async_trait;
+use log::{error, info};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::address_lookup_table::state::AddressLookupTable;
 use solana_sdk::pubkey::Pubkey;
-use solana_client::rpc_client::RpcClient;
+use crate::solana::transaction::message::MessageAddressTableLookup;
 
-pub struct AddressTableCache<'a> {
-    cache: HashMap<Pubkey, Vec<Vec<u8>>>,
-    rpc_client: &'a RpcClient, // Borrowed reference to RpcClient
+// How long a cached table is trusted when no extension or deactivation has been observed
+const DEFAULT_TTL: Duration = Duration::from_secs(300);
+
+/// Errors returned when a lookup table cannot be fetched, decoded or indexed.
+#[derive(Debug, thiserror::Error)]
+pub enum AddressTableError {
+    #[error("failed to fetch lookup table {0}")]
+    Fetch(String),
+    #[error("lookup table account {0} not found")]
+    AccountNotFound(Pubkey),
+    #[error("invalid lookup table account: {0}")]
+    InvalidTable(String),
+    #[error("lookup table account key has {0} bytes, expected 32")]
+    InvalidAccountKey(usize),
+    #[error("lookup index {index} is out of range for table {table} with {len} addresses")]
+    IndexOutOfRange { table: Pubkey, index: u32, len: usize },
+    #[error("IoError {0}")]
+    Io(#[from] std::io::Error),
+    #[error("JsonError {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Raw account data together with the slot it was read at.
+pub struct FetchedAccount {
+    pub slot: u64,
+    pub data: Vec<u8>,
+}
+
+/// Source of lookup table accounts, implemented over RPC in production and mocked in tests.
+#[async_trait]
+pub trait AccountFetcher: Send + Sync {
+    async fn fetch_account(&self, account_key: &Pubkey) -> Result<FetchedAccount, AddressTableError>;
+}
+
+/// [`AccountFetcher`] backed by the nonblocking Solana RPC client.
+pub struct RpcAccountFetcher {
+    rpc_client: RpcClient,
 }
 
-impl<'a> AddressTableCache<'a> {
-    // Initialize the cache with a reference to an RpcClient
-    pub fn new(rpc_client: &'a RpcClient) -> Self {
+impl RpcAccountFetcher {
+    pub fn new(rpc_client: RpcClient) -> Self {
+        RpcAccountFetcher { rpc_client }
+    }
+}
+
+#[async_trait]
+impl AccountFetcher for RpcAccountFetcher {
+    async fn fetch_account(&self, account_key: &Pubkey) -> Result<FetchedAccount, AddressTableError> {
+        let response = self.rpc_client
+            .get_account_with_commitment(account_key, self.rpc_client.commitment())
+            .await
+            .map_err(|e| AddressTableError::Fetch(e.to_string()))?;
+        let account = response.value.ok_or(AddressTableError::AccountNotFound(*account_key))?;
+
+        Ok(FetchedAccount { slot: response.context.slot, data: account.data })
+    }
+}
+
+// A decoded lookup table and what is needed to decide when it goes stale
+struct CachedTable {
+    addresses: Arc<Vec<Pubkey>>,
+    deactivation_slot: u64,
+    fetched_slot: u64,
+    fetched_at: Instant,
+}
+
+/// Cache of address lookup table contents, shared between tasks behind an `Arc`.
+///
+/// Entries expire after a TTL, when the table is seen being extended or deactivated
+/// (see [`AddressTableCache::mark_modified`]), or once a deactivating table passes its
+/// deactivation slot. Concurrent misses for the same table share a single fetch.
+pub struct AddressTableCache {
+    fetcher: Arc<dyn AccountFetcher>,
+    ttl: Duration,
+    current_slot: AtomicU64,
+    cache: RwLock<HashMap<Pubkey, CachedTable>>,
+    in_flight: Mutex<HashMap<Pubkey, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+impl AddressTableCache {
+    // Initialize the cache with the fetcher used on misses
+    pub fn new(fetcher: Arc<dyn AccountFetcher>) -> Self {
         AddressTableCache {
-            cache: HashMap::new(),
-            rpc_client,
+            fetcher,
+            ttl: DEFAULT_TTL,
+            current_slot: AtomicU64::new(0),
+            cache: RwLock::new(HashMap::new()),
+            in_flight: Mutex::new(HashMap::new()),
         }
     }
 
-    // Get the cached address table data for a given account key
-    pub fn get(&self, account_key: &Pubkey) -> Option<Vec<Vec<u8>>> {
-        self.cache.get(account_key).cloned()
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    // Advance the slot used to expire deactivated tables; older slots are ignored
+    pub fn observe_slot(&self, slot: u64) {
+        self.current_slot.fetch_max(slot, Ordering::Relaxed);
+    }
+
+    // Get the cached addresses for a given lookup table, if present and not stale
+    pub fn get(&self, account_key: &Pubkey) -> Option<Arc<Vec<Pubkey>>> {
+        self.fresh(account_key, 0)
     }
 
-    // Insert or update the cache with new data
-    pub fn insert(&mut self, account_key: Pubkey, data: Vec<Vec<u8>>) {
-        self.cache.insert(account_key, data);
+    // Insert or update the cache with addresses read at `slot`
+    pub fn insert(&self, account_key: Pubkey, addresses: Vec<Pubkey>, slot: u64) {
+        self.cache.write().unwrap().insert(account_key, CachedTable {
+            addresses: Arc::new(addresses),
+            deactivation_slot: u64::MAX,
+            fetched_slot: slot,
+            fetched_at: Instant::now(),
+        });
     }
 
-    // Fetch address table data from the RPC and cache it
-    pub fn fetch_and_cache(
-        &mut self,
+    // Drop the cached table so the next lookup fetches it again
+    pub fn invalidate(&self, account_key: &Pubkey) {
+        self.cache.write().unwrap().remove(account_key);
+    }
+
+    // Record that the table was extended, deactivated or closed at `slot`.
+    // Entries read at or before that slot are dropped.
+    pub fn mark_modified(&self, account_key: &Pubkey, slot: u64) {
+        let mut cache = self.cache.write().unwrap();
+        if cache.get(account_key).is_some_and(|table| table.fetched_slot <= slot) {
+            cache.remove(account_key);
+        }
+    }
+
+    // Return the table addresses, fetching them if missing, stale or shorter than `required_len`
+    pub async fn load(
+        &self,
         account_key: Pubkey,
-    ) -> Result<Vec<Vec<u8>>, Box<dyn std::error::Error>> {
-        // Check if data is already cached
-        if let Some(cached_data) = self.get(&account_key) {
-            return Ok(cached_data);
+        required_len: usize,
+    ) -> Result<Arc<Vec<Pubkey>>, AddressTableError> {
+        if let Some(addresses) = self.fresh(&account_key, required_len) {
+            return Ok(addresses);
         }
 
-        // Fetch the address table account data from RPC if not cached
-        let account_data = self.rpc_client.get_account_data(&account_key)?;
-        let chunks: Vec<Vec<u8>> = account_data
-            .chunks(32) // Assuming each chunk is 32 bytes
-            .map(|chunk| chunk.to_vec())
+        // Only one task fetches a given table; the others wait and then read its result
+        let in_flight = InFlight::join(&self.in_flight, account_key);
+        let _guard = in_flight.lock.lock().await;
+
+        if let Some(addresses) = self.fresh(&account_key, required_len) {
+            return Ok(addresses);
+        }
+
+        self.fetch_and_cache(account_key).await
+    }
+
+    // Resolve the addresses loaded by a V0 message's lookups.
+    // Returns (writable, readonly) in the order the runtime appends them to the static
+    // account keys: the writable addresses of every lookup first, then the readonly ones.
+    pub async fn resolve_lookups(
+        &self,
+        lookups: &[MessageAddressTableLookup],
+    ) -> Result<(Vec<Pubkey>, Vec<Pubkey>), AddressTableError> {
+        let mut writable = Vec::new();
+        let mut readonly = Vec::new();
+
+        for lookup in lookups {
+            let account_key = Pubkey::try_from(lookup.account_key.as_slice())
+                .map_err(|_| AddressTableError::InvalidAccountKey(lookup.account_key.len()))?;
+
+            // A table that is too short for the requested indexes was extended since it was cached
+            let required_len = lookup.writable_indexes.iter()
+                .chain(lookup.readonly_indexes.iter())
+                .max()
+                .map_or(0, |&index| index as usize + 1);
+            let addresses = self.load(account_key, required_len).await?;
+
+            for &index in lookup.writable_indexes.iter() {
+                writable.push(lookup_address(&addresses, account_key, index)?);
+            }
+            for &index in lookup.readonly_indexes.iter() {
+                readonly.push(lookup_address(&addresses, account_key, index)?);
+            }
+        }
+
+        Ok((writable, readonly))
+    }
+
+    // Load every table listed in a JSON file of base58 table addresses.
+    // Tables that fail to load are logged and skipped; returns how many were cached.
+    pub async fn prewarm_from_file(&self, path: impl AsRef<Path>) -> Result<usize, AddressTableError> {
+        let contents = std::fs::read_to_string(path)?;
+        let table_keys: Vec<String> = serde_json::from_str(&contents)?;
+
+        let mut loaded = 0;
+        for table_key in table_keys {
+            let account_key = match table_key.parse::<Pubkey>() {
+                Ok(account_key) => account_key,
+                Err(e) => {
+                    error!("Skipping lookup table {}: {}", table_key, e);
+                    continue;
+                }
+            };
+            match self.load(account_key, 0).await {
+                Ok(_) => loaded += 1,
+                Err(e) => error!("Failed to prewarm lookup table {}: {}", account_key, e),
+            }
+        }
+
+        info!("Prewarmed {} address lookup tables", loaded);
+        Ok(loaded)
+    }
+
+    // Cached addresses for the table, unless expired or too short
+    fn fresh(&self, account_key: &Pubkey, required_len: usize) -> Option<Arc<Vec<Pubkey>>> {
+        let cache = self.cache.read().unwrap();
+        let table = cache.get(account_key)?;
+
+        let current_slot = self.current_slot.load(Ordering::Relaxed);
+        let deactivated_since_fetch = table.deactivation_slot != u64::MAX
+            && table.fetched_slot <= table.deactivation_slot
+            && current_slot > table.deactivation_slot;
+
+        if table.fetched_at.elapsed() >= self.ttl || deactivated_since_fetch || table.addresses.len() < required_len {
+            return None;
+        }
+        Some(table.addresses.clone())
+    }
+
+    // Fetch the lookup table through the fetcher, decode its addresses and cache them
+    async fn fetch_and_cache(&self, account_key: Pubkey) -> Result<Arc<Vec<Pubkey>>, AddressTableError> {
+        let account = self.fetcher.fetch_account(&account_key).await?;
+        let table = AddressLookupTable::deserialize(&account.data)
+            .map_err(|e| AddressTableError::InvalidTable(e.to_string()))?;
+
+        let addresses = Arc::new(table.addresses.to_vec());
+        self.cache.write().unwrap().insert(account_key, CachedTable {
+            addresses: addresses.clone(),
+            deactivation_slot: table.meta.deactivation_slot,
+            fetched_slot: account.slot,
+            fetched_at: Instant::now(),
+        });
+
+        Ok(addresses)
+    }
+}
+
+// Decode the addresses stored in an address lookup table account.
+// The account starts with a 56-byte `LookupTableMeta` header, followed by the raw 32-byte addresses.
+pub fn decode_lookup_table(data: &[u8]) -> Result<Vec<Pubkey>, AddressTableError> {
+    let table = AddressLookupTable::deserialize(data)
+        .map_err(|e| AddressTableError::InvalidTable(e.to_string()))?;
+    Ok(table.addresses.to_vec())
+}
+
+// A task's share of the fetch lock for one table. The map entry is removed when the last
+// task holding it leaves, however `load` exits.
+struct InFlight<'a> {
+    in_flight: &'a Mutex<HashMap<Pubkey, Arc<tokio::sync::Mutex<()>>>>,
+    account_key: Pubkey,
+    lock: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl<'a> InFlight<'a> {
+    fn join(in_flight: &'a Mutex<HashMap<Pubkey, Arc<tokio::sync::Mutex<()>>>>, account_key: Pubkey) -> Self {
+        let lock = in_flight.lock().unwrap().entry(account_key).or_default().clone();
+        InFlight { in_flight, account_key, lock }
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        // Clones are only taken under the map lock, so two references means the map's and ours
+        let last = in_flight.get(&self.account_key)
+            .is_some_and(|lock| Arc::ptr_eq(lock, &self.lock) && Arc::strong_count(&self.lock) == 2);
+        if last {
+            in_flight.remove(&self.account_key);
+        }
+    }
+}
+
+// Address at `index` of a lookup table
+fn lookup_address(addresses: &[Pubkey], table: Pubkey, index: u32) -> Result<Pubkey, AddressTableError> {
+    addresses.get(index as usize)
+        .copied()
+        .ok_or(AddressTableError::IndexOutOfRange { table, index, len: addresses.len() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    // Builds lookup table account data the way the on-chain program lays it out:
+    // u32 state tag, deactivation slot, last extended slot, start index,
+    // optional authority, u16 padding, then the addresses.
+    fn lookup_table_account_data(addresses: &[Pubkey], deactivation_slot: u64) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&1u32.to_le_bytes()); // ProgramState::LookupTable
+        data.extend_from_slice(&deactivation_slot.to_le_bytes());
+        data.extend_from_slice(&250_000_000u64.to_le_bytes()); // last_extended_slot
+        data.push(0); // last_extended_slot_start_index
+        data.push(1); // authority: Some
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(&0u16.to_le_bytes()); // padding
+        assert_eq!(data.len(), 56);
+        for address in addresses {
+            data.extend_from_slice(address.as_ref());
+        }
+        data
+    }
+
+    fn lookup(table: &Pubkey, writable_indexes: Vec<u32>, readonly_indexes: Vec<u32>) -> MessageAddressTableLookup {
+        MessageAddressTableLookup {
+            account_key: table.to_bytes().to_vec(),
+            writable_indexes,
+            readonly_indexes,
+        }
+    }
+
+    // In-memory fetcher that counts fetches and can be updated between them
+    #[derive(Default)]
+    struct MockAccountFetcher {
+        accounts: Mutex<HashMap<Pubkey, FetchedAccount>>,
+        fetches: AtomicUsize,
+        delay: Duration,
+    }
+
+    impl MockAccountFetcher {
+        fn set_table(&self, table: Pubkey, addresses: &[Pubkey], deactivation_slot: u64, slot: u64) {
+            let data = lookup_table_account_data(addresses, deactivation_slot);
+            self.accounts.lock().unwrap().insert(table, FetchedAccount { slot, data });
+        }
+
+        fn fetches(&self) -> usize {
+            self.fetches.load(Ordering::SeqCst)
+        }
+    }
+
+    #[async_trait]
+    impl AccountFetcher for MockAccountFetcher {
+        async fn fetch_account(&self, account_key: &Pubkey) -> Result<FetchedAccount, AddressTableError> {
+            self.fetches.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(self.delay).await;
+            let accounts = self.accounts.lock().unwrap();
+            let account = accounts.get(account_key).ok_or(AddressTableError::AccountNotFound(*account_key))?;
+            Ok(FetchedAccount { slot: account.slot, data: account.data.clone() })
+        }
+    }
+
+    fn unique_pubkeys(count: usize) -> Vec<Pubkey> {
+        (0..count).map(|_| Pubkey::new_unique()).collect()
+    }
+
+    #[test]
+    fn test_decode_lookup_table_skips_header() {
+        let addresses = unique_pubkeys(3);
+        let data = lookup_table_account_data(&addresses, u64::MAX);
+
+        assert_eq!(decode_lookup_table(&data).unwrap(), addresses);
+    }
+
+    #[test]
+    fn test_decode_lookup_table_rejects_uninitialized_account() {
+        let data = vec![0u8; 56];
+        assert!(decode_lookup_table(&data).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_lookups_orders_writable_before_readonly() {
+        let fetcher = Arc::new(MockAccountFetcher::default());
+        let (table_a, table_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (a, b) = (unique_pubkeys(4), unique_pubkeys(4));
+        fetcher.set_table(table_a, &a, u64::MAX, 100);
+        fetcher.set_table(table_b, &b, u64::MAX, 100);
+        let cache = AddressTableCache::new(fetcher.clone());
+
+        let (writable, readonly) = cache
+            .resolve_lookups(&[
+                lookup(&table_a, vec![2, 0], vec![3]),
+                lookup(&table_b, vec![1], vec![0, 2]),
+            ])
+            .await
+            .unwrap();
+
+        assert_eq!(writable, vec![a[2], a[0], b[1]]);
+        assert_eq!(readonly, vec![a[3], b[0], b[2]]);
+        assert_eq!(fetcher.fetches(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_misses_share_one_fetch() {
+        let fetcher = Arc::new(MockAccountFetcher { delay: Duration::from_millis(50), ..Default::default() });
+        let table = Pubkey::new_unique();
+        fetcher.set_table(table, &unique_pubkeys(2), u64::MAX, 100);
+        let cache = Arc::new(AddressTableCache::new(fetcher.clone()));
+
+        let tasks: Vec<_> = (0..8)
+            .map(|_| {
+                let cache = cache.clone();
+                tokio::spawn(async move { cache.load(table, 0).await.map(|addresses| addresses.len()) })
+            })
             .collect();
+        for task in tasks {
+            assert_eq!(task.await.unwrap().unwrap(), 2);
+        }
+
+        assert_eq!(fetcher.fetches(), 1);
+        assert!(cache.in_flight.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_entries_expire_after_ttl() {
+        let fetcher = Arc::new(MockAccountFetcher::default());
+        let table = Pubkey::new_unique();
+        fetcher.set_table(table, &unique_pubkeys(2), u64::MAX, 100);
+        let cache = AddressTableCache::new(fetcher.clone()).with_ttl(Duration::from_millis(20));
+
+        cache.load(table, 0).await.unwrap();
+        cache.load(table, 0).await.unwrap();
+        assert_eq!(fetcher.fetches(), 1);
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        cache.load(table, 0).await.unwrap();
+        assert_eq!(fetcher.fetches(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_extended_table_is_refetched() {
+        let fetcher = Arc::new(MockAccountFetcher::default());
+        let table = Pubkey::new_unique();
+        let mut addresses = unique_pubkeys(2);
+        fetcher.set_table(table, &addresses, u64::MAX, 100);
+        let cache = AddressTableCache::new(fetcher.clone());
+        assert_eq!(cache.load(table, 0).await.unwrap().len(), 2);
+
+        // A lookup past the cached end triggers a refetch of the extended table
+        addresses.push(Pubkey::new_unique());
+        fetcher.set_table(table, &addresses, u64::MAX, 110);
+        let (writable, _) = cache.resolve_lookups(&[lookup(&table, vec![2], vec![])]).await.unwrap();
+        assert_eq!(writable, vec![addresses[2]]);
+        assert_eq!(fetcher.fetches(), 2);
+
+        // An extension seen on-chain after the fetch drops the entry
+        cache.mark_modified(&table, 109);
+        assert!(cache.get(&table).is_some());
+        cache.mark_modified(&table, 120);
+        assert!(cache.get(&table).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_deactivated_table_expires_by_slot() {
+        let fetcher = Arc::new(MockAccountFetcher::default());
+        let table = Pubkey::new_unique();
+        fetcher.set_table(table, &unique_pubkeys(2), 200, 150);
+        let cache = AddressTableCache::new(fetcher.clone());
+
+        cache.observe_slot(180);
+        cache.load(table, 0).await.unwrap();
+        assert!(cache.get(&table).is_some());
+
+        cache.observe_slot(201);
+        assert!(cache.get(&table).is_none());
+        fetcher.set_table(table, &unique_pubkeys(2), 200, 201);
+        cache.load(table, 0).await.unwrap();
+        assert!(cache.get(&table).is_some());
+        assert_eq!(fetcher.fetches(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_prewarm_from_file() {
+        let fetcher = Arc::new(MockAccountFetcher::default());
+        let (table_a, table_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        fetcher.set_table(table_a, &unique_pubkeys(1), u64::MAX, 100);
+        fetcher.set_table(table_b, &unique_pubkeys(3), u64::MAX, 100);
+        let cache = AddressTableCache::new(fetcher.clone());
+
+        let path = std::env::temp_dir().join(format!("address_tables_{}.json", table_a));
+        let missing = Pubkey::new_unique();
+        std::fs::write(&path, serde_json::to_string(&[table_a.to_string(), table_b.to_string(), missing.to_string()]).unwrap()).unwrap();
 
-        // Cache the fetched data
-        self.insert(account_key, chunks.clone());
+        let loaded = cache.prewarm_from_file(&path).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
 
-        Ok(chunks)
+        assert_eq!(loaded, 2);
+        assert_eq!(cache.get(&table_b).unwrap().len(), 3);
+        assert!(cache.get(&missing).is_none());
     }
 }
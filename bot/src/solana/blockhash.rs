@@ -0,0 +1,277 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use async_trait::async_trait;
+use log::error;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
+use tokio::task::JoinHandle;
+
+/// Number of blocks a blockhash stays valid for after the block that produced it.
+pub const MAX_PROCESSING_AGE: u64 = 150;
+// Enough history to answer expiry checks for any hash that can still land
+const RECENT_BLOCKHASHES: usize = 2 * MAX_PROCESSING_AGE as usize;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BlockhashError {
+    #[error("RpcError {0}")]
+    Rpc(String),
+    #[error("no blockhash received yet")]
+    NotReady,
+    #[error("blockhash {0} has expired")]
+    Expired(Hash),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RecentBlockhash {
+    pub hash: Hash,
+    /// Last block height at which a transaction using `hash` can still be processed
+    pub last_valid_block_height: u64,
+}
+
+/// Hands out recent blockhashes for transaction construction.
+pub trait BlockhashProvider: Send + Sync {
+    /// The freshest known blockhash, without any network round trip.
+    fn latest(&self) -> Result<RecentBlockhash, BlockhashError>;
+
+    /// Whether a transaction built on `hash` can no longer land.
+    /// Hashes the provider has never seen (or has forgotten) count as expired.
+    fn is_expired(&self, hash: &Hash) -> bool;
+}
+
+/// Where `BlockhashCache` polls blockhashes and block heights from.
+#[async_trait]
+pub trait BlockhashSource: Send + Sync {
+    async fn latest_blockhash(&self) -> Result<RecentBlockhash, BlockhashError>;
+    async fn block_height(&self) -> Result<u64, BlockhashError>;
+}
+
+pub struct RpcBlockhashSource {
+    client: RpcClient,
+    commitment: CommitmentConfig,
+}
+
+impl RpcBlockhashSource {
+    pub fn new(client: RpcClient) -> Self {
+        RpcBlockhashSource {
+            client,
+            commitment: CommitmentConfig::confirmed(),
+        }
+    }
+}
+
+#[async_trait]
+impl BlockhashSource for RpcBlockhashSource {
+    async fn latest_blockhash(&self) -> Result<RecentBlockhash, BlockhashError> {
+        let (hash, last_valid_block_height) = self.client
+            .get_latest_blockhash_with_commitment(self.commitment)
+            .await
+            .map_err(|e| BlockhashError::Rpc(e.to_string()))?;
+        Ok(RecentBlockhash { hash, last_valid_block_height })
+    }
+
+    async fn block_height(&self) -> Result<u64, BlockhashError> {
+        self.client
+            .get_block_height_with_commitment(self.commitment)
+            .await
+            .map_err(|e| BlockhashError::Rpc(e.to_string()))
+    }
+}
+
+#[derive(Default)]
+struct BlockhashState {
+    block_height: Option<u64>,
+    // Oldest first
+    hashes: VecDeque<RecentBlockhash>,
+    // Slot and last tick hash of the newest slot seen in shredstream entries
+    entry_tick: Option<(u64, Hash)>,
+}
+
+impl BlockhashState {
+    fn insert(&mut self, blockhash: RecentBlockhash) {
+        if self.hashes.iter().any(|known| known.hash == blockhash.hash) {
+            return;
+        }
+        self.hashes.push_back(blockhash);
+        if self.hashes.len() > RECENT_BLOCKHASHES {
+            self.hashes.pop_front();
+        }
+    }
+
+    fn is_expired(&self, blockhash: &RecentBlockhash) -> bool {
+        self.block_height.is_some_and(|height| height > blockhash.last_valid_block_height)
+    }
+}
+
+/// Blockhash provider fed by a background RPC poller and/or shredstream entries.
+///
+/// Shredstream blockhashes are the last tick of each slot, promoted once entries for a later
+/// slot arrive. Their last valid block height is estimated from the polled block height, so
+/// at least one poll or `observe_block_height` call is needed before they are handed out.
+#[derive(Default)]
+pub struct BlockhashCache {
+    state: RwLock<BlockhashState>,
+}
+
+impl BlockhashCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&self, blockhash: RecentBlockhash) {
+        self.state.write().unwrap().insert(blockhash);
+    }
+
+    pub fn observe_block_height(&self, block_height: u64) {
+        let mut state = self.state.write().unwrap();
+        state.block_height = Some(state.block_height.map_or(block_height, |height| height.max(block_height)));
+    }
+
+    pub fn block_height(&self) -> Option<u64> {
+        self.state.read().unwrap().block_height
+    }
+
+    // Records a shredstream entry; only ticks carry a hash that can become a blockhash
+    pub fn observe_entry(&self, slot: u64, hash: Hash, is_tick: bool) {
+        if !is_tick {
+            return;
+        }
+        let mut state = self.state.write().unwrap();
+        match state.entry_tick {
+            Some((tick_slot, _)) if slot < tick_slot => return,
+            Some((tick_slot, tick_hash)) if slot > tick_slot => {
+                // The previous slot is complete: its last tick is its blockhash,
+                // and every slot that produced entries is one more block
+                if let Some(height) = state.block_height.as_mut() {
+                    *height += 1;
+                    let last_valid_block_height = *height + MAX_PROCESSING_AGE;
+                    state.insert(RecentBlockhash { hash: tick_hash, last_valid_block_height });
+                }
+            }
+            _ => {}
+        }
+        state.entry_tick = Some((slot, hash));
+    }
+
+    pub async fn refresh(&self, source: &dyn BlockhashSource) -> Result<(), BlockhashError> {
+        let (blockhash, block_height) = tokio::try_join!(source.latest_blockhash(), source.block_height())?;
+        self.observe_block_height(block_height);
+        self.insert(blockhash);
+        Ok(())
+    }
+
+    // Keeps the cache fresh by polling `source` every `interval` until the handle is aborted
+    pub fn spawn_polling(self: &Arc<Self>, source: Arc<dyn BlockhashSource>, interval: Duration) -> JoinHandle<()> {
+        let cache = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = cache.refresh(source.as_ref()).await {
+                    error!("Failed to refresh blockhash: {}", e);
+                }
+            }
+        })
+    }
+}
+
+impl BlockhashProvider for BlockhashCache {
+    fn latest(&self) -> Result<RecentBlockhash, BlockhashError> {
+        let state = self.state.read().unwrap();
+        let freshest = state.hashes.iter()
+            .max_by_key(|blockhash| blockhash.last_valid_block_height)
+            .ok_or(BlockhashError::NotReady)?;
+        if state.is_expired(freshest) {
+            return Err(BlockhashError::Expired(freshest.hash));
+        }
+        Ok(*freshest)
+    }
+
+    fn is_expired(&self, hash: &Hash) -> bool {
+        let state = self.state.read().unwrap();
+        state.hashes.iter()
+            .find(|blockhash| blockhash.hash == *hash)
+            .is_none_or(|blockhash| state.is_expired(blockhash))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct MockSource {
+        responses: Mutex<VecDeque<(RecentBlockhash, u64)>>,
+    }
+
+    #[async_trait]
+    impl BlockhashSource for MockSource {
+        async fn latest_blockhash(&self) -> Result<RecentBlockhash, BlockhashError> {
+            self.responses.lock().unwrap().front().map(|(blockhash, _)| *blockhash).ok_or(BlockhashError::NotReady)
+        }
+
+        async fn block_height(&self) -> Result<u64, BlockhashError> {
+            Ok(self.responses.lock().unwrap().pop_front().ok_or(BlockhashError::NotReady)?.1)
+        }
+    }
+
+    fn blockhash(last_valid_block_height: u64) -> RecentBlockhash {
+        RecentBlockhash { hash: Hash::new_unique(), last_valid_block_height }
+    }
+
+    #[test]
+    fn test_latest_is_freshest_and_expires() {
+        let cache = BlockhashCache::new();
+        assert!(matches!(cache.latest(), Err(BlockhashError::NotReady)));
+
+        let (older, newer) = (blockhash(1_150), blockhash(1_151));
+        cache.insert(newer);
+        cache.insert(older);
+        cache.observe_block_height(1_000);
+        assert_eq!(cache.latest().unwrap(), newer);
+        assert!(!cache.is_expired(&older.hash));
+
+        cache.observe_block_height(1_151);
+        assert!(cache.is_expired(&older.hash));
+        assert!(!cache.is_expired(&newer.hash));
+
+        cache.observe_block_height(1_152);
+        assert!(matches!(cache.latest(), Err(BlockhashError::Expired(hash)) if hash == newer.hash));
+        assert!(cache.is_expired(&Hash::new_unique()));
+    }
+
+    #[test]
+    fn test_last_tick_of_a_slot_becomes_blockhash() {
+        let cache = BlockhashCache::new();
+        let (first_tick, last_tick, next_tick) = (Hash::new_unique(), Hash::new_unique(), Hash::new_unique());
+
+        // Without a known block height the hash can't be given a validity window
+        cache.observe_entry(10, first_tick, true);
+        cache.observe_entry(11, Hash::new_unique(), true);
+        assert!(matches!(cache.latest(), Err(BlockhashError::NotReady)));
+
+        cache.observe_block_height(500);
+        cache.observe_entry(12, first_tick, true);
+        cache.observe_entry(12, Hash::new_unique(), false);
+        cache.observe_entry(12, last_tick, true);
+        cache.observe_entry(13, next_tick, true);
+
+        // Slots 11 and 12 each completed a block
+        assert_eq!(cache.latest().unwrap(), RecentBlockhash { hash: last_tick, last_valid_block_height: 502 + MAX_PROCESSING_AGE });
+        assert_eq!(cache.block_height(), Some(502));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_from_source() {
+        let polled = blockhash(2_150);
+        let source = MockSource { responses: Mutex::new(VecDeque::from([(polled, 2_000)])) };
+        let cache = BlockhashCache::new();
+
+        cache.refresh(&source).await.unwrap();
+
+        assert_eq!(cache.latest().unwrap(), polled);
+        assert_eq!(cache.block_height(), Some(2_000));
+        assert!(cache.refresh(&source).await.is_err());
+    }
+}
@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+use std::env;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use base64::Engine;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::VersionedTransaction;
+use tokio::task::JoinSet;
+use crate::jito::leader_schedule::block_engine_url;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(2);
+// Signatures waiting to be seen on chain; beyond this the oldest are assumed never to land
+const MAX_PENDING: usize = 4096;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BroadcastError {
+    #[error("SerializeError {0}")]
+    Serialize(#[from] bincode::Error),
+    #[error("HttpError {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("ConfigError {0}")]
+    Config(String),
+    #[error("no endpoints to broadcast to")]
+    NoEndpoints,
+    #[error("unknown endpoint {0}")]
+    UnknownEndpoint(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndpointKind {
+    /// Block engine `sendTransaction`, forwarded to the leader as a single-transaction bundle
+    Jito,
+    /// Staked RPC authenticated by an `api-key` query parameter
+    Helius,
+    /// Plain JSON-RPC `sendTransaction`, e.g. a public RPC or a local validator
+    Rpc,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Endpoint {
+    pub name: String,
+    pub kind: EndpointKind,
+    url: String,
+}
+
+impl Endpoint {
+    pub fn new(name: impl Into<String>, kind: EndpointKind, url: impl Into<String>) -> Self {
+        Endpoint { name: name.into(), kind, url: url.into() }
+    }
+
+    // Parses `kind:target`, where a Jito target is a region or a block engine URL.
+    // The spec itself is used as the name so API keys never show up in stats or logs.
+    pub fn parse(spec: &str, helius_api_key: Option<&str>) -> Result<Self, BroadcastError> {
+        let (kind, target) = spec.split_once(':')
+            .ok_or_else(|| BroadcastError::Config(format!("endpoint {} is not kind:target", spec)))?;
+        let endpoint = match kind {
+            "jito" if target.contains("://") => Endpoint::new(spec, EndpointKind::Jito, target),
+            "jito" => Endpoint::new(spec, EndpointKind::Jito, format!("{}/api/v1/transactions", block_engine_url(target))),
+            "helius" => {
+                let api_key = helius_api_key
+                    .ok_or_else(|| BroadcastError::Config(format!("endpoint {} needs HELIUS_API_KEY", spec)))?;
+                Endpoint::new(spec, EndpointKind::Helius, format!("{}?api-key={}", target, api_key))
+            }
+            "rpc" => Endpoint::new(spec, EndpointKind::Rpc, target),
+            _ => return Err(BroadcastError::Config(format!("unknown endpoint kind {}", kind))),
+        };
+        Ok(endpoint)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BroadcastConfig {
+    pub endpoints: Vec<Endpoint>,
+    pub timeout: Duration,
+}
+
+impl BroadcastConfig {
+    /// Reads `BROADCAST_ENDPOINTS` (comma separated `kind:target`, e.g. `jito:ny,rpc:http://127.0.0.1:8899`),
+    /// `HELIUS_API_KEY` for `helius:` endpoints and the optional `BROADCAST_TIMEOUT_MS`.
+    pub fn from_env() -> Result<Self, BroadcastError> {
+        let endpoints = env::var("BROADCAST_ENDPOINTS")
+            .map_err(|_| BroadcastError::Config("BROADCAST_ENDPOINTS not set in environment".to_string()))?;
+        let helius_api_key = env::var("HELIUS_API_KEY").ok();
+        let timeout = match env::var("BROADCAST_TIMEOUT_MS") {
+            Ok(millis) => Duration::from_millis(millis.parse().map_err(|e| BroadcastError::Config(format!("BROADCAST_TIMEOUT_MS: {}", e)))?),
+            Err(_) => DEFAULT_TIMEOUT,
+        };
+        Ok(BroadcastConfig {
+            endpoints: endpoints.split(',')
+                .map(str::trim)
+                .filter(|spec| !spec.is_empty())
+                .map(|spec| Endpoint::parse(spec, helius_api_key.as_deref()))
+                .collect::<Result<_, _>>()?,
+            timeout,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EndpointStats {
+    pub sent: u64,
+    pub errors: u64,
+    pub last_error: Option<String>,
+    pub total_latency: Duration,
+    pub max_latency: Duration,
+    /// Landed transactions this endpoint acknowledged before any other
+    pub first_landed: u64,
+}
+
+impl EndpointStats {
+    pub fn average_latency(&self) -> Option<Duration> {
+        (self.sent > 0).then(|| self.total_latency / self.sent as u32)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndpointResponse {
+    pub endpoint: String,
+    pub latency: Duration,
+    /// The signature the endpoint returned, or its error
+    pub result: Result<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BroadcastReport {
+    pub signature: Signature,
+    /// One per endpoint, in configuration order
+    pub responses: Vec<EndpointResponse>,
+}
+
+impl BroadcastReport {
+    pub fn accepted(&self) -> bool {
+        self.responses.iter().any(|response| response.result.is_ok())
+    }
+}
+
+/// Sends the same signed transaction to several endpoints at once and keeps per-endpoint stats.
+///
+/// Every copy has the same signature, so the landed copy can't be told apart on chain.
+/// `record_landed` credits the endpoint that acknowledged the transaction first.
+pub struct Broadcaster {
+    client: reqwest::Client,
+    endpoints: Vec<Endpoint>,
+    stats: Mutex<HashMap<String, EndpointStats>>,
+    // Endpoints that accepted each in-flight signature, fastest first
+    pending: Mutex<HashMap<Signature, Vec<String>>>,
+}
+
+impl Broadcaster {
+    pub fn new(config: BroadcastConfig) -> Result<Self, BroadcastError> {
+        Ok(Broadcaster {
+            client: reqwest::Client::builder().timeout(config.timeout).build()?,
+            endpoints: config.endpoints,
+            stats: Mutex::new(HashMap::new()),
+            pending: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn endpoints(&self) -> &[Endpoint] {
+        &self.endpoints
+    }
+
+    pub fn stats(&self) -> HashMap<String, EndpointStats> {
+        self.stats.lock().unwrap().clone()
+    }
+
+    pub async fn broadcast(&self, transaction: &VersionedTransaction) -> Result<BroadcastReport, BroadcastError> {
+        self.send(transaction, self.endpoints.iter().collect()).await
+    }
+
+    // Sends only to the named endpoints, e.g. the Jito region of the upcoming leader
+    pub async fn broadcast_to(&self, transaction: &VersionedTransaction, names: &[&str]) -> Result<BroadcastReport, BroadcastError> {
+        let endpoints = names.iter()
+            .map(|name| self.endpoints.iter()
+                .find(|endpoint| endpoint.name == *name)
+                .ok_or_else(|| BroadcastError::UnknownEndpoint(name.to_string())))
+            .collect::<Result<_, _>>()?;
+        self.send(transaction, endpoints).await
+    }
+
+    /// Credits the first endpoint that accepted `signature` once it is seen on chain.
+    pub fn record_landed(&self, signature: &Signature) -> Option<String> {
+        let endpoint = self.pending.lock().unwrap().remove(signature)?.into_iter().next()?;
+        self.stats.lock().unwrap().entry(endpoint.clone()).or_default().first_landed += 1;
+        Some(endpoint)
+    }
+
+    async fn send(&self, transaction: &VersionedTransaction, endpoints: Vec<&Endpoint>) -> Result<BroadcastReport, BroadcastError> {
+        if endpoints.is_empty() {
+            return Err(BroadcastError::NoEndpoints);
+        }
+        let signature = transaction.signatures.first().copied().unwrap_or_default();
+        let encoded = base64::engine::general_purpose::STANDARD.encode(bincode::serialize(transaction)?);
+        let payload = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "sendTransaction",
+            "params": [encoded, { "encoding": "base64", "skipPreflight": true, "maxRetries": 0 }]
+        });
+
+        let mut requests = JoinSet::new();
+        for (index, endpoint) in endpoints.iter().enumerate() {
+            let (client, url, payload) = (self.client.clone(), endpoint.url.clone(), payload.clone());
+            requests.spawn(async move {
+                let start = Instant::now();
+                let result = send_transaction(&client, &url, &payload).await;
+                (index, start.elapsed(), result)
+            });
+        }
+
+        let mut responses = Vec::with_capacity(endpoints.len());
+        while let Some(response) = requests.join_next().await {
+            // Requests don't panic, so a failed join only happens on runtime shutdown
+            if let Ok((index, latency, result)) = response {
+                responses.push((index, EndpointResponse { endpoint: endpoints[index].name.clone(), latency, result }));
+            }
+        }
+        self.record(signature, &responses);
+        responses.sort_by_key(|(index, _)| *index);
+        Ok(BroadcastReport { signature, responses: responses.into_iter().map(|(_, response)| response).collect() })
+    }
+
+    // `responses` is in completion order, so the accepting endpoints are already fastest first
+    fn record(&self, signature: Signature, responses: &[(usize, EndpointResponse)]) {
+        let mut stats = self.stats.lock().unwrap();
+        for (_, response) in responses {
+            let stats = stats.entry(response.endpoint.clone()).or_default();
+            stats.sent += 1;
+            stats.total_latency += response.latency;
+            stats.max_latency = stats.max_latency.max(response.latency);
+            if let Err(e) = &response.result {
+                stats.errors += 1;
+                stats.last_error = Some(e.clone());
+            }
+        }
+
+        let accepted: Vec<String> = responses.iter()
+            .filter(|(_, response)| response.result.is_ok())
+            .map(|(_, response)| response.endpoint.clone())
+            .collect();
+        if accepted.is_empty() {
+            return;
+        }
+        let mut pending = self.pending.lock().unwrap();
+        if pending.len() >= MAX_PENDING {
+            pending.clear();
+        }
+        pending.insert(signature, accepted);
+    }
+}
+
+async fn send_transaction(client: &reqwest::Client, url: &str, payload: &serde_json::Value) -> Result<String, String> {
+    let response = client.post(url).json(payload).send().await.map_err(|e| e.to_string())?;
+    let status = response.status();
+    let body: serde_json::Value = response.json().await.map_err(|e| format!("{}: {}", status, e))?;
+    if let Some(signature) = body["result"].as_str() {
+        return Ok(signature.to_string());
+    }
+    Err(body["error"]["message"].as_str().map_or_else(|| format!("{}: {}", status, body), str::to_string))
+}
+
+// Test endpoint answering every HTTP request with `body` after `delay`
+#[cfg(test)]
+pub(crate) async fn serve(body: &'static str, delay: Duration) -> String {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            tokio::spawn(async move {
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 4096];
+                // Read until the JSON body is complete
+                while !request.ends_with(b"}") {
+                    let read = stream.read(&mut buffer).await.unwrap();
+                    if read == 0 {
+                        return;
+                    }
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                tokio::time::sleep(delay).await;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+                    body.len(),
+                    body,
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            });
+        }
+    });
+    format!("http://{}", addr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::Keypair;
+    use crate::solana::test_utils::signed_transaction;
+
+    fn transaction() -> VersionedTransaction {
+        signed_transaction(&Keypair::new(), &[])
+    }
+
+    #[test]
+    fn test_endpoint_specs() {
+        let jito = Endpoint::parse("jito:ny", None).unwrap();
+        assert_eq!(jito, Endpoint::new("jito:ny", EndpointKind::Jito, "https://ny.mainnet.block-engine.jito.wtf/api/v1/transactions"));
+
+        let helius = Endpoint::parse("helius:https://staked.helius-rpc.com", Some("key")).unwrap();
+        assert_eq!(helius.name, "helius:https://staked.helius-rpc.com");
+        assert_eq!(helius.url, "https://staked.helius-rpc.com?api-key=key");
+
+        assert!(matches!(Endpoint::parse("helius:https://staked.helius-rpc.com", None), Err(BroadcastError::Config(_))));
+        assert!(matches!(Endpoint::parse("carrier-pigeon:coop", None), Err(BroadcastError::Config(_))));
+        assert_eq!(Endpoint::parse("rpc:http://127.0.0.1:8899", None).unwrap().kind, EndpointKind::Rpc);
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_records_latency_errors_and_first_landed() {
+        let fast = serve(r#"{"jsonrpc":"2.0","result":"sig","id":1}"#, Duration::ZERO).await;
+        let slow = serve(r#"{"jsonrpc":"2.0","result":"sig","id":1}"#, Duration::from_millis(200)).await;
+        let failing = serve(r#"{"jsonrpc":"2.0","error":{"code":-32002,"message":"blockhash not found"},"id":1}"#, Duration::ZERO).await;
+        let broadcaster = Broadcaster::new(BroadcastConfig {
+            endpoints: vec![
+                Endpoint::new("slow", EndpointKind::Rpc, slow),
+                Endpoint::new("failing", EndpointKind::Rpc, failing),
+                Endpoint::new("fast", EndpointKind::Jito, fast),
+            ],
+            timeout: DEFAULT_TIMEOUT,
+        }).unwrap();
+        let transaction = transaction();
+
+        let report = broadcaster.broadcast(&transaction).await.unwrap();
+
+        assert!(report.accepted());
+        assert_eq!(report.signature, transaction.signatures[0]);
+        let names: Vec<&str> = report.responses.iter().map(|response| response.endpoint.as_str()).collect();
+        assert_eq!(names, ["slow", "failing", "fast"]);
+        assert_eq!(report.responses[1].result, Err("blockhash not found".to_string()));
+        assert!(report.responses[0].latency >= Duration::from_millis(200));
+
+        assert_eq!(broadcaster.record_landed(&transaction.signatures[0]), Some("fast".to_string()));
+        assert_eq!(broadcaster.record_landed(&transaction.signatures[0]), None);
+        let stats = broadcaster.stats();
+        assert_eq!(stats["fast"].first_landed, 1);
+        assert_eq!(stats["slow"].first_landed, 0);
+        assert_eq!(stats["failing"].errors, 1);
+        assert_eq!(stats["failing"].last_error.as_deref(), Some("blockhash not found"));
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_to_subset() {
+        let url = serve(r#"{"jsonrpc":"2.0","result":"sig","id":1}"#, Duration::ZERO).await;
+        let broadcaster = Broadcaster::new(BroadcastConfig {
+            endpoints: vec![
+                Endpoint::new("local", EndpointKind::Rpc, url),
+                Endpoint::new("unreachable", EndpointKind::Rpc, "http://127.0.0.1:1"),
+            ],
+            timeout: DEFAULT_TIMEOUT,
+        }).unwrap();
+
+        let report = broadcaster.broadcast_to(&transaction(), &["local"]).await.unwrap();
+
+        assert_eq!(report.responses.len(), 1);
+        assert!(!broadcaster.stats().contains_key("unreachable"));
+        assert!(matches!(broadcaster.broadcast_to(&transaction(), &["nope"]).await, Err(BroadcastError::UnknownEndpoint(_))));
+        assert!(matches!(broadcaster.broadcast_to(&transaction(), &[]).await, Err(BroadcastError::NoEndpoints)));
+    }
+}
@@ -0,0 +1,36 @@
+use solana_sdk::pubkey::Pubkey;
+use crate::solana::idl::IdlProgram;
+use crate::solana::transaction::{self, message, DecodeError, DecodedInstruction};
+use super::{DecodeContext, ProgramDecoder};
+
+/// Decodes any Anchor program from its IDL into generic instructions and events.
+pub struct AnchorDecoder {
+    program: IdlProgram,
+}
+
+impl AnchorDecoder {
+    pub fn new(program: IdlProgram) -> Self {
+        AnchorDecoder { program }
+    }
+}
+
+impl ProgramDecoder for AnchorDecoder {
+    fn program_id(&self) -> Pubkey {
+        self.program.program_id
+    }
+
+    fn decode(
+        &self,
+        instruction: &message::CompiledInstruction,
+        ctx: &DecodeContext,
+    ) -> Result<Option<DecodedInstruction>, DecodeError> {
+        if let Some(event) = self.program.decode_event(&instruction.data)? {
+            return Ok(Some(DecodedInstruction::AnchorEvent(event)));
+        }
+
+        let accounts = transaction::instruction_accounts(instruction, ctx.account_keys)?;
+        Ok(self.program
+            .decode_instruction(&instruction.data, &accounts)?
+            .map(DecodedInstruction::Anchor))
+    }
+}
@@ -0,0 +1,44 @@
+use std::str::FromStr;
+use solana_sdk::pubkey::Pubkey;
+use crate::solana::transaction::{message, ComputeUnitLimitInstruction, ComputeUnitPriceInstruction, DecodeError, DecodedInstruction};
+use super::{DecodeContext, ProgramDecoder};
+
+const COMPUTE_BUDGET_PROGRAM_ID: &str = "ComputeBudget111111111111111111111111111111";
+
+// Borsh variant tags of `ComputeBudgetInstruction`
+const SET_COMPUTE_UNIT_LIMIT: u8 = 2;
+const SET_COMPUTE_UNIT_PRICE: u8 = 3;
+
+/// Decodes the compute unit limit and price a transaction requests.
+pub struct ComputeBudgetDecoder;
+
+impl ProgramDecoder for ComputeBudgetDecoder {
+    fn program_id(&self) -> Pubkey {
+        Pubkey::from_str(COMPUTE_BUDGET_PROGRAM_ID).unwrap()
+    }
+
+    fn decode(
+        &self,
+        instruction: &message::CompiledInstruction,
+        _ctx: &DecodeContext,
+    ) -> Result<Option<DecodedInstruction>, DecodeError> {
+        Ok(decode_compute_budget_instruction(&instruction.data))
+    }
+}
+
+// Shared with paths that see raw instruction data, such as shredstream entries
+pub fn decode_compute_budget_instruction(data: &[u8]) -> Option<DecodedInstruction> {
+    match data.first() {
+        Some(&SET_COMPUTE_UNIT_LIMIT) => data.get(1..5).map(|units| {
+            DecodedInstruction::ComputeUnitLimit(ComputeUnitLimitInstruction {
+                units: u32::from_le_bytes(units.try_into().unwrap()),
+            })
+        }),
+        Some(&SET_COMPUTE_UNIT_PRICE) => data.get(1..9).map(|micro_lamports| {
+            DecodedInstruction::ComputeUnitPrice(ComputeUnitPriceInstruction {
+                micro_lamports: u64::from_le_bytes(micro_lamports.try_into().unwrap()),
+            })
+        }),
+        _ => None,
+    }
+}
@@ -0,0 +1,594 @@
+mod anchor;
+mod compute_budget;
+mod pumpfun;
+mod raydium;
+mod system;
+mod token;
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+use solana_sdk::pubkey::Pubkey;
+use crate::solana::idl::{IdlError, IdlProgram};
+use crate::solana::transaction::{self, message, DecodeError, DecodedInstruction};
+
+pub use anchor::AnchorDecoder;
+pub use compute_budget::{decode_compute_budget_instruction, ComputeBudgetDecoder};
+pub use pumpfun::PumpfunDecoder;
+pub use raydium::RaydiumDecoder;
+pub use system::SystemProgramDecoder;
+pub use token::TokenProgramDecoder;
+
+/// What a [`ProgramDecoder`] can see of the transaction around the instruction it decodes.
+pub struct DecodeContext<'a> {
+    /// Full account key list, including addresses loaded from lookup tables.
+    pub account_keys: &'a [Vec<u8>],
+    pub meta: &'a message::TransactionStatusMeta,
+    /// Index of the top-level instruction; inner instructions share their parent's index.
+    pub instruction_index: usize,
+    /// Position among the parent's inner instructions, `None` for a top-level instruction.
+    pub inner_position: Option<usize>,
+    pub inner_instruction_map: &'a HashMap<usize, &'a message::InnerInstructions>,
+    pub slot: u64,
+}
+
+impl<'a> DecodeContext<'a> {
+    // Inner instructions recorded for the current top-level instruction
+    pub fn inner_instructions(&self) -> Result<Vec<message::InnerInstruction>, DecodeError> {
+        transaction::inner_instructions_at(self.inner_instruction_map, self.instruction_index)
+    }
+
+    // Pubkey of the instruction's account at `position`
+    pub fn account(&self, instruction: &message::CompiledInstruction, position: usize) -> Result<Pubkey, DecodeError> {
+        transaction::instruction_account(instruction, self.account_keys, position)
+    }
+
+    // Mint of the token account at `account_index`, taken from the transaction's token balances
+    pub fn token_account_mint(&self, account_index: u32) -> Result<Option<Pubkey>, DecodeError> {
+        let balance = self.meta.post_token_balances.iter()
+            .chain(self.meta.pre_token_balances.iter())
+            .find(|balance| balance.account_index == account_index);
+        match balance {
+            Some(balance) => Pubkey::from_str(&balance.mint)
+                .map(Some)
+                .map_err(|e| DecodeError::InvalidPubkey(format!("{}: {}", balance.mint, e))),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Decodes the instructions of a single on-chain program.
+///
+/// Returning `Ok(None)` means the instruction is not one this decoder understands and is
+/// left out of the decoded transaction.
+pub trait ProgramDecoder: Send + Sync {
+    fn program_id(&self) -> Pubkey;
+
+    fn decode(
+        &self,
+        instruction: &message::CompiledInstruction,
+        ctx: &DecodeContext,
+    ) -> Result<Option<DecodedInstruction>, DecodeError>;
+}
+
+/// Program decoders keyed by program id, consulted by [`transaction::decode`] for every
+/// top-level and inner instruction.
+pub struct ProgramDecoderRegistry {
+    decoders: HashMap<Pubkey, Box<dyn ProgramDecoder>>,
+}
+
+impl ProgramDecoderRegistry {
+    // An empty registry; use `default()` for one with the built-in decoders
+    pub fn new() -> Self {
+        ProgramDecoderRegistry { decoders: HashMap::new() }
+    }
+
+    // Register a decoder, replacing any previous decoder for the same program
+    pub fn register(&mut self, decoder: Box<dyn ProgramDecoder>) {
+        self.decoders.insert(decoder.program_id(), decoder);
+    }
+
+    // Register a generic decoder for the Anchor program described by an IDL file
+    pub fn register_idl(&mut self, path: impl AsRef<Path>) -> Result<(), IdlError> {
+        self.register(Box::new(AnchorDecoder::new(IdlProgram::from_file(path)?)));
+        Ok(())
+    }
+
+    pub fn get(&self, program_id: &Pubkey) -> Option<&dyn ProgramDecoder> {
+        self.decoders.get(program_id).map(|decoder| decoder.as_ref())
+    }
+
+    // Decode the instruction with the decoder registered for `program_id`, if any
+    pub fn decode(
+        &self,
+        program_id: &Pubkey,
+        instruction: &message::CompiledInstruction,
+        ctx: &DecodeContext,
+    ) -> Result<Option<DecodedInstruction>, DecodeError> {
+        match self.get(program_id) {
+            Some(decoder) => decoder.decode(instruction, ctx),
+            None => Ok(None),
+        }
+    }
+}
+
+impl Default for ProgramDecoderRegistry {
+    fn default() -> Self {
+        let mut registry = ProgramDecoderRegistry::new();
+        registry.register(Box::new(PumpfunDecoder));
+        registry.register(Box::new(RaydiumDecoder));
+        registry.register(Box::new(SystemProgramDecoder));
+        registry.register(Box::new(TokenProgramDecoder::spl_token()));
+        registry.register(Box::new(TokenProgramDecoder::spl_token_2022()));
+        registry.register(Box::new(ComputeBudgetDecoder));
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compiled(program_id_index: u32, accounts: Vec<u32>, data: Vec<u8>) -> message::CompiledInstruction {
+        message::CompiledInstruction { program_id_index, accounts, data }
+    }
+
+    fn token_balance(account_index: u32, mint: &Pubkey) -> message::TransactionTokenBalance {
+        message::TransactionTokenBalance {
+            account_index,
+            mint: mint.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn decode_with(
+        registry: &ProgramDecoderRegistry,
+        account_keys: &[Pubkey],
+        meta: &message::TransactionStatusMeta,
+        instruction: &message::CompiledInstruction,
+    ) -> Option<DecodedInstruction> {
+        let account_keys: Vec<Vec<u8>> = account_keys.iter().map(|key| key.to_bytes().to_vec()).collect();
+        let inner_instruction_map = HashMap::new();
+        let ctx = DecodeContext {
+            account_keys: &account_keys,
+            meta,
+            instruction_index: 0,
+            inner_position: None,
+            inner_instruction_map: &inner_instruction_map,
+            slot: 1,
+        };
+        let program_id = Pubkey::try_from(account_keys[instruction.program_id_index as usize].as_slice()).unwrap();
+        registry.decode(&program_id, instruction, &ctx).unwrap()
+    }
+
+    #[test]
+    fn test_system_transfer_is_decoded() {
+        let (from, to) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let data = bincode::serialize(&solana_sdk::system_instruction::SystemInstruction::Transfer { lamports: 42 }).unwrap();
+        let keys = [from, to, solana_sdk::system_program::id()];
+
+        let decoded = decode_with(&ProgramDecoderRegistry::default(), &keys, &Default::default(), &compiled(2, vec![0, 1], data));
+
+        match decoded {
+            Some(DecodedInstruction::SystemTransfer(transfer)) => {
+                assert_eq!(transfer.from_pubkey, from);
+                assert_eq!(transfer.to_pubkey, to);
+                assert_eq!(transfer.lamports, 42);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_undecodable_instructions_degrade_to_unknown() {
+        use prost::Message;
+        use std::sync::Arc;
+        use solana_client::nonblocking::rpc_client::RpcClient;
+        use crate::solana::address_table_cache::{AddressTableCache, RpcAccountFetcher};
+
+        let (from, to) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let data = bincode::serialize(&solana_sdk::system_instruction::SystemInstruction::Transfer { lamports: 42 }).unwrap();
+        let keys = [from, to, solana_sdk::system_program::id()];
+        let message = message::LegacyMessage {
+            account_keys: keys.iter().map(|key| key.to_bytes().to_vec()).collect(),
+            // The first instruction's program index is out of range
+            instructions: vec![compiled(7, vec![], vec![]), compiled(2, vec![0, 1], data)],
+            ..Default::default()
+        };
+        let event = message::TransactionEvent {
+            transaction: Some(message::SanitizedTransaction {
+                message: Some(message::SanitizedMessage {
+                    message_payload: Some(message::sanitized_message::MessagePayload::Legacy(message::LegacyLoadedMessage {
+                        message: Some(message),
+                        ..Default::default()
+                    })),
+                }),
+                ..Default::default()
+            }),
+            transaction_status_meta: Some(message::TransactionStatusMeta {
+                // An inner instruction without its instruction
+                inner_instructions: vec![message::InnerInstructions {
+                    index: 1,
+                    instructions: vec![message::InnerInstruction { instruction: None, stack_height: Some(2) }],
+                }],
+                ..Default::default()
+            }),
+            slot: 1,
+            ..Default::default()
+        };
+        // Legacy messages never look up tables
+        let address_table_cache = AddressTableCache::new(Arc::new(RpcAccountFetcher::new(RpcClient::new("http://127.0.0.1:1".to_string()))));
+
+        let decoded = transaction::decode(&event.encode_to_vec(), &address_table_cache, &ProgramDecoderRegistry::default()).await.unwrap();
+
+        assert_eq!(decoded.instruction_errors, vec!["account_index", "missing_inner_instructions"]);
+        assert!(matches!(decoded.instructions[..], [
+            DecodedInstruction::Unknown,
+            DecodedInstruction::SystemTransfer(_),
+            DecodedInstruction::Unknown,
+        ]));
+    }
+
+    #[test]
+    fn test_token_transfer_takes_mint_from_balances() {
+        let (source, destination, authority, mint) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let token_program = Pubkey::from_str("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA").unwrap();
+        let keys = [source, destination, authority, token_program];
+        let meta = message::TransactionStatusMeta {
+            post_token_balances: vec![token_balance(1, &mint)],
+            ..Default::default()
+        };
+        let mut data = vec![3];
+        data.extend_from_slice(&1_000u64.to_le_bytes());
+
+        let decoded = decode_with(&ProgramDecoderRegistry::default(), &keys, &meta, &compiled(3, vec![0, 1, 2], data));
+
+        match decoded {
+            Some(DecodedInstruction::TokenTransfer(transfer)) => {
+                assert_eq!(transfer.source_pubkey, source);
+                assert_eq!(transfer.destination_pubkey, destination);
+                assert_eq!(transfer.authority_pubkey, authority);
+                assert_eq!(transfer.mint_pubkey, mint);
+                assert_eq!(transfer.amount, 1_000);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_compute_budget_instructions_are_decoded() {
+        let compute_budget = Pubkey::from_str("ComputeBudget111111111111111111111111111111").unwrap();
+        let registry = ProgramDecoderRegistry::default();
+        let mut price = vec![3];
+        price.extend_from_slice(&5_000u64.to_le_bytes());
+        let mut limit = vec![2];
+        limit.extend_from_slice(&200_000u32.to_le_bytes());
+
+        let decoded_price = decode_with(&registry, &[compute_budget], &Default::default(), &compiled(0, vec![], price));
+        let decoded_limit = decode_with(&registry, &[compute_budget], &Default::default(), &compiled(0, vec![], limit));
+
+        assert!(matches!(decoded_price, Some(DecodedInstruction::ComputeUnitPrice(ref price)) if price.micro_lamports == 5_000));
+        assert!(matches!(decoded_limit, Some(DecodedInstruction::ComputeUnitLimit(ref limit)) if limit.units == 200_000));
+    }
+
+    // Self-CPI event data: event IX tag, Anchor event discriminator, Borsh fields
+    fn pumpfun_event(name: &str, fields: &[u8]) -> Vec<u8> {
+        use sha2::{Digest, Sha256};
+        let mut data = vec![0xe4, 0x45, 0xa5, 0x2e, 0x51, 0xcb, 0x9a, 0x1d];
+        data.extend_from_slice(&Sha256::digest(format!("event:{name}"))[..8]);
+        data.extend_from_slice(fields);
+        data
+    }
+
+    fn borsh_string(value: &str) -> Vec<u8> {
+        let mut bytes = (value.len() as u32).to_le_bytes().to_vec();
+        bytes.extend_from_slice(value.as_bytes());
+        bytes
+    }
+
+    fn trade_event_fields(mint: &Pubkey, sol_amount: u64, token_amount: u64, is_buy: bool, user: &Pubkey) -> Vec<u8> {
+        let mut fields = mint.to_bytes().to_vec();
+        fields.extend_from_slice(&sol_amount.to_le_bytes());
+        fields.extend_from_slice(&token_amount.to_le_bytes());
+        fields.push(is_buy as u8);
+        fields.extend_from_slice(user.as_ref());
+        fields.extend_from_slice(&1_730_000_000i64.to_le_bytes());
+        fields.extend_from_slice(&31_500_000_000u64.to_le_bytes());
+        fields.extend_from_slice(&1_038_000_000_000_000u64.to_le_bytes());
+        fields
+    }
+
+    #[test]
+    fn test_pumpfun_trade_event_is_decoded() {
+        let pumpfun = Pubkey::from_str(transaction::PUMPFUN_PROGRAM_ID).unwrap();
+        let (mint, user) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut fields = trade_event_fields(&mint, 1_500_000_000, 35_000_000_000, true, &user);
+        // Trailing bytes from newer event versions are ignored
+        fields.extend_from_slice(&[0xff; 8]);
+
+        let data = pumpfun_event("TradeEvent", &fields);
+        let decoded = decode_with(&ProgramDecoderRegistry::default(), &[pumpfun], &Default::default(), &compiled(0, vec![], data));
+
+        match decoded {
+            Some(DecodedInstruction::PFTradeEvent(event)) => {
+                assert_eq!(event.mint, mint);
+                assert_eq!(event.sol_amount, 1_500_000_000);
+                assert_eq!(event.token_amount, 35_000_000_000);
+                assert!(event.is_buy);
+                assert_eq!(event.user, user);
+                assert_eq!(event.timestamp, 1_730_000_000);
+                assert_eq!(event.virtual_sol_reserves, 31_500_000_000);
+                assert_eq!(event.virtual_token_reserves, 1_038_000_000_000_000);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_pumpfun_create_event_is_decoded() {
+        let pumpfun = Pubkey::from_str(transaction::PUMPFUN_PROGRAM_ID).unwrap();
+        let (mint, bonding_curve, user) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut fields = borsh_string("Token");
+        fields.extend(borsh_string("TKN"));
+        fields.extend(borsh_string("https://example.com/token.json"));
+        fields.extend_from_slice(mint.as_ref());
+        fields.extend_from_slice(bonding_curve.as_ref());
+        fields.extend_from_slice(user.as_ref());
+
+        let data = pumpfun_event("CreateEvent", &fields);
+        let decoded = decode_with(&ProgramDecoderRegistry::default(), &[pumpfun], &Default::default(), &compiled(0, vec![], data));
+
+        match decoded {
+            Some(DecodedInstruction::PFCreateEvent(event)) => {
+                assert_eq!(event.name, "Token");
+                assert_eq!(event.symbol, "TKN");
+                assert_eq!(event.uri, "https://example.com/token.json");
+                assert_eq!(event.mint, mint);
+                assert_eq!(event.bonding_curve, bonding_curve);
+                assert_eq!(event.user, user);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_pumpfun_buy_takes_amounts_from_trade_event() {
+        let pumpfun = Pubkey::from_str(transaction::PUMPFUN_PROGRAM_ID).unwrap();
+        // buy accounts: global, feeRecipient, mint, bondingCurve, associatedBondingCurve, associatedUser, user, ...
+        let mut keys: Vec<Pubkey> = (0..12).map(|_| Pubkey::new_unique()).collect();
+        keys.push(pumpfun);
+        let (mint, user) = (keys[2], keys[6]);
+        let account_keys: Vec<Vec<u8>> = keys.iter().map(|key| key.to_bytes().to_vec()).collect();
+
+        let mut data = crate::solana::idl::discriminator("global", "buy").to_vec();
+        data.extend_from_slice(&40_000_000_000u64.to_le_bytes());
+        data.extend_from_slice(&2_000_000_000u64.to_le_bytes());
+        let buy = compiled(12, (0..12).collect(), data);
+
+        // Another mint is listed first and two accounts move by the same amount
+        let other_mint = Pubkey::new_unique();
+        let meta = message::TransactionStatusMeta {
+            post_token_balances: vec![token_balance(8, &other_mint), token_balance(5, &mint)],
+            ..Default::default()
+        };
+        let event = pumpfun_event("TradeEvent", &trade_event_fields(&mint, 1_500_000_000, 35_123_456_789, true, &user));
+        let inner_instructions = message::InnerInstructions {
+            index: 0,
+            instructions: vec![message::InnerInstruction { instruction: Some(compiled(12, vec![], event)), stack_height: Some(2) }],
+        };
+        let inner_instruction_map = HashMap::from([(0, &inner_instructions)]);
+        let ctx = DecodeContext {
+            account_keys: &account_keys,
+            meta: &meta,
+            instruction_index: 0,
+            inner_position: None,
+            inner_instruction_map: &inner_instruction_map,
+            slot: 1,
+        };
+
+        match ProgramDecoderRegistry::default().decode(&pumpfun, &buy, &ctx).unwrap() {
+            Some(DecodedInstruction::PFBuy(buy)) => {
+                assert_eq!(buy.mint, mint);
+                assert_eq!(buy.user, user);
+                assert_eq!(buy.token_amount, 35_123_456_789);
+                assert_eq!(buy.sol_amount, 1_500_000_000);
+                assert!((buy.amount - 35_123.456789).abs() < 1e-9);
+                assert!((buy.used_sol_amount - 1.5).abs() < 1e-12);
+                assert!((buy.max_sol_cost - 2.0).abs() < 1e-12);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_pumpfun_sell_without_trade_event() {
+        let pumpfun = Pubkey::from_str(transaction::PUMPFUN_PROGRAM_ID).unwrap();
+        let mut keys: Vec<Pubkey> = (0..12).map(|_| Pubkey::new_unique()).collect();
+        keys.push(pumpfun);
+        let mint = keys[2];
+
+        let mut data = crate::solana::idl::discriminator("global", "sell").to_vec();
+        data.extend_from_slice(&40_000_000_000u64.to_le_bytes());
+        data.extend_from_slice(&1_000_000_000u64.to_le_bytes());
+        let sell = compiled(12, (0..12).collect(), data);
+        let executed = message::TransactionStatusMeta::default();
+        let failed = message::TransactionStatusMeta { is_status_err: true, ..Default::default() };
+
+        let account_keys: Vec<Vec<u8>> = keys.iter().map(|key| key.to_bytes().to_vec()).collect();
+        let inner_instruction_map = HashMap::new();
+        let decode = |meta: &message::TransactionStatusMeta| {
+            let ctx = DecodeContext {
+                account_keys: &account_keys,
+                meta,
+                instruction_index: 0,
+                inner_position: None,
+                inner_instruction_map: &inner_instruction_map,
+                slot: 1,
+            };
+            ProgramDecoderRegistry::default().decode(&pumpfun, &sell, &ctx)
+        };
+
+        // The limits are never reported as a fill
+        assert!(matches!(decode(&executed), Err(DecodeError::MissingTradeEvent(key)) if key == mint));
+        match decode(&failed).unwrap() {
+            Some(DecodedInstruction::PFSell(sell)) => {
+                assert_eq!((sell.token_amount, sell.sol_amount), (0, 0));
+                assert!((sell.min_sol_output - 1.0).abs() < 1e-12);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    fn raydium_token_balance(account_index: u32, mint: &Pubkey, decimals: u32) -> message::TransactionTokenBalance {
+        message::TransactionTokenBalance {
+            ui_token_account: Some(message::UiTokenAmount { decimals, ..Default::default() }),
+            ..token_balance(account_index, mint)
+        }
+    }
+
+    fn token_transfer(token_program_index: u32, source: u32, destination: u32, amount: u64) -> message::InnerInstruction {
+        let mut data = vec![3];
+        data.extend_from_slice(&amount.to_le_bytes());
+        message::InnerInstruction { instruction: Some(compiled(token_program_index, vec![source, destination, 2], data)), stack_height: Some(2) }
+    }
+
+    // Keys, swap and meta of a Raydium swap base in of 2 pc for at least 40 coin, with the
+    // 17 account layout without amm_target_orders: amm at 1, coin/pc vaults at 4/5, user
+    // source/destination at 14/15, owner at 16. The token program is key 0, Raydium key 17.
+    fn raydium_swap() -> (Vec<Pubkey>, (Pubkey, Pubkey), message::CompiledInstruction, message::TransactionStatusMeta) {
+        let mut keys: Vec<Pubkey> = (0..17).map(|_| Pubkey::new_unique()).collect();
+        keys[0] = Pubkey::from_str("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA").unwrap();
+        keys.push(Pubkey::from_str(transaction::RAYDIUM_PROGRAM_ID).unwrap());
+        let (coin_mint, pc_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        let mut data = vec![9];
+        data.extend_from_slice(&2_000_000_000u64.to_le_bytes());
+        data.extend_from_slice(&40_000_000u64.to_le_bytes());
+        let swap = compiled(17, (0..17).collect(), data);
+
+        // The user pays pc into the pool and receives coin
+        let meta = message::TransactionStatusMeta {
+            post_token_balances: vec![
+                raydium_token_balance(4, &coin_mint, 6),
+                raydium_token_balance(5, &pc_mint, 9),
+                raydium_token_balance(14, &pc_mint, 9),
+            ],
+            ..Default::default()
+        };
+        (keys, (coin_mint, pc_mint), swap, meta)
+    }
+
+    #[test]
+    fn test_raydium_swap_takes_pool_and_user_from_layout() {
+        let (keys, (coin_mint, pc_mint), swap, meta) = raydium_swap();
+        let raydium = keys[17];
+        let account_keys: Vec<Vec<u8>> = keys.iter().map(|key| key.to_bytes().to_vec()).collect();
+        let inner_instructions = message::InnerInstructions {
+            index: 0,
+            instructions: vec![token_transfer(0, 14, 5, 2_000_000_000), token_transfer(0, 4, 15, 45_500_000)],
+        };
+        let inner_instruction_map = HashMap::from([(0, &inner_instructions)]);
+        let ctx = DecodeContext {
+            account_keys: &account_keys,
+            meta: &meta,
+            instruction_index: 0,
+            inner_position: None,
+            inner_instruction_map: &inner_instruction_map,
+            slot: 1,
+        };
+
+        match ProgramDecoderRegistry::default().decode(&raydium, &swap, &ctx).unwrap() {
+            Some(DecodedInstruction::RaydiumSwapBaseIn(swap)) => {
+                assert_eq!(swap.pool.amm, keys[1]);
+                assert_eq!(swap.pool.coin_vault, keys[4]);
+                assert_eq!(swap.pool.pc_vault, keys[5]);
+                assert_eq!(swap.from_mint, pc_mint);
+                assert_eq!(swap.to_mint, coin_mint);
+                assert_eq!(swap.user, keys[16]);
+                assert_eq!(swap.raw_amount_in, 2_000_000_000);
+                assert_eq!(swap.raw_amount_out, 45_500_000);
+                assert!((swap.amount_in - 2.0).abs() < 1e-12);
+                assert!((swap.amount_out - 45.5).abs() < 1e-12);
+                assert!((swap.min_amount_out - 40.0).abs() < 1e-12);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_raydium_swap_through_cpi_takes_only_its_own_transfers() {
+        let (keys, _, swap, meta) = raydium_swap();
+        let account_keys: Vec<Vec<u8>> = keys.iter().map(|key| key.to_bytes().to_vec()).collect();
+        let nested = |mut inner: message::InnerInstruction| {
+            inner.stack_height = Some(3);
+            inner
+        };
+        // An aggregator moves tokens into and out of the vaults around the swap it invokes
+        let inner_instructions = message::InnerInstructions {
+            index: 0,
+            instructions: vec![
+                token_transfer(0, 14, 5, 1),
+                message::InnerInstruction { instruction: Some(swap.clone()), stack_height: Some(2) },
+                nested(token_transfer(0, 14, 5, 2_000_000_000)),
+                nested(token_transfer(0, 4, 15, 45_500_000)),
+                token_transfer(0, 4, 15, 7),
+            ],
+        };
+        let inner_instruction_map = HashMap::from([(0, &inner_instructions)]);
+        let ctx = DecodeContext {
+            account_keys: &account_keys,
+            meta: &meta,
+            instruction_index: 0,
+            inner_position: Some(1),
+            inner_instruction_map: &inner_instruction_map,
+            slot: 1,
+        };
+
+        match ProgramDecoderRegistry::default().decode(&keys[17], &swap, &ctx).unwrap() {
+            Some(DecodedInstruction::RaydiumSwapBaseIn(swap)) => {
+                assert_eq!(swap.raw_amount_in, 2_000_000_000);
+                assert_eq!(swap.raw_amount_out, 45_500_000);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_raydium_initialize2_is_decoded() {
+        let raydium = Pubkey::from_str(transaction::RAYDIUM_PROGRAM_ID).unwrap();
+        let mut keys: Vec<Pubkey> = (0..21).map(|_| Pubkey::new_unique()).collect();
+        keys.push(raydium);
+
+        let mut data = vec![1, 254];
+        data.extend_from_slice(&1_730_000_000u64.to_le_bytes());
+        data.extend_from_slice(&79_000_000_000u64.to_le_bytes());
+        data.extend_from_slice(&206_900_000_000_000u64.to_le_bytes());
+        let initialize = compiled(21, (0..21).collect(), data);
+
+        match decode_with(&ProgramDecoderRegistry::default(), &keys, &Default::default(), &initialize) {
+            Some(DecodedInstruction::RaydiumInitialize2(init)) => {
+                assert_eq!(init.pool.amm, keys[4]);
+                assert_eq!(init.lp_mint, keys[7]);
+                assert_eq!(init.pool.coin_mint, keys[8]);
+                assert_eq!(init.pool.pc_mint, keys[9]);
+                assert_eq!(init.pool.coin_vault, keys[10]);
+                assert_eq!(init.pool.pc_vault, keys[11]);
+                assert_eq!(init.market, keys[16]);
+                assert_eq!(init.user, keys[17]);
+                assert_eq!(init.nonce, 254);
+                assert_eq!(init.open_time, 1_730_000_000);
+                assert_eq!(init.init_pc_amount, 79_000_000_000);
+                assert_eq!(init.init_coin_amount, 206_900_000_000_000);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unregistered_program_is_skipped() {
+        let program = Pubkey::new_unique();
+        let decoded = decode_with(&ProgramDecoderRegistry::default(), &[program], &Default::default(), &compiled(0, vec![], vec![1, 2, 3]));
+        assert!(decoded.is_none());
+    }
+}
@@ -0,0 +1,33 @@
+use std::str::FromStr;
+use solana_sdk::pubkey::Pubkey;
+use crate::solana::transaction::{self, message, DecodeError, DecodedInstruction};
+use super::{DecodeContext, ProgramDecoder};
+
+/// Decodes Pump.fun bonding curve instructions.
+pub struct PumpfunDecoder;
+
+impl ProgramDecoder for PumpfunDecoder {
+    fn program_id(&self) -> Pubkey {
+        Pubkey::from_str(transaction::PUMPFUN_PROGRAM_ID).unwrap()
+    }
+
+    fn decode(
+        &self,
+        instruction: &message::CompiledInstruction,
+        ctx: &DecodeContext,
+    ) -> Result<Option<DecodedInstruction>, DecodeError> {
+        // Events are logged through a self-CPI, so they arrive here as inner instructions
+        if let Some(event) = transaction::decode_pumpfun_event(&instruction.data)? {
+            return Ok(Some(event));
+        }
+
+        // A failed trade has no inner instructions and so no trade event
+        let inner_instructions = ctx.inner_instruction_map
+            .get(&ctx.instruction_index)
+            .map(|inner| inner.instructions.as_slice())
+            .unwrap_or_default();
+
+        transaction::decode_pumpfun_instruction(instruction, ctx.account_keys, inner_instructions, ctx.inner_position, ctx.meta)
+            .map(Some)
+    }
+}
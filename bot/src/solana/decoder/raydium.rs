@@ -0,0 +1,28 @@
+use std::str::FromStr;
+use solana_sdk::pubkey::Pubkey;
+use crate::solana::transaction::{self, message, DecodeError, DecodedInstruction};
+use super::{DecodeContext, ProgramDecoder};
+
+/// Decodes Raydium AMM v4 pool creation, liquidity and swap instructions.
+pub struct RaydiumDecoder;
+
+impl ProgramDecoder for RaydiumDecoder {
+    fn program_id(&self) -> Pubkey {
+        Pubkey::from_str(transaction::RAYDIUM_PROGRAM_ID).unwrap()
+    }
+
+    fn decode(
+        &self,
+        instruction: &message::CompiledInstruction,
+        ctx: &DecodeContext,
+    ) -> Result<Option<DecodedInstruction>, DecodeError> {
+        // A failed instruction has no inner instructions and moves no tokens
+        let inner_instructions = ctx.inner_instruction_map
+            .get(&ctx.instruction_index)
+            .map(|inner| inner.instructions.as_slice())
+            .unwrap_or_default();
+
+        transaction::decode_raydium_instruction(instruction, ctx.account_keys, inner_instructions, ctx.inner_position, ctx.meta)
+            .map(Some)
+    }
+}
@@ -0,0 +1,37 @@
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::system_instruction::SystemInstruction;
+use crate::solana::transaction::{message, DecodeError, DecodedInstruction, SystemTransferInstruction};
+use super::{DecodeContext, ProgramDecoder};
+
+/// Decodes SOL transfers made through the System program.
+pub struct SystemProgramDecoder;
+
+impl ProgramDecoder for SystemProgramDecoder {
+    fn program_id(&self) -> Pubkey {
+        solana_sdk::system_program::id()
+    }
+
+    fn decode(
+        &self,
+        instruction: &message::CompiledInstruction,
+        ctx: &DecodeContext,
+    ) -> Result<Option<DecodedInstruction>, DecodeError> {
+        // System instructions are bincode encoded with a u32 variant tag
+        let Ok(system_instruction) = bincode::deserialize::<SystemInstruction>(&instruction.data) else {
+            return Ok(None);
+        };
+
+        let (lamports, to_position) = match system_instruction {
+            SystemInstruction::Transfer { lamports } => (lamports, 1),
+            // Accounts are [from, base, to]
+            SystemInstruction::TransferWithSeed { lamports, .. } => (lamports, 2),
+            _ => return Ok(None),
+        };
+
+        Ok(Some(DecodedInstruction::SystemTransfer(SystemTransferInstruction {
+            from_pubkey: ctx.account(instruction, 0)?,
+            to_pubkey: ctx.account(instruction, to_position)?,
+            lamports,
+        })))
+    }
+}
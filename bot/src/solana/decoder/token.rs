@@ -0,0 +1,75 @@
+use std::str::FromStr;
+use solana_sdk::pubkey::Pubkey;
+use crate::solana::transaction::{message, DecodeError, DecodedInstruction, TokenTransferInstruction};
+use super::{DecodeContext, ProgramDecoder};
+
+const SPL_TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+const SPL_TOKEN_2022_PROGRAM_ID: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
+
+// Instruction tags shared by SPL Token and Token-2022
+const TRANSFER: u8 = 3;
+const TRANSFER_CHECKED: u8 = 12;
+
+/// Decodes `Transfer` and `TransferChecked` for an SPL Token compatible program.
+pub struct TokenProgramDecoder {
+    program_id: Pubkey,
+}
+
+impl TokenProgramDecoder {
+    pub fn spl_token() -> Self {
+        TokenProgramDecoder { program_id: Pubkey::from_str(SPL_TOKEN_PROGRAM_ID).unwrap() }
+    }
+
+    pub fn spl_token_2022() -> Self {
+        TokenProgramDecoder { program_id: Pubkey::from_str(SPL_TOKEN_2022_PROGRAM_ID).unwrap() }
+    }
+}
+
+impl ProgramDecoder for TokenProgramDecoder {
+    fn program_id(&self) -> Pubkey {
+        self.program_id
+    }
+
+    fn decode(
+        &self,
+        instruction: &message::CompiledInstruction,
+        ctx: &DecodeContext,
+    ) -> Result<Option<DecodedInstruction>, DecodeError> {
+        let Some(amount_bytes) = instruction.data.get(1..9) else {
+            return Ok(None);
+        };
+        let amount = u64::from_le_bytes(amount_bytes.try_into().unwrap());
+
+        // Account positions of (source, destination, authority) for each instruction
+        let (source, destination, authority, mint) = match instruction.data[0] {
+            TRANSFER => (0, 1, 2, None),
+            TRANSFER_CHECKED => (0, 2, 3, Some(ctx.account(instruction, 1)?)),
+            _ => return Ok(None),
+        };
+
+        // A plain transfer does not name the mint, so look it up from the token balances
+        let mint = match mint {
+            Some(mint) => mint,
+            None => {
+                let mut mint = None;
+                for position in [source, destination] {
+                    if let Some(&account_index) = instruction.accounts.get(position) {
+                        mint = mint.or(ctx.token_account_mint(account_index)?);
+                    }
+                }
+                match mint {
+                    Some(mint) => mint,
+                    None => return Ok(None),
+                }
+            }
+        };
+
+        Ok(Some(DecodedInstruction::TokenTransfer(TokenTransferInstruction {
+            source_pubkey: ctx.account(instruction, source)?,
+            destination_pubkey: ctx.account(instruction, destination)?,
+            mint_pubkey: mint,
+            amount,
+            authority_pubkey: ctx.account(instruction, authority)?,
+        })))
+    }
+}
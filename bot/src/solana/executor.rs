@@ -0,0 +1,797 @@
+use std::collections::HashMap;
+use std::env;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use async_trait::async_trait;
+use dotenv::dotenv;
+use log::{error, warn};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::VersionedTransaction;
+use spl_associated_token_account::get_associated_token_address;
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use tokio::task::JoinHandle;
+use crate::financial_services::bonding_curve::{BondingCurve, CurveError, SellAmount};
+use crate::financial_services::curve_cache::{CurveCache, CurveCacheError};
+use crate::jito::bundle_client::{BundleClient, BundleError};
+use crate::jito::bundle_results::{BundleResultTracker, OrderStatus};
+use crate::jito::leader_schedule::{LeaderScheduler, SubmissionDecision};
+use crate::jito::tip_oracle::{TipOracle, TipPolicy};
+use crate::solana::address_table_cache::{AccountFetcher, AddressTableError};
+use crate::solana::blockhash::{BlockhashError, BlockhashProvider};
+use crate::solana::broadcaster::{BroadcastError, Broadcaster};
+use crate::solana::fee_estimator::PriorityFeeEstimator;
+use crate::solana::order::{OrderBook, OrderError, OrderId};
+use crate::solana::position_tracker::PositionTracker;
+use crate::solana::signer::TradeSigner;
+use crate::solana::trade_builder::{spl_token_program_id, JitoTip, TradeBuilder, TradeParams};
+use crate::solana::transaction::{
+    self, DecodedInstruction, DecodedTransaction, PFBuyInstruction, PFSellInstruction, TokenAccount,
+};
+
+const LAMPORTS_PER_SOL: f64 = 1_000_000_000.0;
+// Pump.fun mints all use six decimals
+const PUMPFUN_TOKEN_UNITS: f64 = 1_000_000.0;
+// Used when MAX_PRICE_IMPACT_BPS is unset
+const DEFAULT_MAX_PRICE_IMPACT_BPS: u64 = 1_000;
+// Used when PRIORITY_FEE_PERCENTILE and PRIORITY_FEE_SLOTS are unset
+const DEFAULT_PRIORITY_FEE_PERCENTILE: u8 = 75;
+const DEFAULT_PRIORITY_FEE_SLOTS: u64 = 150;
+// A bundle held this long for a Jito leader is sent through the default block engine anyway
+const MAX_LEADER_WAIT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExecutorError {
+    #[error("CurveError {0}")]
+    Curve(#[from] CurveError),
+    #[error("CurveCacheError {0}")]
+    CurveCache(#[from] CurveCacheError),
+    #[error("no cached bonding curve for {0}")]
+    UnknownCurve(Pubkey),
+    #[error("FetchError {0}")]
+    Fetch(#[from] AddressTableError),
+    #[error("invalid token account {0}")]
+    InvalidTokenAccount(Pubkey),
+    #[error("BlockhashError {0}")]
+    Blockhash(#[from] BlockhashError),
+    #[error("no Jito tips observed yet for the tip policy")]
+    NoTipEstimate,
+    #[error("OrderError {0}")]
+    Order(#[from] OrderError),
+    #[error("BroadcastError {0}")]
+    Broadcast(#[from] BroadcastError),
+    #[error("BundleError {0}")]
+    Bundle(#[from] BundleError),
+    #[error("no endpoint accepted transaction {0}")]
+    Rejected(Signature),
+    #[error("price moved past the slippage bound: needed {needed} lamports, bound {bound}")]
+    SlippageExceeded { needed: u64, bound: u64 },
+    #[error("cannot sell {needed} tokens of {mint}, only {held} held")]
+    InsufficientHolding { mint: Pubkey, held: u64, needed: u64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+/// Tokens and lamports that changed hands in a trade; `sol` includes the Pump.fun fee.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fill {
+    pub mint: Pubkey,
+    pub side: Side,
+    pub tokens: u64,
+    pub sol: u64,
+    pub fee: u64,
+}
+
+/// What an executor did with an order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Execution {
+    pub signature: Signature,
+    /// Set for orders tracked by an `OrderBook`
+    pub order_id: Option<OrderId>,
+    /// Known straight away for paper trades; live fills arrive through the order book
+    pub fill: Option<Fill>,
+}
+
+/// Places Pump.fun orders for a strategy, for real or on paper.
+#[async_trait]
+pub trait Executor: Send + Sync {
+    async fn buy(&self, mint: &Pubkey, sol_budget: u64, slippage_bps: u64) -> Result<Execution, ExecutorError>;
+    async fn sell(&self, mint: &Pubkey, amount: SellAmount, slippage_bps: u64) -> Result<Execution, ExecutorError>;
+}
+
+/// Fee and risk settings for `LiveExecutor`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LiveExecutorConfig {
+    /// Priority fee in micro-lamports per compute unit, used when the fee estimator has no data
+    pub compute_unit_price: u64,
+    /// Used when the fee estimator has no data
+    pub compute_unit_limit: u32,
+    /// Percentile of the compute budgets other Pump.fun traders set over the last `fee_slots` slots
+    pub fee_percentile: u8,
+    pub fee_slots: u64,
+    pub tip_policy: TipPolicy,
+    pub max_impact_bps: u64,
+}
+
+impl LiveExecutorConfig {
+    // COMPUTE_PRICE and COMPUTE_LIMIT are required as the fallback compute budget;
+    // PRIORITY_FEE_PERCENTILE, PRIORITY_FEE_SLOTS and MAX_PRICE_IMPACT_BPS have defaults
+    pub fn from_env(tip_policy: TipPolicy) -> Result<Self, Box<dyn std::error::Error>> {
+        dotenv().ok();
+        let compute_unit_price = env::var("COMPUTE_PRICE").map_err(|_| "COMPUTE_PRICE not set in environment")?.parse()?;
+        let compute_unit_limit = env::var("COMPUTE_LIMIT").map_err(|_| "COMPUTE_LIMIT not set in environment")?.parse()?;
+        let fee_percentile = match env::var("PRIORITY_FEE_PERCENTILE") {
+            Ok(percentile) => percentile.parse()?,
+            Err(_) => DEFAULT_PRIORITY_FEE_PERCENTILE,
+        };
+        let fee_slots = match env::var("PRIORITY_FEE_SLOTS") {
+            Ok(slots) => slots.parse()?,
+            Err(_) => DEFAULT_PRIORITY_FEE_SLOTS,
+        };
+        let max_impact_bps = match env::var("MAX_PRICE_IMPACT_BPS") {
+            Ok(bps) => bps.parse()?,
+            Err(_) => DEFAULT_MAX_PRICE_IMPACT_BPS,
+        };
+        Ok(LiveExecutorConfig {
+            compute_unit_price,
+            compute_unit_limit,
+            fee_percentile,
+            fee_slots,
+            tip_policy,
+            max_impact_bps,
+        })
+    }
+}
+
+/// Signs orders, registers them with the `OrderBook` and broadcasts them.
+///
+/// Every transaction it sends belongs to an order, so the order book follows it until it lands,
+/// fails or expires, and `spawn_resubmission` rebuilds the ones whose blockhash expired.
+/// With a `BundleClient` orders carry a Jito tip to one of its cached tip accounts and go out as
+/// bundles; without one they are broadcast untipped. With a `BundleResultTracker` as well, each
+/// bundle's results move its order along until the bundle is done or the order is resubmitted.
+/// With a `LeaderScheduler` bundles wait for an upcoming Jito leader and go to the block engine of
+/// its region when a client for that region was added with `with_regional_bundle_client`.
+/// With a `PriorityFeeEstimator` the compute budget follows what other Pump.fun traders set,
+/// falling back to the configured one.
+pub struct LiveExecutor {
+    signer: Arc<dyn TradeSigner>,
+    config: LiveExecutorConfig,
+    curve_cache: Arc<CurveCache>,
+    fetcher: Arc<dyn AccountFetcher>,
+    blockhash_provider: Arc<dyn BlockhashProvider>,
+    tip_oracle: Arc<TipOracle>,
+    order_book: Arc<OrderBook>,
+    broadcaster: Arc<Broadcaster>,
+    bundle_client: Option<Arc<BundleClient>>,
+    bundle_results: Option<Arc<BundleResultTracker>>,
+    leader_scheduler: Option<Arc<LeaderScheduler>>,
+    fee_estimator: Option<Arc<PriorityFeeEstimator>>,
+    // Keyed by block engine URL, as given in `SubmissionDecision::Redirect`
+    regional_bundle_clients: HashMap<String, Arc<BundleClient>>,
+}
+
+impl LiveExecutor {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        signer: Arc<dyn TradeSigner>,
+        config: LiveExecutorConfig,
+        curve_cache: Arc<CurveCache>,
+        fetcher: Arc<dyn AccountFetcher>,
+        blockhash_provider: Arc<dyn BlockhashProvider>,
+        tip_oracle: Arc<TipOracle>,
+        order_book: Arc<OrderBook>,
+        broadcaster: Arc<Broadcaster>,
+    ) -> Self {
+        LiveExecutor {
+            signer,
+            config,
+            curve_cache,
+            fetcher,
+            blockhash_provider,
+            tip_oracle,
+            order_book,
+            broadcaster,
+            bundle_client: None,
+            bundle_results: None,
+            leader_scheduler: None,
+            fee_estimator: None,
+            regional_bundle_clients: HashMap::new(),
+        }
+    }
+
+    pub fn with_bundle_client(mut self, bundle_client: Arc<BundleClient>) -> Self {
+        self.bundle_client = Some(bundle_client);
+        self
+    }
+
+    // The tracker has to be subscribed, e.g. with `BundleResultTracker::spawn`
+    pub fn with_bundle_results(mut self, bundle_results: Arc<BundleResultTracker>) -> Self {
+        self.bundle_results = Some(bundle_results);
+        self
+    }
+
+    // The scheduler has to be kept current, e.g. with `LeaderScheduler::spawn`
+    pub fn with_leader_scheduler(mut self, leader_scheduler: Arc<LeaderScheduler>) -> Self {
+        self.leader_scheduler = Some(leader_scheduler);
+        self
+    }
+
+    pub fn with_fee_estimator(mut self, fee_estimator: Arc<PriorityFeeEstimator>) -> Self {
+        self.fee_estimator = Some(fee_estimator);
+        self
+    }
+
+    pub fn with_regional_bundle_client(mut self, block_engine_url: String, bundle_client: Arc<BundleClient>) -> Self {
+        self.regional_bundle_clients.insert(block_engine_url, bundle_client);
+        self
+    }
+
+    // Waits for a Jito leader and picks the client of the block engine it is reachable through
+    async fn bundle_client_for_next_leader<'a>(&'a self, bundle_client: &'a Arc<BundleClient>) -> &'a Arc<BundleClient> {
+        let Some(leader_scheduler) = &self.leader_scheduler else {
+            return bundle_client;
+        };
+        match tokio::time::timeout(MAX_LEADER_WAIT, leader_scheduler.wait_for_leader()).await {
+            Ok(SubmissionDecision::Redirect { block_engine_url, .. }) => {
+                self.regional_bundle_clients.get(&block_engine_url).unwrap_or(bundle_client)
+            }
+            Ok(_) => bundle_client,
+            Err(_) => {
+                warn!("No Jito leader within {:?}, sending the bundle anyway", MAX_LEADER_WAIT);
+                bundle_client
+            }
+        }
+    }
+
+    async fn trade_params(&self) -> Result<TradeParams, ExecutorError> {
+        let tip = match &self.bundle_client {
+            Some(bundle_client) => {
+                let lamports = self.config.tip_policy.resolve(&self.tip_oracle).ok_or(ExecutorError::NoTipEstimate)?;
+                Some(JitoTip { account: bundle_client.random_tip_account().await?, lamports })
+            }
+            None => None,
+        };
+        let params = TradeParams::from_provider(
+            self.blockhash_provider.as_ref(),
+            self.config.compute_unit_price,
+            self.config.compute_unit_limit,
+            tip,
+        )?;
+        Ok(match &self.fee_estimator {
+            Some(fee_estimator) => params.with_estimated_compute_budget(
+                fee_estimator,
+                &Pubkey::from_str(transaction::PUMPFUN_PROGRAM_ID).unwrap(),
+                self.config.fee_percentile,
+                self.config.fee_slots,
+            ),
+            None => params,
+        })
+    }
+
+    pub fn payer(&self) -> Pubkey {
+        self.signer.pubkey()
+    }
+
+    /// Signs arbitrary trade instructions into a new order and sends it.
+    pub async fn submit(&self, instructions: Vec<Instruction>) -> Result<Execution, ExecutorError> {
+        let builder = TradeBuilder::new(self.signer.as_ref(), self.trade_params().await?);
+        let (order_id, transaction) = self.order_book.create(&builder, instructions).await?;
+        let signature = self.send(order_id, &transaction).await?;
+        Ok(Execution { signature, order_id: Some(order_id), fill: None })
+    }
+
+    /// Rebuilds and resends orders whose blockhash expired; orders out of attempts become `Expired`.
+    pub async fn resubmit_expired(&self) {
+        for (order_id, rebuilt) in self.order_book.resubmit_expired(self.signer.as_ref(), self.blockhash_provider.as_ref()).await {
+            let result = match rebuilt {
+                Ok(transaction) => self.send(order_id, &transaction).await.map(|_| ()),
+                Err(e) => self.order_book.mark_failed(order_id, e.to_string()).map_err(ExecutorError::from),
+            };
+            if let Err(e) = result {
+                error!("Failed to resubmit order {}: {}", order_id, e);
+            }
+        }
+    }
+
+    // Checks for expired orders every `interval` until the handle is aborted
+    pub fn spawn_resubmission(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let executor = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                executor.resubmit_expired().await;
+            }
+        })
+    }
+
+    // Sends a Built order's transaction and records the outcome in the order book
+    async fn send(&self, order_id: OrderId, transaction: &VersionedTransaction) -> Result<Signature, ExecutorError> {
+        if let Some(bundle_client) = &self.bundle_client {
+            let bundle_client = self.bundle_client_for_next_leader(bundle_client).await;
+            return match bundle_client.send_bundle(std::slice::from_ref(transaction)).await {
+                Ok(bundle_id) => {
+                    self.order_book.mark_sent(order_id)?;
+                    if let Some(bundle_results) = &self.bundle_results {
+                        self.follow_bundle(order_id, bundle_results.track(order_id, bundle_id));
+                    }
+                    Ok(transaction.signatures[0])
+                }
+                Err(e) => {
+                    self.order_book.mark_failed(order_id, e.to_string())?;
+                    Err(e.into())
+                }
+            };
+        }
+        match self.broadcaster.broadcast(transaction).await {
+            Ok(report) if report.accepted() => {
+                self.order_book.mark_sent(order_id)?;
+                Ok(report.signature)
+            }
+            Ok(report) => {
+                self.order_book.mark_failed(order_id, "rejected by every endpoint".to_string())?;
+                Err(ExecutorError::Rejected(report.signature))
+            }
+            Err(e) => {
+                self.order_book.mark_failed(order_id, e.to_string())?;
+                Err(e.into())
+            }
+        }
+    }
+
+    // Tracking the order's next bundle closes `status`, which ends the task
+    fn follow_bundle(&self, order_id: OrderId, mut status: OrderStatus) {
+        let order_book = Arc::clone(&self.order_book);
+        tokio::spawn(async move {
+            while let Some(bundle_status) = status.changed().await {
+                if let Err(e) = order_book.apply_bundle_status(order_id, &bundle_status) {
+                    error!("Failed to apply bundle result to order {}: {}", order_id, e);
+                }
+                if bundle_status.is_terminal() {
+                    break;
+                }
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl Executor for LiveExecutor {
+    async fn buy(&self, mint: &Pubkey, sol_budget: u64, slippage_bps: u64) -> Result<Execution, ExecutorError> {
+        let curve = self.curve_cache.get_or_seed(self.fetcher.as_ref(), mint).await?;
+        let order = curve.buy_order(sol_budget, slippage_bps, self.config.max_impact_bps)?;
+        let user = self.signer.pubkey();
+        let instructions = vec![
+            create_associated_token_account_idempotent(&user, &user, mint, &spl_token_program_id()),
+            transaction::pumpfun_buy_instruction(mint, &user, order.amount, order.max_sol_cost),
+        ];
+        self.submit(instructions).await
+    }
+
+    async fn sell(&self, mint: &Pubkey, amount: SellAmount, slippage_bps: u64) -> Result<Execution, ExecutorError> {
+        let curve = self.curve_cache.get_or_seed(self.fetcher.as_ref(), mint).await?;
+        let user = self.signer.pubkey();
+        let token_account = get_associated_token_address(&user, mint);
+        let account = self.fetcher.fetch_account(&token_account).await?;
+        let balance = TokenAccount::decode(&account.data).ok_or(ExecutorError::InvalidTokenAccount(token_account))?.amount;
+        let order = curve.sell_order(amount.resolve(balance), slippage_bps, self.config.max_impact_bps)?;
+        let instruction = transaction::pumpfun_sell_instruction(mint, &user, order.amount, order.min_sol_output);
+        self.submit(vec![instruction]).await
+    }
+}
+
+/// Simulation settings for `PaperExecutor`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaperConfig {
+    /// Delay between placing an order and filling it, during which the live curve may move
+    pub latency: Duration,
+    /// Network fee, priority fee and tip charged per trade
+    pub fee_lamports: u64,
+    pub max_impact_bps: u64,
+}
+
+/// Fills orders instantly against the live curve-state cache without sending anything.
+///
+/// Orders are sized from the curve when placed and filled from the curve after `latency`, failing
+/// like the program would when the price moved past the slippage bound. Fills are recorded as
+/// Pump.fun trades of a made-up wallet in the executor's own `PositionTracker`, so paper PnL is
+/// computed the same way as live PnL and never mixes with it.
+pub struct PaperExecutor {
+    wallet: Pubkey,
+    config: PaperConfig,
+    curve_cache: Arc<CurveCache>,
+    positions: Mutex<PositionTracker>,
+    // Raw token balance per mint, for percentage sells
+    holdings: Mutex<HashMap<Pubkey, u64>>,
+}
+
+impl PaperExecutor {
+    pub fn new(curve_cache: Arc<CurveCache>, config: PaperConfig) -> Self {
+        let wallet = Pubkey::new_unique();
+        PaperExecutor {
+            wallet,
+            config,
+            curve_cache,
+            positions: Mutex::new(PositionTracker::new_with_accounts(vec![wallet])),
+            holdings: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The made-up wallet paper positions are tracked under.
+    pub fn wallet(&self) -> Pubkey {
+        self.wallet
+    }
+
+    pub fn positions(&self) -> MutexGuard<'_, PositionTracker> {
+        self.positions.lock().unwrap()
+    }
+
+    pub fn holding(&self, mint: &Pubkey) -> u64 {
+        self.holdings.lock().unwrap().get(mint).copied().unwrap_or(0)
+    }
+
+    // Prices the paper holdings at the live curves, so `pl` reflects moves since the last paper fill
+    pub fn mark_to_market(&self) {
+        let mut positions = self.positions.lock().unwrap();
+        for mint in self.holdings.lock().unwrap().keys() {
+            if let Some(curve) = self.curve_cache.get(mint) {
+                let sol = curve.virtual_sol_reserves as f64 / LAMPORTS_PER_SOL;
+                let tokens = curve.virtual_token_reserves as f64 / PUMPFUN_TOKEN_UNITS;
+                positions.update_price(*mint, sol / tokens);
+            }
+        }
+    }
+
+    /// Paper PnL of `mint` in SOL, comparable to `PositionTracker::get_pl` of a live wallet.
+    pub fn pl(&self, mint: &Pubkey) -> Option<f64> {
+        self.positions.lock().unwrap().get_pl(&self.wallet, mint)
+    }
+
+    fn curve(&self, mint: &Pubkey) -> Result<BondingCurve, ExecutorError> {
+        self.curve_cache.get(mint).ok_or(ExecutorError::UnknownCurve(*mint))
+    }
+
+    // Sells are sized before the latency sleep, so the holding is checked again here: an
+    // overlapping sell may have spent it in the meantime
+    fn record(&self, fill: Fill, instruction: DecodedInstruction) -> Result<Execution, ExecutorError> {
+        let mut positions = self.positions.lock().unwrap();
+        let mut holdings = self.holdings.lock().unwrap();
+        let holding = holdings.entry(fill.mint).or_default();
+        *holding = match fill.side {
+            Side::Buy => *holding + fill.tokens,
+            Side::Sell => holding.checked_sub(fill.tokens).ok_or(ExecutorError::InsufficientHolding {
+                mint: fill.mint,
+                held: *holding,
+                needed: fill.tokens,
+            })?,
+        };
+
+        let signature = Signature::new_unique();
+        let mut transaction = DecodedTransaction::new_empty();
+        transaction.slot = self.curve_cache.get_cached(&fill.mint).map_or(0, |cached| cached.slot);
+        transaction.fee = self.config.fee_lamports as f64 / LAMPORTS_PER_SOL;
+        transaction.signatures = vec![signature.as_ref().to_vec()];
+        transaction.instructions = vec![instruction];
+        positions.update_by_transaction(&transaction);
+        Ok(Execution { signature, order_id: None, fill: Some(fill) })
+    }
+}
+
+#[async_trait]
+impl Executor for PaperExecutor {
+    async fn buy(&self, mint: &Pubkey, sol_budget: u64, slippage_bps: u64) -> Result<Execution, ExecutorError> {
+        let order = self.curve(mint)?.buy_order(sol_budget, slippage_bps, self.config.max_impact_bps)?;
+        tokio::time::sleep(self.config.latency).await;
+
+        let quote = self.curve(mint)?.quote_buy_exact_tokens(order.amount)?;
+        if quote.total() > order.max_sol_cost {
+            return Err(ExecutorError::SlippageExceeded { needed: quote.total(), bound: order.max_sol_cost });
+        }
+        let fill = Fill { mint: *mint, side: Side::Buy, tokens: quote.tokens_out, sol: quote.total(), fee: quote.fee };
+        self.record(fill, DecodedInstruction::PFBuy(PFBuyInstruction {
+            amount: quote.tokens_out as f64 / PUMPFUN_TOKEN_UNITS,
+            max_sol_cost: order.max_sol_cost as f64 / LAMPORTS_PER_SOL,
+            mint: *mint,
+            used_sol_amount: quote.total() as f64 / LAMPORTS_PER_SOL,
+            user: self.wallet,
+            token_amount: quote.tokens_out,
+            sol_amount: quote.total(),
+        }))
+    }
+
+    async fn sell(&self, mint: &Pubkey, amount: SellAmount, slippage_bps: u64) -> Result<Execution, ExecutorError> {
+        let tokens = amount.resolve(self.holding(mint));
+        let order = self.curve(mint)?.sell_order(tokens, slippage_bps, self.config.max_impact_bps)?;
+        tokio::time::sleep(self.config.latency).await;
+
+        let quote = self.curve(mint)?.quote_sell(order.amount)?;
+        if quote.net() < order.min_sol_output {
+            return Err(ExecutorError::SlippageExceeded { needed: quote.net(), bound: order.min_sol_output });
+        }
+        let fill = Fill { mint: *mint, side: Side::Sell, tokens: quote.tokens_in, sol: quote.net(), fee: quote.fee };
+        self.record(fill, DecodedInstruction::PFSell(PFSellInstruction {
+            amount: quote.tokens_in as f64 / PUMPFUN_TOKEN_UNITS,
+            min_sol_output: order.min_sol_output as f64 / LAMPORTS_PER_SOL,
+            mint: *mint,
+            received_sol_amount: quote.net() as f64 / LAMPORTS_PER_SOL,
+            user: self.wallet,
+            token_amount: quote.tokens_in,
+            sol_amount: quote.net(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::Ordering;
+    use solana_client::nonblocking::rpc_client::RpcClient;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::signature::Keypair;
+    use solana_sdk::system_instruction;
+    use jito_protos::bundle::{bundle_result, BundleResult, Processed};
+    use jito_protos::searcher::{GetRegionsResponse, NextScheduledLeaderResponse};
+    use crate::jito::bundle_client::tests::{connect, serve, MockSearcher};
+    use crate::jito::leader_schedule::block_engine_url;
+    use crate::solana::address_table_cache::RpcAccountFetcher;
+    use crate::solana::blockhash::{BlockhashCache, RecentBlockhash};
+    use crate::solana::broadcaster::{self, BroadcastConfig, Endpoint, EndpointKind};
+    use crate::solana::order::{OrderState, ResubmitPolicy};
+
+    fn executor(latency: Duration) -> (Arc<CurveCache>, PaperExecutor, Pubkey) {
+        let curve_cache = Arc::new(CurveCache::default());
+        let mint = Pubkey::new_unique();
+        curve_cache.insert(mint, 1, BondingCurve::traded(5_000_000_000));
+        let config = PaperConfig { latency, fee_lamports: 100_000, max_impact_bps: 2_000 };
+        (Arc::clone(&curve_cache), PaperExecutor::new(curve_cache, config), mint)
+    }
+
+    #[tokio::test]
+    async fn test_paper_round_trip() {
+        let (_, executor, mint) = executor(Duration::ZERO);
+
+        let bought = executor.buy(&mint, 1_000_000_000, 100).await.unwrap().fill.unwrap();
+        assert!(bought.sol <= 1_000_000_000);
+        assert_eq!(executor.holding(&mint), bought.tokens);
+        assert!(executor.positions().has_position(&executor.wallet(), &mint));
+
+        let sold = executor.sell(&mint, SellAmount::Percent(50), 100).await.unwrap().fill.unwrap();
+        assert_eq!(sold.tokens, bought.tokens / 2);
+        assert_eq!(executor.holding(&mint), bought.tokens - sold.tokens);
+        // The curve is live state; paper trades never move it, so only fees are lost
+        assert!(sold.sol < bought.sol / 2);
+        executor.mark_to_market();
+        assert!(executor.pl(&mint).unwrap() < 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_price_moving_during_latency() {
+        let (curve_cache, executor, mint) = executor(Duration::from_millis(100));
+        let executor = Arc::new(executor);
+        let order = {
+            let executor = Arc::clone(&executor);
+            tokio::spawn(async move { executor.buy(&mint, 1_000_000_000, 100).await })
+        };
+        // Someone else buys 10 SOL while the paper order is in flight
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        curve_cache.insert(mint, 2, BondingCurve::traded(15_000_000_000));
+
+        // A buy needs more SOL than its bound allows
+        let Err(ExecutorError::SlippageExceeded { needed, bound }) = order.await.unwrap() else {
+            panic!("the buy should exceed its slippage bound");
+        };
+        assert!(needed > bound);
+        assert_eq!(executor.holding(&mint), 0);
+        assert!(matches!(
+            executor.buy(&Pubkey::new_unique(), 1_000, 100).await,
+            Err(ExecutorError::UnknownCurve(_))
+        ));
+
+        // Someone else sells while a paper sell is in flight; it would return less than its bound
+        executor.buy(&mint, 1_000_000_000, 100).await.unwrap();
+        let order = {
+            let executor = Arc::clone(&executor);
+            tokio::spawn(async move { executor.sell(&mint, SellAmount::Percent(100), 100).await })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        curve_cache.insert(mint, 3, BondingCurve::traded(5_000_000_000));
+        let Err(ExecutorError::SlippageExceeded { needed, bound }) = order.await.unwrap() else {
+            panic!("the sell should fall short of its slippage bound");
+        };
+        assert!(needed < bound);
+    }
+
+    #[tokio::test]
+    async fn test_overlapping_sells_cannot_oversell() {
+        let (_, executor, mint) = executor(Duration::from_millis(50));
+        let executor = Arc::new(executor);
+        let bought = executor.buy(&mint, 1_000_000_000, 100).await.unwrap().fill.unwrap();
+
+        // Both sells size themselves from the full holding before either records its fill
+        let sells: Vec<_> = (0..2).map(|_| {
+            let executor = Arc::clone(&executor);
+            tokio::spawn(async move { executor.sell(&mint, SellAmount::Percent(100), 100).await })
+        }).collect();
+        let mut results = Vec::new();
+        for sell in sells {
+            results.push(sell.await.unwrap());
+        }
+
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 1);
+        assert!(results.iter().any(|result| matches!(
+            result,
+            Err(ExecutorError::InsufficientHolding { held: 0, needed, .. }) if *needed == bought.tokens
+        )));
+        assert_eq!(executor.holding(&mint), 0);
+    }
+
+    fn live_executor(url: String, blockhash_cache: Arc<BlockhashCache>, order_book: Arc<OrderBook>) -> LiveExecutor {
+        let config = LiveExecutorConfig {
+            compute_unit_price: 1_000,
+            compute_unit_limit: 100_000,
+            fee_percentile: 75,
+            fee_slots: 150,
+            tip_policy: TipPolicy::Fixed(1_000),
+            max_impact_bps: 1_000,
+        };
+        let broadcaster = Broadcaster::new(BroadcastConfig {
+            endpoints: vec![Endpoint::new("local", EndpointKind::Rpc, url)],
+            timeout: Duration::from_secs(1),
+        }).unwrap();
+        LiveExecutor::new(
+            Arc::new(Keypair::new()),
+            config,
+            Arc::new(CurveCache::default()),
+            Arc::new(RpcAccountFetcher::new(RpcClient::new("http://127.0.0.1:1".to_string()))),
+            blockhash_cache,
+            Arc::new(TipOracle::default()),
+            order_book,
+            Arc::new(broadcaster),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_live_orders_are_tracked_until_expired() {
+        let blockhash_cache = Arc::new(BlockhashCache::new());
+        blockhash_cache.insert(RecentBlockhash { hash: Hash::new_unique(), last_valid_block_height: 150 });
+        blockhash_cache.observe_block_height(100);
+        let order_book = Arc::new(OrderBook::new(ResubmitPolicy::never()));
+        let transfer = |executor: &LiveExecutor| vec![system_instruction::transfer(&executor.payer(), &Pubkey::new_unique(), 1)];
+
+        let rejecting = broadcaster::serve(r#"{"jsonrpc":"2.0","error":{"code":-32002,"message":"blockhash not found"},"id":1}"#, Duration::ZERO).await;
+        let executor = live_executor(rejecting, Arc::clone(&blockhash_cache), Arc::clone(&order_book));
+        let Err(ExecutorError::Rejected(signature)) = executor.submit(transfer(&executor)).await else {
+            panic!("the only endpoint rejects every transaction");
+        };
+        let rejected = order_book.order_for_signature(&signature).unwrap();
+        assert!(matches!(order_book.state(rejected), Some(OrderState::Failed { .. })));
+
+        let accepting = broadcaster::serve(r#"{"jsonrpc":"2.0","result":"sig","id":1}"#, Duration::ZERO).await;
+        let executor = live_executor(accepting, Arc::clone(&blockhash_cache), Arc::clone(&order_book));
+        let execution = executor.submit(transfer(&executor)).await.unwrap();
+        let order_id = execution.order_id.unwrap();
+        assert_eq!(order_book.state(order_id), Some(OrderState::Sent));
+
+        executor.resubmit_expired().await;
+        assert_eq!(order_book.state(order_id), Some(OrderState::Sent));
+        blockhash_cache.observe_block_height(151);
+        executor.resubmit_expired().await;
+        assert_eq!(order_book.state(order_id), Some(OrderState::Expired));
+    }
+
+    #[tokio::test]
+    async fn test_bundled_orders_tip_a_cached_tip_account_and_follow_results() {
+        let tip_accounts: Vec<Pubkey> = (0..8).map(|_| Pubkey::new_unique()).collect();
+        let searcher = Arc::new(MockSearcher {
+            tip_accounts: tip_accounts.iter().map(Pubkey::to_string).collect(),
+            ..Default::default()
+        });
+        let (bundle_client, exit) = connect(serve(searcher.clone()).await).await;
+        let blockhash_cache = Arc::new(BlockhashCache::new());
+        blockhash_cache.insert(RecentBlockhash { hash: Hash::new_unique(), last_valid_block_height: 150 });
+        let order_book = Arc::new(OrderBook::new(ResubmitPolicy::never()));
+        // Nothing listens here, so anything broadcast instead of bundled would fail
+        let bundle_results = Arc::new(BundleResultTracker::new());
+        let executor = live_executor("http://127.0.0.1:1".to_string(), blockhash_cache, Arc::clone(&order_book))
+            .with_bundle_client(Arc::new(bundle_client))
+            .with_bundle_results(Arc::clone(&bundle_results));
+
+        let instructions = vec![system_instruction::transfer(&executor.payer(), &Pubkey::new_unique(), 1)];
+        let execution = executor.submit(instructions).await.unwrap();
+
+        assert_eq!(order_book.state(execution.order_id.unwrap()), Some(OrderState::Sent));
+        let transaction: VersionedTransaction = {
+            let bundles = searcher.bundles.lock().unwrap();
+            assert_eq!(bundles.len(), 1);
+            bincode::deserialize(&bundles[0].packets[0].data).unwrap()
+        };
+        assert_eq!(transaction.signatures[0], execution.signature);
+        let keys = transaction.message.static_account_keys();
+        assert!(keys.iter().any(|key| tip_accounts.contains(key)));
+        assert_eq!(searcher.tip_account_calls.load(Ordering::SeqCst), 1);
+
+        let order_id = execution.order_id.unwrap();
+        bundle_results.handle_result(BundleResult {
+            bundle_id: "bundle-1".to_string(),
+            result: Some(bundle_result::Result::Processed(Processed {
+                validator_identity: "validator".to_string(),
+                slot: 10,
+                bundle_index: 0,
+            })),
+        });
+        // The result reaches the order book from a spawned task
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while order_book.state(order_id) != Some(OrderState::Seen { slot: 10 }) {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .unwrap();
+        exit.store(true, Ordering::Relaxed);
+    }
+
+    #[tokio::test]
+    async fn test_bundles_follow_the_next_leader_to_its_region() {
+        let home = Arc::new(MockSearcher {
+            tip_accounts: vec![Pubkey::new_unique().to_string()],
+            regions: GetRegionsResponse {
+                current_region: "ny".to_string(),
+                available_regions: vec!["ny".to_string(), "frankfurt".to_string()],
+            },
+            next_leader: NextScheduledLeaderResponse {
+                current_slot: 100,
+                next_leader_slot: 102,
+                next_leader_identity: "validator".to_string(),
+                next_leader_region: "frankfurt".to_string(),
+            },
+            ..Default::default()
+        });
+        let frankfurt = Arc::new(MockSearcher::default());
+        let (home_client, home_exit) = connect(serve(home.clone()).await).await;
+        let (frankfurt_client, frankfurt_exit) = connect(serve(frankfurt.clone()).await).await;
+        let leader_scheduler = Arc::new(LeaderScheduler::new(home_client.searcher_client(), vec!["ny".to_string(), "frankfurt".to_string()]));
+        leader_scheduler.refresh_schedule().await.unwrap();
+        leader_scheduler.refresh_next_leader().await.unwrap();
+
+        let blockhash_cache = Arc::new(BlockhashCache::new());
+        blockhash_cache.insert(RecentBlockhash { hash: Hash::new_unique(), last_valid_block_height: 150 });
+        let order_book = Arc::new(OrderBook::new(ResubmitPolicy::never()));
+        let executor = live_executor("http://127.0.0.1:1".to_string(), blockhash_cache, order_book)
+            .with_bundle_client(Arc::new(home_client))
+            .with_leader_scheduler(leader_scheduler)
+            .with_regional_bundle_client(block_engine_url("frankfurt"), Arc::new(frankfurt_client));
+
+        let instructions = vec![system_instruction::transfer(&executor.payer(), &Pubkey::new_unique(), 1)];
+        executor.submit(instructions).await.unwrap();
+
+        assert!(home.bundles.lock().unwrap().is_empty());
+        assert_eq!(frankfurt.bundles.lock().unwrap().len(), 1);
+        home_exit.store(true, Ordering::Relaxed);
+        frankfurt_exit.store(true, Ordering::Relaxed);
+    }
+
+    #[tokio::test]
+    async fn test_compute_budget_follows_the_fee_estimator() {
+        use crate::solana::fee_estimator::ComputeBudget;
+        let blockhash_cache = Arc::new(BlockhashCache::new());
+        blockhash_cache.insert(RecentBlockhash { hash: Hash::new_unique(), last_valid_block_height: 150 });
+        let fee_estimator = Arc::new(PriorityFeeEstimator::default());
+        let executor = live_executor("http://127.0.0.1:1".to_string(), blockhash_cache, Arc::new(OrderBook::new(ResubmitPolicy::never())))
+            .with_fee_estimator(Arc::clone(&fee_estimator));
+
+        let params = executor.trade_params().await.unwrap();
+        assert_eq!((params.compute_unit_price, params.compute_unit_limit), (1_000, 100_000));
+
+        let pumpfun = Pubkey::from_str(transaction::PUMPFUN_PROGRAM_ID).unwrap();
+        fee_estimator.observe(1, [pumpfun], ComputeBudget { compute_unit_price: Some(50_000), compute_unit_limit: Some(70_000) });
+        let params = executor.trade_params().await.unwrap();
+        assert_eq!((params.compute_unit_price, params.compute_unit_limit), (50_000, 70_000));
+    }
+}
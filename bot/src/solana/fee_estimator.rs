@@ -0,0 +1,220 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::RwLock;
+use solana_sdk::compute_budget;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::VersionedTransaction;
+use crate::solana::decoder::decode_compute_budget_instruction;
+use crate::solana::transaction::{DecodedInstruction, DecodedTransaction, PUMPFUN_PROGRAM_ID, RAYDIUM_PROGRAM_ID};
+
+// Slots of history kept per program, about a minute
+const DEFAULT_MAX_SLOTS: u64 = 150;
+
+/// The compute budget a transaction asked for.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ComputeBudget {
+    /// Micro-lamports per compute unit, `None` when the transaction pays no priority fee
+    pub compute_unit_price: Option<u64>,
+    pub compute_unit_limit: Option<u32>,
+}
+
+impl ComputeBudget {
+    pub fn from_decoded(instructions: &[DecodedInstruction]) -> Self {
+        let mut budget = ComputeBudget::default();
+        for instruction in instructions {
+            budget.apply(instruction);
+        }
+        budget
+    }
+
+    // Only static account keys are checked; the compute budget program can't be loaded from a lookup table
+    pub fn from_transaction(transaction: &VersionedTransaction) -> Self {
+        let account_keys = transaction.message.static_account_keys();
+        let mut budget = ComputeBudget::default();
+        for instruction in transaction.message.instructions() {
+            if account_keys.get(instruction.program_id_index as usize) != Some(&compute_budget::id()) {
+                continue;
+            }
+            if let Some(decoded) = decode_compute_budget_instruction(&instruction.data) {
+                budget.apply(&decoded);
+            }
+        }
+        budget
+    }
+
+    fn apply(&mut self, instruction: &DecodedInstruction) {
+        match instruction {
+            DecodedInstruction::ComputeUnitPrice(price) => self.compute_unit_price = Some(price.micro_lamports),
+            DecodedInstruction::ComputeUnitLimit(limit) => self.compute_unit_limit = Some(limit.units),
+            _ => {}
+        }
+    }
+}
+
+/// Rolling per-program record of the priority fees and compute unit limits other traders set.
+///
+/// Fed from decoded Kafka transactions with `observe_decoded` and from shredstream entries with
+/// `observe_transaction`; only transactions touching one of the tracked programs are recorded.
+pub struct PriorityFeeEstimator {
+    programs: HashSet<Pubkey>,
+    max_slots: u64,
+    budgets: RwLock<HashMap<Pubkey, BTreeMap<u64, Vec<ComputeBudget>>>>,
+}
+
+impl Default for PriorityFeeEstimator {
+    // Tracks Pump.fun and Raydium AMM v4
+    fn default() -> Self {
+        let programs = [PUMPFUN_PROGRAM_ID, RAYDIUM_PROGRAM_ID].iter().map(|program| Pubkey::from_str(program).unwrap());
+        Self::new(programs, DEFAULT_MAX_SLOTS)
+    }
+}
+
+impl PriorityFeeEstimator {
+    pub fn new(programs: impl IntoIterator<Item = Pubkey>, max_slots: u64) -> Self {
+        PriorityFeeEstimator {
+            programs: programs.into_iter().collect(),
+            max_slots,
+            budgets: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn observe(&self, slot: u64, programs: impl IntoIterator<Item = Pubkey>, budget: ComputeBudget) {
+        let mut budgets = self.budgets.write().unwrap();
+        let programs: HashSet<Pubkey> = programs.into_iter().filter(|program| self.programs.contains(program)).collect();
+        for program in programs {
+            let slots = budgets.entry(program).or_default();
+            slots.entry(slot).or_default().push(budget);
+            let newest = *slots.keys().next_back().unwrap();
+            if newest >= self.max_slots {
+                *slots = slots.split_off(&(newest - self.max_slots + 1));
+            }
+        }
+    }
+
+    // The programs are those a decoder recognised, including ones reached through CPI
+    pub fn observe_decoded(&self, transaction: &DecodedTransaction) {
+        let (pumpfun, raydium) = (Pubkey::from_str(PUMPFUN_PROGRAM_ID).unwrap(), Pubkey::from_str(RAYDIUM_PROGRAM_ID).unwrap());
+        let programs = transaction.instructions.iter().filter_map(|instruction| match instruction {
+            DecodedInstruction::PFBuy(_)
+            | DecodedInstruction::PFSell(_)
+            | DecodedInstruction::PFCreate(_)
+            | DecodedInstruction::PFTradeEvent(_)
+            | DecodedInstruction::PFCreateEvent(_)
+            | DecodedInstruction::PFSetParams(_)
+            | DecodedInstruction::PFInitialize
+            | DecodedInstruction::PFWithdraw => Some(pumpfun),
+            DecodedInstruction::RaydiumSwapBaseIn(_)
+            | DecodedInstruction::RaydiumSwapBaseOut(_)
+            | DecodedInstruction::RaydiumInitialize2(_)
+            | DecodedInstruction::RaydiumDeposit(_)
+            | DecodedInstruction::RaydiumWithdraw(_) => Some(raydium),
+            _ => None,
+        });
+        self.observe(transaction.slot, programs, ComputeBudget::from_decoded(&transaction.instructions));
+    }
+
+    // Any static account key counts as touching a program, since entries carry no inner instructions
+    pub fn observe_transaction(&self, slot: u64, transaction: &VersionedTransaction) {
+        let account_keys = transaction.message.static_account_keys();
+        if !account_keys.iter().any(|key| self.programs.contains(key)) {
+            return;
+        }
+        self.observe(slot, account_keys.iter().copied(), ComputeBudget::from_transaction(transaction));
+    }
+
+    /// Nearest-rank `percentile` (0-100) of the compute unit price paid by transactions touching
+    /// `program` in the last `slots` slots. Transactions without a price count as paying zero.
+    pub fn compute_unit_price(&self, program: &Pubkey, percentile: u8, slots: u64) -> Option<u64> {
+        self.percentile(program, percentile, slots, |budget| Some(budget.compute_unit_price.unwrap_or(0)))
+    }
+
+    /// Like `compute_unit_price`, over the transactions that set a compute unit limit.
+    pub fn compute_unit_limit(&self, program: &Pubkey, percentile: u8, slots: u64) -> Option<u32> {
+        self.percentile(program, percentile, slots, |budget| budget.compute_unit_limit.map(u64::from))
+            .map(|limit| limit as u32)
+    }
+
+    fn percentile(
+        &self,
+        program: &Pubkey,
+        percentile: u8,
+        slots: u64,
+        value: impl Fn(&ComputeBudget) -> Option<u64>,
+    ) -> Option<u64> {
+        if slots == 0 {
+            return None;
+        }
+        let budgets = self.budgets.read().unwrap();
+        let program_budgets = budgets.get(program)?;
+        let newest = *program_budgets.keys().next_back()?;
+        let oldest = newest.saturating_sub(slots - 1);
+        let mut window: Vec<u64> = program_budgets.range(oldest..=newest)
+            .flat_map(|(_, budgets)| budgets.iter().filter_map(&value))
+            .collect();
+        if window.is_empty() {
+            return None;
+        }
+        window.sort_unstable();
+        let rank = (percentile.min(100) as usize * window.len()).div_ceil(100);
+        Some(window[rank.saturating_sub(1)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::compute_budget::ComputeBudgetInstruction;
+    use solana_sdk::instruction::Instruction;
+    use solana_sdk::signature::Keypair;
+    use crate::solana::test_utils::signed_transaction;
+    use crate::solana::transaction::ComputeUnitPriceInstruction;
+
+    fn program_call(program: &str) -> Instruction {
+        Instruction::new_with_bytes(Pubkey::from_str(program).unwrap(), &[0], vec![])
+    }
+
+    #[test]
+    fn test_shredstream_transactions_are_attributed_to_programs() {
+        let estimator = PriorityFeeEstimator::default();
+        let pumpfun = Pubkey::from_str(PUMPFUN_PROGRAM_ID).unwrap();
+
+        estimator.observe_transaction(10, &signed_transaction(&Keypair::new(), &[
+            ComputeBudgetInstruction::set_compute_unit_price(5_000),
+            ComputeBudgetInstruction::set_compute_unit_limit(80_000),
+            program_call(PUMPFUN_PROGRAM_ID),
+        ]));
+        estimator.observe_transaction(10, &signed_transaction(&Keypair::new(), &[program_call(PUMPFUN_PROGRAM_ID)]));
+        // Not a tracked program
+        estimator.observe_transaction(10, &signed_transaction(&Keypair::new(), &[
+            ComputeBudgetInstruction::set_compute_unit_price(1_000_000),
+            program_call("11111111111111111111111111111111"),
+        ]));
+
+        assert_eq!(estimator.compute_unit_price(&pumpfun, 50, 1), Some(0));
+        assert_eq!(estimator.compute_unit_price(&pumpfun, 100, 1), Some(5_000));
+        assert_eq!(estimator.compute_unit_limit(&pumpfun, 50, 1), Some(80_000));
+        assert_eq!(estimator.compute_unit_price(&Pubkey::from_str(RAYDIUM_PROGRAM_ID).unwrap(), 50, 1), None);
+    }
+
+    #[test]
+    fn test_decoded_transactions_and_window() {
+        let estimator = PriorityFeeEstimator::new([Pubkey::from_str(PUMPFUN_PROGRAM_ID).unwrap()], 10);
+        let pumpfun = Pubkey::from_str(PUMPFUN_PROGRAM_ID).unwrap();
+        for (slot, micro_lamports) in [(1, 1_000_000), (20, 100), (21, 200), (22, 300), (22, 400)] {
+            let mut transaction = DecodedTransaction::new_empty();
+            transaction.slot = slot;
+            transaction.instructions = vec![
+                DecodedInstruction::ComputeUnitPrice(ComputeUnitPriceInstruction { micro_lamports }),
+                DecodedInstruction::PFWithdraw,
+            ];
+            estimator.observe_decoded(&transaction);
+        }
+
+        // Slot 1 fell out of the retained history
+        assert_eq!(estimator.compute_unit_price(&pumpfun, 100, 1_000), Some(400));
+        assert_eq!(estimator.compute_unit_price(&pumpfun, 0, 1_000), Some(100));
+        assert_eq!(estimator.compute_unit_price(&pumpfun, 75, 2), Some(400));
+        assert_eq!(estimator.compute_unit_price(&pumpfun, 50, 2), Some(300));
+        assert_eq!(estimator.compute_unit_limit(&pumpfun, 50, 2), None);
+    }
+}
@@ -0,0 +1,385 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use log::warn;
+use crate::solana::account_mirror::{slot_commitment, AccountMirrorError};
+use crate::solana::order::Commitment;
+use crate::solana::position_tracker::PositionTracker;
+use crate::solana::transaction::message::SlotStatusEvent;
+use crate::solana::transaction::DecodedTransaction;
+
+// Slots a transaction may stay pending before its slot status is given up on, about two minutes.
+// Leaves room for the slot-status topic lagging behind the transaction topic.
+const MAX_PENDING_SLOTS: u64 = 300;
+
+/// A slot reaching a new commitment, or dropping off with the fork it was on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotChange {
+    Reached { slot: u64, commitment: Commitment },
+    Abandoned(u64),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SlotInfo {
+    parent: Option<u64>,
+    commitment: Commitment,
+}
+
+/// Slot tree above the root, built from the Kafka `SlotStatusEvent` stream.
+///
+/// Confirming or rooting a slot also confirms or roots its ancestors. Rooting a slot abandons
+/// every slot that isn't on its fork, and slots building on an abandoned one are abandoned too.
+#[derive(Debug, Default)]
+pub struct SlotTracker {
+    slots: BTreeMap<u64, SlotInfo>,
+    root: u64,
+    // Abandoned slots, kept for `MAX_PENDING_SLOTS` below the root so late transactions are recognised
+    abandoned: BTreeSet<u64>,
+}
+
+impl SlotTracker {
+    pub fn root(&self) -> u64 {
+        self.root
+    }
+
+    /// Commitment `slot` reached, `None` for slots not seen yet and abandoned ones. Other slots
+    /// at or below the root count as finalized.
+    pub fn commitment(&self, slot: u64) -> Option<Commitment> {
+        if self.is_abandoned(slot) {
+            return None;
+        }
+        if slot <= self.root && self.root > 0 {
+            return Some(Commitment::Finalized);
+        }
+        self.slots.get(&slot).map(|info| info.commitment)
+    }
+
+    /// Whether `slot` was on a fork dropped by rooting.
+    pub fn is_abandoned(&self, slot: u64) -> bool {
+        self.abandoned.contains(&slot)
+    }
+
+    pub fn apply(&mut self, event: &SlotStatusEvent) -> Result<Vec<SlotChange>, AccountMirrorError> {
+        let commitment = slot_commitment(event)?;
+        if event.slot <= self.root || self.is_abandoned(event.slot) {
+            return Ok(Vec::new());
+        }
+        // Branches off below the root or off a dropped fork
+        if self.root > 0 && (event.parent < self.root || self.is_abandoned(event.parent)) {
+            self.abandoned.insert(event.slot);
+            return Ok(vec![SlotChange::Abandoned(event.slot)]);
+        }
+        let info = self.slots.entry(event.slot).or_insert(SlotInfo { parent: None, commitment: Commitment::Processed });
+        info.parent = Some(event.parent);
+
+        let mut changes = Vec::new();
+        let mut slot = Some(event.slot);
+        while let Some(current) = slot.filter(|slot| *slot > self.root) {
+            let Some(info) = self.slots.get_mut(&current) else {
+                break;
+            };
+            if info.commitment < commitment {
+                info.commitment = commitment;
+                changes.push(SlotChange::Reached { slot: current, commitment });
+            }
+            slot = info.parent;
+        }
+        // Ancestors first
+        changes.reverse();
+
+        if commitment == Commitment::Finalized {
+            changes.extend(self.set_root(event.slot));
+        }
+        Ok(changes)
+    }
+
+    // A slot at or below the root is abandoned when it isn't an ancestor of the root, unless it was
+    // confirmed, in case a parent link is missing. A slot above the root is abandoned when it
+    // descends from an abandoned slot or branches off below the root.
+    fn set_root(&mut self, root: u64) -> Vec<SlotChange> {
+        let mut ancestors = HashSet::new();
+        let mut slot = Some(root);
+        while let Some(current) = slot {
+            ancestors.insert(current);
+            slot = self.slots.get(&current).and_then(|info| info.parent);
+        }
+
+        let mut abandoned = HashSet::new();
+        for (&slot, info) in &self.slots {
+            let is_abandoned = if slot <= root {
+                !ancestors.contains(&slot) && info.commitment < Commitment::Confirmed
+            } else {
+                info.parent.is_some_and(|parent| parent < root || abandoned.contains(&parent))
+            };
+            if is_abandoned {
+                abandoned.insert(slot);
+            }
+        }
+
+        let mut abandoned: Vec<u64> = abandoned.into_iter().collect();
+        abandoned.sort_unstable();
+        self.slots = self.slots.split_off(&(root + 1));
+        self.slots.retain(|slot, _| abandoned.binary_search(slot).is_err());
+        self.root = root;
+        self.abandoned.extend(&abandoned);
+        self.abandoned = self.abandoned.split_off(&root.saturating_sub(MAX_PENDING_SLOTS));
+        abandoned.into_iter().map(SlotChange::Abandoned).collect()
+    }
+}
+
+/// Applies decoded transactions to a `PositionTracker` at a configurable commitment, following forks.
+///
+/// Transactions are buffered per slot. At confirmed or finalized commitment they reach the tracker
+/// once their slot does, and are dropped if it's abandoned. At processed commitment they're applied
+/// straight away to a speculative tracker, which is rebuilt without the abandoned slots when a
+/// fork is dropped.
+pub struct ForkAwarePositions {
+    commitment: Commitment,
+    slots: SlotTracker,
+    pending: BTreeMap<u64, Vec<DecodedTransaction>>,
+    // Transactions from slots that reached the commitment, or confirmed at processed commitment
+    settled: PositionTracker,
+    // Settled plus every pending transaction, only at processed commitment
+    speculative: Option<PositionTracker>,
+}
+
+impl ForkAwarePositions {
+    pub fn new(tracker: PositionTracker, commitment: Commitment) -> Self {
+        let speculative = (commitment == Commitment::Processed).then(|| tracker.clone());
+        ForkAwarePositions {
+            commitment,
+            slots: SlotTracker::default(),
+            pending: BTreeMap::new(),
+            settled: tracker,
+            speculative,
+        }
+    }
+
+    pub fn commitment(&self) -> Commitment {
+        self.commitment
+    }
+
+    pub fn slots(&self) -> &SlotTracker {
+        &self.slots
+    }
+
+    /// Positions at the configured commitment.
+    pub fn tracker(&self) -> &PositionTracker {
+        self.speculative.as_ref().unwrap_or(&self.settled)
+    }
+
+    // For reporting; changes made through it are lost when a rollback rebuilds the tracker
+    pub fn tracker_mut(&mut self) -> &mut PositionTracker {
+        self.speculative.as_mut().unwrap_or(&mut self.settled)
+    }
+
+    pub fn pending_transactions(&self) -> usize {
+        self.pending.values().map(Vec::len).sum()
+    }
+
+    pub fn observe_transaction(&mut self, transaction: DecodedTransaction) {
+        if self.slots.is_abandoned(transaction.slot) {
+            return;
+        }
+        if let Some(speculative) = self.speculative.as_mut() {
+            speculative.update_by_transaction(&transaction);
+        }
+        if self.slots.commitment(transaction.slot).is_some_and(|commitment| commitment >= self.settle_at()) {
+            self.settled.update_by_transaction(&transaction);
+        } else {
+            self.pending.entry(transaction.slot).or_default().push(transaction);
+        }
+        self.expire_pending();
+    }
+
+    pub fn observe_slot_status(&mut self, event: &SlotStatusEvent) -> Result<(), AccountMirrorError> {
+        let mut rolled_back = false;
+        for change in self.slots.apply(event)? {
+            match change {
+                SlotChange::Reached { slot, commitment } if commitment >= self.settle_at() => {
+                    if let Some(mut transactions) = self.pending.remove(&slot) {
+                        transactions.sort_by_key(|transaction| transaction.index);
+                        for transaction in &transactions {
+                            self.settled.update_by_transaction(transaction);
+                        }
+                    }
+                }
+                SlotChange::Reached { .. } => {}
+                SlotChange::Abandoned(slot) => {
+                    rolled_back |= self.pending.remove(&slot).is_some();
+                }
+            }
+        }
+        if rolled_back && self.speculative.is_some() {
+            self.rebuild_speculative();
+        }
+        Ok(())
+    }
+
+    // Processed slots can still be dropped, so even at processed commitment they only settle once confirmed
+    fn settle_at(&self) -> Commitment {
+        self.commitment.max(Commitment::Confirmed)
+    }
+
+    fn rebuild_speculative(&mut self) {
+        let mut speculative = self.settled.clone();
+        for transactions in self.pending.values() {
+            let mut transactions: Vec<&DecodedTransaction> = transactions.iter().collect();
+            transactions.sort_by_key(|transaction| transaction.index);
+            for transaction in transactions {
+                speculative.update_by_transaction(transaction);
+            }
+        }
+        self.speculative = Some(speculative);
+    }
+
+    // Without slot statuses, e.g. when the topic isn't consumed, old pending slots are settled at
+    // processed commitment, where they're already visible, and dropped otherwise
+    fn expire_pending(&mut self) {
+        let Some(&newest) = self.pending.keys().next_back() else {
+            return;
+        };
+        let Some(oldest_kept) = newest.checked_sub(MAX_PENDING_SLOTS) else {
+            return;
+        };
+        let kept = self.pending.split_off(&oldest_kept);
+        let expired = std::mem::replace(&mut self.pending, kept);
+        for (slot, transactions) in expired {
+            if self.commitment == Commitment::Processed {
+                for transaction in &transactions {
+                    self.settled.update_by_transaction(transaction);
+                }
+            } else {
+                warn!("Dropping {} transactions of slot {} that never reached {:?}", transactions.len(), slot, self.commitment);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::pubkey::Pubkey;
+    use crate::solana::transaction::message::SlotStatus;
+    use crate::solana::transaction::{DecodedInstruction, PFBuyInstruction};
+
+    fn status(slot: u64, parent: u64, status: SlotStatus) -> SlotStatusEvent {
+        SlotStatusEvent { slot, parent, status: status as i32 }
+    }
+
+    fn buy(slot: u64, user: Pubkey, mint: Pubkey) -> DecodedTransaction {
+        let mut transaction = DecodedTransaction::new_empty();
+        transaction.slot = slot;
+        transaction.signatures = vec![vec![slot as u8; 64]];
+        transaction.instructions = vec![DecodedInstruction::PFBuy(PFBuyInstruction {
+            amount: 1_000.0,
+            max_sol_cost: 0.1,
+            mint,
+            used_sol_amount: 0.05,
+            user,
+            token_amount: 1_000_000_000,
+            sol_amount: 50_000_000,
+        })];
+        transaction
+    }
+
+    // 10 <- 11 <- 13 is the surviving fork, 10 <- 12 <- 14 is dropped
+    fn forked(tracker: &mut SlotTracker) -> Vec<SlotChange> {
+        let mut changes = Vec::new();
+        for (slot, parent) in [(10, 9), (11, 10), (12, 10), (13, 11), (14, 12)] {
+            changes.extend(tracker.apply(&status(slot, parent, SlotStatus::Processed)).unwrap());
+        }
+        changes
+    }
+
+    #[test]
+    fn test_confirming_and_rooting_follow_ancestry() {
+        let mut tracker = SlotTracker::default();
+        assert!(forked(&mut tracker).is_empty());
+
+        assert_eq!(tracker.apply(&status(13, 11, SlotStatus::Confirmed)).unwrap(), vec![
+            SlotChange::Reached { slot: 10, commitment: Commitment::Confirmed },
+            SlotChange::Reached { slot: 11, commitment: Commitment::Confirmed },
+            SlotChange::Reached { slot: 13, commitment: Commitment::Confirmed },
+        ]);
+        assert_eq!(tracker.commitment(12), Some(Commitment::Processed));
+
+        let changes = tracker.apply(&status(11, 10, SlotStatus::Rooted)).unwrap();
+        assert_eq!(changes, vec![
+            SlotChange::Reached { slot: 10, commitment: Commitment::Finalized },
+            SlotChange::Reached { slot: 11, commitment: Commitment::Finalized },
+            SlotChange::Abandoned(12),
+            SlotChange::Abandoned(14),
+        ]);
+        assert_eq!(tracker.root(), 11);
+        assert_eq!(tracker.commitment(13), Some(Commitment::Confirmed));
+        assert_eq!(tracker.commitment(12), None);
+        assert_eq!(tracker.commitment(14), None);
+        assert!(tracker.is_abandoned(12));
+
+        // A late child of the dropped fork is abandoned too
+        assert_eq!(tracker.apply(&status(15, 14, SlotStatus::Processed)).unwrap(), vec![SlotChange::Abandoned(15)]);
+        assert_eq!(tracker.commitment(15), None);
+    }
+
+    #[test]
+    fn test_confirmed_commitment_buffers_until_confirmed() {
+        let (user, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut positions = ForkAwarePositions::new(PositionTracker::new_with_accounts(vec![user]), Commitment::Confirmed);
+        for (slot, parent) in [(10, 9), (11, 10), (12, 10)] {
+            positions.observe_slot_status(&status(slot, parent, SlotStatus::Processed)).unwrap();
+        }
+
+        positions.observe_transaction(buy(12, user, mint));
+        assert!(!positions.tracker().has_position(&user, &mint));
+        assert_eq!(positions.pending_transactions(), 1);
+
+        positions.observe_slot_status(&status(11, 10, SlotStatus::Rooted)).unwrap();
+        assert!(!positions.tracker().has_position(&user, &mint));
+        assert_eq!(positions.pending_transactions(), 0);
+
+        positions.observe_transaction(buy(11, user, mint));
+        assert!(positions.tracker().has_position(&user, &mint));
+    }
+
+    #[test]
+    fn test_processed_commitment_rolls_back_dropped_forks() {
+        let (user, kept_mint, dropped_mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut positions = ForkAwarePositions::new(PositionTracker::new_with_accounts(vec![user]), Commitment::Processed);
+        for (slot, parent) in [(10, 9), (11, 10), (12, 10), (13, 11)] {
+            positions.observe_slot_status(&status(slot, parent, SlotStatus::Processed)).unwrap();
+        }
+
+        positions.observe_transaction(buy(12, user, dropped_mint));
+        positions.observe_transaction(buy(13, user, kept_mint));
+        assert!(positions.tracker().has_position(&user, &dropped_mint));
+        assert!(positions.tracker().has_position(&user, &kept_mint));
+
+        positions.observe_slot_status(&status(11, 10, SlotStatus::Rooted)).unwrap();
+        assert!(!positions.tracker().has_position(&user, &dropped_mint));
+        // Slot 13 is still pending but stays visible
+        assert!(positions.tracker().has_position(&user, &kept_mint));
+        assert_eq!(positions.pending_transactions(), 1);
+
+        positions.observe_slot_status(&status(13, 11, SlotStatus::Confirmed)).unwrap();
+        assert_eq!(positions.pending_transactions(), 0);
+        assert!(positions.tracker().has_position(&user, &kept_mint));
+    }
+
+    #[test]
+    fn test_late_transactions_of_abandoned_forks_are_dropped() {
+        let (user, kept_mint, dropped_mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        for commitment in [Commitment::Processed, Commitment::Finalized] {
+            let mut positions = ForkAwarePositions::new(PositionTracker::new_with_accounts(vec![user]), commitment);
+            for (slot, parent) in [(10, 9), (11, 10), (12, 10), (13, 11)] {
+                positions.observe_slot_status(&status(slot, parent, SlotStatus::Processed)).unwrap();
+            }
+            positions.observe_slot_status(&status(13, 11, SlotStatus::Rooted)).unwrap();
+
+            // Slot 12 is below the new root but was never on its fork
+            positions.observe_transaction(buy(12, user, dropped_mint));
+            positions.observe_transaction(buy(11, user, kept_mint));
+            assert!(!positions.tracker().has_position(&user, &dropped_mint));
+            assert!(positions.tracker().has_position(&user, &kept_mint));
+            assert_eq!(positions.pending_transactions(), 0);
+        }
+    }
+}
@@ -0,0 +1,485 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use convert_case::{Case, Casing};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use solana_sdk::pubkey::Pubkey;
+
+// Pump.fun IDL, embedded at build time
+const PUMPFUN_IDL: &str = include_str!("../../IDL/pump-fun.json");
+
+// Anchor prefixes self-CPI event data with this tag, followed by the event discriminator
+pub const EVENT_IX_TAG: [u8; 8] = [0xe4, 0x45, 0xa5, 0x2e, 0x51, 0xcb, 0x9a, 0x1d];
+
+/// Errors returned when an IDL cannot be loaded or data does not match it.
+#[derive(Debug, thiserror::Error)]
+pub enum IdlError {
+    #[error("IoError {0}")]
+    Io(#[from] std::io::Error),
+    #[error("JsonError {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("IDL has no program address")]
+    MissingAddress,
+    #[error("invalid program address {0}")]
+    InvalidAddress(String),
+    #[error("type {0} is not supported")]
+    UnsupportedType(String),
+    #[error("type {0} is not defined in the IDL")]
+    UndefinedType(String),
+    #[error("data ended while reading {0}")]
+    UnexpectedEnd(String),
+    #[error("invalid data for {field}: {reason}")]
+    InvalidData { field: String, reason: String },
+}
+
+/// Anchor IDL in the legacy (pre 0.30) JSON format used by `bot/IDL`.
+#[derive(Debug, Deserialize)]
+pub struct Idl {
+    pub name: String,
+    pub instructions: Vec<IdlInstruction>,
+    #[serde(default)]
+    pub accounts: Vec<IdlTypeDef>,
+    #[serde(default)]
+    pub types: Vec<IdlTypeDef>,
+    #[serde(default)]
+    pub events: Vec<IdlEvent>,
+    #[serde(default)]
+    pub metadata: Option<IdlMetadata>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IdlMetadata {
+    pub address: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IdlInstruction {
+    pub name: String,
+    pub accounts: Vec<IdlAccountItem>,
+    pub args: Vec<IdlField>,
+}
+
+/// An instruction account, or a named group of accounts that is flattened in order.
+#[derive(Debug, Deserialize)]
+pub struct IdlAccountItem {
+    pub name: String,
+    #[serde(default)]
+    pub accounts: Vec<IdlAccountItem>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IdlField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: IdlType,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IdlEvent {
+    pub name: String,
+    pub fields: Vec<IdlField>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IdlTypeDef {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: IdlTypeDefBody,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum IdlTypeDefBody {
+    Struct { fields: Vec<IdlField> },
+    Enum { variants: Vec<IdlEnumVariant> },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IdlEnumVariant {
+    pub name: String,
+    #[serde(default)]
+    pub fields: Vec<IdlField>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum IdlType {
+    Primitive(String),
+    Vec { vec: Box<IdlType> },
+    Option { option: Box<IdlType> },
+    Array { array: (Box<IdlType>, usize) },
+    Defined { defined: String },
+}
+
+/// A Borsh value decoded according to an [`IdlType`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum IdlValue {
+    Bool(bool),
+    Unsigned(u128),
+    Signed(i128),
+    String(String),
+    Bytes(Vec<u8>),
+    Pubkey(Pubkey),
+    Option(Option<Box<IdlValue>>),
+    Vec(Vec<IdlValue>),
+    Struct(Vec<(String, IdlValue)>),
+    Enum { variant: String, fields: Vec<(String, IdlValue)> },
+}
+
+impl IdlValue {
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            IdlValue::Unsigned(value) => u64::try_from(*value).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            IdlValue::Signed(value) => i64::try_from(*value).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            IdlValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            IdlValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_pubkey(&self) -> Option<Pubkey> {
+        match self {
+            IdlValue::Pubkey(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+/// An instruction decoded with an IDL: its name, arguments and named accounts.
+#[derive(Debug, Clone)]
+pub struct AnchorInstruction {
+    pub program_id: Pubkey,
+    pub name: String,
+    pub args: Vec<(String, IdlValue)>,
+    pub accounts: Vec<(String, Pubkey)>,
+}
+
+/// An event decoded from an Anchor self-CPI log.
+#[derive(Debug, Clone)]
+pub struct AnchorEvent {
+    pub program_id: Pubkey,
+    pub name: String,
+    pub fields: Vec<(String, IdlValue)>,
+}
+
+impl AnchorInstruction {
+    pub fn arg(&self, name: &str) -> Option<&IdlValue> {
+        find_named(&self.args, name)
+    }
+
+    pub fn account(&self, name: &str) -> Option<Pubkey> {
+        self.accounts.iter().find(|(account, _)| account == name).map(|(_, pubkey)| *pubkey)
+    }
+}
+
+impl AnchorEvent {
+    pub fn field(&self, name: &str) -> Option<&IdlValue> {
+        find_named(&self.fields, name)
+    }
+}
+
+fn find_named<'a>(values: &'a [(String, IdlValue)], name: &str) -> Option<&'a IdlValue> {
+    values.iter().find(|(field, _)| field == name).map(|(_, value)| value)
+}
+
+/// An IDL with its instruction and event discriminators precomputed.
+pub struct IdlProgram {
+    pub program_id: Pubkey,
+    pub idl: Idl,
+    instructions: HashMap<[u8; 8], usize>,
+    events: HashMap<[u8; 8], usize>,
+    types: HashMap<String, usize>,
+}
+
+// Anchor discriminator: the first 8 bytes of sha256("<namespace>:<name>")
+pub fn discriminator(namespace: &str, name: &str) -> [u8; 8] {
+    let hash = Sha256::digest(format!("{namespace}:{name}"));
+    hash[..8].try_into().unwrap()
+}
+
+impl IdlProgram {
+    // Parse an IDL, taking the program id from its metadata
+    pub fn from_json(json: &str) -> Result<Self, IdlError> {
+        let idl: Idl = serde_json::from_str(json)?;
+        let address = idl.metadata.as_ref()
+            .and_then(|metadata| metadata.address.clone())
+            .ok_or(IdlError::MissingAddress)?;
+        let program_id = Pubkey::from_str(&address).map_err(|_| IdlError::InvalidAddress(address))?;
+        Ok(Self::new(program_id, idl))
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, IdlError> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn new(program_id: Pubkey, idl: Idl) -> Self {
+        // Instruction names are camelCase in the IDL but hashed in snake_case
+        let instructions = idl.instructions.iter().enumerate()
+            .map(|(index, instruction)| (discriminator("global", &instruction.name.to_case(Case::Snake)), index))
+            .collect();
+        let events = idl.events.iter().enumerate()
+            .map(|(index, event)| (discriminator("event", &event.name), index))
+            .collect();
+        let types = idl.types.iter().chain(idl.accounts.iter()).enumerate()
+            .map(|(index, type_def)| (type_def.name.clone(), index))
+            .collect();
+        IdlProgram { program_id, idl, instructions, events, types }
+    }
+
+    // The embedded Pump.fun IDL
+    pub fn pumpfun() -> &'static IdlProgram {
+        static PUMPFUN: OnceLock<IdlProgram> = OnceLock::new();
+        PUMPFUN.get_or_init(|| IdlProgram::from_json(PUMPFUN_IDL).expect("bundled Pump.fun IDL is valid"))
+    }
+
+    pub fn instruction(&self, name: &str) -> Option<&IdlInstruction> {
+        self.idl.instructions.iter().find(|instruction| instruction.name == name)
+    }
+
+    // Decode instruction data and name its accounts.
+    // `accounts` are the instruction's resolved account keys in order; extra IDL accounts
+    // beyond the keys given are left out. Returns `None` for an unknown discriminator.
+    pub fn decode_instruction(&self, data: &[u8], accounts: &[Pubkey]) -> Result<Option<AnchorInstruction>, IdlError> {
+        let Some(index) = data.get(..8).and_then(|prefix| self.instructions.get(prefix)) else {
+            return Ok(None);
+        };
+        let instruction = &self.idl.instructions[*index];
+
+        let mut reader = &data[8..];
+        let args = self.read_fields(&instruction.args, &mut reader)?;
+
+        let mut names = Vec::new();
+        flatten_accounts(&instruction.accounts, &mut names);
+        let accounts = names.into_iter().zip(accounts.iter().copied()).collect();
+
+        Ok(Some(AnchorInstruction { program_id: self.program_id, name: instruction.name.clone(), args, accounts }))
+    }
+
+    // Decode a self-CPI event log. Returns `None` if the data is not an event of this IDL.
+    pub fn decode_event(&self, data: &[u8]) -> Result<Option<AnchorEvent>, IdlError> {
+        if !data.starts_with(&EVENT_IX_TAG) {
+            return Ok(None);
+        }
+        let Some(index) = data.get(8..16).and_then(|prefix| self.events.get(prefix)) else {
+            return Ok(None);
+        };
+        let event = &self.idl.events[*index];
+
+        let mut reader = &data[16..];
+        let fields = self.read_fields(&event.fields, &mut reader)?;
+
+        Ok(Some(AnchorEvent { program_id: self.program_id, name: event.name.clone(), fields }))
+    }
+
+    // Decode account data of a type declared under `accounts`, after its 8-byte discriminator
+    pub fn decode_account(&self, name: &str, data: &[u8]) -> Result<Option<Vec<(String, IdlValue)>>, IdlError> {
+        if data.get(..8) != Some(&discriminator("account", name)[..]) {
+            return Ok(None);
+        }
+        let Some(IdlTypeDef { ty: IdlTypeDefBody::Struct { fields }, .. }) = self.idl.accounts.iter().find(|account| account.name == name) else {
+            return Err(IdlError::UndefinedType(name.to_string()));
+        };
+        let mut reader = &data[8..];
+        self.read_fields(fields, &mut reader).map(Some)
+    }
+
+    fn read_fields(&self, fields: &[IdlField], reader: &mut &[u8]) -> Result<Vec<(String, IdlValue)>, IdlError> {
+        fields.iter()
+            .map(|field| Ok((field.name.clone(), self.read_value(&field.ty, &field.name, reader)?)))
+            .collect()
+    }
+
+    fn read_value(&self, ty: &IdlType, field: &str, reader: &mut &[u8]) -> Result<IdlValue, IdlError> {
+        match ty {
+            IdlType::Primitive(name) => read_primitive(name, field, reader),
+            IdlType::Vec { vec } => {
+                let len = u32::from_le_bytes(take::<4>(field, reader)?) as usize;
+                (0..len).map(|_| self.read_value(vec, field, reader)).collect::<Result<_, _>>().map(IdlValue::Vec)
+            }
+            IdlType::Option { option } => match take::<1>(field, reader)?[0] {
+                0 => Ok(IdlValue::Option(None)),
+                1 => Ok(IdlValue::Option(Some(Box::new(self.read_value(option, field, reader)?)))),
+                tag => Err(IdlError::InvalidData { field: field.to_string(), reason: format!("option tag {}", tag) }),
+            },
+            IdlType::Array { array: (item, len) } => {
+                (0..*len).map(|_| self.read_value(item, field, reader)).collect::<Result<_, _>>().map(IdlValue::Vec)
+            }
+            IdlType::Defined { defined } => {
+                let index = self.types.get(defined).ok_or_else(|| IdlError::UndefinedType(defined.clone()))?;
+                let type_def = self.idl.types.iter().chain(self.idl.accounts.iter()).nth(*index).unwrap();
+                match &type_def.ty {
+                    IdlTypeDefBody::Struct { fields } => self.read_fields(fields, reader).map(IdlValue::Struct),
+                    IdlTypeDefBody::Enum { variants } => {
+                        let tag = take::<1>(field, reader)?[0] as usize;
+                        let variant = variants.get(tag).ok_or_else(|| IdlError::InvalidData {
+                            field: field.to_string(),
+                            reason: format!("{} has no variant {}", defined, tag),
+                        })?;
+                        let fields = self.read_fields(&variant.fields, reader)?;
+                        Ok(IdlValue::Enum { variant: variant.name.clone(), fields })
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Flatten nested account groups into the order the accounts are passed
+fn flatten_accounts(items: &[IdlAccountItem], names: &mut Vec<String>) {
+    for item in items {
+        if item.accounts.is_empty() {
+            names.push(item.name.clone());
+        } else {
+            flatten_accounts(&item.accounts, names);
+        }
+    }
+}
+
+fn take<const N: usize>(field: &str, reader: &mut &[u8]) -> Result<[u8; N], IdlError> {
+    if reader.len() < N {
+        return Err(IdlError::UnexpectedEnd(field.to_string()));
+    }
+    let (bytes, rest) = reader.split_at(N);
+    *reader = rest;
+    Ok(bytes.try_into().unwrap())
+}
+
+fn read_primitive(name: &str, field: &str, reader: &mut &[u8]) -> Result<IdlValue, IdlError> {
+    let value = match name {
+        "bool" => IdlValue::Bool(take::<1>(field, reader)?[0] != 0),
+        "u8" => IdlValue::Unsigned(take::<1>(field, reader)?[0] as u128),
+        "u16" => IdlValue::Unsigned(u16::from_le_bytes(take(field, reader)?) as u128),
+        "u32" => IdlValue::Unsigned(u32::from_le_bytes(take(field, reader)?) as u128),
+        "u64" => IdlValue::Unsigned(u64::from_le_bytes(take(field, reader)?) as u128),
+        "u128" => IdlValue::Unsigned(u128::from_le_bytes(take(field, reader)?)),
+        "i8" => IdlValue::Signed(take::<1>(field, reader)?[0] as i8 as i128),
+        "i16" => IdlValue::Signed(i16::from_le_bytes(take(field, reader)?) as i128),
+        "i32" => IdlValue::Signed(i32::from_le_bytes(take(field, reader)?) as i128),
+        "i64" => IdlValue::Signed(i64::from_le_bytes(take(field, reader)?) as i128),
+        "i128" => IdlValue::Signed(i128::from_le_bytes(take(field, reader)?)),
+        "publicKey" | "pubkey" => IdlValue::Pubkey(Pubkey::new_from_array(take(field, reader)?)),
+        "string" | "bytes" => {
+            let len = u32::from_le_bytes(take::<4>(field, reader)?) as usize;
+            if reader.len() < len {
+                return Err(IdlError::UnexpectedEnd(field.to_string()));
+            }
+            let (bytes, rest) = reader.split_at(len);
+            *reader = rest;
+            if name == "bytes" {
+                IdlValue::Bytes(bytes.to_vec())
+            } else {
+                let value = String::from_utf8(bytes.to_vec())
+                    .map_err(|e| IdlError::InvalidData { field: field.to_string(), reason: e.to_string() })?;
+                IdlValue::String(value)
+            }
+        }
+        other => return Err(IdlError::UnsupportedType(other.to_string())),
+    };
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pumpfun_discriminators_match_anchor() {
+        let pumpfun = IdlProgram::pumpfun();
+        let buy = hex_literal::hex!("66063d1201daebea");
+        let sell = hex_literal::hex!("33e685a4017f83ad");
+
+        assert_eq!(discriminator("global", "buy"), buy);
+        assert_eq!(discriminator("global", "sell"), sell);
+        assert_eq!(pumpfun.program_id.to_string(), "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P");
+        // camelCase IDL names are hashed in snake_case
+        let set_params = discriminator("global", "set_params");
+        assert_eq!(pumpfun.decode_instruction(&set_params, &[]).unwrap_err().to_string(), "data ended while reading feeRecipient");
+    }
+
+    #[test]
+    fn test_decode_buy_names_args_and_accounts() {
+        let pumpfun = IdlProgram::pumpfun();
+        let mut data = discriminator("global", "buy").to_vec();
+        data.extend_from_slice(&1_000_000u64.to_le_bytes());
+        data.extend_from_slice(&50_000_000u64.to_le_bytes());
+        let accounts: Vec<Pubkey> = (0..12).map(|_| Pubkey::new_unique()).collect();
+
+        let buy = pumpfun.decode_instruction(&data, &accounts).unwrap().unwrap();
+
+        assert_eq!(buy.name, "buy");
+        assert_eq!(buy.arg("amount").and_then(IdlValue::as_u64), Some(1_000_000));
+        assert_eq!(buy.arg("maxSolCost").and_then(IdlValue::as_u64), Some(50_000_000));
+        assert_eq!(buy.account("mint"), Some(accounts[2]));
+        assert_eq!(buy.account("user"), Some(accounts[6]));
+    }
+
+    #[test]
+    fn test_decode_unknown_discriminator_is_none() {
+        let pumpfun = IdlProgram::pumpfun();
+        assert!(pumpfun.decode_instruction(&[0u8; 24], &[]).unwrap().is_none());
+        assert!(pumpfun.decode_event(&[0u8; 24]).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_decode_defined_types_from_runtime_idl() {
+        let program = IdlProgram::from_json(r#"{
+            "name": "example",
+            "instructions": [{
+                "name": "placeOrder",
+                "accounts": [{"name": "market"}, {"name": "owner", "accounts": [{"name": "authority"}, {"name": "payer"}]}],
+                "args": [
+                    {"name": "side", "type": {"defined": "Side"}},
+                    {"name": "limits", "type": {"defined": "Limits"}},
+                    {"name": "memo", "type": {"option": "string"}},
+                    {"name": "ids", "type": {"vec": "u16"}}
+                ]
+            }],
+            "types": [
+                {"name": "Side", "type": {"kind": "enum", "variants": [{"name": "Bid"}, {"name": "Ask"}]}},
+                {"name": "Limits", "type": {"kind": "struct", "fields": [{"name": "price", "type": "u64"}, {"name": "offset", "type": "i32"}]}}
+            ],
+            "metadata": {"address": "11111111111111111111111111111111"}
+        }"#).unwrap();
+
+        let mut data = discriminator("global", "place_order").to_vec();
+        data.push(1);
+        data.extend_from_slice(&42u64.to_le_bytes());
+        data.extend_from_slice(&(-3i32).to_le_bytes());
+        data.push(0);
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&7u16.to_le_bytes());
+        data.extend_from_slice(&9u16.to_le_bytes());
+        let accounts: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+
+        let order = program.decode_instruction(&data, &accounts).unwrap().unwrap();
+
+        assert_eq!(order.arg("side"), Some(&IdlValue::Enum { variant: "Ask".to_string(), fields: vec![] }));
+        assert_eq!(order.arg("limits"), Some(&IdlValue::Struct(vec![
+            ("price".to_string(), IdlValue::Unsigned(42)),
+            ("offset".to_string(), IdlValue::Signed(-3)),
+        ])));
+        assert_eq!(order.arg("memo"), Some(&IdlValue::Option(None)));
+        assert_eq!(order.arg("ids"), Some(&IdlValue::Vec(vec![IdlValue::Unsigned(7), IdlValue::Unsigned(9)])));
+        assert_eq!(order.account("payer"), Some(accounts[2]));
+    }
+}
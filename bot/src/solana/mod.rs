@@ -1,4 +1,18 @@
 pub mod transaction;
 pub mod position_tracker;
 pub mod wallet;
-pub mod address_table_cache;
\ No newline at end of file
+pub mod address_table_cache;
+pub mod decoder;
+pub mod idl;
+pub mod trade_builder;
+pub mod blockhash;
+pub mod fee_estimator;
+pub mod broadcaster;
+pub mod order;
+pub mod account_mirror;
+pub mod fork_tracker;
+pub mod executor;
+pub mod signer;
+pub mod signer_daemon;
+#[cfg(test)]
+pub(crate) mod test_utils;
@@ -0,0 +1,699 @@
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use async_trait::async_trait;
+use dotenv::dotenv;
+use log::error;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::VersionedTransaction;
+use tokio::task::JoinHandle;
+use crate::solana::blockhash::{BlockhashError, BlockhashProvider};
+use crate::solana::signer::TradeSigner;
+use crate::solana::trade_builder::{TradeBuildError, TradeBuilder, TradeParams};
+use crate::solana::transaction::DecodedTransaction;
+
+use crate::jito::bundle_results::{BundleStatus, DropReason};
+
+pub use crate::jito::bundle_results::OrderId;
+
+// getSignatureStatuses accepts at most this many signatures per call
+const MAX_STATUS_BATCH: usize = 256;
+
+#[derive(Debug, thiserror::Error)]
+pub enum OrderError {
+    #[error("unknown order {0}")]
+    UnknownOrder(OrderId),
+    #[error("order {id} can't go from {from:?} to {to:?}")]
+    InvalidTransition { id: OrderId, from: OrderState, to: OrderState },
+    #[error("TradeBuildError {0}")]
+    Build(#[from] TradeBuildError),
+    #[error("BlockhashError {0}")]
+    Blockhash(#[from] BlockhashError),
+    #[error("RpcError {0}")]
+    Rpc(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Commitment {
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+impl std::str::FromStr for Commitment {
+    type Err = String;
+
+    // Accepts the RPC names; "rooted" is the Kafka plugin's name for finalized
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "processed" => Ok(Commitment::Processed),
+            "confirmed" => Ok(Commitment::Confirmed),
+            "finalized" | "rooted" => Ok(Commitment::Finalized),
+            other => Err(format!("unknown commitment {}", other)),
+        }
+    }
+}
+
+/// Where an order is in its lifecycle.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrderState {
+    /// Signed, not sent yet
+    Built,
+    Sent,
+    /// Executed in `slot`, seen in shredstream or Kafka, but not confirmed yet
+    Seen { slot: u64 },
+    Confirmed { slot: u64 },
+    Finalized { slot: u64 },
+    Failed { reason: String },
+    /// The blockhash expired before the order landed and it wasn't resubmitted
+    Expired,
+}
+
+impl OrderState {
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, OrderState::Finalized { .. } | OrderState::Failed { .. } | OrderState::Expired)
+    }
+
+    fn is_landed(&self) -> bool {
+        matches!(self, OrderState::Confirmed { .. } | OrderState::Finalized { .. })
+    }
+
+    // Whether the order's current transaction can still land
+    fn is_in_flight(&self) -> bool {
+        matches!(self, OrderState::Sent | OrderState::Seen { .. })
+    }
+
+    fn can_become(&self, next: &OrderState) -> bool {
+        use OrderState::*;
+        match (self, next) {
+            (Built, Sent | Failed { .. }) => true,
+            // Rebuilt with a fresh blockhash after the previous one expired
+            (Sent | Seen { .. }, Built) => true,
+            (Sent, Seen { .. }) => true,
+            (Sent | Seen { .. }, Confirmed { .. } | Finalized { .. } | Failed { .. } | Expired) => true,
+            (Confirmed { .. }, Finalized { .. }) => true,
+            _ => false,
+        }
+    }
+}
+
+/// A state an order entered and when.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+    pub state: OrderState,
+    pub at: SystemTime,
+}
+
+/// How orders whose blockhash expired are retried.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResubmitPolicy {
+    /// Total number of transactions an order may be built as, including the first
+    pub max_attempts: u32,
+    /// Raise the compute unit price and tip by this many basis points on every retry
+    pub fee_bump_bps: u64,
+}
+
+impl ResubmitPolicy {
+    pub fn never() -> Self {
+        ResubmitPolicy { max_attempts: 1, fee_bump_bps: 0 }
+    }
+
+    // RESUBMIT_MAX_ATTEMPTS and RESUBMIT_FEE_BUMP_BPS, never resubmitting when unset
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        dotenv().ok();
+        let mut policy = Self::never();
+        if let Ok(max_attempts) = env::var("RESUBMIT_MAX_ATTEMPTS") {
+            policy.max_attempts = max_attempts.parse()?;
+        }
+        if let Ok(fee_bump_bps) = env::var("RESUBMIT_FEE_BUMP_BPS") {
+            policy.fee_bump_bps = fee_bump_bps.parse()?;
+        }
+        Ok(policy)
+    }
+}
+
+/// The status of a signature as reported by a [`SignatureStatusSource`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignatureStatus {
+    pub slot: u64,
+    pub commitment: Commitment,
+    pub err: Option<String>,
+}
+
+/// Where `OrderBook` polls signature statuses from.
+#[async_trait]
+pub trait SignatureStatusSource: Send + Sync {
+    async fn statuses(&self, signatures: &[Signature]) -> Result<Vec<Option<SignatureStatus>>, OrderError>;
+}
+
+pub struct RpcSignatureStatusSource {
+    client: RpcClient,
+}
+
+impl RpcSignatureStatusSource {
+    pub fn new(client: RpcClient) -> Self {
+        RpcSignatureStatusSource { client }
+    }
+}
+
+#[async_trait]
+impl SignatureStatusSource for RpcSignatureStatusSource {
+    async fn statuses(&self, signatures: &[Signature]) -> Result<Vec<Option<SignatureStatus>>, OrderError> {
+        let statuses = self.client
+            .get_signature_statuses(signatures)
+            .await
+            .map_err(|e| OrderError::Rpc(e.to_string()))?
+            .value;
+        Ok(statuses.into_iter()
+            .map(|status| status.map(|status| {
+                let commitment = if status.satisfies_commitment(CommitmentConfig::finalized()) {
+                    Commitment::Finalized
+                } else if status.satisfies_commitment(CommitmentConfig::confirmed()) {
+                    Commitment::Confirmed
+                } else {
+                    Commitment::Processed
+                };
+                SignatureStatus { slot: status.slot, commitment, err: status.err.map(|err| err.to_string()) }
+            }))
+            .collect())
+    }
+}
+
+/// One trade from signing until it lands, fails or expires.
+pub struct Order {
+    id: OrderId,
+    // The trade instructions and settings, kept to rebuild the transaction on expiry
+    instructions: Vec<Instruction>,
+    params: TradeParams,
+    transaction: VersionedTransaction,
+    attempts: u32,
+    history: Vec<Transition>,
+    // What the landed transaction did, held back until it is confirmed
+    pending_fill: Option<DecodedTransaction>,
+}
+
+impl Order {
+    pub fn id(&self) -> OrderId {
+        self.id
+    }
+
+    pub fn state(&self) -> &OrderState {
+        &self.history.last().expect("orders start Built").state
+    }
+
+    pub fn history(&self) -> &[Transition] {
+        &self.history
+    }
+
+    /// The transaction of the latest attempt.
+    pub fn transaction(&self) -> &VersionedTransaction {
+        &self.transaction
+    }
+
+    pub fn signature(&self) -> Signature {
+        self.transaction.signatures[0]
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    pub fn params(&self) -> &TradeParams {
+        &self.params
+    }
+
+    fn transition(&mut self, state: OrderState) -> Result<(), OrderError> {
+        if *self.state() == state {
+            return Ok(());
+        }
+        if !self.state().can_become(&state) {
+            return Err(OrderError::InvalidTransition { id: self.id, from: self.state().clone(), to: state });
+        }
+        self.history.push(Transition { state, at: SystemTime::now() });
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct OrderBookState {
+    next_id: OrderId,
+    orders: HashMap<OrderId, Order>,
+    // Every signature an order was ever built with, since an older attempt can still land
+    by_signature: HashMap<Signature, OrderId>,
+    confirmed_fills: Vec<DecodedTransaction>,
+}
+
+impl OrderBookState {
+    fn order_mut(&mut self, id: OrderId) -> Result<&mut Order, OrderError> {
+        self.orders.get_mut(&id).ok_or(OrderError::UnknownOrder(id))
+    }
+
+    fn apply_status(&mut self, signature: &Signature, status: SignatureStatus) {
+        let Some(&id) = self.by_signature.get(signature) else {
+            return;
+        };
+        let order = self.orders.get_mut(&id).unwrap();
+        if order.state().is_terminal() || matches!(order.state(), OrderState::Built) {
+            return;
+        }
+        let state = match (&status.err, status.commitment) {
+            (Some(err), _) => OrderState::Failed { reason: err.clone() },
+            (None, Commitment::Processed) => OrderState::Seen { slot: status.slot },
+            (None, Commitment::Confirmed) => OrderState::Confirmed { slot: status.slot },
+            (None, Commitment::Finalized) => OrderState::Finalized { slot: status.slot },
+        };
+        // Finalizing an unconfirmed order passes through Confirmed, so every landed order has confirmed once
+        if matches!(state, OrderState::Finalized { .. }) && !order.state().is_landed() {
+            let _ = order.transition(OrderState::Confirmed { slot: status.slot });
+        }
+        if order.transition(state).is_err() {
+            return;
+        }
+        match order.state() {
+            OrderState::Confirmed { .. } | OrderState::Finalized { .. } => {
+                if let Some(fill) = order.pending_fill.take() {
+                    self.confirmed_fills.push(fill);
+                }
+            }
+            OrderState::Failed { .. } => order.pending_fill = None,
+            _ => {}
+        }
+    }
+}
+
+/// Tracks orders from signing to confirmation and resubmits them when their blockhash expires.
+///
+/// Landed transactions are matched to orders by signature. Their effects are released through
+/// `drain_confirmed_fills` only once confirmed, so `PositionTracker` never sees a fill that could
+/// still be rolled back.
+pub struct OrderBook {
+    policy: ResubmitPolicy,
+    state: Mutex<OrderBookState>,
+}
+
+impl OrderBook {
+    pub fn new(policy: ResubmitPolicy) -> Self {
+        OrderBook { policy, state: Mutex::new(OrderBookState::default()) }
+    }
+
+    /// Signs `instructions` with the builder into a new order and returns its id and transaction.
+    pub async fn create(&self, builder: &TradeBuilder<'_>, instructions: Vec<Instruction>) -> Result<(OrderId, VersionedTransaction), OrderError> {
+        let transaction = builder.build(instructions.clone()).await?;
+        let mut state = self.state.lock().unwrap();
+        state.next_id += 1;
+        let id = state.next_id;
+        state.by_signature.insert(transaction.signatures[0], id);
+        state.orders.insert(id, Order {
+            id,
+            instructions,
+            params: *builder.params(),
+            transaction: transaction.clone(),
+            attempts: 1,
+            history: vec![Transition { state: OrderState::Built, at: SystemTime::now() }],
+            pending_fill: None,
+        });
+        Ok((id, transaction))
+    }
+
+    pub fn mark_sent(&self, id: OrderId) -> Result<(), OrderError> {
+        self.state.lock().unwrap().order_mut(id)?.transition(OrderState::Sent)
+    }
+
+    pub fn mark_failed(&self, id: OrderId, reason: String) -> Result<(), OrderError> {
+        self.state.lock().unwrap().order_mut(id)?.transition(OrderState::Failed { reason })
+    }
+
+    pub fn state(&self, id: OrderId) -> Option<OrderState> {
+        self.state.lock().unwrap().orders.get(&id).map(|order| order.state().clone())
+    }
+
+    pub fn history(&self, id: OrderId) -> Vec<Transition> {
+        self.state.lock().unwrap().orders.get(&id).map(|order| order.history.clone()).unwrap_or_default()
+    }
+
+    pub fn order_for_signature(&self, signature: &Signature) -> Option<OrderId> {
+        self.state.lock().unwrap().by_signature.get(signature).copied()
+    }
+
+    // An order's transaction showed up in shredstream entries; it isn't executed yet, so only `slot` is recorded
+    pub fn observe_signature(&self, signature: &Signature, slot: u64) {
+        let mut state = self.state.lock().unwrap();
+        let Some(&id) = state.by_signature.get(signature) else {
+            return;
+        };
+        let order = state.orders.get_mut(&id).unwrap();
+        if matches!(order.state(), OrderState::Sent) {
+            let _ = order.transition(OrderState::Seen { slot });
+        }
+    }
+
+    /// Matches an executed Kafka transaction to its order.
+    /// Transactions of other signers are handed back untouched.
+    pub fn observe_transaction(&self, transaction: DecodedTransaction) -> Option<DecodedTransaction> {
+        let Some(signature) = transaction.signatures.first().and_then(|signature| Signature::try_from(signature.as_slice()).ok()) else {
+            return Some(transaction);
+        };
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+        let Some(&id) = state.by_signature.get(&signature) else {
+            return Some(transaction);
+        };
+        let (slot, err) = (transaction.slot, transaction.error.clone());
+        let order = state.orders.get_mut(&id).unwrap();
+        match order.state() {
+            // Statuses were polled before the transaction came through Kafka
+            _ if err.is_some() => {}
+            landed if landed.is_landed() => state.confirmed_fills.push(transaction),
+            in_flight if !in_flight.is_terminal() => order.pending_fill = Some(transaction),
+            _ => {}
+        }
+        state.apply_status(&signature, SignatureStatus { slot, commitment: Commitment::Processed, err });
+        None
+    }
+
+    pub fn apply_status(&self, signature: &Signature, status: SignatureStatus) {
+        self.state.lock().unwrap().apply_status(signature, status);
+    }
+
+    /// Applies a block engine result for the bundle carrying the order's current transaction.
+    /// Landing is still confirmed through signature statuses, which release the fill.
+    pub fn apply_bundle_status(&self, id: OrderId, status: &BundleStatus) -> Result<(), OrderError> {
+        let mut state = self.state.lock().unwrap();
+        let order = state.order_mut(id)?;
+        if !order.state().is_in_flight() {
+            return Ok(());
+        }
+        match status {
+            BundleStatus::Processed { slot, .. } if matches!(order.state(), OrderState::Sent) => {
+                order.transition(OrderState::Seen { slot: *slot })
+            }
+            BundleStatus::Rejected(reason) => order.transition(OrderState::Failed { reason: format!("bundle rejected: {:?}", reason) }),
+            // `resubmit_expired` rebuilds the order while it has attempts left
+            BundleStatus::Dropped(DropReason::BlockhashExpired) if order.attempts >= self.policy.max_attempts => {
+                order.transition(OrderState::Expired)
+            }
+            BundleStatus::Dropped(DropReason::BlockhashExpired) => Ok(()),
+            BundleStatus::Dropped(reason) => order.transition(OrderState::Failed { reason: format!("bundle dropped: {:?}", reason) }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fills of orders confirmed since the last call, ready for `PositionTracker`.
+    pub fn drain_confirmed_fills(&self) -> Vec<DecodedTransaction> {
+        std::mem::take(&mut self.state.lock().unwrap().confirmed_fills)
+    }
+
+    /// Rebuilds in-flight orders whose blockhash expired with a fresh blockhash and bumped fees,
+    /// or marks them expired once they are out of attempts.
+    /// The rebuilt transactions are returned for the caller to send and `mark_sent`.
+    pub async fn resubmit_expired(
+        &self,
+        signer: &dyn TradeSigner,
+        provider: &dyn BlockhashProvider,
+    ) -> Vec<(OrderId, Result<VersionedTransaction, OrderError>)> {
+        // Signing may wait on a remote signer, so orders are rebuilt outside the lock
+        let expired: Vec<(OrderId, Signature, Vec<Instruction>, TradeParams)> = {
+            let mut state = self.state.lock().unwrap();
+            let mut expired = Vec::new();
+            for order in state.orders.values_mut() {
+                if !order.state().is_in_flight() || !provider.is_expired(&order.params.recent_blockhash) {
+                    continue;
+                }
+                if order.attempts >= self.policy.max_attempts {
+                    let _ = order.transition(OrderState::Expired);
+                    continue;
+                }
+                expired.push((order.id, order.signature(), order.instructions.clone(), order.params.bumped(self.policy.fee_bump_bps)));
+            }
+            expired
+        };
+
+        let mut rebuilt = Vec::new();
+        for (id, signature, instructions, params) in expired {
+            let result = match rebuild(signer, provider, instructions, params).await {
+                Ok((params, transaction)) => {
+                    let mut guard = self.state.lock().unwrap();
+                    let state = &mut *guard;
+                    let order = state.orders.get_mut(&id).unwrap();
+                    // It landed, failed or was rebuilt by another caller while this one was signing
+                    if !order.state().is_in_flight() || order.signature() != signature {
+                        continue;
+                    }
+                    order.transition(OrderState::Built).map(|_| {
+                        order.params = params;
+                        order.transaction = transaction.clone();
+                        order.attempts += 1;
+                        order.pending_fill = None;
+                        state.by_signature.insert(transaction.signatures[0], id);
+                        transaction
+                    })
+                }
+                Err(e) => Err(e),
+            };
+            rebuilt.push((id, result));
+        }
+        rebuilt
+    }
+
+    pub async fn refresh_statuses(&self, source: &dyn SignatureStatusSource) -> Result<(), OrderError> {
+        // Every attempt of an in-flight or unfinalized order, since any of them may have landed
+        let signatures: Vec<Signature> = {
+            let state = self.state.lock().unwrap();
+            state.by_signature.iter()
+                .filter(|(_, id)| state.orders[*id].state().is_in_flight()
+                    || matches!(state.orders[*id].state(), OrderState::Confirmed { .. }))
+                .map(|(signature, _)| *signature)
+                .collect()
+        };
+        for batch in signatures.chunks(MAX_STATUS_BATCH) {
+            let statuses = source.statuses(batch).await?;
+            let mut state = self.state.lock().unwrap();
+            for (signature, status) in batch.iter().zip(statuses) {
+                if let Some(status) = status {
+                    state.apply_status(signature, status);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Polls signature statuses every `interval` until the handle is aborted
+    pub fn spawn_status_polling(self: &Arc<Self>, source: Arc<dyn SignatureStatusSource>, interval: Duration) -> JoinHandle<()> {
+        let book = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = book.refresh_statuses(source.as_ref()).await {
+                    error!("Failed to refresh order statuses: {}", e);
+                }
+            }
+        })
+    }
+}
+
+// Signs the order's instructions again under `params` with the provider's freshest blockhash
+async fn rebuild(
+    signer: &dyn TradeSigner,
+    provider: &dyn BlockhashProvider,
+    instructions: Vec<Instruction>,
+    mut params: TradeParams,
+) -> Result<(TradeParams, VersionedTransaction), OrderError> {
+    params.recent_blockhash = provider.latest()?.hash;
+    let transaction = TradeBuilder::new(signer, params).build(instructions).await?;
+    Ok((params, transaction))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::pubkey::Pubkey;
+    use solana_sdk::signature::{Keypair, Signer};
+    use crate::solana::blockhash::{BlockhashCache, RecentBlockhash};
+    use crate::solana::trade_builder::JitoTip;
+    use crate::solana::transaction::DecodedInstruction;
+
+    fn params(recent_blockhash: Hash) -> TradeParams {
+        TradeParams {
+            compute_unit_price: 10_000,
+            compute_unit_limit: 100_000,
+            tip: Some(JitoTip { account: Pubkey::new_unique(), lamports: 1_000 }),
+            recent_blockhash,
+        }
+    }
+
+    fn transfer(signer: &Keypair) -> Vec<Instruction> {
+        vec![solana_sdk::system_instruction::transfer(&signer.pubkey(), &Pubkey::new_unique(), 1)]
+    }
+
+    fn landed(signature: &Signature, slot: u64, error: Option<&str>) -> DecodedTransaction {
+        let mut transaction = DecodedTransaction::new_empty();
+        transaction.slot = slot;
+        transaction.signatures = vec![signature.as_ref().to_vec()];
+        transaction.instructions = vec![DecodedInstruction::PFWithdraw];
+        transaction.error = error.map(str::to_string);
+        transaction
+    }
+
+    fn states(book: &OrderBook, id: OrderId) -> Vec<OrderState> {
+        book.history(id).into_iter().map(|transition| transition.state).collect()
+    }
+
+    #[tokio::test]
+    async fn test_fill_is_released_on_confirmation() {
+        let signer = Keypair::new();
+        let book = OrderBook::new(ResubmitPolicy::never());
+        let (id, transaction) = book.create(&TradeBuilder::new(&signer, params(Hash::new_unique())), transfer(&signer)).await.unwrap();
+        let signature = transaction.signatures[0];
+        book.mark_sent(id).unwrap();
+
+        let other = landed(&Signature::new_unique(), 9, None);
+        assert!(book.observe_transaction(other).is_some());
+        assert!(book.observe_transaction(landed(&signature, 10, None)).is_none());
+        assert!(book.drain_confirmed_fills().is_empty());
+
+        book.apply_status(&signature, SignatureStatus { slot: 10, commitment: Commitment::Confirmed, err: None });
+        assert_eq!(book.drain_confirmed_fills().len(), 1);
+        book.apply_status(&signature, SignatureStatus { slot: 10, commitment: Commitment::Finalized, err: None });
+        assert!(book.drain_confirmed_fills().is_empty());
+
+        assert_eq!(states(&book, id), vec![
+            OrderState::Built,
+            OrderState::Sent,
+            OrderState::Seen { slot: 10 },
+            OrderState::Confirmed { slot: 10 },
+            OrderState::Finalized { slot: 10 },
+        ]);
+        let history = book.history(id);
+        assert!(history.windows(2).all(|pair| pair[0].at <= pair[1].at));
+    }
+
+    #[tokio::test]
+    async fn test_failed_transaction_never_fills() {
+        let signer = Keypair::new();
+        let book = OrderBook::new(ResubmitPolicy::never());
+        let (id, transaction) = book.create(&TradeBuilder::new(&signer, params(Hash::new_unique())), transfer(&signer)).await.unwrap();
+        book.mark_sent(id).unwrap();
+
+        book.observe_transaction(landed(&transaction.signatures[0], 10, Some("custom program error: 0x1772")));
+        book.apply_status(&transaction.signatures[0], SignatureStatus { slot: 10, commitment: Commitment::Confirmed, err: None });
+
+        assert_eq!(book.state(id), Some(OrderState::Failed { reason: "custom program error: 0x1772".to_string() }));
+        assert!(book.drain_confirmed_fills().is_empty());
+        assert!(matches!(book.mark_sent(id), Err(OrderError::InvalidTransition { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_expired_order_is_rebuilt_with_bumped_fee() {
+        let signer = Keypair::new();
+        let cache = BlockhashCache::new();
+        let first = RecentBlockhash { hash: Hash::new_unique(), last_valid_block_height: 150 };
+        cache.insert(first);
+        cache.observe_block_height(100);
+        let book = OrderBook::new(ResubmitPolicy { max_attempts: 2, fee_bump_bps: 5_000 });
+        let (id, transaction) = book.create(&TradeBuilder::new(&signer, params(first.hash)), transfer(&signer)).await.unwrap();
+        book.mark_sent(id).unwrap();
+
+        assert!(book.resubmit_expired(&signer, &cache).await.is_empty());
+
+        let second = RecentBlockhash { hash: Hash::new_unique(), last_valid_block_height: 300 };
+        cache.insert(second);
+        cache.observe_block_height(151);
+        let rebuilt = book.resubmit_expired(&signer, &cache).await;
+        assert_eq!(rebuilt.len(), 1);
+        let resubmitted = rebuilt.into_iter().next().unwrap().1.unwrap();
+        assert_eq!(*resubmitted.message.recent_blockhash(), second.hash);
+        assert_ne!(resubmitted.signatures[0], transaction.signatures[0]);
+        assert_eq!(book.order_for_signature(&resubmitted.signatures[0]), Some(id));
+        {
+            let state = book.state.lock().unwrap();
+            let order = &state.orders[&id];
+            assert_eq!(order.params().compute_unit_price, 15_000);
+            assert_eq!(order.params().tip.unwrap().lamports, 1_500);
+            assert_eq!(order.attempts(), 2);
+        }
+        book.mark_sent(id).unwrap();
+
+        // Out of attempts
+        cache.observe_block_height(301);
+        assert!(book.resubmit_expired(&signer, &cache).await.is_empty());
+        assert_eq!(states(&book, id), vec![
+            OrderState::Built,
+            OrderState::Sent,
+            OrderState::Built,
+            OrderState::Sent,
+            OrderState::Expired,
+        ]);
+    }
+
+    struct MockStatusSource {
+        finalized: Signature,
+    }
+
+    #[async_trait]
+    impl SignatureStatusSource for MockStatusSource {
+        async fn statuses(&self, signatures: &[Signature]) -> Result<Vec<Option<SignatureStatus>>, OrderError> {
+            Ok(signatures.iter()
+                .map(|signature| (*signature == self.finalized)
+                    .then_some(SignatureStatus { slot: 42, commitment: Commitment::Finalized, err: None }))
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_statuses_are_polled() {
+        let signer = Keypair::new();
+        let book = OrderBook::new(ResubmitPolicy::never());
+        let builder = TradeBuilder::new(&signer, params(Hash::new_unique()));
+        let (landed_id, landed) = book.create(&builder, transfer(&signer)).await.unwrap();
+        let (pending_id, _) = book.create(&builder, transfer(&signer)).await.unwrap();
+        book.mark_sent(landed_id).unwrap();
+        book.mark_sent(pending_id).unwrap();
+
+        book.refresh_statuses(&MockStatusSource { finalized: landed.signatures[0] }).await.unwrap();
+
+        assert_eq!(book.state(landed_id), Some(OrderState::Finalized { slot: 42 }));
+        assert_eq!(book.state(pending_id), Some(OrderState::Sent));
+    }
+
+    #[tokio::test]
+    async fn test_bundle_results_drive_orders() {
+        use crate::jito::bundle_results::RejectReason;
+        let signer = Keypair::new();
+        let book = OrderBook::new(ResubmitPolicy { max_attempts: 2, fee_bump_bps: 0 });
+        let mut orders = Vec::new();
+        for _ in 0..3 {
+            let (id, _) = book.create(&TradeBuilder::new(&signer, params(Hash::new_unique())), transfer(&signer)).await.unwrap();
+            book.mark_sent(id).unwrap();
+            orders.push(id);
+        }
+
+        book.apply_bundle_status(orders[0], &BundleStatus::Accepted { slot: 9, validator_identity: "validator".to_string() }).unwrap();
+        assert_eq!(book.state(orders[0]), Some(OrderState::Sent));
+        book.apply_bundle_status(orders[0], &BundleStatus::Processed { slot: 10, validator_identity: "validator".to_string(), bundle_index: 0 }).unwrap();
+        assert_eq!(book.state(orders[0]), Some(OrderState::Seen { slot: 10 }));
+
+        book.apply_bundle_status(orders[1], &BundleStatus::Rejected(RejectReason::Unspecified)).unwrap();
+        assert!(matches!(book.state(orders[1]), Some(OrderState::Failed { .. })));
+        // Nothing moves an order out of a terminal state
+        book.apply_bundle_status(orders[1], &BundleStatus::Processed { slot: 10, validator_identity: "validator".to_string(), bundle_index: 0 }).unwrap();
+        assert!(matches!(book.state(orders[1]), Some(OrderState::Failed { .. })));
+
+        // An expired blockhash is left for resubmission while attempts remain
+        book.apply_bundle_status(orders[2], &BundleStatus::Dropped(DropReason::BlockhashExpired)).unwrap();
+        assert_eq!(book.state(orders[2]), Some(OrderState::Sent));
+        book.apply_bundle_status(orders[2], &BundleStatus::Dropped(DropReason::PartiallyProcessed)).unwrap();
+        assert!(matches!(book.state(orders[2]), Some(OrderState::Failed { .. })));
+
+        let book = OrderBook::new(ResubmitPolicy::never());
+        let (id, _) = book.create(&TradeBuilder::new(&signer, params(Hash::new_unique())), transfer(&signer)).await.unwrap();
+        book.mark_sent(id).unwrap();
+        book.apply_bundle_status(id, &BundleStatus::Dropped(DropReason::BlockhashExpired)).unwrap();
+        assert_eq!(book.state(id), Some(OrderState::Expired));
+    }
+}
@@ -8,7 +8,7 @@ use std::time::{UNIX_EPOCH, SystemTime, Duration};
 use std::error::Error;
 use colored::*;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Position {
     token_amount: f64,
     sol_invested: f64,
@@ -73,7 +73,7 @@ impl fmt::Display for Position {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Account {
     positions: HashMap<Pubkey, Position>,
 }
@@ -102,6 +102,7 @@ impl Account {
     }
 }
 
+#[derive(Clone)]
 pub struct PositionTracker {
     last_price_in_sol: HashMap<Pubkey, f64>,
     accounts: HashMap<Pubkey, Account>,
@@ -178,7 +179,11 @@ impl PositionTracker {
         Ok(results)
     }
     
+    // Failed transactions still show their instructions, but nothing was bought or sold
     pub fn update_by_transaction(&mut self, transaction: &transaction::DecodedTransaction) {
+        if transaction.error.is_some() {
+            return;
+        }
         let mut add_fee = false;
         let mut affected_positions: HashSet<(Pubkey, Pubkey)> = HashSet::new();
         
@@ -0,0 +1,253 @@
+use std::env;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use dotenv::dotenv;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer, SignerError};
+use crate::solana::wallet::{self, KeystoreError, Unlock};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Signs the transactions the trade builders produce.
+///
+/// Implemented in-process by `Keypair` and `KeystoreSigner`, and out of process by `RemoteSigner`,
+/// which keeps the secret key in a signer daemon so the trading process never holds it.
+/// Async callers sign through `sign_async`, which never blocks the runtime.
+#[async_trait]
+pub trait TradeSigner: Signer + Send + Sync {
+    // For APIs that take plain signers, e.g. `Transaction::try_sign`
+    fn as_signer(&self) -> &dyn Signer;
+
+    // In-process signers are cheap enough to sign inline
+    async fn sign_async(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        self.try_sign_message(message)
+    }
+}
+
+impl TradeSigner for Keypair {
+    fn as_signer(&self) -> &dyn Signer {
+        self
+    }
+}
+
+/// A keypair unlocked from an encrypted keystore file.
+pub struct KeystoreSigner {
+    keypair: Keypair,
+    path: PathBuf,
+}
+
+impl KeystoreSigner {
+    pub fn open(path: impl AsRef<Path>, unlock: &Unlock) -> Result<Self, KeystoreError> {
+        let path = path.as_ref();
+        Ok(KeystoreSigner { keypair: wallet::load_keypair(path, unlock)?, path: path.to_path_buf() })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Signer for KeystoreSigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        self.keypair.try_pubkey()
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        self.keypair.try_sign_message(message)
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+impl TradeSigner for KeystoreSigner {
+    fn as_signer(&self) -> &dyn Signer {
+        self
+    }
+}
+
+/// One line of JSON sent to the signer daemon.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum SignerRequest {
+    Pubkey,
+    /// `message` is the base64 serialized transaction message
+    Sign { message: String },
+}
+
+/// The daemon's one-line JSON answer to a `SignerRequest`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignerResponse {
+    Pubkey(String),
+    Signature(String),
+    /// Refused by the daemon's policy, or a malformed request
+    Error(String),
+}
+
+/// Signs through a signer daemon listening on a local Unix socket.
+///
+/// The connection is reopened once if it fails, so the daemon can be restarted under a running
+/// bot. Signatures are verified against the daemon's public key before they're returned.
+/// The socket I/O blocks, so `sign_async` runs it on the blocking thread pool.
+pub struct RemoteSigner {
+    pubkey: Pubkey,
+    connection: Arc<DaemonConnection>,
+}
+
+impl RemoteSigner {
+    pub fn connect(socket_path: impl Into<PathBuf>) -> Result<Self, SignerError> {
+        Self::connect_with_timeout(socket_path, DEFAULT_TIMEOUT)
+    }
+
+    pub fn connect_with_timeout(socket_path: impl Into<PathBuf>, timeout: Duration) -> Result<Self, SignerError> {
+        let connection = DaemonConnection {
+            socket_path: socket_path.into(),
+            timeout,
+            stream: Mutex::new(None),
+        };
+        let pubkey = match connection.call(&SignerRequest::Pubkey)? {
+            SignerResponse::Pubkey(pubkey) => pubkey.parse().map_err(|_| SignerError::Protocol(format!("invalid pubkey {}", pubkey)))?,
+            response => return Err(unexpected(response)),
+        };
+        Ok(RemoteSigner { pubkey, connection: Arc::new(connection) })
+    }
+
+    pub fn socket_path(&self) -> &Path {
+        &self.connection.socket_path
+    }
+}
+
+// The daemon socket, shared with blocking tasks signing on the signer's behalf
+struct DaemonConnection {
+    socket_path: PathBuf,
+    timeout: Duration,
+    stream: Mutex<Option<BufReader<UnixStream>>>,
+}
+
+impl DaemonConnection {
+    fn sign(&self, pubkey: &Pubkey, message: &[u8]) -> Result<Signature, SignerError> {
+        let request = SignerRequest::Sign { message: STANDARD.encode(message) };
+        let signature: Signature = match self.call(&request)? {
+            SignerResponse::Signature(signature) => signature.parse().map_err(|_| SignerError::Protocol(format!("invalid signature {}", signature)))?,
+            SignerResponse::Error(reason) => return Err(SignerError::Custom(reason)),
+            response => return Err(unexpected(response)),
+        };
+        if !signature.verify(pubkey.as_ref(), message) {
+            return Err(SignerError::Protocol("signature doesn't verify against the daemon's key".to_string()));
+        }
+        Ok(signature)
+    }
+
+    fn call(&self, request: &SignerRequest) -> Result<SignerResponse, SignerError> {
+        let mut line = serde_json::to_string(request).map_err(|e| SignerError::Protocol(e.to_string()))?;
+        line.push('\n');
+
+        let mut connection = self.stream.lock().unwrap();
+        let reused = connection.is_some();
+        match self.exchange(&mut connection, &line) {
+            Err(_) if reused => {
+                // The daemon may have restarted since the connection was opened
+                *connection = None;
+                self.exchange(&mut connection, &line)
+            }
+            result => result,
+        }
+    }
+
+    fn exchange(&self, connection: &mut Option<BufReader<UnixStream>>, line: &str) -> Result<SignerResponse, SignerError> {
+        if connection.is_none() {
+            let stream = UnixStream::connect(&self.socket_path).map_err(connection_error)?;
+            stream.set_read_timeout(Some(self.timeout)).map_err(connection_error)?;
+            stream.set_write_timeout(Some(self.timeout)).map_err(connection_error)?;
+            *connection = Some(BufReader::new(stream));
+        }
+        let reader = connection.as_mut().unwrap();
+        let result = (|| {
+            reader.get_mut().write_all(line.as_bytes())?;
+            let mut response = String::new();
+            if reader.read_line(&mut response)? == 0 {
+                return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "signer daemon closed the connection"));
+            }
+            Ok(response)
+        })();
+        match result {
+            Ok(response) => serde_json::from_str(&response).map_err(|e| SignerError::Protocol(e.to_string())),
+            Err(e) => {
+                *connection = None;
+                Err(connection_error(e))
+            }
+        }
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.pubkey)
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        self.connection.sign(&self.pubkey, message)
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+#[async_trait]
+impl TradeSigner for RemoteSigner {
+    fn as_signer(&self) -> &dyn Signer {
+        self
+    }
+
+    async fn sign_async(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        let (connection, pubkey, message) = (Arc::clone(&self.connection), self.pubkey, message.to_vec());
+        tokio::task::spawn_blocking(move || connection.sign(&pubkey, &message))
+            .await
+            .map_err(|e| SignerError::Custom(e.to_string()))?
+    }
+}
+
+// SIGNER_SOCKET, then KEYSTORE_PATH unlocked by KEYSTORE_KEY_FILE or KEYSTORE_PASSPHRASE, then a
+// base58 PRIVATE_KEY. Unlocking or connecting is slow, so call this once at startup and share the signer.
+pub fn signer_from_env() -> Result<Arc<dyn TradeSigner>, SignerError> {
+    dotenv().ok();
+    if let Ok(socket_path) = env::var("SIGNER_SOCKET") {
+        return Ok(Arc::new(RemoteSigner::connect(socket_path)?));
+    }
+    if let Ok(path) = env::var("KEYSTORE_PATH") {
+        let unlock = Unlock::from_env().map_err(keystore_error)?;
+        return Ok(Arc::new(KeystoreSigner::open(path, &unlock).map_err(keystore_error)?));
+    }
+    let private_key = zeroize::Zeroizing::new(
+        env::var("PRIVATE_KEY").map_err(|_| SignerError::Custom("none of SIGNER_SOCKET, KEYSTORE_PATH or PRIVATE_KEY is set".to_string()))?,
+    );
+    let secret = zeroize::Zeroizing::new(
+        bs58::decode(private_key.as_str()).into_vec().map_err(|e| SignerError::InvalidInput(e.to_string()))?,
+    );
+    let keypair = Keypair::from_bytes(&secret).map_err(|e| SignerError::InvalidInput(e.to_string()))?;
+    Ok(Arc::new(keypair))
+}
+
+fn connection_error(error: std::io::Error) -> SignerError {
+    SignerError::Connection(error.to_string())
+}
+
+fn keystore_error(error: KeystoreError) -> SignerError {
+    SignerError::Custom(error.to_string())
+}
+
+fn unexpected(response: SignerResponse) -> SignerError {
+    match response {
+        SignerResponse::Error(reason) => SignerError::Custom(reason),
+        response => SignerError::Protocol(format!("unexpected response {:?}", response)),
+    }
+}
@@ -0,0 +1,285 @@
+use std::collections::HashSet;
+use std::env;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::thread;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use dotenv::dotenv;
+use log::{error, info, warn};
+use solana_program::message::VersionedMessage;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_instruction::SystemInstruction;
+use crate::solana::idl::discriminator;
+use crate::solana::signer::{SignerRequest, SignerResponse};
+use crate::solana::transaction::PUMPFUN_PROGRAM_ID;
+
+// ComputeBudgetInstruction::SetComputeUnitPrice
+const SET_COMPUTE_UNIT_PRICE: u8 = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PolicyViolation {
+    #[error("malformed message: {0}")]
+    Malformed(String),
+    #[error("fee payer {0} isn't the daemon's key")]
+    ForeignPayer(Pubkey),
+    #[error("program {0} isn't allowed")]
+    ProgramNotAllowed(Pubkey),
+    #[error("instruction of {0} isn't allowed")]
+    InstructionNotAllowed(Pubkey),
+    #[error("compute unit price {price} exceeds {max}")]
+    ComputeUnitPrice { price: u64, max: u64 },
+    #[error("transaction may spend {lamports} lamports, more than {max}")]
+    Spend { lamports: u64, max: u64 },
+}
+
+/// What the signer daemon agrees to sign. Anything it can't account for is refused.
+///
+/// Spend is counted from system transfers and account creations, which include Jito tips, and
+/// from the `max_sol_cost` of Pump.fun buys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignerPolicy {
+    pub allowed_programs: HashSet<Pubkey>,
+    pub max_lamports_per_transaction: u64,
+    /// Micro-lamports per compute unit
+    pub max_compute_unit_price: u64,
+}
+
+impl SignerPolicy {
+    pub fn default_programs() -> HashSet<Pubkey> {
+        HashSet::from([
+            solana_sdk::compute_budget::id(),
+            solana_sdk::system_program::id(),
+            spl_associated_token_account::id(),
+            Pubkey::from_str(PUMPFUN_PROGRAM_ID).unwrap(),
+        ])
+    }
+
+    // SIGNER_MAX_LAMPORTS and SIGNER_MAX_COMPUTE_UNIT_PRICE are required; SIGNER_ALLOWED_PROGRAMS is a
+    // comma-separated list replacing `default_programs`
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        dotenv().ok();
+        let max_lamports_per_transaction = env::var("SIGNER_MAX_LAMPORTS")
+            .map_err(|_| "SIGNER_MAX_LAMPORTS not set in environment")?.parse()?;
+        let max_compute_unit_price = env::var("SIGNER_MAX_COMPUTE_UNIT_PRICE")
+            .map_err(|_| "SIGNER_MAX_COMPUTE_UNIT_PRICE not set in environment")?.parse()?;
+        let allowed_programs = match env::var("SIGNER_ALLOWED_PROGRAMS") {
+            Ok(programs) => programs.split(',')
+                .map(|program| Pubkey::from_str(program.trim()))
+                .collect::<Result<_, _>>()?,
+            Err(_) => Self::default_programs(),
+        };
+        Ok(SignerPolicy { allowed_programs, max_lamports_per_transaction, max_compute_unit_price })
+    }
+
+    pub fn check(&self, signer: &Pubkey, message: &VersionedMessage) -> Result<(), PolicyViolation> {
+        let keys = message.static_account_keys();
+        let payer = *keys.first().ok_or_else(|| PolicyViolation::Malformed("no accounts".to_string()))?;
+        if payer != *signer {
+            return Err(PolicyViolation::ForeignPayer(payer));
+        }
+
+        let pumpfun = Pubkey::from_str(PUMPFUN_PROGRAM_ID).unwrap();
+        let mut spend: u64 = 0;
+        for instruction in message.instructions() {
+            // Program ids can't come from lookup tables
+            let program = *keys.get(instruction.program_id_index as usize)
+                .ok_or_else(|| PolicyViolation::Malformed("program id out of range".to_string()))?;
+            if !self.allowed_programs.contains(&program) {
+                return Err(PolicyViolation::ProgramNotAllowed(program));
+            }
+            let data = &instruction.data;
+            if program == solana_sdk::compute_budget::id() {
+                if data.first() == Some(&SET_COMPUTE_UNIT_PRICE) {
+                    let price = read_u64(data, 1)?;
+                    if price > self.max_compute_unit_price {
+                        return Err(PolicyViolation::ComputeUnitPrice { price, max: self.max_compute_unit_price });
+                    }
+                }
+            } else if program == solana_sdk::system_program::id() {
+                // Assign, nonce and the rest could hand the wallet to someone else
+                let lamports = match bincode::deserialize::<SystemInstruction>(data) {
+                    Ok(SystemInstruction::Transfer { lamports })
+                    | Ok(SystemInstruction::TransferWithSeed { lamports, .. })
+                    | Ok(SystemInstruction::CreateAccount { lamports, .. })
+                    | Ok(SystemInstruction::CreateAccountWithSeed { lamports, .. }) => lamports,
+                    _ => return Err(PolicyViolation::InstructionNotAllowed(program)),
+                };
+                spend = spend.saturating_add(lamports);
+            } else if program == pumpfun {
+                let method = data.get(..8).ok_or_else(|| PolicyViolation::Malformed("short Pump.fun data".to_string()))?;
+                if method == discriminator("global", "buy") {
+                    // amount, then max_sol_cost
+                    spend = spend.saturating_add(read_u64(data, 16)?);
+                } else if method != discriminator("global", "sell") && method != discriminator("global", "create") {
+                    return Err(PolicyViolation::InstructionNotAllowed(program));
+                }
+            }
+        }
+        if spend > self.max_lamports_per_transaction {
+            return Err(PolicyViolation::Spend { lamports: spend, max: self.max_lamports_per_transaction });
+        }
+        Ok(())
+    }
+}
+
+/// Holds a keypair and signs messages sent over a Unix socket if its `SignerPolicy` allows them.
+///
+/// Speaks the line-delimited JSON `SignerRequest`/`SignerResponse` protocol of `RemoteSigner`.
+pub struct SignerDaemon {
+    keypair: Keypair,
+    policy: SignerPolicy,
+}
+
+impl SignerDaemon {
+    pub fn new(keypair: Keypair, policy: SignerPolicy) -> Self {
+        SignerDaemon { keypair, policy }
+    }
+
+    pub fn pubkey(&self) -> Pubkey {
+        self.keypair.pubkey()
+    }
+
+    pub fn handle(&self, request: SignerRequest) -> SignerResponse {
+        match request {
+            SignerRequest::Pubkey => SignerResponse::Pubkey(self.pubkey().to_string()),
+            SignerRequest::Sign { message } => {
+                let bytes = match STANDARD.decode(&message) {
+                    Ok(bytes) => bytes,
+                    Err(e) => return SignerResponse::Error(format!("invalid base64: {}", e)),
+                };
+                let decoded: VersionedMessage = match bincode::deserialize(&bytes) {
+                    Ok(decoded) => decoded,
+                    Err(e) => return SignerResponse::Error(PolicyViolation::Malformed(e.to_string()).to_string()),
+                };
+                if let Err(violation) = self.policy.check(&self.pubkey(), &decoded) {
+                    warn!("Refused to sign: {}", violation);
+                    return SignerResponse::Error(violation.to_string());
+                }
+                SignerResponse::Signature(self.keypair.sign_message(&bytes).to_string())
+            }
+        }
+    }
+
+    // Serves each connection on its own thread until the listener fails
+    pub fn serve(self: Arc<Self>, listener: UnixListener) -> io::Result<()> {
+        info!("Signer daemon for {} listening", self.pubkey());
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let daemon = Arc::clone(&self);
+            thread::spawn(move || {
+                if let Err(e) = daemon.serve_connection(stream) {
+                    error!("Signer connection failed: {}", e);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    fn serve_connection(&self, stream: UnixStream) -> io::Result<()> {
+        let mut writer = stream.try_clone()?;
+        for line in BufReader::new(stream).lines() {
+            let response = match serde_json::from_str(&line?) {
+                Ok(request) => self.handle(request),
+                Err(e) => SignerResponse::Error(format!("invalid request: {}", e)),
+            };
+            let mut response = serde_json::to_string(&response)?;
+            response.push('\n');
+            writer.write_all(response.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64, PolicyViolation> {
+    data.get(offset..offset + 8)
+        .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or_else(|| PolicyViolation::Malformed("short instruction data".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::signature::SignerError;
+    use solana_sdk::system_instruction;
+    use crate::solana::signer::RemoteSigner;
+    use crate::solana::trade_builder::{JitoTip, TradeBuilder, TradeBuildError, TradeParams};
+    use crate::solana::transaction;
+
+    fn policy() -> SignerPolicy {
+        SignerPolicy {
+            allowed_programs: SignerPolicy::default_programs(),
+            max_lamports_per_transaction: 1_000_000_000,
+            max_compute_unit_price: 100_000,
+        }
+    }
+
+    fn params(compute_unit_price: u64) -> TradeParams {
+        TradeParams {
+            compute_unit_price,
+            compute_unit_limit: 200_000,
+            tip: Some(JitoTip { account: Pubkey::new_unique(), lamports: 10_000 }),
+            recent_blockhash: Hash::new_unique(),
+        }
+    }
+
+    fn spawn_daemon(policy: SignerPolicy) -> (Pubkey, std::path::PathBuf) {
+        let socket_path = env::temp_dir().join(format!("signer-test-{}.sock", Keypair::new().pubkey()));
+        let listener = UnixListener::bind(&socket_path).unwrap();
+        let daemon = Arc::new(SignerDaemon::new(Keypair::new(), policy));
+        let pubkey = daemon.pubkey();
+        thread::spawn(move || daemon.serve(listener));
+        (pubkey, socket_path)
+    }
+
+    #[tokio::test]
+    async fn test_remote_signer_signs_within_policy() {
+        let (pubkey, socket_path) = spawn_daemon(policy());
+        let signer = RemoteSigner::connect(&socket_path).unwrap();
+        assert_eq!(signer.pubkey(), pubkey);
+
+        let mint = Pubkey::new_unique();
+        let transaction = TradeBuilder::new(&signer, params(50_000)).pumpfun_buy(&mint, 1_000, 500_000_000, true).await.unwrap();
+        assert!(transaction.verify_with_results().iter().all(|valid| *valid));
+        assert_eq!(transaction.message.static_account_keys()[0], pubkey);
+
+        // A 1 SOL max cost is at the spend limit on its own, but not with the tip
+        let result = TradeBuilder::new(&signer, params(50_000)).pumpfun_buy(&mint, 1_000, 1_000_000_000, true).await;
+        assert!(matches!(result, Err(TradeBuildError::Signer(SignerError::Custom(_)))));
+        // Over the priority fee limit
+        let result = TradeBuilder::new(&signer, params(200_000)).pumpfun_sell(&mint, 1_000, 0).await;
+        assert!(matches!(result, Err(TradeBuildError::Signer(SignerError::Custom(_)))));
+        std::fs::remove_file(socket_path).unwrap();
+    }
+
+    #[test]
+    fn test_policy_refuses_foreign_programs_and_assign() {
+        let signer = Keypair::new();
+        let payer = signer.pubkey();
+        let check = |instructions: &[solana_program::instruction::Instruction]| {
+            let message = solana_program::message::v0::Message::try_compile(&payer, instructions, &[], Hash::new_unique()).unwrap();
+            policy().check(&payer, &VersionedMessage::V0(message))
+        };
+
+        assert_eq!(check(&[system_instruction::transfer(&payer, &Pubkey::new_unique(), 1_000)]), Ok(()));
+        assert_eq!(
+            check(&[system_instruction::assign(&payer, &Pubkey::new_unique())]),
+            Err(PolicyViolation::InstructionNotAllowed(solana_sdk::system_program::id()))
+        );
+        let token_program = Pubkey::from_str(transaction::TOKEN_PROGRAM_ID).unwrap();
+        let close = solana_program::instruction::Instruction::new_with_bytes(token_program, &[9], vec![]);
+        assert_eq!(check(&[close]), Err(PolicyViolation::ProgramNotAllowed(token_program)));
+        // A message paid by someone else
+        let other = Pubkey::new_unique();
+        let message = solana_program::message::v0::Message::try_compile(
+            &other,
+            &[system_instruction::transfer(&other, &payer, 1)],
+            &[],
+            Hash::new_unique(),
+        ).unwrap();
+        assert_eq!(policy().check(&payer, &VersionedMessage::V0(message)), Err(PolicyViolation::ForeignPayer(other)));
+    }
+}
@@ -0,0 +1,11 @@
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::message::{v0, VersionedMessage};
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::VersionedTransaction;
+
+// V0 transaction of `instructions` paid and signed by `payer`, on a made-up blockhash
+pub(crate) fn signed_transaction(payer: &Keypair, instructions: &[Instruction]) -> VersionedTransaction {
+    let message = v0::Message::try_compile(&payer.pubkey(), instructions, &[], Hash::new_unique()).unwrap();
+    VersionedTransaction::try_new(VersionedMessage::V0(message), &[payer]).unwrap()
+}
@@ -0,0 +1,330 @@
+use std::str::FromStr;
+use solana_program::instruction::Instruction;
+use solana_program::message::{v0, CompileError, VersionedMessage};
+use solana_sdk::address_lookup_table_account::AddressLookupTableAccount;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::SignerError;
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::VersionedTransaction;
+use spl_associated_token_account::instruction::create_associated_token_account;
+use crate::solana::blockhash::{BlockhashError, BlockhashProvider};
+use crate::solana::fee_estimator::PriorityFeeEstimator;
+use crate::solana::signer::TradeSigner;
+use crate::solana::transaction;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TradeBuildError {
+    #[error("CompileError {0}")]
+    Compile(#[from] CompileError),
+    #[error("SignerError {0}")]
+    Signer(#[from] SignerError),
+}
+
+/// Lamports paid to a Jito tip account so the block engine includes the transaction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JitoTip {
+    pub account: Pubkey,
+    pub lamports: u64,
+}
+
+/// Fee, tip and blockhash settings applied to every transaction a `TradeBuilder` builds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TradeParams {
+    /// Priority fee in micro-lamports per compute unit
+    pub compute_unit_price: u64,
+    pub compute_unit_limit: u32,
+    pub tip: Option<JitoTip>,
+    pub recent_blockhash: Hash,
+}
+
+impl TradeParams {
+    // Uses the provider's freshest blockhash, failing rather than building on an expired one
+    pub fn from_provider(
+        provider: &dyn BlockhashProvider,
+        compute_unit_price: u64,
+        compute_unit_limit: u32,
+        tip: Option<JitoTip>,
+    ) -> Result<Self, BlockhashError> {
+        Ok(TradeParams {
+            compute_unit_price,
+            compute_unit_limit,
+            tip,
+            recent_blockhash: provider.latest()?.hash,
+        })
+    }
+
+    // Raises the compute unit price and tip by `bps` basis points, rounding up so any bump is at least one unit
+    pub fn bumped(mut self, bps: u64) -> Self {
+        let bump = |value: u64| value.saturating_add(((value as u128 * bps as u128).div_ceil(10_000)) as u64);
+        self.compute_unit_price = bump(self.compute_unit_price);
+        if let Some(tip) = self.tip.as_mut() {
+            tip.lamports = bump(tip.lamports);
+        }
+        self
+    }
+
+    // Pays `percentile` of the compute unit price and limit recently set around `program`,
+    // keeping the current values where there's no data
+    pub fn with_estimated_compute_budget(
+        mut self,
+        estimator: &PriorityFeeEstimator,
+        program: &Pubkey,
+        percentile: u8,
+        slots: u64,
+    ) -> Self {
+        if let Some(compute_unit_price) = estimator.compute_unit_price(program, percentile, slots) {
+            self.compute_unit_price = compute_unit_price;
+        }
+        if let Some(compute_unit_limit) = estimator.compute_unit_limit(program, percentile, slots) {
+            self.compute_unit_limit = compute_unit_limit;
+        }
+        self
+    }
+}
+
+/// Builds signed trade transactions without sending them.
+///
+/// Every transaction is laid out as `[compute unit price, compute unit limit, ..trade, tip]`
+/// and signed by the builder's signer, which also pays the fees.
+/// Submitting is left to the caller, e.g. through a `Broadcaster`.
+pub struct TradeBuilder<'a> {
+    signer: &'a dyn TradeSigner,
+    params: TradeParams,
+    address_lookup_tables: Vec<AddressLookupTableAccount>,
+}
+
+impl<'a> TradeBuilder<'a> {
+    pub fn new(signer: &'a dyn TradeSigner, params: TradeParams) -> Self {
+        TradeBuilder {
+            signer,
+            params,
+            address_lookup_tables: Vec::new(),
+        }
+    }
+
+    // Compiles the message against these tables to shrink the transaction
+    pub fn with_address_lookup_tables(mut self, tables: Vec<AddressLookupTableAccount>) -> Self {
+        self.address_lookup_tables = tables;
+        self
+    }
+
+    pub fn params(&self) -> &TradeParams {
+        &self.params
+    }
+
+    pub fn payer(&self) -> Pubkey {
+        self.signer.pubkey()
+    }
+
+    /// Buys `amount` raw tokens of `mint` on the Pump.fun bonding curve, paying at most `max_sol_cost` lamports.
+    pub async fn pumpfun_buy(
+        &self,
+        mint: &Pubkey,
+        amount: u64,
+        max_sol_cost: u64,
+        create_token_account: bool,
+    ) -> Result<VersionedTransaction, TradeBuildError> {
+        let user = self.payer();
+        let mut instructions = Vec::new();
+        if create_token_account {
+            instructions.push(create_associated_token_account(&user, &user, mint, &spl_token_program_id()));
+        }
+        instructions.push(transaction::pumpfun_buy_instruction(mint, &user, amount, max_sol_cost));
+        self.build(instructions).await
+    }
+
+    /// Sells `amount` raw tokens of `mint` on the Pump.fun bonding curve for at least `min_sol_output` lamports.
+    pub async fn pumpfun_sell(
+        &self,
+        mint: &Pubkey,
+        amount: u64,
+        min_sol_output: u64,
+    ) -> Result<VersionedTransaction, TradeBuildError> {
+        let instruction = transaction::pumpfun_sell_instruction(mint, &self.payer(), amount, min_sol_output);
+        self.build(vec![instruction]).await
+    }
+
+    /// Wraps arbitrary trade instructions with the compute budget and tip, then signs them.
+    pub async fn build(&self, trade_instructions: Vec<Instruction>) -> Result<VersionedTransaction, TradeBuildError> {
+        let payer = self.payer();
+        let mut instructions = Vec::with_capacity(trade_instructions.len() + 3);
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(self.params.compute_unit_price));
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(self.params.compute_unit_limit));
+        instructions.extend(trade_instructions);
+        if let Some(tip) = self.params.tip {
+            instructions.push(system_instruction::transfer(&payer, &tip.account, tip.lamports));
+        }
+
+        let message = v0::Message::try_compile(
+            &payer,
+            &instructions,
+            &self.address_lookup_tables,
+            self.params.recent_blockhash,
+        )?;
+        let message = VersionedMessage::V0(message);
+
+        // The payer is the only required signer
+        let signature = self.signer.sign_async(&message.serialize()).await?;
+        Ok(VersionedTransaction {
+            signatures: vec![signature],
+            message,
+        })
+    }
+}
+
+pub(crate) fn spl_token_program_id() -> Pubkey {
+    Pubkey::from_str(transaction::TOKEN_PROGRAM_ID).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::{Keypair, Signer};
+    use crate::solana::blockhash::RecentBlockhash;
+    use crate::solana::fee_estimator::ComputeBudget;
+
+    // Always hands out the same hash, expiring it on demand
+    struct FakeBlockhashProvider {
+        blockhash: RecentBlockhash,
+        expired: bool,
+    }
+
+    impl BlockhashProvider for FakeBlockhashProvider {
+        fn latest(&self) -> Result<RecentBlockhash, BlockhashError> {
+            if self.expired {
+                return Err(BlockhashError::Expired(self.blockhash.hash));
+            }
+            Ok(self.blockhash)
+        }
+
+        fn is_expired(&self, hash: &Hash) -> bool {
+            self.expired || *hash != self.blockhash.hash
+        }
+    }
+
+    fn params(tip: Option<JitoTip>) -> TradeParams {
+        TradeParams {
+            compute_unit_price: 250_000,
+            compute_unit_limit: 120_000,
+            tip,
+            recent_blockhash: Hash::new_unique(),
+        }
+    }
+
+    // Expands the compiled instructions back into (program id, account keys, data)
+    fn instructions(transaction: &VersionedTransaction) -> Vec<(Pubkey, Vec<Pubkey>, Vec<u8>)> {
+        let keys = transaction.message.static_account_keys();
+        transaction.message.instructions().iter()
+            .map(|instruction| (
+                keys[instruction.program_id_index as usize],
+                instruction.accounts.iter().map(|&index| keys[index as usize]).collect(),
+                instruction.data.clone(),
+            ))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_pumpfun_buy_layout() {
+        let signer = Keypair::new();
+        let tip = JitoTip { account: Pubkey::new_unique(), lamports: 10_000 };
+        let params = params(Some(tip));
+        let mint = Pubkey::new_unique();
+
+        let transaction = TradeBuilder::new(&signer, params)
+            .pumpfun_buy(&mint, 35_000_000_000, 1_100_000_000, false)
+            .await
+            .unwrap();
+
+        assert_eq!(*transaction.message.recent_blockhash(), params.recent_blockhash);
+        assert_eq!(transaction.message.static_account_keys()[0], signer.pubkey());
+        assert!(transaction.verify_with_results().into_iter().all(|valid| valid));
+
+        let instructions = instructions(&transaction);
+        assert_eq!(instructions.len(), 4);
+
+        let mut price = vec![3];
+        price.extend_from_slice(&250_000u64.to_le_bytes());
+        assert_eq!(instructions[0].2, price);
+        let mut limit = vec![2];
+        limit.extend_from_slice(&120_000u32.to_le_bytes());
+        assert_eq!(instructions[1].2, limit);
+
+        let expected = transaction::pumpfun_buy_instruction(&mint, &signer.pubkey(), 35_000_000_000, 1_100_000_000);
+        let (program_id, accounts, data) = &instructions[2];
+        assert_eq!(*program_id, expected.program_id);
+        assert_eq!(*accounts, expected.accounts.iter().map(|meta| meta.pubkey).collect::<Vec<_>>());
+        assert_eq!(data[..8], crate::solana::idl::discriminator("global", "buy"));
+        assert_eq!(data[8..16], 35_000_000_000u64.to_le_bytes());
+        assert_eq!(data[16..], 1_100_000_000u64.to_le_bytes());
+
+        let (program_id, accounts, data) = &instructions[3];
+        assert_eq!(*program_id, solana_sdk::system_program::id());
+        assert_eq!(*accounts, vec![signer.pubkey(), tip.account]);
+        assert_eq!(data[4..], 10_000u64.to_le_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_sell_without_tip_is_deterministic() {
+        let signer = Keypair::new();
+        let params = params(None);
+        let mint = Pubkey::new_unique();
+        let builder = TradeBuilder::new(&signer, params);
+
+        let first = builder.pumpfun_sell(&mint, 1_000_000, 0).await.unwrap();
+        let second = builder.pumpfun_sell(&mint, 1_000_000, 0).await.unwrap();
+
+        assert_eq!(instructions(&first).len(), 3);
+        assert_eq!(first.signatures, second.signatures);
+        assert_eq!(bincode::serialize(&first).unwrap(), bincode::serialize(&second).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_params_use_provider_blockhash() {
+        let mut provider = FakeBlockhashProvider {
+            blockhash: RecentBlockhash { hash: Hash::new_unique(), last_valid_block_height: 1_150 },
+            expired: false,
+        };
+        let signer = Keypair::new();
+
+        let params = TradeParams::from_provider(&provider, 1, 200_000, None).unwrap();
+        let transaction = TradeBuilder::new(&signer, params).pumpfun_sell(&Pubkey::new_unique(), 1, 0).await.unwrap();
+        assert_eq!(*transaction.message.recent_blockhash(), provider.blockhash.hash);
+
+        provider.expired = true;
+        assert!(matches!(TradeParams::from_provider(&provider, 1, 200_000, None), Err(BlockhashError::Expired(_))));
+    }
+
+    #[test]
+    fn test_estimated_compute_budget() {
+        let program = Pubkey::new_unique();
+        let estimator = PriorityFeeEstimator::new([program], 150);
+        let unchanged = params(None).with_estimated_compute_budget(&estimator, &program, 75, 20);
+        assert_eq!((unchanged.compute_unit_price, unchanged.compute_unit_limit), (params(None).compute_unit_price, params(None).compute_unit_limit));
+
+        estimator.observe(1, [program], ComputeBudget { compute_unit_price: Some(7_500), compute_unit_limit: None });
+        let estimated = params(None).with_estimated_compute_budget(&estimator, &program, 75, 20);
+        assert_eq!(estimated.compute_unit_price, 7_500);
+        // Nothing set a limit yet
+        assert_eq!(estimated.compute_unit_limit, params(None).compute_unit_limit);
+
+        estimator.observe(2, [program], ComputeBudget { compute_unit_price: Some(7_500), compute_unit_limit: Some(80_000) });
+        assert_eq!(params(None).with_estimated_compute_budget(&estimator, &program, 75, 20).compute_unit_limit, 80_000);
+    }
+
+    #[tokio::test]
+    async fn test_create_token_account_precedes_buy() {
+        let signer = Keypair::new();
+        let mint = Pubkey::new_unique();
+
+        let transaction = TradeBuilder::new(&signer, params(None))
+            .pumpfun_buy(&mint, 1, 1, true)
+            .await
+            .unwrap();
+
+        let instructions = instructions(&transaction);
+        assert_eq!(instructions[2].0, spl_associated_token_account::id());
+        assert_eq!(instructions[3].0, Pubkey::from_str(transaction::PUMPFUN_PROGRAM_ID).unwrap());
+    }
+}
@@ -3,7 +3,6 @@ use base58::ToBase58;
 use base64::{Engine as _, engine::{general_purpose}};
 use solana_sdk::pubkey::Pubkey;
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256}; // Import the Sha256 hasher
 use prost::Message as ProstMessage;
 use std::collections::HashMap;
 use std::error::Error;
@@ -13,28 +12,32 @@ use dotenv::dotenv;
 use borsh::{BorshDeserialize};
 use bincode;
 use bs58;
-use rand::Rng;
 use reqwest::Client;
 use solana_program::instruction::{Instruction, AccountMeta};
 use std::convert::TryInto;
-use solana_sdk::transaction::Transaction;
+use std::str::FromStr;
+use solana_sdk::transaction::{Transaction, VersionedTransaction};
 use solana_sdk::instruction::CompiledInstruction;
-use solana_sdk::signature::{Keypair, Signer};
-use solana_client::rpc_client::RpcClient;
+use solana_sdk::signature::{Signature, SignerError};
 use solana_sdk::hash::Hash;
 use solana_sdk::system_instruction;
 use serde_json::json;
 use spl_associated_token_account::get_associated_token_address;
 use spl_associated_token_account::instruction::create_associated_token_account;
 use rusqlite::{Connection, params, Result};
-use log::{error};
-use hex_literal::hex;
-use crate::solana::address_table_cache::{AddressTableCache};
-use std::process;
+use log::{error, warn};
+use crate::financial_services::bonding_curve::SellAmount;
+use crate::solana::address_table_cache::{AddressTableCache, AddressTableError};
+use crate::solana::decoder::{DecodeContext, ProgramDecoderRegistry};
+use crate::solana::blockhash::BlockhashProvider;
+use crate::solana::broadcaster::Broadcaster;
+use crate::solana::executor::{Execution, Executor, LiveExecutor};
+use crate::solana::signer::TradeSigner;
+use crate::solana::idl::{discriminator, AnchorEvent, AnchorInstruction, IdlError, IdlProgram, IdlValue, EVENT_IX_TAG};
 #[derive(Debug, Deserialize, Serialize)]
 pub struct PFInitializeInstruction;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PFSetParamsInstruction {
     pub fee_recipient: Pubkey,
     pub initial_virtual_token_reserves: u64,
@@ -44,7 +47,7 @@ pub struct PFSetParamsInstruction {
     pub fee_basis_points: u64,
 }
 
-#[derive(Debug, BorshDeserialize, Serialize)]
+#[derive(Debug, Clone, BorshDeserialize, Serialize)]
 pub struct PFCreateInstruction{
     pub name: String,
     pub symbol: String,
@@ -52,13 +55,39 @@ pub struct PFCreateInstruction{
     pub mint: Pubkey,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// `TradeEvent` emitted by Pump.fun on every buy and sell.
+#[derive(Debug, Clone, BorshDeserialize, Serialize)]
+pub struct PFTradeEvent {
+    pub mint: Pubkey,
+    pub sol_amount: u64,
+    pub token_amount: u64,
+    pub is_buy: bool,
+    pub user: Pubkey,
+    pub timestamp: i64,
+    pub virtual_sol_reserves: u64,
+    pub virtual_token_reserves: u64,
+}
+
+/// `CreateEvent` emitted by Pump.fun when a token is launched.
+#[derive(Debug, Clone, BorshDeserialize, Serialize)]
+pub struct PFCreateEvent {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub mint: Pubkey,
+    pub bonding_curve: Pubkey,
+    pub user: Pubkey,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PFBuyInstruction {
     pub amount: f64,
     pub max_sol_cost: f64,
     pub mint: Pubkey,
     pub used_sol_amount:f64,
-    pub user:Pubkey
+    pub user:Pubkey,
+    pub token_amount: u64, // Raw token amount bought
+    pub sol_amount: u64, // Lamports paid
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -67,13 +96,15 @@ pub struct PFBuyInstructionData {
     pub max_sol_cost: u64,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PFSellInstruction {
     pub amount: f64,
     pub min_sol_output: f64,
     pub mint: Pubkey,
     pub received_sol_amount:f64,
-    pub user:Pubkey
+    pub user:Pubkey,
+    pub token_amount: u64, // Raw token amount sold
+    pub sol_amount: u64, // Lamports received
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -88,14 +119,14 @@ pub struct PFWithdrawInstruction;
 // use anchor_lang::idl;
 // use std::fs;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SystemTransferInstruction {
     pub from_pubkey: Pubkey,
     pub to_pubkey: Pubkey,
     pub lamports: u64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TokenTransferInstruction {
     pub source_pubkey: Pubkey,
     pub destination_pubkey: Pubkey,
@@ -104,14 +135,62 @@ pub struct TokenTransferInstruction {
     pub authority_pubkey: Pubkey,
 }
 
-#[derive(Debug)]
+/// Raydium AMM v4 pool accounts named by an instruction.
+/// Coin is the pool's base token and pc its quote token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RaydiumPool {
+    pub amm: Pubkey,
+    pub coin_mint: Pubkey,
+    pub pc_mint: Pubkey,
+    pub coin_vault: Pubkey,
+    pub pc_vault: Pubkey,
+}
+
+#[derive(Debug, Clone)]
+pub struct RaydiumInitialize2Instruction {
+    pub pool: RaydiumPool,
+    pub lp_mint: Pubkey,
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub nonce: u8,
+    pub open_time: u64,
+    pub init_coin_amount: u64,
+    pub init_pc_amount: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct RaydiumDepositInstruction {
+    pub pool: RaydiumPool,
+    pub lp_mint: Pubkey,
+    pub user: Pubkey,
+    pub max_coin_amount: u64,
+    pub max_pc_amount: u64,
+    pub base_side: u64,
+    pub coin_amount: u64,
+    pub pc_amount: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct RaydiumWithdrawInstruction {
+    pub pool: RaydiumPool,
+    pub lp_mint: Pubkey,
+    pub user: Pubkey,
+    pub lp_amount: u64,
+    pub coin_amount: u64,
+    pub pc_amount: u64,
+}
+
+#[derive(Debug, Clone)]
 pub struct RaydiumSwapBaseInInstruction {
     pub from_mint: Pubkey,
     pub to_mint: Pubkey,
     pub amount_in: f64,
     pub min_amount_out: f64,
     pub amount_out: f64,
-    pub user:Pubkey
+    pub user:Pubkey,
+    pub pool: RaydiumPool,
+    pub raw_amount_in: u64,
+    pub raw_amount_out: u64,
 }
 
 #[derive(Debug, Serialize)]
@@ -121,14 +200,17 @@ pub struct RaydiumSwapBaseInInstructionData {
     pub min_amount_out: u64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct RaydiumSwapBaseOutInstruction {
     pub from_mint: Pubkey,
     pub to_mint: Pubkey,
     pub max_amount_in: f64,
     pub amount_out: f64,
     pub amount_in: f64,
-    pub user:Pubkey
+    pub user:Pubkey,
+    pub pool: RaydiumPool,
+    pub raw_amount_in: u64,
+    pub raw_amount_out: u64,
 }
 
 #[derive(Debug, Serialize)]
@@ -138,6 +220,16 @@ pub struct RaydiumSwapBaseOutInstructionData {
     pub amount_out: u64,
 }
 
+#[derive(Debug, Clone)]
+pub struct ComputeUnitLimitInstruction {
+    pub units: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct ComputeUnitPriceInstruction {
+    pub micro_lamports: u64,
+}
+
 #[derive(Debug, Serialize)]
 pub struct SetComputeUnitPriceInstructionData {
     pub opcode: u8,
@@ -150,36 +242,33 @@ pub struct SetComputeUnitLimitInstructionData {
     pub units: u32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum DecodedInstruction {
     PFBuy(PFBuyInstruction),
     PFSell(PFSellInstruction),
     PFCreate(PFCreateInstruction),
+    PFTradeEvent(PFTradeEvent),
+    PFCreateEvent(PFCreateEvent),
     PFSetParams(PFSetParamsInstruction),
     RaydiumSwapBaseIn(RaydiumSwapBaseInInstruction),
     RaydiumSwapBaseOut(RaydiumSwapBaseOutInstruction),
+    RaydiumInitialize2(RaydiumInitialize2Instruction),
+    RaydiumDeposit(RaydiumDepositInstruction),
+    RaydiumWithdraw(RaydiumWithdrawInstruction),
     PFInitialize,
     PFWithdraw,
     Unknown,
     SystemTransfer(SystemTransferInstruction),
     TokenTransfer(TokenTransferInstruction),
+    ComputeUnitLimit(ComputeUnitLimitInstruction),
+    ComputeUnitPrice(ComputeUnitPriceInstruction),
+    Anchor(AnchorInstruction),
+    AnchorEvent(AnchorEvent),
 }
 
-#[derive(Deserialize, Debug)]
-struct TipAccountResponse {
-    #[allow(unused)]
-    jsonrpc: String,
-    result: Vec<String>,
-    #[allow(unused)]
-    id: u32,
-}
-
-// JitoRPC Endpoint
-const JITO_RPC_ENDPOINT: &str = "https://frankfurt.mainnet.block-engine.jito.wtf/api/v1/transactions";
-
-const PUMPFUN_PROGRAM_ID: &str = "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P";
-const RAYDIUM_PROGRAM_ID: &str = "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8";
-const COMPUTE_BUDGET_PROGRAM_ID: &str = "ComputeBudget111111111111111111111111111111";
+pub(crate) const PUMPFUN_PROGRAM_ID: &str = "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P";
+pub(crate) const RAYDIUM_PROGRAM_ID: &str = "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8";
+const PUMPFUN_TOKEN_DECIMALS: u32 = 6;
 const GLOBAL: &str = "4wTV1YmiEkRvAtNtsSGPtUrqRYQMe5SKy2uB4Jjaxnjf";
 const FEE_RECIPIENT: &str = "CebN5WGQ4jvEPvsVU4EoHEpgzq1VV7AbicfhtW4xC9iM";
 const SYSTEM_PROGRAM: &str = "11111111111111111111111111111111";
@@ -212,6 +301,33 @@ pub struct TokenAccount {
     pub close_authority: Option<Pubkey>,
 }
 
+impl TokenAccount {
+    // SPL token account layout: options are a u32 tag followed by the value, 165 bytes in all
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() < 165 {
+            return None;
+        }
+        let pubkey_at = |offset: usize| Pubkey::try_from(&data[offset..offset + 32]).ok();
+        let u64_at = |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+        let option_at = |offset: usize| if data[offset] == 0 { None } else { pubkey_at(offset + 4) };
+        let state = match data[108] {
+            0 => AccountState::Uninitialized,
+            1 => AccountState::Initialized,
+            2 => AccountState::Frozen,
+            _ => return None,
+        };
+        Some(TokenAccount {
+            mint: pubkey_at(0)?,
+            owner: pubkey_at(32)?,
+            amount: u64_at(64),
+            delegate: option_at(72),
+            delegated_amount: u64_at(121),
+            state,
+            close_authority: option_at(129),
+        })
+    }
+}
+
 /// Account states for the SPL Token account.
 #[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
 pub enum AccountState {
@@ -223,7 +339,7 @@ pub enum AccountState {
     Frozen,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DecodedTransaction {
     pub instructions: Vec<DecodedInstruction>,  // A list of instructions
     pub recent_block_hash: Vec<u8>,        // Block hash of the transaction
@@ -231,6 +347,8 @@ pub struct DecodedTransaction {
     pub index: u64,                     // Index within the block
     pub fee: f64,                         // Transaction fee in lamports
     pub signatures: Vec<Vec<u8>>,                // Signature of the transaction in base58
+    pub error: Option<String>,              // Why the transaction failed on chain, None if it succeeded
+    pub instruction_errors: Vec<&'static str>, // Kinds of the errors of instructions decoded as Unknown
 }
 
 impl DecodedTransaction {
@@ -243,6 +361,8 @@ impl DecodedTransaction {
             index : 0,
             fee : 0.0,
             signatures : Vec::new(),
+            error : None,
+            instruction_errors : Vec::new(),
         }
     }
 
@@ -262,6 +382,8 @@ impl DecodedTransaction {
             index,
             fee,
             signatures,
+            error: None,
+            instruction_errors: Vec::new(),
         }
     }
 
@@ -271,711 +393,555 @@ impl DecodedTransaction {
     }
 }
 
-pub mod message {
-    include!("../../bin/proto/blockdaemon.solana.accountsdb_plugin_kafka.types.rs");
+/// Errors returned by [`decode`] when a Kafka transaction event cannot be decoded. Errors of single
+/// instructions only turn them `Unknown` and are kept in `DecodedTransaction::instruction_errors`.
+#[derive(Debug, thiserror::Error)]
+pub enum DecodeError {
+    #[error("ProtobufError {0}")]
+    Protobuf(#[from] prost::DecodeError),
+    #[error("transaction event has no transaction")]
+    MissingTransaction,
+    #[error("transaction event has no status meta")]
+    MissingMeta,
+    #[error("sanitized message has no message")]
+    MissingMessage,
+    #[error("missing inner instructions for instruction {0}")]
+    MissingInnerInstructions(usize),
+    #[error("account index {index} is out of range for {len} accounts")]
+    AccountIndex { index: usize, len: usize },
+    #[error("invalid token balance: {0}")]
+    InvalidBalance(String),
+    #[error("invalid pubkey: {0}")]
+    InvalidPubkey(String),
+    #[error("invalid instruction data: {0}")]
+    InvalidInstructionData(String),
+    #[error("executed Pump.fun trade on {0} has no trade event")]
+    MissingTradeEvent(Pubkey),
+    #[error("AddressLookupError {0}")]
+    AddressLookup(#[from] AddressTableError),
+    #[error("DatabaseError {0}")]
+    Database(#[from] rusqlite::Error),
 }
 
-const SYSTEM_PROGRAM_ID: &str = "11111111111111111111111111111111";
-const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
-
-// fn decode_protobuf(buffer: &[u8]) -> Result<message::TransactionEvent, prost::DecodeError> {
-//     message::TransactionEvent::decode(buffer)
-// }
-
-pub async fn send_transaction_to_helius(base58_str: &str) -> Result<String, Box<dyn std::error::Error>> {
-    // Your Helius API key
-    const HELIUS_API_KEY: &str = "50d2f11a-972d-4918-9b20-3718333d437e";
-    
-    // Helius API URL
-    let helius_url = format!("https://staked.helius-rpc.com?api-key={}", HELIUS_API_KEY);
-
-    // Construct the request body
-    #[derive(Serialize)]
-    struct TransactionRequest {
-        transaction: String,
-        skip_preflight: bool,
-        preflight_commitment: String,
+impl DecodeError {
+    /// Stable name of the variant, used to count decode failures per kind.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DecodeError::Protobuf(_) => "protobuf",
+            DecodeError::MissingTransaction => "missing_transaction",
+            DecodeError::MissingMeta => "missing_meta",
+            DecodeError::MissingMessage => "missing_message",
+            DecodeError::MissingInnerInstructions(_) => "missing_inner_instructions",
+            DecodeError::AccountIndex { .. } => "account_index",
+            DecodeError::InvalidBalance(_) => "invalid_balance",
+            DecodeError::InvalidPubkey(_) => "invalid_pubkey",
+            DecodeError::InvalidInstructionData(_) => "invalid_instruction_data",
+            DecodeError::MissingTradeEvent(_) => "missing_trade_event",
+            DecodeError::AddressLookup(_) => "address_lookup",
+            DecodeError::Database(_) => "database",
+        }
     }
-
-    // Prepare the JSON payload
-    let payload = serde_json::json!({
-        "jsonrpc": "2.0",
-        "id": 1,
-        "method": "sendTransaction",
-        "params": [base58_str]
-    });
-
-    // Send the POST request to JitoRPC
-    let client = reqwest::Client::new();
-    let response = client.post(helius_url)
-        .json(&payload)
-        .send()
-        .await?;
-
-    println!("{:#?}", response);
-    // Parse the response
-    // if response.status().is_success() {
-    //     let json:serde_json::Value = response.json().await?;
-    //     println!("{:#?}", json);
-    //     if let Some(signature) = json.get("signature").and_then(|v| v.as_str()) {
-    //         Ok(signature.to_string())
-    //     } else {
-    //         Err("Unexpected response format: Missing 'signature'".into())
-    //     }
-    // } else {
-    //     let error_text = response.text().await?;
-    //     Err(format!("Failed to send transaction: {}", error_text).into())
-    // }
-
-    Ok("test".to_string())
 }
 
+impl From<bincode::Error> for DecodeError {
+    fn from(e: bincode::Error) -> Self {
+        DecodeError::InvalidInstructionData(e.to_string())
+    }
+}
 
-pub async fn buy_helius(
-    amount_f: f64,
-    max_sol_cost_f: f64,
-    mint: &str,
-    decimal: u32,
-    include_create: bool,
-    recent_blockhash: Option<Hash>,
-) {
-    dotenv().ok();
-
-    let amount = (amount_f * 10_u64.pow(decimal) as f64) as u64;
-    let max_sol_cost = (max_sol_cost_f * 10_u64.pow(9) as f64) as u64;
-    // Create an RPC client to fetch the recent blockhash
-    let client = RpcClient::new("https://api.mainnet-beta.solana.com");
-
-    //let recent_blockhash_value = recent_blockhash.unwrap();
-
-    // Example: Fetch recent blockhash
-    let recent_blockhash2 = client.get_latest_blockhash().unwrap();
-
-    // Base58-encoded private key (replace with actual private key)
-    let private_key_base58 = env::var("PRIVATE_KEY").expect("PRIVATE_KEY not set in environment");
-    let jito_tip_amount_str = env::var("JITO_TIP_AMOUNT").expect("JITO_TIP_AMOUNT not set in environment");
-    println!("WTF41!!!");
-    // Parse the Jito tip amount as a u64 (assuming it's stored in lamports)
-    let jito_tip_amount: u64 = jito_tip_amount_str.parse().expect("Failed to parse JITO_TIP_AMOUNT");
-    println!("WTF42!!!");
-    // Create Keypair for the payer and signers (from private key)
-    let payer = keypair_from_base58(&private_key_base58); // This keypair will pay for the transaction fees
-    let signer = keypair_from_base58(&private_key_base58); // Example signer, replace with actual signer
-    println!("WTF44!!!");
-    //std::thread::sleep(std::time::Duration::new(1, 0));
-
-    // Extract the user's public key from the Keypair
-    let user_pubkey = payer.pubkey();
-    let mut instructions = Vec::new();
-    println!("WTF4!!!");
-    // Fetch the environment variables as strings
-    let compute_price_str = env::var("COMPUTE_PRICE").expect("COMPUTE_PRICE not set in environment");
-    let compute_limit_str = env::var("COMPUTE_LIMIT").expect("COMPUTE_LIMIT not set in environment");
-
-    // Convert to u32 and u64
-    let compute_price: u64 = compute_price_str
-        .parse()
-        .expect("COMPUTE_PRICE must be a valid u32");
-    let compute_limit: u32 = compute_limit_str
-        .parse()
-        .expect("COMPUTE_LIMIT must be a valid u64");
-
-    let compute_price_instruction = make_set_compute_unit_price_instruction(compute_price).await.unwrap();
-    let compute_limit_instruction = make_set_compute_unit_limit_instruction(compute_limit).await.unwrap();
-
-    println!("WTF5!!!");
-    instructions.push(compute_price_instruction);
-    instructions.push(compute_limit_instruction);
-
-    if include_create {
-        let token_program_id = pubkey_from_base58(TOKEN_PROGRAM_ID).unwrap();
-
-        let create_instruction = create_associated_token_account(
-            &user_pubkey,   // The payer for the transaction
-            &user_pubkey,  // The owner of the ATA
-            &pubkey_from_base58(mint).unwrap(),    // The mint address of the token
-            &token_program_id
-        );
+impl From<IdlError> for DecodeError {
+    fn from(e: IdlError) -> Self {
+        DecodeError::InvalidInstructionData(e.to_string())
+    }
+}
 
-        instructions.push(create_instruction);
+impl From<std::io::Error> for DecodeError {
+    fn from(e: std::io::Error) -> Self {
+        DecodeError::InvalidInstructionData(e.to_string())
     }
+}
 
+pub mod message {
+    include!("../../bin/proto/blockdaemon.solana.accountsdb_plugin_kafka.types.rs");
+}
 
-    println!("WTF7!!!");
-    // Create the buy instruction
-    let buy_instruction = make_buy_instruction(
-        amount, 
-        max_sol_cost, 
-        mint, 
-        &user_pubkey.to_string()
-    ).await.unwrap();
-    instructions.push(buy_instruction);
-    println!("WTF!2!!");
-    // Add the Jito tip instruction
-    let tip_instruction = create_jito_tip_instruction(user_pubkey, jito_tip_amount).await.unwrap(); // Tip amount (in lamports)
-    println!("WTF!42!!");
-    instructions.push(tip_instruction);
-
-    println!("!!!!!!!!!! {:?} {}", recent_blockhash2, mint);
-    println!("!!!!!!!!!! {}", chrono::Local::now().format("%Y-%m-%d %H:%M:%S"));
-
-    // Create the transaction with the payer and instructions
-    let transaction = make_transaction_from_instructions(
-        instructions,
-        &payer,                     // The payer of the transaction fees
-        vec![&payer, &signer],      // List of signers
-        recent_blockhash2.clone()            // Recent blockhash
-    ).await.unwrap();
-
-    // Serialize the transaction into raw bytes
-    let serialized_tx = serialize_transaction(&transaction).unwrap();
-
-    // Convert the raw bytes to Base64 (if you want to print or send via API)
-    let base58_tx = bs58::encode(&serialized_tx).into_string();
-
-    let res = send_transaction_to_helius(&base58_tx).await.unwrap();
-    
-    println!("{:?}",res);
-    // Ok(())
+pub(crate) const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+const ADDRESS_LOOKUP_TABLE_PROGRAM_ID: &str = "AddressLookupTab1e1111111111111111111111111";
 
-}
+// fn decode_protobuf(buffer: &[u8]) -> Result<message::TransactionEvent, prost::DecodeError> {
+//     message::TransactionEvent::decode(buffer)
+// }
 
-pub fn decode(payload: &[u8], address_table_cache: &mut AddressTableCache) -> Result<DecodedTransaction, Box<dyn Error>> {
+pub async fn decode(
+    payload: &[u8],
+    address_table_cache: &AddressTableCache,
+    registry: &ProgramDecoderRegistry,
+) -> Result<DecodedTransaction, DecodeError> {
 
     let mut transaction = DecodedTransaction::new_empty();
 
     // Attempt to decode the transaction event from the payload
     let tx_event = message::TransactionEvent::decode(&payload[..])
         .map_err(|e| {
-            let error_message = format!("Failed to decode protobuf message: {}", e);
-            error!("{}", error_message); // Explicitly prints the error
-            error_message // Return the error message for further handling
+            error!("Failed to decode protobuf message: {}", e);
+            DecodeError::Protobuf(e)
         })?;
 
-    // Process each transaction inside the event
-    if let Some(ref tx) = tx_event.transaction {
-        for sanitized_message in tx.message.iter() {
-            // Extract V0 message payload, if it exists
-            if let Some(message::sanitized_message::MessagePayload::V0(v0_loaded_message)) =
-                &sanitized_message.message_payload
-            {
-                transaction.recent_block_hash = v0_loaded_message.message.clone().unwrap().recent_block_hash;
-                //transaction.set_recent_block_hash(v0_loaded_message.recent_block_hash);
-                for v0_message in v0_loaded_message.message.iter() {
-                                     
-                    let mut inner_instruction_map = HashMap::new();
-                    for inner_instruction in tx_event.transaction_status_meta.clone().unwrap().inner_instructions{
-                        inner_instruction_map.insert(inner_instruction.index as usize, inner_instruction);
-                    }
+    let tx = tx_event.transaction.as_ref().ok_or(DecodeError::MissingTransaction)?;
+    let meta = tx_event.transaction_status_meta.as_ref().ok_or(DecodeError::MissingMeta)?;
+    address_table_cache.observe_slot(tx_event.slot);
 
-                    // Loop through each instruction and process the ones for Pumpfun
-                    for (index, instruction) in v0_message.instructions.iter().enumerate() {
-
-                        let mut instructions = vec![instruction.clone()];
-
-                        // Append inner instructions if they exist for the current index
-                        if let Some(inner_instruction_data) = inner_instruction_map.get(&index) {
-                            // Iterate over each inner instruction and add it to the vector
-                            for inner_inst in &inner_instruction_data.instructions {
-                                instructions.push(inner_inst.clone().instruction.expect("There is no instruction in inner instruction!"));
-                            }
-                        }
-                        for instruction in instructions {
-                            let program_id_index = instruction.program_id_index as usize;
-                            if program_id_index >= v0_message.account_keys.len() {
-                                //println!("Skipping::{}", bs58::encode(tx_event.transaction.clone().unwrap().signatures[0].clone()).into_string());
-                                continue;
-                            } 
-                            
-                            let program_id = v0_message.account_keys[program_id_index].to_base58();
-
-                            if program_id == PUMPFUN_PROGRAM_ID {
-                                let mut account_keys = v0_message.account_keys.clone();
-
-                                let inner_instructions = inner_instruction_map[&index].instructions.clone();
-
-                                let post_balances = tx_event.transaction_status_meta.clone().unwrap().post_token_balances;
-                                let pre_balances = tx_event.transaction_status_meta.clone().unwrap().pre_token_balances;
-
-                                let mut amount_changes: HashMap<u64, f64> = HashMap::new();
-                                let mint = pubkey_from_base58(&post_balances[0].mint)?;
-                                for (_index, (post_balance, pre_balance)) in post_balances.iter().zip(pre_balances.iter()).enumerate() {
-                                    let post_amount_str = post_balance.ui_token_account.clone().unwrap().amount;
-                                    let pre_amount_str = pre_balance.ui_token_account.clone().unwrap().amount;
-
-                                    let post_ui_amount = post_balance.ui_token_account.clone().unwrap().ui_amount;
-                                    let pre_ui_amount = pre_balance.ui_token_account.clone().unwrap().ui_amount;
-
-                                    let post_amount_i64: i64 = post_amount_str.parse()
-                                        .map_err(|e| format!("Failed to parse post amount: {}", e))?; // Handle parse error
-                                    let pre_amount_i64: i64 = pre_amount_str.parse()
-                                        .map_err(|e| format!("Failed to parse pre amount: {}", e))?; // Handle parse error
-
-                                    let amount_change: i64 = post_amount_i64 - pre_amount_i64;
-
-                                    let post_ui_amount_f64: f64 = post_ui_amount.expect("expected ui amount, found None");
-                                    let pre_ui_amount_f64: f64 = pre_ui_amount.expect("Expected ui amount, found None");
-
-                                    let ui_amount_change: f64 = post_ui_amount_f64 - pre_ui_amount_f64;
-                                    // Store the amount change and mint address in the HashMap
-                                    // Assuming you want to store the absolute value of the amount change as the key
-                                    if amount_change != 0 {
-                                        amount_changes.insert(amount_change.abs() as u64, ui_amount_change); // Store absolute amount change as u64 key
-                                    }
-                                }
-                                let decoded_instruction = decode_pumpfun_instruction(&instruction, account_keys.clone(), inner_instructions, amount_changes, bs58::encode(tx_event.transaction.clone().unwrap().signatures[0].clone()).into_string().as_str(), mint)?;
-                                //println!("{:#?}", decoded_instruction);
-                                transaction.add_instruction(decoded_instruction);
-                            } else if program_id == RAYDIUM_PROGRAM_ID {
-
-                                let mut account_keys = v0_message.account_keys.clone();
-
-                                /*for lookup in v0_message.address_table_lookup.iter() {
-                                    // Decode the address table's public key
-                                    let account_key_array: [u8; 32] = lookup.account_key
-                                        .as_slice() // Get a slice of the vector
-                                        .try_into() // Try converting it into an array
-                                        .expect("account_key should always be 32 bytes"); // Panic if the length is incorrect
-                                    let account_key = Pubkey::new_from_array(account_key_array);
-                            
-                                    let chunks = address_table_cache.fetch_and_cache(account_key)?;
-                                    // Resolve writable keys
-                                    let writable_keys: Vec<Vec<u8>> = lookup
-                                        .writable_indexes
-                                        .iter()
-                                        .map(|&index| chunks[index as usize].clone())
-                                        .collect();
-                            
-                                    // Resolve readonly keys
-                                    let readonly_keys: Vec<Vec<u8>> = lookup
-                                        .readonly_indexes
-                                        .iter()
-                                        .map(|&index| chunks[index as usize].clone())
-                                        .collect();
-
-                                    account_keys.extend(writable_keys);
-                                    account_keys.extend(readonly_keys);
-                                }*/
-
-                                let inner_instructions = inner_instruction_map[&index].instructions.clone();
-
-                                let post_balances = tx_event.transaction_status_meta.clone().unwrap().post_token_balances;
-                                let pre_balances = tx_event.transaction_status_meta.clone().unwrap().pre_token_balances;
-
-                                let mut amount_changes: HashMap<u64, (Pubkey, f64)> = HashMap::new();
-                                let mut user_index:i32 = -1;
-
-                                for (index, (post_balance, pre_balance)) in post_balances.iter().zip(pre_balances.iter()).enumerate() {
-                                    if instruction.accounts.len() > 17 && (post_balance.account_index == instruction.accounts[15] || post_balance.account_index == instruction.accounts[16]) {
-                                        user_index = index as i32;
-                                    }
-
-                                    let post_amount_str = post_balance.ui_token_account.clone().unwrap().amount;
-                                    let pre_amount_str = pre_balance.ui_token_account.clone().unwrap().amount;
-
-                                    let post_ui_amount = post_balance.ui_token_account.clone().unwrap().ui_amount;
-                                    let pre_ui_amount = pre_balance.ui_token_account.clone().unwrap().ui_amount;
-
-                                    let post_amount_i64: i64 = post_amount_str.parse()
-                                        .map_err(|e| format!("Failed to parse post amount: {}", e))?; // Handle parse error
-                                    let pre_amount_i64: i64 = pre_amount_str.parse()
-                                        .map_err(|e| format!("Failed to parse pre amount: {}", e))?; // Handle parse error
-
-                                    let amount_change: i64 = post_amount_i64 - pre_amount_i64;
-
-                                    let post_ui_amount_f64: f64 = post_ui_amount.expect("Expected ui amount, found None");
-                                    let pre_ui_amount_f64: f64 = pre_ui_amount.expect("Expected ui amount, found None");
-
-                                    let ui_amount_change: f64 = post_ui_amount_f64 - pre_ui_amount_f64;
-                                    // Store the amount change and mint address in the HashMap
-                                    // Assuming you want to store the absolute value of the amount change as the key
-                                    if amount_change != 0 {
-                                        amount_changes.insert(amount_change.abs() as u64, (pubkey_from_base58(&post_balance.mint)?, ui_amount_change)); // Store absolute amount change as u64 key
-                                    }
-                                }
-                                if user_index >= 0 {
-                                    let decoded_instruction = decode_raydium_instruction(&instruction, inner_instructions, amount_changes, account_keys.clone(), pubkey_from_base58(post_balances[user_index as usize].owner.as_str())?)?;
-                                    transaction.add_instruction(decoded_instruction);
-                                }
-                            } else if program_id == SYSTEM_PROGRAM_ID {
-                                
-                            }
-                            else {
-
-                            }
-                        }
-                    }
-                }
-            } else if let Some(message::sanitized_message::MessagePayload::Legacy(legacy_loaded_message)) = 
-                &sanitized_message.message_payload {
-                let message = legacy_loaded_message.message.clone().unwrap();
+    let mut inner_instruction_map = HashMap::new();
+    for inner_instruction in &meta.inner_instructions {
+        inner_instruction_map.insert(inner_instruction.index as usize, inner_instruction);
+    }
 
-                transaction.recent_block_hash = message.recent_block_hash;
+    // Process each transaction inside the event
+    for sanitized_message in tx.message.iter() {
+        // Extract the account keys and instructions from either a V0 or a legacy message
+        let (account_keys, instructions) = match &sanitized_message.message_payload {
+            Some(message::sanitized_message::MessagePayload::V0(v0_loaded_message)) => {
+                let v0_message = v0_loaded_message.message.as_ref().ok_or(DecodeError::MissingMessage)?;
+                transaction.recent_block_hash = v0_message.recent_block_hash.clone();
+                let account_keys = v0_account_keys(v0_loaded_message, v0_message, address_table_cache).await?;
+                (account_keys, &v0_message.instructions)
+            }
+            Some(message::sanitized_message::MessagePayload::Legacy(legacy_loaded_message)) => {
+                let legacy_message = legacy_loaded_message.message.as_ref().ok_or(DecodeError::MissingMessage)?;
+                transaction.recent_block_hash = legacy_message.recent_block_hash.clone();
+                (legacy_message.account_keys.clone(), &legacy_message.instructions)
+            }
+            None => {
+                error!("Message payload is not correct. It is not matching with V0Message or LegacyMessage!");
+                continue;
+            }
+        };
 
-                let mut inner_instruction_map = HashMap::new();
-                for inner_instruction in tx_event.transaction_status_meta.clone().unwrap().inner_instructions{
-                    inner_instruction_map.insert(inner_instruction.index as usize, inner_instruction);
-                }
+        // Loop through each instruction and hand it to the decoder registered for its program
+        for (index, instruction) in instructions.iter().enumerate() {
+            let mut instructions = vec![Some(instruction.clone())];
 
-                // Loop through each instruction and process the ones for Pumpfun
-                for (index, instruction) in message.instructions.iter().enumerate() {
-                    let mut instructions = vec![instruction.clone()];
+            // Append inner instructions if they exist for the current index
+            if let Some(inner_instruction_data) = inner_instruction_map.get(&index) {
+                instructions.extend(inner_instruction_data.instructions.iter().map(|inner| inner.instruction.clone()));
+            }
 
-                    // Append inner instructions if they exist for the current index
-                    if let Some(inner_instruction_data) = inner_instruction_map.get(&index) {
-                        // Iterate over each inner instruction and add it to the vector
-                        for inner_inst in &inner_instruction_data.instructions {
-                            instructions.push(inner_inst.clone().instruction.expect("There is no instruction in inner instruction!"));
-                        }
-                    }
-                    for instruction in instructions {
-                        let program_id_index = instruction.program_id_index as usize;
-                        let program_id = message.account_keys[program_id_index].to_base58();
-                        
-                        if program_id == PUMPFUN_PROGRAM_ID {
-                            let account_index = *instruction.accounts.get(0).ok_or("Missing account at index 0")?;
-                            if account_index < message.account_keys.len() as u32 {
-                                let inner_instructions = inner_instruction_map[&index].instructions.clone();
-
-                                let post_balances = tx_event.transaction_status_meta.clone().unwrap().post_token_balances;
-                                let pre_balances = tx_event.transaction_status_meta.clone().unwrap().pre_token_balances;
-
-                                let mut amount_changes: HashMap<u64, f64> = HashMap::new();
-                                let mint = pubkey_from_base58(&post_balances[0].mint)?;
-
-                                for (_index, (post_balance, pre_balance)) in post_balances.iter().zip(pre_balances.iter()).enumerate() {
-                                    let post_amount_str = post_balance.ui_token_account.clone().unwrap().amount;
-                                    let pre_amount_str = pre_balance.ui_token_account.clone().unwrap().amount;
-
-                                    let post_ui_amount = post_balance.ui_token_account.clone().unwrap().ui_amount;
-                                    let pre_ui_amount = pre_balance.ui_token_account.clone().unwrap().ui_amount;
-
-                                    let post_amount_i64: i64 = post_amount_str.parse()
-                                        .map_err(|e| format!("Failed to parse post amount: {}", e))?; // Handle parse error
-                                    let pre_amount_i64: i64 = pre_amount_str.parse()
-                                        .map_err(|e| format!("Failed to parse pre amount: {}", e))?; // Handle parse error
-
-                                    let amount_change: i64 = post_amount_i64 - pre_amount_i64;
-
-                                    let post_ui_amount_f64: f64 = post_ui_amount.expect("Expected ui amount, found None");
-                                    let pre_ui_amount_f64: f64 = pre_ui_amount.expect("Expected ui amount, found None");
-
-                                    let ui_amount_change: f64 = post_ui_amount_f64 - pre_ui_amount_f64;
-                                    // Store the amount change and mint address in the HashMap
-                                    // Assuming you want to store the absolute value of the amount change as the key
-                                    if amount_change != 0 {
-                                        amount_changes.insert(amount_change.abs() as u64, ui_amount_change); // Store absolute amount change as u64 key
-                                    }
-                                }
-                                let decoded_instruction = decode_pumpfun_instruction(&instruction, message.account_keys.clone(), inner_instructions, amount_changes, bs58::encode(tx_event.transaction.clone().unwrap().signatures[0].clone()).into_string().as_str(), mint)?;
-                                //println!("{:#?}", decoded_instruction);
-                                transaction.add_instruction(decoded_instruction);
-                            }
-                        } else if program_id == RAYDIUM_PROGRAM_ID {
-                            let inner_instructions = inner_instruction_map[&index].instructions.clone();
-
-                            let post_balances = tx_event.transaction_status_meta.clone().unwrap().post_token_balances;
-                            let pre_balances = tx_event.transaction_status_meta.clone().unwrap().pre_token_balances;
-
-                            let mut amount_changes: HashMap<u64, (Pubkey, f64)> = HashMap::new();
-                            let mut user_index:i32 = -1;
-
-                            for (index, (post_balance, pre_balance)) in post_balances.iter().zip(pre_balances.iter()).enumerate() {
-                                if instruction.accounts.len() > 17 && (post_balance.account_index == instruction.accounts[15] || post_balance.account_index == instruction.accounts[16]) {
-                                    user_index = index as i32;
-                                }
-                                let post_amount_str = post_balance.ui_token_account.clone().unwrap().amount;
-                                let pre_amount_str = pre_balance.ui_token_account.clone().unwrap().amount;
-
-                                let post_ui_amount = post_balance.ui_token_account.clone().unwrap().ui_amount;
-                                let pre_ui_amount = pre_balance.ui_token_account.clone().unwrap().ui_amount;
-
-                                let post_amount_i64: i64 = post_amount_str.parse()
-                                    .map_err(|e| format!("Failed to parse post amount: {}", e))?; // Handle parse error
-                                let pre_amount_i64: i64 = pre_amount_str.parse()
-                                    .map_err(|e| format!("Failed to parse pre amount: {}", e))?; // Handle parse error
-
-                                let amount_change: i64 = post_amount_i64 - pre_amount_i64;
-
-                                let post_ui_amount_f64: f64 = post_ui_amount.expect("Expected ui amount, found None");
-                                let pre_ui_amount_f64: f64 = pre_ui_amount.expect("Expected ui amount, found None");
-
-                                let ui_amount_change: f64 = post_ui_amount_f64 - pre_ui_amount_f64;
-                                // Store the amount change and mint address in the HashMap
-                                // Assuming you want to store the absolute value of the amount change as the key
-                                if amount_change != 0 {
-                                    amount_changes.insert(amount_change.abs() as u64, (pubkey_from_base58(&post_balance.mint)?, ui_amount_change)); // Store absolute amount change as u64 key
-                                }
-                            }
-                            if user_index >= 0 {
-                                let decoded_instruction = decode_raydium_instruction(&instruction, inner_instructions, amount_changes, message.account_keys.clone(), pubkey_from_base58(post_balances[user_index as usize].owner.as_str())?)?;
-                                transaction.add_instruction(decoded_instruction);
-                            }
-                        } else if program_id == SYSTEM_PROGRAM_ID {
-                            
-                        }
-                        else {
-
-                        }
+            for (position, instruction) in instructions.into_iter().enumerate() {
+                let ctx = DecodeContext {
+                    account_keys: &account_keys,
+                    meta,
+                    instruction_index: index,
+                    inner_position: position.checked_sub(1),
+                    inner_instruction_map: &inner_instruction_map,
+                    slot: tx_event.slot,
+                };
+                let decoded = instruction
+                    .ok_or(DecodeError::MissingInnerInstructions(index))
+                    .and_then(|instruction| decode_instruction(&instruction, &ctx, address_table_cache, registry));
+                match decoded {
+                    Ok(Some(decoded_instruction)) => transaction.add_instruction(decoded_instruction),
+                    Ok(None) => {}
+                    // One undecodable instruction doesn't hide the rest of the transaction
+                    Err(e) => {
+                        warn!("Failed to decode instruction {} in slot {}: {}", index, tx_event.slot, e);
+                        transaction.instruction_errors.push(e.kind());
+                        transaction.add_instruction(DecodedInstruction::Unknown);
                     }
                 }
             }
-            else {
-                error!("Message payload is not correct. It is not matching with V0Message or LegacyMessage!")
-            }
         }
-    } else {
-        error!("Tx Event have no transaction!");
     }
 
-    transaction.fee = tx_event.transaction_status_meta.unwrap().fee as f64 / 1000000000.0;
+    transaction.fee = meta.fee as f64 / 1000000000.0;
+    transaction.error = meta.is_status_err.then(|| meta.error_info.clone());
     transaction.slot = tx_event.slot;
     transaction.index = tx_event.index;
-    transaction.signatures = tx_event.transaction.unwrap().signatures;
+    transaction.signatures = tx.signatures.clone();
 
     Ok(transaction)
 }
 
-pub fn decode_raydium_instruction(
+// Decodes one instruction with the decoder registered for its program, `None` when there is none
+fn decode_instruction(
     instruction: &message::CompiledInstruction,
-    inner_instructions: Vec<InnerInstruction>,
-    amount_changes: HashMap<u64, (Pubkey, f64)>,
-    accounts: Vec<Vec<u8>>,
-    user: Pubkey,
-) -> Result<DecodedInstruction, Box<dyn Error>> {
-    if instruction.data[0] == 9 {
-        if instruction.data.len() >= 17 {
-            // Parse amount_in from bytes 1..9
-            let amount_in = u64::from_le_bytes(instruction.data[1..9].try_into().unwrap());
-            // Parse min_amount_out from bytes 9..17
-            let min_amount_out = u64::from_le_bytes(instruction.data[9..17].try_into().unwrap());
-            let mut amount_out:u64 = 0;
-            for inner_instruction in inner_instructions {
-                if let Some(instruction) = &inner_instruction.instruction {
-                    // Check if the program ID matches the Token Program ID
-                    let program_id_index = instruction.program_id_index as usize;
-                    if program_id_index >= accounts.len() {
-                        continue;
-                    }
-                    let program_id = accounts[program_id_index].to_base58();
-                    
-                    if program_id == TOKEN_PROGRAM_ID && instruction.data[0] == 3 {
-                        let decoded = u64::from_le_bytes(
-                            instruction.data[1..].try_into().unwrap()
-                        );
-        
-                        // Logic to handle amount_out vs amount_in
-                        // For instance, ensure `amount_out` is not the same as `amount_in`
-                        if decoded != amount_in {
-                            amount_out = decoded;
-                        }
-                    }
-                }
-            }
+    ctx: &DecodeContext,
+    address_table_cache: &AddressTableCache,
+    registry: &ProgramDecoderRegistry,
+) -> Result<Option<DecodedInstruction>, DecodeError> {
+    let program_id_index = instruction.program_id_index as usize;
+    let program_id_key = ctx.account_keys.get(program_id_index)
+        .ok_or(DecodeError::AccountIndex { index: program_id_index, len: ctx.account_keys.len() })?;
+    let program_id = Pubkey::try_from(program_id_key.as_slice())
+        .map_err(|_| DecodeError::InvalidPubkey(format!("account key has {} bytes", program_id_key.len())))?;
+
+    if program_id.to_string() == ADDRESS_LOOKUP_TABLE_PROGRAM_ID {
+        // Extend, deactivate and close change the table, so drop any cached copy
+        if matches!(instruction.data.first(), Some(2..=4)) {
+            let table = instruction_account(instruction, ctx.account_keys, 0)?;
+            address_table_cache.mark_modified(&table, ctx.slot);
+        }
+        return Ok(None);
+    }
 
-            // Initialize mints for the "from" and "to" tokens
-            let (from_mint, to_mint);
-
-            // Lookup from_mint and to_mint using amount_in and amount_out from amount_changes
-            if let Some((from_mint_val, ui_amount_in)) = amount_changes.get(&amount_in) {
-                if let Some((to_mint_val, ui_amount_out)) = amount_changes.get(&amount_out) {
-                    // Assign the values to the mutable variables
-                    from_mint = *from_mint_val;
-                    to_mint = *to_mint_val;
-
-                    let min_ui_amount_out = min_amount_out as f64 / (amount_in as f64 / ui_amount_in.abs());
-
-                    // Create the decoded Raydium swap instruction
-                    let decoded = RaydiumSwapBaseInInstruction {
-                        from_mint,
-                        to_mint,
-                        amount_in:ui_amount_in.abs(),
-                        min_amount_out:min_ui_amount_out,
-                        amount_out:ui_amount_out.abs(),
-                        user,
-                    };
-
-                    let mut valid = true;
-
-                    // Ensure we do not access out of bounds
-                    if instruction.accounts.len() < 15 {
-                        valid = false;
-                    } else {
-                        for i in 0..15 {
-                            if instruction.accounts[i] as usize >= accounts.len() {
-                                valid = false;
-                                break; // Exit early if any index is invalid
-                            }
-                        }
-                    }
+    registry.decode(&program_id, instruction, ctx)
+}
 
-                    if valid {
-                        let program_id = bs58::encode(accounts[instruction.accounts[0] as usize].clone()).into_string();
-                        let amm_address = bs58::encode(accounts[instruction.accounts[1] as usize].clone()).into_string();
-                        let amm_authority = bs58::encode(accounts[instruction.accounts[2] as usize].clone()).into_string();
-                        let amm_open_orders = bs58::encode(accounts[instruction.accounts[3] as usize].clone()).into_string();
-                        let amm_target_orders = bs58::encode(accounts[instruction.accounts[4] as usize].clone()).into_string();
-                        let pool_coin_token_account = bs58::encode(accounts[instruction.accounts[5] as usize].clone()).into_string();
-                        let pool_pc_token_account = bs58::encode(accounts[instruction.accounts[6] as usize].clone()).into_string();
-                        let serum_program = bs58::encode(accounts[instruction.accounts[7] as usize].clone()).into_string();
-                        let serum_market = bs58::encode(accounts[instruction.accounts[8] as usize].clone()).into_string();
-                        let serum_bids = bs58::encode(accounts[instruction.accounts[9] as usize].clone()).into_string();
-                        let serum_asks = bs58::encode(accounts[instruction.accounts[10] as usize].clone()).into_string();
-                        let serum_event_queue = bs58::encode(accounts[instruction.accounts[11] as usize].clone()).into_string();
-                        let serum_coin_vault_account = bs58::encode(accounts[instruction.accounts[12] as usize].clone()).into_string();
-                        let serum_pc_vault_account = bs58::encode(accounts[instruction.accounts[13] as usize].clone()).into_string();
-                        let serum_vault_signer = bs58::encode(accounts[instruction.accounts[14] as usize].clone()).into_string();
-                        let mint_address = if from_mint.to_string() == "So11111111111111111111111111111111111111112" {
-                            to_mint.to_string()
-                        } else {
-                            from_mint.to_string()
-                        };                        
-
-                        let conn = Connection::open("raydium.db")?;
-                        let _ = save_to_db(
-                            &conn,
-                            &program_id,
-                            &amm_address,
-                            &amm_authority,
-                            &amm_open_orders,
-                            &amm_target_orders,
-                            &pool_coin_token_account,
-                            &pool_pc_token_account,
-                            &serum_program,
-                            &serum_market,
-                            &serum_bids,
-                            &serum_asks,
-                            &serum_event_queue,
-                            &serum_coin_vault_account,
-                            &serum_pc_vault_account,
-                            &serum_vault_signer,
-                            &mint_address,
-                        );
-                    }
-                    return Ok(DecodedInstruction::RaydiumSwapBaseIn(decoded));
-                }
-            }
-        } else {
-            println!("Instruction data is too short to decode.");
+// Returns the full account key list of a V0 message: the static keys followed by the
+// writable and then the readonly addresses loaded from address lookup tables.
+// The addresses loaded by the validator are used when present, otherwise the lookups are
+// resolved through the address table cache.
+async fn v0_account_keys(
+    v0_loaded_message: &message::V0LoadedMessage,
+    v0_message: &message::V0Message,
+    address_table_cache: &AddressTableCache,
+) -> Result<Vec<Vec<u8>>, DecodeError> {
+    let mut account_keys = v0_message.account_keys.clone();
+    if v0_message.address_table_lookup.is_empty() {
+        return Ok(account_keys);
+    }
+
+    let lookup_count: usize = v0_message.address_table_lookup
+        .iter()
+        .map(|lookup| lookup.writable_indexes.len() + lookup.readonly_indexes.len())
+        .sum();
+
+    if let Some(loaded_addresses) = v0_loaded_message.loaded_adresses.as_ref() {
+        if loaded_addresses.writable.len() + loaded_addresses.readonly.len() == lookup_count {
+            account_keys.extend(loaded_addresses.writable.iter().cloned());
+            account_keys.extend(loaded_addresses.readonly.iter().cloned());
+            return Ok(account_keys);
         }
-    } else if instruction.data[0] == 11 {
-        if instruction.data.len() >= 17 {
-            // Parse amount_in from bytes 1..9
-            let max_amount_in = u64::from_le_bytes(instruction.data[1..9].try_into().unwrap());
-            // Parse min_amount_out from bytes 9..17
-            let amount_out = u64::from_le_bytes(instruction.data[9..17].try_into().unwrap());
-            // Parse amount_out from inner instruction
-            let mut amount_in:u64 = 0;
-            for inner_instruction in inner_instructions {
-                if let Some(instruction) = &inner_instruction.instruction {
-                    // Check if the program ID matches the Token Program ID
-                    let program_id_index = instruction.program_id_index as usize;
-                    if program_id_index >= accounts.len() {
-                        continue;
-                    }
-                    let program_id = accounts[program_id_index].to_base58();
-                    
-                    if program_id == TOKEN_PROGRAM_ID && instruction.data[0] == 3 {
-                        let decoded = u64::from_le_bytes(
-                            instruction.data[1..].try_into().unwrap()
-                        );
-        
-                        // Logic to handle amount_out vs amount_in
-                        // For instance, ensure `amount_out` is not the same as `amount_in`
-                        if decoded != amount_out {
-                            amount_in = decoded;
-                        }
-                    }
-                }
-            }
+    }
 
-            // Initialize mints for the "from" and "to" tokens
-            let (from_mint, to_mint);
-
-            // Lookup from_mint and to_mint using amount_in and amount_out from amount_changes
-            if let Some((from_mint_val, ui_amount_in)) = amount_changes.get(&amount_in) {
-                if let Some((to_mint_val, ui_amount_out)) = amount_changes.get(&amount_out) {
-                    // Assign the values to the mutable variables
-                    from_mint = *from_mint_val;
-                    to_mint = *to_mint_val;
-
-                    let max_ui_amount_in = max_amount_in as f64 / (amount_in as f64 / ui_amount_in.abs());
-
-                    // Create the decoded Raydium swap instruction
-                    let decoded = RaydiumSwapBaseOutInstruction {
-                        from_mint,
-                        to_mint,
-                        max_amount_in:max_ui_amount_in,
-                        amount_out:ui_amount_out.abs(),
-                        amount_in:ui_amount_in.abs(),
-                        user,
-                    };
-
-                    let mut valid = true;
-
-                    // Ensure we do not access out of bounds
-                    if instruction.accounts.len() < 15 {
-                        valid = false;
-                    } else {
-                        for i in 0..15 {
-                            if instruction.accounts[i] as usize >= accounts.len() {
-                                valid = false;
-                                break; // Exit early if any index is invalid
-                            }
-                        }
-                    }
+    let (writable, readonly) = address_table_cache
+        .resolve_lookups(&v0_message.address_table_lookup)
+        .await?;
+    account_keys.extend(writable.iter().map(|address| address.to_bytes().to_vec()));
+    account_keys.extend(readonly.iter().map(|address| address.to_bytes().to_vec()));
 
-                    if valid {
-                        let program_id = bs58::encode(accounts[instruction.accounts[0] as usize].clone()).into_string();
-                        let amm_address = bs58::encode(accounts[instruction.accounts[1] as usize].clone()).into_string();
-                        let amm_authority = bs58::encode(accounts[instruction.accounts[2] as usize].clone()).into_string();
-                        let amm_open_orders = bs58::encode(accounts[instruction.accounts[3] as usize].clone()).into_string();
-                        let amm_target_orders = bs58::encode(accounts[instruction.accounts[4] as usize].clone()).into_string();
-                        let pool_coin_token_account = bs58::encode(accounts[instruction.accounts[5] as usize].clone()).into_string();
-                        let pool_pc_token_account = bs58::encode(accounts[instruction.accounts[6] as usize].clone()).into_string();
-                        let serum_program = bs58::encode(accounts[instruction.accounts[7] as usize].clone()).into_string();
-                        let serum_market = bs58::encode(accounts[instruction.accounts[8] as usize].clone()).into_string();
-                        let serum_bids = bs58::encode(accounts[instruction.accounts[9] as usize].clone()).into_string();
-                        let serum_asks = bs58::encode(accounts[instruction.accounts[10] as usize].clone()).into_string();
-                        let serum_event_queue = bs58::encode(accounts[instruction.accounts[11] as usize].clone()).into_string();
-                        let serum_coin_vault_account = bs58::encode(accounts[instruction.accounts[12] as usize].clone()).into_string();
-                        let serum_pc_vault_account = bs58::encode(accounts[instruction.accounts[13] as usize].clone()).into_string();
-                        let serum_vault_signer = bs58::encode(accounts[instruction.accounts[14] as usize].clone()).into_string();
-                        let mint_address = if from_mint.to_string() == "So11111111111111111111111111111111111111112" {
-                            to_mint.to_string()
-                        } else {
-                            from_mint.to_string()
-                        };                        
-
-                        let conn = Connection::open("raydium.db")?;
-                        let _ = save_to_db(
-                            &conn,
-                            &program_id,
-                            &amm_address,
-                            &amm_authority,
-                            &amm_open_orders,
-                            &amm_target_orders,
-                            &pool_coin_token_account,
-                            &pool_pc_token_account,
-                            &serum_program,
-                            &serum_market,
-                            &serum_bids,
-                            &serum_asks,
-                            &serum_event_queue,
-                            &serum_coin_vault_account,
-                            &serum_pc_vault_account,
-                            &serum_vault_signer,
-                            &mint_address,
-                        );
-                    }
-                    return Ok(DecodedInstruction::RaydiumSwapBaseOut(decoded));
-                }
+    Ok(account_keys)
+}
+
+// Returns the inner instructions recorded for the top-level instruction at `index`
+pub(crate) fn inner_instructions_at(
+    inner_instruction_map: &HashMap<usize, &message::InnerInstructions>,
+    index: usize,
+) -> Result<Vec<InnerInstruction>, DecodeError> {
+    inner_instruction_map
+        .get(&index)
+        .map(|inner| inner.instructions.clone())
+        .ok_or(DecodeError::MissingInnerInstructions(index))
+}
+
+// Resolves the pubkey referenced by the instruction's account at `position`
+pub(crate) fn instruction_account(
+    instruction: &message::CompiledInstruction,
+    accounts: &[Vec<u8>],
+    position: usize,
+) -> Result<Pubkey, DecodeError> {
+    let index = *instruction.accounts.get(position)
+        .ok_or(DecodeError::AccountIndex { index: position, len: instruction.accounts.len() })? as usize;
+    let key = accounts.get(index)
+        .ok_or(DecodeError::AccountIndex { index, len: accounts.len() })?;
+    let key_array: [u8; 32] = key.as_slice()
+        .try_into()
+        .map_err(|_| DecodeError::InvalidPubkey(format!("account key has {} bytes", key.len())))?;
+    Ok(Pubkey::new_from_array(key_array))
+}
+
+pub(crate) fn parse_pubkey(base58_str: &str) -> Result<Pubkey, DecodeError> {
+    Pubkey::from_str(base58_str).map_err(|e| DecodeError::InvalidPubkey(format!("{}: {}", base58_str, e)))
+}
+const RAYDIUM_INITIALIZE2: u8 = 1;
+const RAYDIUM_DEPOSIT: u8 = 3;
+const RAYDIUM_WITHDRAW: u8 = 4;
+const RAYDIUM_SWAP_BASE_IN: u8 = 9;
+const RAYDIUM_SWAP_BASE_OUT: u8 = 11;
+
+// A token transfer made by an inner instruction
+struct TokenMovement {
+    source: u32,
+    destination: u32,
+    amount: u64,
+}
+
+// Collects the SPL token transfers (Transfer and TransferChecked) among the inner instructions
+fn inner_token_transfers(inner_instructions: &[InnerInstruction], accounts: &[Vec<u8>]) -> Vec<TokenMovement> {
+    inner_instructions.iter()
+        .filter_map(|inner| inner.instruction.as_ref())
+        .filter(|instruction| accounts.get(instruction.program_id_index as usize)
+            .is_some_and(|program_id| program_id.to_base58() == TOKEN_PROGRAM_ID))
+        .filter_map(|instruction| {
+            let amount = u64::from_le_bytes(instruction.data.get(1..9)?.try_into().ok()?);
+            match instruction.data.first() {
+                Some(&3) => Some(TokenMovement { source: *instruction.accounts.first()?, destination: *instruction.accounts.get(1)?, amount }),
+                Some(&12) => Some(TokenMovement { source: *instruction.accounts.first()?, destination: *instruction.accounts.get(2)?, amount }),
+                _ => None,
             }
-        } else {
-            println!("Instruction data is too short to decode.");
+        })
+        .collect()
+}
+
+// The inner instructions a Raydium instruction made itself: all of them when it's top-level, otherwise
+// the ones right after `inner_position` running deeper in the call stack. Without stack heights, the
+// token instructions directly following it.
+fn own_inner_instructions<'a>(
+    inner_instructions: &'a [InnerInstruction],
+    inner_position: Option<usize>,
+    accounts: &[Vec<u8>],
+) -> &'a [InnerInstruction] {
+    let Some(position) = inner_position else {
+        return inner_instructions;
+    };
+    let height = inner_instructions.get(position).and_then(|inner| inner.stack_height);
+    let following = inner_instructions.get(position + 1..).unwrap_or_default();
+    let end = following.iter()
+        .position(|inner| match (height, inner.stack_height) {
+            (Some(height), Some(inner_height)) => inner_height <= height,
+            _ => !inner.instruction.as_ref()
+                .and_then(|instruction| accounts.get(instruction.program_id_index as usize))
+                .is_some_and(|program_id| program_id.to_base58() == TOKEN_PROGRAM_ID),
+        })
+        .unwrap_or(following.len());
+    &following[..end]
+}
+
+// Looks up the mint and decimals of a token account from the transaction's token balances
+fn token_account_mint(meta: &message::TransactionStatusMeta, account_index: u32) -> Result<(Pubkey, u32), DecodeError> {
+    let balance = meta.post_token_balances.iter()
+        .chain(meta.pre_token_balances.iter())
+        .find(|balance| balance.account_index == account_index)
+        .ok_or_else(|| DecodeError::InvalidBalance(format!("no token balance for account {}", account_index)))?;
+    let decimals = balance.ui_token_account.as_ref()
+        .ok_or_else(|| DecodeError::InvalidBalance("missing token amount".to_string()))?
+        .decimals;
+    Ok((parse_pubkey(&balance.mint)?, decimals))
+}
+
+fn ui_amount(amount: u64, decimals: u32) -> f64 {
+    amount as f64 / 10f64.powi(decimals as i32)
+}
+
+fn raydium_u64(data: &[u8], offset: usize) -> Result<u64, DecodeError> {
+    data.get(offset..offset + 8)
+        .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or_else(|| DecodeError::InvalidInstructionData(format!("raydium instruction data has {} bytes", data.len())))
+}
+
+// Resolves the pool for instructions that only name its vaults; the mints come from the vault balances
+fn raydium_pool(
+    instruction: &message::CompiledInstruction,
+    accounts: &[Vec<u8>],
+    meta: &message::TransactionStatusMeta,
+    amm: usize,
+    coin_vault: usize,
+    pc_vault: usize,
+) -> Result<(RaydiumPool, u32, u32), DecodeError> {
+    let account_index = |position: usize| instruction.accounts.get(position).copied()
+        .ok_or(DecodeError::AccountIndex { index: position, len: instruction.accounts.len() });
+    let (coin_mint, coin_decimals) = token_account_mint(meta, account_index(coin_vault)?)?;
+    let (pc_mint, pc_decimals) = token_account_mint(meta, account_index(pc_vault)?)?;
+
+    let pool = RaydiumPool {
+        amm: instruction_account(instruction, accounts, amm)?,
+        coin_mint,
+        pc_mint,
+        coin_vault: instruction_account(instruction, accounts, coin_vault)?,
+        pc_vault: instruction_account(instruction, accounts, pc_vault)?,
+    };
+    Ok((pool, coin_decimals, pc_decimals))
+}
+
+// Decode a Raydium AMM v4 instruction.
+// Pool, vaults and user are taken from the instruction's account layout, and the filled
+// amounts from the token transfers the program made in and out of the pool vaults.
+// `inner_position` locates an instruction invoked through CPI among `inner_instructions`.
+pub fn decode_raydium_instruction(
+    instruction: &message::CompiledInstruction,
+    accounts: &[Vec<u8>],
+    inner_instructions: &[InnerInstruction],
+    inner_position: Option<usize>,
+    meta: &message::TransactionStatusMeta,
+) -> Result<DecodedInstruction, DecodeError> {
+    let inner_instructions = own_inner_instructions(inner_instructions, inner_position, accounts);
+    let data = &instruction.data;
+    let account = |position| instruction_account(instruction, accounts, position);
+
+    match data.first() {
+        Some(&RAYDIUM_INITIALIZE2) => {
+            // nonce: u8, open_time: u64, init_pc_amount: u64, init_coin_amount: u64
+            let nonce = *data.get(1)
+                .ok_or_else(|| DecodeError::InvalidInstructionData("initialize2 is missing its nonce".to_string()))?;
+            Ok(DecodedInstruction::RaydiumInitialize2(RaydiumInitialize2Instruction {
+                pool: RaydiumPool {
+                    amm: account(4)?,
+                    coin_mint: account(8)?,
+                    pc_mint: account(9)?,
+                    coin_vault: account(10)?,
+                    pc_vault: account(11)?,
+                },
+                lp_mint: account(7)?,
+                market: account(16)?,
+                user: account(17)?,
+                nonce,
+                open_time: raydium_u64(data, 2)?,
+                init_pc_amount: raydium_u64(data, 10)?,
+                init_coin_amount: raydium_u64(data, 18)?,
+            }))
+        }
+        Some(&RAYDIUM_DEPOSIT) => {
+            let (pool, _, _) = raydium_pool(instruction, accounts, meta, 1, 6, 7)?;
+            let transfers = inner_token_transfers(inner_instructions, accounts);
+            let deposited = |vault: usize| transfers.iter()
+                .filter(|transfer| Some(&transfer.destination) == instruction.accounts.get(vault))
+                .map(|transfer| transfer.amount)
+                .sum();
+
+            Ok(DecodedInstruction::RaydiumDeposit(RaydiumDepositInstruction {
+                pool,
+                lp_mint: account(5)?,
+                user: account(12)?,
+                max_coin_amount: raydium_u64(data, 1)?,
+                max_pc_amount: raydium_u64(data, 9)?,
+                base_side: raydium_u64(data, 17)?,
+                coin_amount: deposited(6),
+                pc_amount: deposited(7),
+            }))
+        }
+        Some(&RAYDIUM_WITHDRAW) => {
+            // Older layouts pass amm_target_orders at position 4, shifting every later account
+            let offset = usize::from(instruction.accounts.len() >= 22);
+            let (pool, _, _) = raydium_pool(instruction, accounts, meta, 1, 5 + offset, 6 + offset)?;
+            let transfers = inner_token_transfers(inner_instructions, accounts);
+            let withdrawn = |vault: usize| transfers.iter()
+                .filter(|transfer| Some(&transfer.source) == instruction.accounts.get(vault))
+                .map(|transfer| transfer.amount)
+                .sum();
+
+            Ok(DecodedInstruction::RaydiumWithdraw(RaydiumWithdrawInstruction {
+                pool,
+                lp_mint: account(4 + offset)?,
+                user: account(17 + offset)?,
+                lp_amount: raydium_u64(data, 1)?,
+                coin_amount: withdrawn(5 + offset),
+                pc_amount: withdrawn(6 + offset),
+            }))
         }
+        Some(&opcode @ (RAYDIUM_SWAP_BASE_IN | RAYDIUM_SWAP_BASE_OUT)) => {
+            decode_raydium_swap(opcode, instruction, accounts, inner_instructions, meta)
+        }
+        _ => Ok(DecodedInstruction::Unknown),
+    }
+}
+
+// Both swap variants share one layout:
+// [token_program, amm, amm_authority, amm_open_orders, (amm_target_orders), pool_coin_vault, pool_pc_vault,
+//  serum_program, serum_market, serum_bids, serum_asks, serum_event_queue, serum_coin_vault,
+//  serum_pc_vault, serum_vault_signer, user_source, user_destination, user_owner]
+fn decode_raydium_swap(
+    opcode: u8,
+    instruction: &message::CompiledInstruction,
+    accounts: &[Vec<u8>],
+    inner_instructions: &[InnerInstruction],
+    meta: &message::TransactionStatusMeta,
+) -> Result<DecodedInstruction, DecodeError> {
+    let offset = usize::from(instruction.accounts.len() >= 18);
+    let (coin_vault, pc_vault) = (4 + offset, 5 + offset);
+    let (pool, coin_decimals, pc_decimals) = raydium_pool(instruction, accounts, meta, 1, coin_vault, pc_vault)?;
+    let user = instruction_account(instruction, accounts, 16 + offset)?;
+    let first_arg = raydium_u64(&instruction.data, 1)?;
+    let second_arg = raydium_u64(&instruction.data, 9)?;
+
+    // The user pays into one vault and is paid out of the other
+    let vault_indexes = [instruction.accounts[coin_vault], instruction.accounts[pc_vault]];
+    let transfers = inner_token_transfers(inner_instructions, accounts);
+    let paid_in = transfers.iter().find(|transfer| vault_indexes.contains(&transfer.destination));
+    let paid_out = transfers.iter().find(|transfer| vault_indexes.contains(&transfer.source));
+    let (Some(paid_in), Some(paid_out)) = (paid_in, paid_out) else {
+        // A failed swap moves no tokens
+        return Ok(DecodedInstruction::Unknown);
+    };
+
+    let ((from_mint, from_decimals), (to_mint, to_decimals)) = if paid_in.destination == vault_indexes[0] {
+        ((pool.coin_mint, coin_decimals), (pool.pc_mint, pc_decimals))
+    } else {
+        ((pool.pc_mint, pc_decimals), (pool.coin_mint, coin_decimals))
+    };
+    let (raw_amount_in, raw_amount_out) = (paid_in.amount, paid_out.amount);
+
+    save_raydium_pool(instruction, accounts, &pool, offset)?;
+
+    if opcode == RAYDIUM_SWAP_BASE_IN {
+        Ok(DecodedInstruction::RaydiumSwapBaseIn(RaydiumSwapBaseInInstruction {
+            from_mint,
+            to_mint,
+            amount_in: ui_amount(raw_amount_in, from_decimals),
+            min_amount_out: ui_amount(second_arg, to_decimals),
+            amount_out: ui_amount(raw_amount_out, to_decimals),
+            user,
+            pool,
+            raw_amount_in,
+            raw_amount_out,
+        }))
+    } else {
+        Ok(DecodedInstruction::RaydiumSwapBaseOut(RaydiumSwapBaseOutInstruction {
+            from_mint,
+            to_mint,
+            max_amount_in: ui_amount(first_arg, from_decimals),
+            amount_out: ui_amount(raw_amount_out, to_decimals),
+            amount_in: ui_amount(raw_amount_in, from_decimals),
+            user,
+            pool,
+            raw_amount_in,
+            raw_amount_out,
+        }))
+    }
+}
+
+// Records the pool's accounts so later swaps can be built without fetching the AMM state.
+// Only the layout carrying amm_target_orders fills every column.
+fn save_raydium_pool(
+    instruction: &message::CompiledInstruction,
+    accounts: &[Vec<u8>],
+    pool: &RaydiumPool,
+    offset: usize,
+) -> Result<(), DecodeError> {
+    if offset == 0 {
+        return Ok(());
     }
-    Ok(DecodedInstruction::Unknown)
+    let Ok(keys) = (0..15)
+        .map(|position| instruction_account(instruction, accounts, position).map(|key| key.to_string()))
+        .collect::<Result<Vec<_>, _>>()
+    else {
+        return Ok(());
+    };
+    let mint_address = if pool.pc_mint.to_string() == "So11111111111111111111111111111111111111112" {
+        pool.coin_mint.to_string()
+    } else {
+        pool.pc_mint.to_string()
+    };
+
+    let conn = Connection::open("raydium.db")?;
+    let _ = save_to_db(
+        &conn,
+        &keys[0],
+        &keys[1],
+        &keys[2],
+        &keys[3],
+        &keys[4],
+        &keys[5],
+        &keys[6],
+        &keys[7],
+        &keys[8],
+        &keys[9],
+        &keys[10],
+        &keys[11],
+        &keys[12],
+        &keys[13],
+        &keys[14],
+        &mint_address,
+    );
+    Ok(())
 }
 
 fn save_to_db(
@@ -1014,174 +980,188 @@ fn save_to_db(
     Ok(())
 }
 
-pub fn decode_sol_amount(
-    instruction: &message::CompiledInstruction,
-) -> u64 {
-    let lamports:u64 = bincode::deserialize(&instruction.data[48..56]).expect("Failed to Deserialize");
-    return lamports;
+/// Decodes a Pump.fun self-CPI event log.
+/// Returns `None` when the data is not an event, and `Unknown` for events without a variant.
+pub fn decode_pumpfun_event(data: &[u8]) -> Result<Option<DecodedInstruction>, DecodeError> {
+    if !data.starts_with(&EVENT_IX_TAG) || data.len() < 16 {
+        return Ok(None);
+    }
+    let (event_discriminator, mut event_data) = (&data[8..16], &data[16..]);
+
+    // Deserialize from a reader so fields appended by newer program versions are ignored
+    if event_discriminator == discriminator("event", "TradeEvent") {
+        let event = PFTradeEvent::deserialize(&mut event_data)?;
+        Ok(Some(DecodedInstruction::PFTradeEvent(event)))
+    } else if event_discriminator == discriminator("event", "CreateEvent") {
+        let event = PFCreateEvent::deserialize(&mut event_data)?;
+        Ok(Some(DecodedInstruction::PFCreateEvent(event)))
+    } else {
+        Ok(Some(DecodedInstruction::Unknown))
+    }
 }
 
-/// Decodes the amount of SOL transferred if the instruction is a system transfer.
-pub fn decode_system_transfer_amount(
-    instruction: &message::CompiledInstruction
-) -> u64 {
+// Finds the TradeEvent Pump.fun logged for a trade on `mint`.
+// The event is emitted after the trade instruction, so the search starts right after
+// `inner_position` (or at the first inner instruction for a top-level trade).
+fn find_trade_event(
+    inner_instructions: &[InnerInstruction],
+    inner_position: Option<usize>,
+    accounts: &[Vec<u8>],
+    mint: &Pubkey,
+    is_buy: bool,
+) -> Result<Option<PFTradeEvent>, DecodeError> {
+    let start = inner_position.map_or(0, |position| position + 1);
+
+    for inner_instruction in inner_instructions.iter().skip(start) {
+        if let Some(instruction) = &inner_instruction.instruction {
+            let program_id = accounts.get(instruction.program_id_index as usize);
+            if program_id.map_or(false, |key| key.to_base58() == PUMPFUN_PROGRAM_ID) {
+                if let Some(DecodedInstruction::PFTradeEvent(event)) = decode_pumpfun_event(&instruction.data)? {
+                    if event.mint == *mint && event.is_buy == is_buy {
+                        return Ok(Some(event));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(None)
+}
 
-    let opcode:u32 = bincode::deserialize(&instruction.data[..4]).expect("Failed to Deserialize");
-    if opcode != 2 {
-        return 0;
+// Raw token and lamport amounts a trade filled, from its TradeEvent.
+// A failed transaction filled nothing and logs no event; an executed trade always logs one.
+fn trade_fill(event: Option<PFTradeEvent>, meta: &message::TransactionStatusMeta, mint: &Pubkey) -> Result<(u64, u64), DecodeError> {
+    match event {
+        Some(event) => Ok((event.token_amount, event.sol_amount)),
+        None if meta.is_status_err => Ok((0, 0)),
+        None => Err(DecodeError::MissingTradeEvent(*mint)),
     }
-    // Deserialize the amount of lamports from the instruction data
-    let lamports: u64 = bincode::deserialize(&instruction.data[4..]).expect("Failed to Deserialize");
-    return lamports;
 }
 
-// Simplified decode function
+// Decimals of `mint` from the transaction's token balances, defaulting to Pump.fun's 6
+fn mint_decimals(meta: &message::TransactionStatusMeta, mint: &Pubkey) -> u32 {
+    let mint = mint.to_string();
+    meta.post_token_balances.iter()
+        .chain(meta.pre_token_balances.iter())
+        .find(|balance| balance.mint == mint)
+        .and_then(|balance| balance.ui_token_account.as_ref())
+        .map_or(PUMPFUN_TOKEN_DECIMALS, |amount| amount.decimals)
+}
+
+// Decode a Pump.fun instruction using the bundled IDL.
+// Buy and sell take the mint and user from their named accounts and the filled amounts
+// from the TradeEvent.
 pub fn decode_pumpfun_instruction(
     instruction: &message::CompiledInstruction,
-    accounts: Vec<Vec<u8>>,
-    inner_instructions: Vec<InnerInstruction>,
-    amount_changes: HashMap<u64, f64>,
-    signature: &str,
-    mint:Pubkey
-) -> Result<DecodedInstruction, Box<dyn Error>> {
-    // List of recognized instructions
-    let instructions = ["buy", "sell", "create", "setparams", "initialize", "withdraw"];
-
-    // Try to find a matching discriminator in the data
-    let idl_instruction = instructions.iter().find(|&idl_instr| {
-        let discriminator = get_discriminator(idl_instr, None);
-        instruction.data.starts_with(&discriminator)
-    });
-
-    match idl_instruction {
-        Some(&"buy") => {
-            let user_index = instruction.accounts.get(6).ok_or("Missing mint account")?;
-            let user_vec = &accounts[*user_index as usize];
-
-            // Ensure the vector has exactly 32 bytes
-            let user_array: [u8; 32] = user_vec
-                .clone()
-                .try_into()
-                .map_err(|_| "Invalid user account key length")?;
-            
-            let mut used_sol_amount = 0;
-
-            for inner_instruction in inner_instructions {
-                let instruction = inner_instruction.instruction.clone().unwrap();
-                let expected_bytes: [u8; 16] = hex!("e445a52e51cb9a1dbddb7fd34ee661ee");
-                if accounts.len() > (instruction.program_id_index as usize) && accounts[instruction.program_id_index as usize].to_base58() == PUMPFUN_PROGRAM_ID && instruction.data.len() >= 16 && instruction.data[..16] == expected_bytes {
-                    used_sol_amount += decode_sol_amount(&instruction);
-                }      
-            }
-
-            // Create the Pubkey from the 32-byte array
-            let user = Pubkey::new_from_array(user_array);
+    accounts: &[Vec<u8>],
+    inner_instructions: &[InnerInstruction],
+    inner_position: Option<usize>,
+    meta: &message::TransactionStatusMeta,
+) -> Result<DecodedInstruction, DecodeError> {
+    let instruction_keys = instruction_accounts(instruction, accounts)?;
+    let Some(decoded) = IdlProgram::pumpfun().decode_instruction(&instruction.data, &instruction_keys)? else {
+        return Ok(DecodedInstruction::Unknown);
+    };
 
-            // Manually deserialize only the known part (amount, max_sol_cost)
-            let (amount, max_sol_cost): (u64, u64) = bincode::deserialize(&instruction.data[8..])?;
-            if let Some(ui_amount) = amount_changes.get(&amount){
-                let decoded = PFBuyInstruction { amount: (*ui_amount).abs(), max_sol_cost:(max_sol_cost as f64 / 1000000000.0), mint: mint, used_sol_amount:(used_sol_amount as f64 / 1000000000.0), user:user };
-                return Ok(DecodedInstruction::PFBuy(decoded));
-            }
-            Ok(DecodedInstruction::Unknown)
+    match decoded.name.as_str() {
+        "buy" => {
+            let mint = idl_account(&decoded, "mint")?;
+            let user = idl_account(&decoded, "user")?;
+            let max_sol_cost = idl_u64(&decoded, "maxSolCost")?;
+            let event = find_trade_event(inner_instructions, inner_position, accounts, &mint, true)?;
+            let (token_amount, sol_amount) = trade_fill(event, meta, &mint)?;
+            let decimals = mint_decimals(meta, &mint);
+
+            let decoded = PFBuyInstruction {
+                amount: token_amount as f64 / 10f64.powi(decimals as i32),
+                max_sol_cost: max_sol_cost as f64 / 1000000000.0,
+                mint,
+                used_sol_amount: sol_amount as f64 / 1000000000.0,
+                user,
+                token_amount,
+                sol_amount,
+            };
+            Ok(DecodedInstruction::PFBuy(decoded))
         },
-        Some(&"sell") => {
-            let user_index = instruction.accounts.get(6).ok_or("Missing user account")?;
-
-            if *user_index as usize > accounts.len() {
-                println!("{}", signature);
-            }
-            let user_vec = &accounts[*user_index as usize];
-
-            // Ensure the vector has exactly 32 bytes
-            let user_array: [u8; 32] = user_vec
-                .clone()
-                .try_into()
-                .map_err(|_| "Invalid user account key length")?;
-
-            let mut received_sol_amount = 0;
-
-            for inner_instruction in inner_instructions {
-                let instruction = inner_instruction.instruction.clone().unwrap();
-                let expected_bytes: [u8; 16] = hex!("e445a52e51cb9a1dbddb7fd34ee661ee");
-                if accounts.len() > (instruction.program_id_index as usize) && accounts[instruction.program_id_index as usize].to_base58() == PUMPFUN_PROGRAM_ID && instruction.data.len() >= 16 && instruction.data[..16] == expected_bytes {
-                    received_sol_amount += decode_sol_amount(&instruction);
-                }      
-            }
-
-            // Create the Pubkey from the 32-byte array
-            let user = Pubkey::new_from_array(user_array);
-            // Manually deserialize only the known part (amount, max_sol_cost)
-            let (amount, min_sol_output): (u64, u64) = bincode::deserialize(&instruction.data[8..])?;
-            if let Some(ui_amount) = amount_changes.get(&amount){
-                let decoded = PFSellInstruction { amount: (*ui_amount).abs(), min_sol_output: (min_sol_output as f64 / 1000000000.0), mint: mint, received_sol_amount: (received_sol_amount as f64 / 1000000000.0), user:user };
-                return Ok(DecodedInstruction::PFSell(decoded));
-            }
-            Ok(DecodedInstruction::Unknown)
+        "sell" => {
+            let mint = idl_account(&decoded, "mint")?;
+            let user = idl_account(&decoded, "user")?;
+            let min_sol_output = idl_u64(&decoded, "minSolOutput")?;
+            let event = find_trade_event(inner_instructions, inner_position, accounts, &mint, false)?;
+            let (token_amount, sol_amount) = trade_fill(event, meta, &mint)?;
+            let decimals = mint_decimals(meta, &mint);
+
+            let decoded = PFSellInstruction {
+                amount: token_amount as f64 / 10f64.powi(decimals as i32),
+                min_sol_output: min_sol_output as f64 / 1000000000.0,
+                mint,
+                received_sol_amount: sol_amount as f64 / 1000000000.0,
+                user,
+                token_amount,
+                sol_amount,
+            };
+            Ok(DecodedInstruction::PFSell(decoded))
         },
-        Some(&"create") => {
-            // Manually deserialize only the known part (amount, max_sol_cost)
-            match <(String, String, String)>::try_from_slice(&instruction.data[8..]) {
-                Ok(data) => {
-                    let decoded = PFCreateInstruction {
-                        name: data.0,
-                        symbol: data.1,
-                        uri: data.2,
-                        mint,
-                    };
-                    Ok(DecodedInstruction::PFCreate(decoded))
-                }
-                Err(e) => {
-                    // Return an error instead of unit `()`
-                    Err(Box::new(e))
-                }
-            }
+        "create" => {
+            let decoded = PFCreateInstruction {
+                name: idl_string(&decoded, "name")?,
+                symbol: idl_string(&decoded, "symbol")?,
+                uri: idl_string(&decoded, "uri")?,
+                mint: idl_account(&decoded, "mint")?,
+            };
+            Ok(DecodedInstruction::PFCreate(decoded))
         },
-        Some(&"setparams") => {
-            let decoded = bincode::deserialize::<PFSetParamsInstruction>(&instruction.data[8..])?;
+        "setParams" => {
+            let fee_recipient = decoded.arg("feeRecipient")
+                .and_then(IdlValue::as_pubkey)
+                .ok_or_else(|| DecodeError::InvalidInstructionData("setParams has no feeRecipient".to_string()))?;
+            let decoded = PFSetParamsInstruction {
+                fee_recipient,
+                initial_virtual_token_reserves: idl_u64(&decoded, "initialVirtualTokenReserves")?,
+                initial_virtual_sol_reserves: idl_u64(&decoded, "initialVirtualSolReserves")?,
+                initial_real_token_reserves: idl_u64(&decoded, "initialRealTokenReserves")?,
+                token_total_supply: idl_u64(&decoded, "tokenTotalSupply")?,
+                fee_basis_points: idl_u64(&decoded, "feeBasisPoints")?,
+            };
             Ok(DecodedInstruction::PFSetParams(decoded))
         },
-        Some(&"initialize") => Ok(DecodedInstruction::PFInitialize),
-        Some(&"withdraw") => Ok(DecodedInstruction::PFWithdraw),
+        "initialize" => Ok(DecodedInstruction::PFInitialize),
+        "withdraw" => Ok(DecodedInstruction::PFWithdraw),
         _ => Ok(DecodedInstruction::Unknown),
     }
 }
 
-// Function to get the 8-byte discriminator from the instruction name
-fn get_discriminator(instruction_name: &str, param:Option<&str>) -> Vec<u8> {
-    let mut hasher = Sha256::new();
-    let input = match param {
-        Some(p) => format!("global:{p}:{instruction_name}"),
-        None => format!("global:{instruction_name}"),
-    };
-    hasher.update(input);
-    let hash = hasher.finalize();
-    hash[..8].to_vec() // First 8 bytes of the SHA-256 hash as the discriminator
+// Resolves every account of the instruction to its pubkey
+pub(crate) fn instruction_accounts(
+    instruction: &message::CompiledInstruction,
+    accounts: &[Vec<u8>],
+) -> Result<Vec<Pubkey>, DecodeError> {
+    (0..instruction.accounts.len())
+        .map(|position| instruction_account(instruction, accounts, position))
+        .collect()
 }
 
-pub async fn create(mint_str:&str) -> Result<(), Box<dyn std::error::Error>>{
-    dotenv().ok();
-
-    // Create an RPC client to fetch the recent blockhash
-    let client = RpcClient::new("https://api.mainnet-beta.solana.com");
-
-    // Example: Fetch recent blockhash
-    let recent_blockhash = client.get_latest_blockhash()?;
-
-    // Base58-encoded private key (replace with actual private key)
-    let private_key_base58 = env::var("PRIVATE_KEY").expect("PRIVATE_KEY not set in environment");
-    
-    let jito_tip_amount_str = env::var("JITO_TIP_AMOUNT").expect("JITO_TIP_AMOUNT not set in environment");
+fn idl_u64(instruction: &AnchorInstruction, name: &str) -> Result<u64, DecodeError> {
+    instruction.arg(name)
+        .and_then(IdlValue::as_u64)
+        .ok_or_else(|| DecodeError::InvalidInstructionData(format!("{} has no u64 argument {}", instruction.name, name)))
+}
 
-    // Parse the Jito tip amount as a u64 (assuming it's stored in lamports)
-    let jito_tip_amount: u64 = jito_tip_amount_str.parse().expect("Failed to parse JITO_TIP_AMOUNT");
+fn idl_string(instruction: &AnchorInstruction, name: &str) -> Result<String, DecodeError> {
+    instruction.arg(name)
+        .and_then(IdlValue::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| DecodeError::InvalidInstructionData(format!("{} has no string argument {}", instruction.name, name)))
+}
 
-    // Create Keypair for the payer and signers (from private key)
-    let payer = keypair_from_base58(&private_key_base58); // This keypair will pay for the transaction fees
-    let signer = keypair_from_base58(&private_key_base58); // Example signer, replace with actual signer
+fn idl_account(instruction: &AnchorInstruction, name: &str) -> Result<Pubkey, DecodeError> {
+    instruction.account(name)
+        .ok_or_else(|| DecodeError::InvalidInstructionData(format!("{} has no account {}", instruction.name, name)))
+}
 
-    // Extract the user's public key from the Keypair
-    let user_pubkey = payer.pubkey();
+pub async fn create(executor: &LiveExecutor, mint_str:&str) -> Result<Execution, Box<dyn std::error::Error>>{
+    // Extract the user's public key from the signer
+    let user_pubkey = executor.payer();
     let mint = pubkey_from_base58(mint_str)?;
     let token_program_id = pubkey_from_base58(TOKEN_PROGRAM_ID)?;
     //Create token account instruction
@@ -1192,45 +1172,13 @@ pub async fn create(mint_str:&str) -> Result<(), Box<dyn std::error::Error>>{
         &token_program_id
     );
 
-    // Combine the instructions into a single list
-    let mut instructions = vec![create_instruction];
-
-    // Add the Jito tip instruction
-    let tip_instruction = create_jito_tip_instruction(user_pubkey, jito_tip_amount).await?; // Tip amount (in lamports)
-    instructions.push(tip_instruction);
-
-    // Create the transaction with the payer and instructions
-    let transaction = make_transaction_from_instructions(
-        instructions,
-        &payer,                     // The payer of the transaction fees
-        vec![&payer, &signer],      // List of signers
-        recent_blockhash            // Recent blockhash
-    ).await?;
-
-    // Print the transaction details (this is just an example of printing the encoded transaction)
-
-    // Serialize the transaction into raw bytes
-    let serialized_tx = serialize_transaction(&transaction)?;
-
-    // Convert the raw bytes to Base64 (if you want to print or send via API)
-    let base58_tx = bs58::encode(&serialized_tx).into_string();
-
-    let res = send_transaction_to_jito(&base58_tx).await?;
-
-    println!("{:?}",res);
-
-    Ok(())
+    // The executor adds the compute budget and tip
+    Ok(executor.submit(vec![create_instruction]).await?)
 }
 
-pub async fn transfer_sol(amount_f: f64, target: &str) -> Result<(), Box<dyn Error>> {
-    // Base58-encoded private key (replace with actual private key)
-    let private_key_base58 = env::var("PRIVATE_KEY").expect("PRIVATE_KEY not set in environment");
-    // Create Keypair for the payer and signers (from private key)
-    let payer = keypair_from_base58(&private_key_base58); // This keypair will pay for the transaction fees
-    let payer_pubkey = payer.pubkey();
-
-    // Connect to a Solana RPC node (you can use a public RPC or your own)
-    let rpc_client = RpcClient::new("https://api.mainnet-beta.solana.com");
+pub async fn transfer_sol(signer: &dyn TradeSigner, broadcaster: &Broadcaster, blockhash_provider: &dyn BlockhashProvider, amount_f: f64, target: &str) -> Result<Signature, Box<dyn Error>> {
+    // The signer pays the fees and signs the transaction
+    let payer_pubkey = signer.pubkey();
 
     // Convert the amount of SOL to lamports (1 SOL = 1 billion lamports)
     let amount_lamports = (amount_f * 1_000_000_000.0) as u64;
@@ -1244,443 +1192,160 @@ pub async fn transfer_sol(amount_f: f64, target: &str) -> Result<(), Box<dyn Err
     // Create the transaction and add the transfer instruction
     let mut transaction = Transaction::new_with_payer(&[transfer_instruction], Some(&payer_pubkey));
 
-    // Get the recent blockhash from the network
-    let recent_blockhash = rpc_client.get_latest_blockhash()?;
-
-    // Set the blockhash and sign the transaction
-    transaction.sign(&[&payer], recent_blockhash);
+    // Set the cached blockhash and sign the transaction
+    sign_transaction(&mut transaction, signer, blockhash_provider.latest()?.hash).await?;
 
-    // Send the transaction to the network
-    let signature = rpc_client.send_and_confirm_transaction(&transaction)?;
-
-    println!("{}", signature);
-
-    Ok(())
+    // Send the transaction to every endpoint of the broadcaster
+    submit_transaction(broadcaster, &transaction.into()).await
 }
 
-pub async fn buy(amount_f:f64, max_sol_cost_f:f64, mint:&str, decimal:u32, include_create:bool, recent_blockhash: Option<&Hash>) -> Result<(), Box<dyn std::error::Error>>{
-    dotenv().ok();
-
+// Buys `amount_f` tokens of `mint` for at most `max_sol_cost_f` SOL as an order of the executor's order book
+pub async fn buy(
+    executor: &LiveExecutor,
+    amount_f:f64,
+    max_sol_cost_f:f64,
+    mint:&str,
+    decimal:u32,
+    include_create:bool,
+) -> Result<Execution, Box<dyn std::error::Error>>{
     let amount = (amount_f * 10_u64.pow(decimal) as f64) as u64;
     let max_sol_cost = (max_sol_cost_f * 10_u64.pow(9) as f64) as u64;
 
-    if recent_blockhash.is_none() {
-        // Create an RPC client to fetch the recent blockhash
-        let client = RpcClient::new("https://api.mainnet-beta.solana.com");
-
-        // Example: Fetch recent blockhash
-        let recent_blockhash = Some(client.get_latest_blockhash());
-    }
-
-    let recent_blockhash_value = recent_blockhash.unwrap();
-
-    // Base58-encoded private key (replace with actual private key)
-    let private_key_base58 = env::var("PRIVATE_KEY").expect("PRIVATE_KEY not set in environment");
-    let jito_tip_amount_str = env::var("JITO_TIP_AMOUNT").expect("JITO_TIP_AMOUNT not set in environment");
-
-    // Parse the Jito tip amount as a u64 (assuming it's stored in lamports)
-    let jito_tip_amount: u64 = jito_tip_amount_str.parse().expect("Failed to parse JITO_TIP_AMOUNT");
-
-    // Create Keypair for the payer and signers (from private key)
-    let payer = keypair_from_base58(&private_key_base58); // This keypair will pay for the transaction fees
-    let signer = keypair_from_base58(&private_key_base58); // Example signer, replace with actual signer
-
-    // Extract the user's public key from the Keypair
-    let user_pubkey = payer.pubkey();
+    let mint = pubkey_from_base58(mint)?;
+    let user = executor.payer();
     let mut instructions = Vec::new();
-
-    // Fetch the environment variables as strings
-    let compute_price_str = env::var("COMPUTE_PRICE").expect("COMPUTE_PRICE not set in environment");
-    let compute_limit_str = env::var("COMPUTE_LIMIT").expect("COMPUTE_LIMIT not set in environment");
-
-    // Convert to u32 and u64
-    let compute_price: u64 = compute_price_str
-        .parse()
-        .expect("COMPUTE_PRICE must be a valid u32");
-    let compute_limit: u32 = compute_limit_str
-        .parse()
-        .expect("COMPUTE_LIMIT must be a valid u64");
-
-    let compute_price_instruction = make_set_compute_unit_price_instruction(compute_price).await?;
-    let compute_limit_instruction = make_set_compute_unit_limit_instruction(compute_limit).await?;
-
-    instructions.push(compute_price_instruction);
-    instructions.push(compute_limit_instruction);
-
     if include_create {
-        let token_program_id = pubkey_from_base58(TOKEN_PROGRAM_ID)?;
-
-        let create_instruction = create_associated_token_account(
-            &user_pubkey,   // The payer for the transaction
-            &user_pubkey,  // The owner of the ATA
-            &pubkey_from_base58(mint)?,    // The mint address of the token
-            &token_program_id
-        );
-
-        instructions.push(create_instruction);
+        instructions.push(create_associated_token_account(&user, &user, &mint, &pubkey_from_base58(TOKEN_PROGRAM_ID)?));
     }
-
-    // Create the buy instruction
-    let buy_instruction = make_buy_instruction(
-        amount, 
-        max_sol_cost, 
-        mint, 
-        &user_pubkey.to_string()
-    ).await?;
-    instructions.push(buy_instruction);
-
-    // Add the Jito tip instruction
-    let tip_instruction = create_jito_tip_instruction(user_pubkey, jito_tip_amount).await?; // Tip amount (in lamports)
-    instructions.push(tip_instruction);
-
-    // Create the transaction with the payer and instructions
-    let transaction = make_transaction_from_instructions(
-        instructions,
-        &payer,                     // The payer of the transaction fees
-        vec![&payer, &signer],      // List of signers
-        recent_blockhash_value.clone()            // Recent blockhash
-    ).await?;
-
-    // Serialize the transaction into raw bytes
-    let serialized_tx = serialize_transaction(&transaction)?;
-
-    // Convert the raw bytes to Base64 (if you want to print or send via API)
-    let base58_tx = bs58::encode(&serialized_tx).into_string();
-
-    let res = send_transaction_to_jito(&base58_tx).await?;
-
-    println!("Sent TX result: {:?}",res);
-    process::exit(0);
-    Ok(())
+    instructions.push(pumpfun_buy_instruction(&mint, &user, amount, max_sol_cost));
+    Ok(executor.submit(instructions).await?)
 }
 
-pub async fn sell(amount_f:f64, min_sol_output_f:f64, mint:&str, decimal:u32) -> Result<(), Box<dyn std::error::Error>>{
-    dotenv().ok();
-
+// Sells `amount_f` tokens of `mint` for at least `min_sol_output_f` SOL as an order of the executor's order book
+pub async fn sell(
+    executor: &LiveExecutor,
+    amount_f:f64,
+    min_sol_output_f:f64,
+    mint:&str,
+    decimal:u32,
+) -> Result<Execution, Box<dyn std::error::Error>>{
     let amount = (amount_f * 10_u64.pow(decimal) as f64) as u64;
     let min_sol_output = (min_sol_output_f * 10_u64.pow(9) as f64) as u64;
-    // Create an RPC client to fetch the recent blockhash
-    let client = RpcClient::new("https://api.mainnet-beta.solana.com");
-
-    // Example: Fetch recent blockhash
-    let recent_blockhash = client.get_latest_blockhash()?;
-
-    // Base58-encoded private key (replace with actual private key)
-    let private_key_base58 = env::var("PRIVATE_KEY").expect("PRIVATE_KEY not set in environment");
-    let jito_tip_amount_str = env::var("JITO_TIP_AMOUNT").expect("JITO_TIP_AMOUNT not set in environment");
-
-    // Parse the Jito tip amount as a u64 (assuming it's stored in lamports)
-    let jito_tip_amount: u64 = jito_tip_amount_str.parse().expect("Failed to parse JITO_TIP_AMOUNT");
-
-    // Create Keypair for the payer and signers (from private key)
-    let payer = keypair_from_base58(&private_key_base58); // This keypair will pay for the transaction fees
-    let signer = keypair_from_base58(&private_key_base58); // Example signer, replace with actual signer
-
-    // Extract the user's public key from the Keypair
-    let user_pubkey = payer.pubkey();
-
-    // Fetch the environment variables as strings
-    let compute_price_str = env::var("COMPUTE_PRICE").expect("COMPUTE_PRICE not set in environment");
-    let compute_limit_str = env::var("COMPUTE_LIMIT").expect("COMPUTE_LIMIT not set in environment");
 
-    // Convert to u32 and u64
-    let compute_price: u64 = compute_price_str
-        .parse()
-        .expect("COMPUTE_PRICE must be a valid u32");
-    let compute_limit: u32 = compute_limit_str
-        .parse()
-        .expect("COMPUTE_LIMIT must be a valid u64");
-
-    let compute_price_instruction = make_set_compute_unit_price_instruction(compute_price).await?;
-    let compute_limit_instruction = make_set_compute_unit_limit_instruction(compute_limit).await?;
-
-    // Create the sell instruction
-    let sell_instruction = make_sell_instruction(
-        amount, 
-        min_sol_output, 
-        mint,
-        &user_pubkey.to_string()
-    ).await?;
-
-    // Combine the instructions into a single list
-    let mut instructions = vec![compute_price_instruction, compute_limit_instruction, sell_instruction];
-
-    // Add the Jito tip instruction
-    let tip_instruction = create_jito_tip_instruction(user_pubkey, jito_tip_amount).await?; // Tip amount (in lamports)
-    instructions.push(tip_instruction);
-
-    // Create the transaction with the payer and instructions
-    let transaction = make_transaction_from_instructions(
-        instructions,
-        &payer,                     // The payer of the transaction fees
-        vec![&payer, &signer],      // List of signers
-        recent_blockhash            // Recent blockhash
-    ).await?;
-
-    // Serialize the transaction into raw bytes
-    let serialized_tx = serialize_transaction(&transaction)?;
-
-    // Convert the raw bytes to Base64 (if you want to print or send via API)
-    let base58_tx = bs58::encode(&serialized_tx).into_string();
-
-    let res = send_transaction_to_jito(&base58_tx).await?;
-
-    println!("{:?}",res);
-
-    Ok(())
+    let instruction = pumpfun_sell_instruction(&pubkey_from_base58(mint)?, &executor.payer(), amount, min_sol_output);
+    Ok(executor.submit(vec![instruction]).await?)
 }
 
-// Helper function to send the transaction to JitoRPC
-async fn send_transaction_to_jito(base58_tx: &str) -> Result<String, Box<dyn std::error::Error>> {
-    // Prepare the JSON payload
-    let payload = serde_json::json!({
-        "jsonrpc": "2.0",
-        "id": 1,
-        "method": "sendTransaction",
-        "params": [base58_tx]
-    });
-
-    // Send the POST request to JitoRPC
-    let client = reqwest::Client::new();
-    let response = client.post(JITO_RPC_ENDPOINT)
-        .json(&payload)
-        .send()
-        .await?;
-
-    // Check the response status
-    if response.status().is_success() {
-        // Parse the response JSON
-        let response_json: serde_json::Value = response.json().await?;
-        // Extract and return the transaction signature
-        if let Some(signature) = response_json["result"].as_str() {
-            Ok(signature.into())
-        } else {
-            Err("Failed to retrieve transaction signature".into())
-        }
-    } else {
-        Err(format!("Error sending transaction: {:?}", response.text().await?).into())
-    }
-}
-
-pub fn keypair_from_base58(private_key_base58: &str) -> Keypair {
-    let decoded = bs58::decode(private_key_base58).into_vec().expect("Failed to decode base58 private key");
-    Keypair::from_bytes(&decoded).expect("Failed to create keypair from decoded bytes")
+// Buys as many tokens as `sol_budget` lamports buy at the cached curve state, fee included, tolerating
+// `slippage_bps` of adverse price movement. Refuses orders whose price impact exceeds the executor's limit.
+pub async fn buy_with_sol(
+    executor: &LiveExecutor,
+    sol_budget: u64,
+    slippage_bps: u64,
+    mint: &str,
+) -> Result<Execution, Box<dyn std::error::Error>> {
+    Ok(executor.buy(&pubkey_from_base58(mint)?, sol_budget, slippage_bps).await?)
 }
 
-async fn make_set_compute_unit_price_instruction(
-    micro_lamports: u64
-) -> Result<Instruction, Box<dyn std::error::Error>> {
-    let instruction_data = bincode::serialize(&SetComputeUnitPriceInstructionData{opcode:3, micro_lamports})?;
-
-    let program_id_pubkey = pubkey_from_base58(COMPUTE_BUDGET_PROGRAM_ID)?;
-
-    let instruction = Instruction {
-        program_id: program_id_pubkey,
-        accounts:vec![],
-        data: instruction_data,
-    };
-
-    Ok(instruction)
+// Sells `amount` of the signer's holding of `mint` at the cached curve state, accepting `slippage_bps`
+// less than the quoted proceeds. Refuses orders whose price impact exceeds the executor's limit.
+pub async fn sell_tokens(
+    executor: &LiveExecutor,
+    amount: SellAmount,
+    slippage_bps: u64,
+    mint: &str,
+) -> Result<Execution, Box<dyn std::error::Error>> {
+    Ok(executor.sell(&pubkey_from_base58(mint)?, amount, slippage_bps).await?)
 }
 
-async fn make_set_compute_unit_limit_instruction(
-    units: u32
-) -> Result<Instruction, Box<dyn std::error::Error>> {
-    let instruction_data = bincode::serialize(&SetComputeUnitLimitInstructionData{opcode:2, units})?;
-
-    let program_id_pubkey = pubkey_from_base58(COMPUTE_BUDGET_PROGRAM_ID)?;
-
-    let instruction = Instruction {
-        program_id: program_id_pubkey,
-        accounts:vec![],
-        data: instruction_data,
-    };
-
-    Ok(instruction)
+/// Sends a signed transaction to every endpoint of the broadcaster and returns its signature.
+pub async fn submit_transaction(broadcaster: &Broadcaster, transaction: &VersionedTransaction) -> Result<Signature, Box<dyn std::error::Error>> {
+    let report = broadcaster.broadcast(transaction).await?;
+    if !report.accepted() {
+        return Err(format!("no endpoint accepted transaction {}", report.signature).into());
+    }
+    Ok(report.signature)
 }
 
-async fn make_buy_instruction(
-    amount: u64,
-    max_sol_cost: u64,
-    mint: &str,         // Mint account as a parameter
-    user: &str,         // User account as a parameter
-) -> Result<Instruction, Box<dyn std::error::Error>> {
-
-    // Step 1: Prepare the instruction data
+/// Builds a Pump.fun buy of `amount` raw tokens paying at most `max_sol_cost` lamports.
+pub fn pumpfun_buy_instruction(mint: &Pubkey, user: &Pubkey, amount: u64, max_sol_cost: u64) -> Instruction {
     let instruction = PFBuyInstructionData{
         amount,
         max_sol_cost,
     };
 
-    // Serialize the instruction data using bincode
-    let serialized_instruction = bincode::serialize(&instruction)?;
+    // Discriminator (8 bytes) followed by the bincode-serialized arguments
+    let mut instruction_data = discriminator("global", "buy").to_vec();
+    instruction_data.extend(bincode::serialize(&instruction).unwrap());
 
-    // Create a byte array large enough to hold the discriminator (8 bytes) + serialized data
-    let mut instruction_data = vec![0u8; 8 + serialized_instruction.len()];
+    let program_id_pubkey = Pubkey::from_str(PUMPFUN_PROGRAM_ID).unwrap();
+    let bonding_curve_pubkey = get_bonding_curve_account(mint);
+    let associated_bonding_curve_pubkey = get_associated_token_address(&bonding_curve_pubkey, mint);
+    let associated_user_pubkey = get_associated_token_address(user, mint);
 
-    // Insert the discriminator into the first 8 bytes
-    instruction_data[..8].copy_from_slice(&get_discriminator("buy", None));
-    instruction_data[8..].copy_from_slice(&serialized_instruction);
-
-    // Convert Base58 strings to Pubkey using the helper function
-    let global_pubkey = pubkey_from_base58(GLOBAL)?;
-    let fee_recipient_pubkey = pubkey_from_base58(FEE_RECIPIENT)?;
-    let bonding_curve_pubkey = get_bonding_curve_account(mint, PUMPFUN_PROGRAM_ID).expect("Failed to derive bonding curve account");
-    let associated_bonding_curve_pubkey = get_associated_bonding_curve_account(mint, &bonding_curve_pubkey).expect("Failed to derive bonding curve account");
-    let system_program_pubkey = pubkey_from_base58(SYSTEM_PROGRAM)?;
-    let token_program_pubkey = pubkey_from_base58(TOKEN_PROGRAM)?;
-    let rent_pubkey = pubkey_from_base58(RENT)?;
-    let event_authority_pubkey = pubkey_from_base58(EVENT_AUTHORITY)?;
-    let program_id_pubkey = pubkey_from_base58(PUMPFUN_PROGRAM_ID)?;
-
-    let user_pubkey = pubkey_from_base58(user)?;
-    let mint_pubkey = pubkey_from_base58(mint)?;
-    let associated_user_pubkey = get_associated_token_address(&user_pubkey, &mint_pubkey);
-
-    // Step 3: Create the instruction by defining account metas
     let accounts = vec![
-        AccountMeta::new(global_pubkey, false),                  // Global
-        AccountMeta::new(fee_recipient_pubkey, false),           // Fee Recipient
-        AccountMeta::new(mint_pubkey, false),                    // Mint (parameter)
-        AccountMeta::new(bonding_curve_pubkey, false),           // Bonding Curve
-        AccountMeta::new(associated_bonding_curve_pubkey, false),// Associated Bonding Curve
-        AccountMeta::new(associated_user_pubkey, false),         // Associated User
-        AccountMeta::new(user_pubkey, true),                     // User (parameter)
-        AccountMeta::new_readonly(system_program_pubkey, false), // System Program
-        AccountMeta::new_readonly(token_program_pubkey, false),  // Token Program
-        AccountMeta::new_readonly(rent_pubkey, false),           // Rent
-        AccountMeta::new(event_authority_pubkey, false),         // Event Authority
-        AccountMeta::new(program_id_pubkey, false),              // Program ID
+        AccountMeta::new(Pubkey::from_str(GLOBAL).unwrap(), false),                     // Global
+        AccountMeta::new(Pubkey::from_str(FEE_RECIPIENT).unwrap(), false),              // Fee Recipient
+        AccountMeta::new(*mint, false),                                                 // Mint
+        AccountMeta::new(bonding_curve_pubkey, false),                                  // Bonding Curve
+        AccountMeta::new(associated_bonding_curve_pubkey, false),                       // Associated Bonding Curve
+        AccountMeta::new(associated_user_pubkey, false),                                // Associated User
+        AccountMeta::new(*user, true),                                                  // User
+        AccountMeta::new_readonly(Pubkey::from_str(SYSTEM_PROGRAM).unwrap(), false),    // System Program
+        AccountMeta::new_readonly(Pubkey::from_str(TOKEN_PROGRAM).unwrap(), false),     // Token Program
+        AccountMeta::new_readonly(Pubkey::from_str(RENT).unwrap(), false),              // Rent
+        AccountMeta::new(Pubkey::from_str(EVENT_AUTHORITY).unwrap(), false),            // Event Authority
+        AccountMeta::new(program_id_pubkey, false),                                     // Program ID
     ];
 
-    // Step 4: Create and return the final instruction
-    let buy_instruction = Instruction {
+    Instruction {
         program_id: program_id_pubkey,
         accounts,
         data: instruction_data,
-    };
-
-    Ok(buy_instruction)
-}
-
-// Helper function to create a Jito tip instruction
-async fn create_jito_tip_instruction(
-    sender_pubkey: Pubkey,  // Sender's public key passed as parameter
-    tip_amount: u64,         // Tip amount in lamports
-) -> Result<Instruction, Box<dyn Error>> {
-
-    // Prepare the JSON-RPC request payload
-    let url = "https://ny.mainnet.block-engine.jito.wtf/api/v1/bundles";
-    let payload = json!({
-        "jsonrpc": "2.0",
-        "id": 1,
-        "method": "getTipAccounts",
-        "params": []
-    });
-
-    // Create an HTTP client
-    let client = Client::new();
-    
-    // Send the POST request
-    let response = client.post(url).json(&payload).send().await?;
-    
-    // Parse the JSON response
-    let tip_accounts: TipAccountResponse = response.json().await?;
-    
-    // Ensure there are accounts available
-    let num_accounts = tip_accounts.result.len();
-    if num_accounts == 0 {
-        return Err("No tip accounts available.".into());
     }
-
-    // Create a random number generator and select a random account
-    let mut rng = rand::thread_rng();
-    let random_index = rng.gen_range(0..num_accounts);
-
-    // Convert the selected tip account to a Pubkey
-    let random_account_pubkey = pubkey_from_base58(&tip_accounts.result[random_index])?;
-
-    // Create the instruction to send the tip (in lamports)
-    let instruction = system_instruction::transfer(
-        &sender_pubkey,           // Sender's public key (from params)
-        &random_account_pubkey,   // The randomly selected Jito tip account
-        tip_amount,               // Tip amount in lamports (1 SOL = 1,000,000,000 lamports)
-    );
-
-    Ok(instruction)
 }
 
-async fn make_sell_instruction(
-    amount: u64,
-    min_sol_output: u64,
-    mint: &str,         // Mint account as a parameter
-    user: &str,         // User account as a parameter
-) -> Result<Instruction, Box<dyn std::error::Error>> {
-
-    // Step 1: Prepare the instruction data
+/// Builds a Pump.fun sell of `amount` raw tokens for at least `min_sol_output` lamports.
+pub fn pumpfun_sell_instruction(mint: &Pubkey, user: &Pubkey, amount: u64, min_sol_output: u64) -> Instruction {
     let instruction = PFSellInstructionData{
         amount,
         min_sol_output,
     };
 
-    // Serialize the instruction data using bincode
-    let serialized_instruction = bincode::serialize(&instruction)?;
-
-    // Create a byte array large enough to hold the discriminator (8 bytes) + serialized data
-    let mut instruction_data = vec![0u8; 8 + serialized_instruction.len()];
+    // Discriminator (8 bytes) followed by the bincode-serialized arguments
+    let mut instruction_data = discriminator("global", "sell").to_vec();
+    instruction_data.extend(bincode::serialize(&instruction).unwrap());
 
-    // Insert the discriminator into the first 8 bytes
-    instruction_data[..8].copy_from_slice(&get_discriminator("sell", None));
-    instruction_data[8..].copy_from_slice(&serialized_instruction);
+    let program_id_pubkey = Pubkey::from_str(PUMPFUN_PROGRAM_ID).unwrap();
+    let bonding_curve_pubkey = get_bonding_curve_account(mint);
+    let associated_bonding_curve_pubkey = get_associated_token_address(&bonding_curve_pubkey, mint);
+    let associated_user_pubkey = get_associated_token_address(user, mint);
 
-    // Convert Base58 strings to Pubkey using the helper function
-    let global_pubkey = pubkey_from_base58(GLOBAL)?;
-    let fee_recipient_pubkey = pubkey_from_base58(FEE_RECIPIENT)?;
-    let bonding_curve_pubkey = get_bonding_curve_account(mint, PUMPFUN_PROGRAM_ID).expect("Failed to derive bonding curve account");
-    let associated_bonding_curve_pubkey = get_associated_bonding_curve_account(mint, &bonding_curve_pubkey).expect("Failed to derive bonding curve account");
-    let system_program_pubkey = pubkey_from_base58(SYSTEM_PROGRAM)?;
-    let token_program_pubkey = pubkey_from_base58(TOKEN_PROGRAM)?;
-    let associated_token_account_program = pubkey_from_base58(ASSOCIATED_TOKEN_ACCOUNT_PROGRAM)?;
-    let event_authority_pubkey = pubkey_from_base58(EVENT_AUTHORITY)?;
-    let program_id_pubkey = pubkey_from_base58(PUMPFUN_PROGRAM_ID)?;
-
-    let user_pubkey = pubkey_from_base58(user)?;
-    let mint_pubkey = pubkey_from_base58(mint)?;
-    let associated_user_pubkey = get_associated_token_address(&user_pubkey, &mint_pubkey);
-
-    // Step 3: Create the instruction by defining account metas
     let accounts = vec![
-        AccountMeta::new(global_pubkey, false),                  // Global
-        AccountMeta::new(fee_recipient_pubkey, false),           // Fee Recipient
-        AccountMeta::new(mint_pubkey, false),                    // Mint (parameter)
-        AccountMeta::new(bonding_curve_pubkey, false),           // Bonding Curve
-        AccountMeta::new(associated_bonding_curve_pubkey, false),// Associated Bonding Curve
-        AccountMeta::new(associated_user_pubkey, false),         // Associated User
-        AccountMeta::new(user_pubkey, true),                     // User (parameter)
-        AccountMeta::new_readonly(system_program_pubkey, false), // System Program
-        AccountMeta::new_readonly(associated_token_account_program, false), // Associated Token Account Program
-        AccountMeta::new_readonly(token_program_pubkey, false),  // Token Program
-        AccountMeta::new(event_authority_pubkey, false),         // Event Authority
-        AccountMeta::new(program_id_pubkey, false),              // Program ID
+        AccountMeta::new(Pubkey::from_str(GLOBAL).unwrap(), false),                     // Global
+        AccountMeta::new(Pubkey::from_str(FEE_RECIPIENT).unwrap(), false),              // Fee Recipient
+        AccountMeta::new(*mint, false),                                                 // Mint
+        AccountMeta::new(bonding_curve_pubkey, false),                                  // Bonding Curve
+        AccountMeta::new(associated_bonding_curve_pubkey, false),                       // Associated Bonding Curve
+        AccountMeta::new(associated_user_pubkey, false),                                // Associated User
+        AccountMeta::new(*user, true),                                                  // User
+        AccountMeta::new_readonly(Pubkey::from_str(SYSTEM_PROGRAM).unwrap(), false),    // System Program
+        AccountMeta::new_readonly(Pubkey::from_str(ASSOCIATED_TOKEN_ACCOUNT_PROGRAM).unwrap(), false), // Associated Token Account Program
+        AccountMeta::new_readonly(Pubkey::from_str(TOKEN_PROGRAM).unwrap(), false),     // Token Program
+        AccountMeta::new(Pubkey::from_str(EVENT_AUTHORITY).unwrap(), false),            // Event Authority
+        AccountMeta::new(program_id_pubkey, false),                                     // Program ID
     ];
 
-    // Step 4: Create and return the final instruction
-    let sell_instruction = Instruction {
+    Instruction {
         program_id: program_id_pubkey,
         accounts,
         data: instruction_data,
-    };
-
-    Ok(sell_instruction)
+    }
 }
 
-pub async fn raydium_swap_get_account_keys_by_api(token:&str) -> Result<(), Box<dyn std::error::Error>> {
+pub async fn raydium_swap_get_account_keys_by_api(user_pubkey: &Pubkey, token:&str) -> Result<(), Box<dyn std::error::Error>> {
     dotenv().ok();
 
-    let private_key_base58 = env::var("PRIVATE_KEY").expect("PRIVATE_KEY not set in environment");
-    let signer = keypair_from_base58(&private_key_base58);
-    let user_pubkey = signer.pubkey();
-
     let client = Client::new();
 
     let auth_token = env::var("BLOXROUTE_AUTH_TOKEN").expect("BLOXROUTE_AUTH_TOKEN not set in environment");
@@ -1774,32 +1439,14 @@ pub async fn raydium_swap_get_account_keys_by_api(token:&str) -> Result<(), Box<
     Ok(())
 }
 
-pub async fn raydium_swap_base_in(from_mint:&str, to_mint:&str, amount_in_f:f64, min_amount_out_f:f64, in_decimal:u32, out_decimal:u32, include_create:bool) -> Result<(), Box<dyn std::error::Error>> {
-
-    dotenv().ok();
+#[allow(clippy::too_many_arguments)]
+pub async fn raydium_swap_base_in(executor: &LiveExecutor, from_mint:&str, to_mint:&str, amount_in_f:f64, min_amount_out_f:f64, in_decimal:u32, out_decimal:u32, include_create:bool) -> Result<Execution, Box<dyn std::error::Error>> {
 
     let amount_in = (amount_in_f * 10_u64.pow(in_decimal) as f64) as u64;
     let min_amount_out = (min_amount_out_f * 10_u64.pow(out_decimal) as f64) as u64;
 
-    // Create an RPC client to fetch the recent blockhash
-    let client = RpcClient::new("https://api.mainnet-beta.solana.com");
-
-    // Example: Fetch recent blockhash
-    let recent_blockhash = client.get_latest_blockhash()?;
-
-    // Base58-encoded private key (replace with actual private key)
-    let private_key_base58 = env::var("PRIVATE_KEY").expect("PRIVATE_KEY not set in environment");
-    let jito_tip_amount_str = env::var("JITO_TIP_AMOUNT").expect("JITO_TIP_AMOUNT not set in environment");
-
-    // Parse the Jito tip amount as a u64 (assuming it's stored in lamports)
-    let jito_tip_amount: u64 = jito_tip_amount_str.parse().expect("Failed to parse JITO_TIP_AMOUNT");
-
-    // Create Keypair for the payer and signers (from private key)
-    let payer = keypair_from_base58(&private_key_base58); // This keypair will pay for the transaction fees
-    let signer = keypair_from_base58(&private_key_base58); // Example signer, replace with actual signer
-
-    // Extract the user's public key from the Keypair
-    let user_pubkey = payer.pubkey();
+    // Extract the user's public key from the signer
+    let user_pubkey = executor.payer();
     let mut instructions = Vec::new();
 
     if include_create && to_mint != "So11111111111111111111111111111111111111112" {
@@ -1828,58 +1475,18 @@ pub async fn raydium_swap_base_in(from_mint:&str, to_mint:&str, amount_in_f:f64,
     // Combine the instructions into a single list
     instructions.push(raydium_swap_instruction);
 
-    // Add the Jito tip instruction
-    let tip_instruction = create_jito_tip_instruction(user_pubkey, jito_tip_amount).await?; // Tip amount (in lamports)
-    instructions.push(tip_instruction);
-
-    // Create the transaction with the payer and instructions
-    let transaction = make_transaction_from_instructions(
-        instructions,
-        &payer,                     // The payer of the transaction fees
-        vec![&payer, &signer],      // List of signers
-        recent_blockhash            // Recent blockhash
-    ).await?;
-
-
-    // Serialize the transaction into raw bytes
-    let serialized_tx = serialize_transaction(&transaction)?;
-
-    // Convert the raw bytes to Base64 (if you want to print or send via API)
-    let base58_tx = bs58::encode(&serialized_tx).into_string();
-
-    let res = send_transaction_to_jito(&base58_tx).await?;
-
-    println!("{:?}",res);
-
-    Ok(())
+    // The executor adds the compute budget and tip
+    Ok(executor.submit(instructions).await?)
 }
 
-pub async fn raydium_swap_base_out(from_mint:&str, to_mint:&str, max_amount_in_f:f64, amount_out_f:f64, in_decimal:u32, out_decimal:u32, include_create:bool) -> Result<(), Box<dyn std::error::Error>> {
-
-    dotenv().ok();
+#[allow(clippy::too_many_arguments)]
+pub async fn raydium_swap_base_out(executor: &LiveExecutor, from_mint:&str, to_mint:&str, max_amount_in_f:f64, amount_out_f:f64, in_decimal:u32, out_decimal:u32, include_create:bool) -> Result<Execution, Box<dyn std::error::Error>> {
 
     let max_amount_in = (max_amount_in_f * 10_u64.pow(in_decimal) as f64) as u64;
     let amount_out = (amount_out_f * 10_u64.pow(out_decimal) as f64) as u64;
 
-    // Create an RPC client to fetch the recent blockhash
-    let client = RpcClient::new("https://api.mainnet-beta.solana.com");
-
-    // Example: Fetch recent blockhash
-    let recent_blockhash = client.get_latest_blockhash()?;
-
-    // Base58-encoded private key (replace with actual private key)
-    let private_key_base58 = env::var("PRIVATE_KEY").expect("PRIVATE_KEY not set in environment");
-    let jito_tip_amount_str = env::var("JITO_TIP_AMOUNT").expect("JITO_TIP_AMOUNT not set in environment");
-
-    // Parse the Jito tip amount as a u64 (assuming it's stored in lamports)
-    let jito_tip_amount: u64 = jito_tip_amount_str.parse().expect("Failed to parse JITO_TIP_AMOUNT");
-
-    // Create Keypair for the payer and signers (from private key)
-    let payer = keypair_from_base58(&private_key_base58); // This keypair will pay for the transaction fees
-    let signer = keypair_from_base58(&private_key_base58); // Example signer, replace with actual signer
-
-    // Extract the user's public key from the Keypair
-    let user_pubkey = payer.pubkey();
+    // Extract the user's public key from the signer
+    let user_pubkey = executor.payer();
     let mut instructions = Vec::new();
 
     if include_create && to_mint != "So11111111111111111111111111111111111111112" {
@@ -1905,30 +1512,9 @@ pub async fn raydium_swap_base_out(from_mint:&str, to_mint:&str, max_amount_in_f
         user_pubkey
     ).await?;
     instructions.push(raydium_swap_instruction);
-    
-    // Add the Jito tip instruction
-    let tip_instruction = create_jito_tip_instruction(user_pubkey, jito_tip_amount).await?; // Tip amount (in lamports)
-    instructions.push(tip_instruction);
-    
-    // Create the transaction with the payer and instructions
-    let transaction = make_transaction_from_instructions(
-        instructions,
-        &payer,                     // The payer of the transaction fees
-        vec![&payer, &signer],      // List of signers
-        recent_blockhash            // Recent blockhash
-    ).await?;
-    
-    // Serialize the transaction into raw bytes
-    let serialized_tx = serialize_transaction(&transaction)?;
-
-    // Convert the raw bytes to Base64 (if you want to print or send via API)
-    let base58_tx = bs58::encode(&serialized_tx).into_string();
 
-    let res = send_transaction_to_jito(&base58_tx).await?;
-
-    println!("{:?}",res);
-
-    Ok(())
+    // The executor adds the compute budget and tip
+    Ok(executor.submit(instructions).await?)
 }
 
 async fn make_raydium_swap_base_in_instruction(from_mint:Pubkey, to_mint:Pubkey, amount_in:u64, min_amount_out:u64, user_pubkey:Pubkey) -> Result<Instruction, Box<dyn std::error::Error>> {
@@ -1973,7 +1559,7 @@ async fn make_raydium_swap_base_in_instruction(from_mint:Pubkey, to_mint:Pubkey,
 
     if let Err(rusqlite::Error::QueryReturnedNoRows) = account_data {
         // If no record is found, call your function and then try again
-        let _ = raydium_swap_get_account_keys_by_api(mint_address.as_str());
+        let _ = raydium_swap_get_account_keys_by_api(&user_pubkey, mint_address.as_str());
         
         // Attempt the query again after calling the function
         account_data = stmt.query_row(
@@ -2100,7 +1686,7 @@ async fn make_raydium_swap_base_out_instruction(from_mint:Pubkey, to_mint:Pubkey
     
     if let Err(rusqlite::Error::QueryReturnedNoRows) = account_data {
         // If no record is found, call your function and then try again
-        let _ = raydium_swap_get_account_keys_by_api(mint_address.as_str()).await;
+        let _ = raydium_swap_get_account_keys_by_api(&user_pubkey, mint_address.as_str()).await;
         
         // Attempt the query again after calling the function
         account_data = stmt.query_row(
@@ -2185,31 +1771,12 @@ async fn make_raydium_swap_base_out_instruction(from_mint:Pubkey, to_mint:Pubkey
     Ok(swap_instruction)
 }
 
-async fn make_transaction_from_instructions(
-    instructions: Vec<Instruction>,   // List of instructions to include in the transaction
-    payer: &Keypair,                  // The payer of the transaction fees
-    signers: Vec<&Keypair>,           // Signers of the transaction
-    recent_blockhash: Hash,           // Recent blockhash for the transaction
-) -> Result<Transaction, Box<dyn std::error::Error>> {
-
-    // Step 1: Create a new transaction with the provided instructions
-    let mut transaction = Transaction::new_with_payer(&instructions, Some(&payer.pubkey()));
-
-    // Step 2: Set the recent blockhash (required for Solana transactions)
-    match transaction.try_sign(&signers, recent_blockhash) {
-        Ok(_) => {
-            // Successfully signed the transaction
-        }
-        Err(e) => {
-            // Print the error details
-            println!("{:#?}", e);
-            // Optionally, handle the error further or return it
-            return Err(Box::new(e)); // or your specific error type
-        }
-    }
-
-    // Step 3: Return the created transaction
-    Ok(transaction)
+// Signs a transaction whose only required signer is its fee payer, without blocking on a remote signer
+async fn sign_transaction(transaction: &mut Transaction, signer: &dyn TradeSigner, recent_blockhash: Hash) -> Result<(), SignerError> {
+    transaction.message.recent_blockhash = recent_blockhash;
+    let signature = signer.sign_async(&transaction.message_data()).await?;
+    transaction.signatures = vec![signature];
+    Ok(())
 }
 
 // Helper function to convert a Base58-encoded string to `Pubkey`
@@ -2226,30 +1793,12 @@ fn pubkey_from_base58(base58_str: &str) -> Result<Pubkey, Box<dyn std::error::Er
     Ok(Pubkey::from(key_bytes))
 }
 
-// Helper function to serialize the transaction
-fn serialize_transaction(transaction: &Transaction) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    // Use bincode to serialize the transaction into a Vec<u8> (byte array)
-    let serialized = bincode::serialize(transaction)?;
-    Ok(serialized)
+// The Pump.fun BondingCurve account of a mint, a PDA of the program
+pub fn get_bonding_curve_account(mint: &Pubkey) -> Pubkey {
+    let program_id_pubkey = Pubkey::from_str(PUMPFUN_PROGRAM_ID).unwrap();
+    Pubkey::find_program_address(&[b"bonding-curve", mint.as_ref()], &program_id_pubkey).0
 }
 
-fn get_bonding_curve_account(
-    mint_address: &str,
-    program_id: &str
-) -> Result<Pubkey, Box<dyn Error>>{
-    // Parse the mint address and program ID into Pubkeys
-    let mint_pubkey = pubkey_from_base58(mint_address)?;
-    let program_pubkey = pubkey_from_base58(program_id)?;
-
-    // Use seeds to generate the PDA (Program-Derived Address)
-    let (pda, _bump_seed) = Pubkey::find_program_address(
-        &[ b"bonding-curve", mint_pubkey.as_ref()],
-        &program_pubkey,
-    );
-    Ok(pda)
-}
-
-
 pub fn get_associated_bonding_curve_account(
     mint_address: &str,
     bonding_account: &Pubkey,
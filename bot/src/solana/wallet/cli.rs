@@ -1,8 +1,10 @@
 use clap::{Parser, Subcommand};
 use solana_client::rpc_client::RpcClient;
-use std::path::Path;
+use std::{io, path::Path};
+use zeroize::Zeroizing;
 
 use super::{
+    keystore::{Unlock, KEYSTORE_SUFFIX},
     types::{Wallet, WalletType},
     manager::WalletManager,
 };
@@ -10,6 +12,8 @@ use super::{
 #[derive(Parser)]
 #[clap(author, version, about, long_about=None)]
 struct Cli {
+    #[arg(short, long, global = true, help = "Unlock keystores with this key file instead of a passphrase")]
+    key_file: Option<String>,
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -17,7 +21,7 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     KeyGen {
-        #[arg(short, long, help = "Name for the key files (will create name.json and name_secret.json)")]
+        #[arg(short, long, help = "Name for the key files (will create name_keystore.json)")]
         name: String,
         #[arg(short, long, help = "Type of wallet (main, intermediate, or trading)", value_parser = ["main", "intermediate", "trading"])]
         wallet_type: String,
@@ -52,35 +56,68 @@ enum Commands {
         #[arg(short, long)]
         sol: f64,
     },
+    MigrateKeystore {
+        #[arg(short, long, help = "Directory of plaintext *_secret.json files (default: data/wallets/secrets)")]
+        dir: Option<String>,
+        #[arg(long, help = "Keep the plaintext files after encrypting them")]
+        keep_plaintext: bool,
+    },
 }
 
 const SERVER_URL: &str = "https://api.mainnet-beta.solana.com";
 
+// --key-file, then KEYSTORE_KEY_FILE or KEYSTORE_PASSPHRASE, then a prompt. New keystores ask twice.
+fn resolve_unlock(key_file: Option<&str>, confirm: bool) -> io::Result<Unlock> {
+    if let Some(path) = key_file {
+        return Ok(Unlock::key_file(path));
+    }
+    if let Ok(unlock) = Unlock::from_env() {
+        return Ok(unlock);
+    }
+    let passphrase = Zeroizing::new(rpassword::prompt_password("Keystore passphrase: ")?);
+    if confirm {
+        let repeated = Zeroizing::new(rpassword::prompt_password("Repeat passphrase: ")?);
+        if *repeated != *passphrase {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Passphrases don't match"));
+        }
+    }
+    Ok(Unlock::Passphrase(passphrase))
+}
+
 pub fn main() {
     let cli = Cli::parse();
+    let Some(command) = &cli.command else {
+        return;
+    };
+    let creates_keystores = matches!(
+        command,
+        Commands::KeyGen { .. } | Commands::GenerateWallets { .. } | Commands::MigrateKeystore { .. }
+    );
+    let unlock = match resolve_unlock(cli.key_file.as_deref(), creates_keystores) {
+        Ok(unlock) => unlock,
+        Err(e) => {
+            eprintln!("Error reading passphrase: {}", e);
+            return;
+        }
+    };
     let client = RpcClient::new(SERVER_URL);
-    let mut wallet_manager = WalletManager::new(client);
+    let mut wallet_manager = WalletManager::new(client, unlock);
 
-    match &cli.command {
-        Some(Commands::KeyGen { name, wallet_type }) => {
+    match command {
+        Commands::KeyGen { name, wallet_type } => {
             println!("Generating key files with name: {} and type: {}", name, wallet_type);
-            let wallet_type = match wallet_type.as_str() {
-                "main" => WalletType::Main,
-                "intermediate" => WalletType::Intermediate,
-                "trading" => WalletType::Trading,
-                _ => panic!("Invalid wallet type"),
-            };
-            if let Err(e) = Wallet::generate(wallet_type, name) {
+            let wallet_type: WalletType = wallet_type.parse().expect("Invalid wallet type");
+            if let Err(e) = Wallet::generate(wallet_type, name, wallet_manager.unlock()) {
                 eprintln!("Error generating keypair: {}", e);
             }
         }
-        Some(Commands::GenerateWallets { amount, group_name }) => {
+        Commands::GenerateWallets { amount, group_name } => {
             let group = group_name.as_deref().unwrap_or("default");
             if let Err(e) = wallet_manager.generate_wallet_group(*amount, group) {
                 eprintln!("Error generating wallets: {}", e);
             }
         }
-        Some(Commands::GetWalletBalance { wallet_type, number, group_name }) => {
+        Commands::GetWalletBalance { wallet_type, number, group_name } => {
             let group = group_name.as_deref().unwrap_or("default");
             let wallet_name = match (wallet_type.as_str(), number) {
                 ("main", None) => format!("{}-main-wallet", group),
@@ -95,9 +132,9 @@ pub fn main() {
             let secret_file_path = Path::new("data")
                 .join("wallets")
                 .join("secrets")
-                .join(format!("{}_secret.json", wallet_name));
+                .join(format!("{}{}", wallet_name, KEYSTORE_SUFFIX));
 
-            match Wallet::from_json_file(secret_file_path.to_str().unwrap()) {
+            match Wallet::from_json_file(secret_file_path.to_str().unwrap(), wallet_manager.unlock()) {
                 Ok(mut wallet) => {
                     if let Err(e) = wallet.update_balance() {
                         eprintln!("Error getting wallet balance: {}", e);
@@ -110,20 +147,21 @@ pub fn main() {
                 Err(e) => eprintln!("Error loading wallet: {}", e),
             }
         }
-        Some(Commands::DrainWallets { group_name }) => {
+        Commands::DrainWallets { group_name } => {
             let group = group_name.as_deref().unwrap_or("default");
             if let Err(e) = wallet_manager.drain_wallets(group) {
                 eprintln!("Error draining wallets: {}", e);
             }
         }
-        Some(Commands::FillTradingWallets { group_name }) => {
+        Commands::FillTradingWallets { group_name } => {
             let group = group_name.as_deref().unwrap_or("default");
             if let Err(e) = wallet_manager.fill_trading_wallets(group) {
                 eprintln!("Error filling trading wallets: {}", e);
             }
         }
-        Some(Commands::Transfer { from_wallet, to, sol }) => {
-            match (Wallet::from_json_file(from_wallet), Wallet::from_json_file(to)) {
+        Commands::Transfer { from_wallet, to, sol } => {
+            let unlock = wallet_manager.unlock();
+            match (Wallet::from_json_file(from_wallet, unlock), Wallet::from_json_file(to, unlock)) {
                 (Ok(from), Ok(to_wallet)) => {
                     if let Err(e) = from.transfer_sol_amount(&wallet_manager.client, &to_wallet, *sol) {
                         eprintln!("Error transferring SOL: {}", e);
@@ -135,6 +173,12 @@ pub fn main() {
                 (_, Err(e)) => eprintln!("Error loading to wallet: {}", e),
             }
         }
-        None => {}
+        Commands::MigrateKeystore { dir, keep_plaintext } => {
+            let dir = dir.as_deref().unwrap_or("data/wallets/secrets");
+            match wallet_manager.migrate_secret_files(dir, *keep_plaintext) {
+                Ok(migrated) => println!("Migrated {} wallets", migrated.len()),
+                Err(e) => eprintln!("Error migrating wallets: {}", e),
+            }
+        }
     }
 }
@@ -0,0 +1,344 @@
+use std::{
+    env, fmt,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+use aes_gcm::{
+    aead::{Aead, Payload},
+    Aes256Gcm, KeyInit, Nonce,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use dotenv::dotenv;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use solana_sdk::signature::{Keypair, Signer};
+use zeroize::Zeroizing;
+
+pub const KEYSTORE_VERSION: u32 = 1;
+pub const KEYSTORE_SUFFIX: &str = "_keystore.json";
+pub const LEGACY_SECRET_SUFFIX: &str = "_secret.json";
+const KDF_SCRYPT: &str = "scrypt";
+const CIPHER_AES_256_GCM: &str = "aes-256-gcm";
+const SALT_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+
+#[derive(Debug, thiserror::Error)]
+pub enum KeystoreError {
+    #[error("IoError {0}")]
+    Io(#[from] io::Error),
+    #[error("JsonError {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("unsupported keystore version {0}")]
+    UnsupportedVersion(u32),
+    #[error("unsupported {0}")]
+    Unsupported(String),
+    #[error("invalid keystore: {0}")]
+    Invalid(String),
+    #[error("wrong passphrase or key file, or the keystore was modified")]
+    Decrypt,
+    #[error("neither KEYSTORE_KEY_FILE nor KEYSTORE_PASSPHRASE is set")]
+    NoUnlock,
+}
+
+impl From<KeystoreError> for io::Error {
+    fn from(error: KeystoreError) -> Self {
+        match error {
+            KeystoreError::Io(e) => e,
+            e => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+        }
+    }
+}
+
+/// How a keystore is unlocked: a passphrase, or the contents of a key file.
+pub enum Unlock {
+    Passphrase(Zeroizing<String>),
+    KeyFile(PathBuf),
+}
+
+impl fmt::Debug for Unlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Unlock::Passphrase(_) => f.write_str("Passphrase(..)"),
+            Unlock::KeyFile(path) => f.debug_tuple("KeyFile").field(path).finish(),
+        }
+    }
+}
+
+impl Unlock {
+    pub fn passphrase(passphrase: impl Into<String>) -> Self {
+        Unlock::Passphrase(Zeroizing::new(passphrase.into()))
+    }
+
+    pub fn key_file(path: impl Into<PathBuf>) -> Self {
+        Unlock::KeyFile(path.into())
+    }
+
+    // KEYSTORE_KEY_FILE takes precedence over KEYSTORE_PASSPHRASE
+    pub fn from_env() -> Result<Self, KeystoreError> {
+        dotenv().ok();
+        if let Ok(path) = env::var("KEYSTORE_KEY_FILE") {
+            return Ok(Unlock::key_file(path));
+        }
+        env::var("KEYSTORE_PASSPHRASE").map(Unlock::passphrase).map_err(|_| KeystoreError::NoUnlock)
+    }
+
+    fn secret(&self) -> Result<Zeroizing<Vec<u8>>, KeystoreError> {
+        let secret = match self {
+            Unlock::Passphrase(passphrase) => Zeroizing::new(passphrase.as_bytes().to_vec()),
+            Unlock::KeyFile(path) => Zeroizing::new(fs::read(path)?),
+        };
+        if secret.is_empty() {
+            return Err(KeystoreError::Invalid("empty passphrase or key file".to_string()));
+        }
+        Ok(secret)
+    }
+}
+
+/// scrypt cost parameters; the defaults take about a tenth of a second and 32 MiB per unlock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScryptParams {
+    pub log_n: u8,
+    pub r: u32,
+    pub p: u32,
+}
+
+impl Default for ScryptParams {
+    fn default() -> Self {
+        ScryptParams { log_n: 15, r: 8, p: 1 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KdfSection {
+    name: String,
+    salt: String,
+    #[serde(flatten)]
+    params: ScryptParams,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CipherSection {
+    name: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// A keypair encrypted with AES-256-GCM under a key derived from a passphrase or key file with scrypt.
+///
+/// The public key is stored in the clear and authenticated as associated data, so wallets can be
+/// listed without unlocking them and a keystore can't be relabelled with another address.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Keystore {
+    pub version: u32,
+    pub public_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wallet_type: Option<String>,
+    kdf: KdfSection,
+    cipher: CipherSection,
+}
+
+impl Keystore {
+    pub fn encrypt(keypair: &Keypair, unlock: &Unlock, params: ScryptParams) -> Result<Self, KeystoreError> {
+        let public_key = keypair.pubkey().to_string();
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let key = derive_key(&unlock.secret()?, &salt, params)?;
+        let secret = Zeroizing::new(keypair.to_bytes());
+        let ciphertext = Aes256Gcm::new_from_slice(key.as_slice())
+            .map_err(|_| KeystoreError::Invalid("key length".to_string()))?
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: secret.as_slice(), aad: public_key.as_bytes() })
+            .map_err(|_| KeystoreError::Decrypt)?;
+
+        Ok(Keystore {
+            version: KEYSTORE_VERSION,
+            public_key,
+            wallet_type: None,
+            kdf: KdfSection { name: KDF_SCRYPT.to_string(), salt: STANDARD.encode(salt), params },
+            cipher: CipherSection {
+                name: CIPHER_AES_256_GCM.to_string(),
+                nonce: STANDARD.encode(nonce),
+                ciphertext: STANDARD.encode(ciphertext),
+            },
+        })
+    }
+
+    pub fn with_wallet_type(mut self, wallet_type: impl Into<String>) -> Self {
+        self.wallet_type = Some(wallet_type.into());
+        self
+    }
+
+    pub fn decrypt(&self, unlock: &Unlock) -> Result<Keypair, KeystoreError> {
+        if self.version != KEYSTORE_VERSION {
+            return Err(KeystoreError::UnsupportedVersion(self.version));
+        }
+        if self.kdf.name != KDF_SCRYPT {
+            return Err(KeystoreError::Unsupported(format!("kdf {}", self.kdf.name)));
+        }
+        if self.cipher.name != CIPHER_AES_256_GCM {
+            return Err(KeystoreError::Unsupported(format!("cipher {}", self.cipher.name)));
+        }
+        let salt = decode_field("salt", &self.kdf.salt)?;
+        let nonce = decode_field("nonce", &self.cipher.nonce)?;
+        if nonce.len() != NONCE_LEN {
+            return Err(KeystoreError::Invalid("nonce length".to_string()));
+        }
+        let ciphertext = decode_field("ciphertext", &self.cipher.ciphertext)?;
+
+        let key = derive_key(&unlock.secret()?, &salt, self.kdf.params)?;
+        let secret = Zeroizing::new(
+            Aes256Gcm::new_from_slice(key.as_slice())
+                .map_err(|_| KeystoreError::Invalid("key length".to_string()))?
+                .decrypt(Nonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: self.public_key.as_bytes() })
+                .map_err(|_| KeystoreError::Decrypt)?,
+        );
+        let keypair = Keypair::from_bytes(&secret).map_err(|e| KeystoreError::Invalid(e.to_string()))?;
+        if keypair.pubkey().to_string() != self.public_key {
+            return Err(KeystoreError::Invalid("secret key doesn't match the public key".to_string()));
+        }
+        Ok(keypair)
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, KeystoreError> {
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    // Creates the file readable by the owner only; refuses to overwrite an existing keystore
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), KeystoreError> {
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(path)?;
+        serde_json::to_writer_pretty(&mut file, self)?;
+        file.sync_all()?;
+        Ok(())
+    }
+}
+
+/// Reads a keypair from a keystore file.
+pub fn load_keypair(path: impl AsRef<Path>, unlock: &Unlock) -> Result<Keypair, KeystoreError> {
+    Keystore::read(path)?.decrypt(unlock)
+}
+
+/// Encrypts a plaintext `*_secret.json` wallet into a `*_keystore.json` next to it.
+///
+/// The new keystore is read back and decrypted before the plaintext file is touched. Unless
+/// `keep_plaintext` is set, the plaintext file is then overwritten and removed. Returns the
+/// keystore path.
+pub fn migrate_secret_file(
+    path: impl AsRef<Path>,
+    unlock: &Unlock,
+    params: ScryptParams,
+    keep_plaintext: bool,
+) -> Result<PathBuf, KeystoreError> {
+    let path = path.as_ref();
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    let name = file_name
+        .strip_suffix(LEGACY_SECRET_SUFFIX)
+        .ok_or_else(|| KeystoreError::Invalid(format!("{} is not a {} file", path.display(), LEGACY_SECRET_SUFFIX)))?;
+    let keystore_path = path.with_file_name(format!("{}{}", name, KEYSTORE_SUFFIX));
+
+    let legacy: serde_json::Value = serde_json::from_slice(&Zeroizing::new(fs::read(path)?))?;
+    let secret_base58 = Zeroizing::new(
+        legacy["secretKey"].as_str().ok_or_else(|| KeystoreError::Invalid("secretKey not found".to_string()))?.to_string(),
+    );
+    let secret = Zeroizing::new(
+        bs58::decode(secret_base58.as_str()).into_vec().map_err(|e| KeystoreError::Invalid(e.to_string()))?,
+    );
+    let keypair = Keypair::from_bytes(&secret).map_err(|e| KeystoreError::Invalid(e.to_string()))?;
+
+    let mut keystore = Keystore::encrypt(&keypair, unlock, params)?;
+    if let Some(wallet_type) = legacy["walletType"].as_str() {
+        keystore = keystore.with_wallet_type(wallet_type);
+    }
+    keystore.write(&keystore_path)?;
+    if load_keypair(&keystore_path, unlock)?.pubkey() != keypair.pubkey() {
+        return Err(KeystoreError::Invalid(format!("{} doesn't round-trip", keystore_path.display())));
+    }
+
+    if !keep_plaintext {
+        // Best effort: journaling and copy-on-write filesystems may keep old blocks around
+        let len = fs::metadata(path)?.len() as usize;
+        let mut file = OpenOptions::new().write(true).open(path)?;
+        file.write_all(&vec![0u8; len])?;
+        file.sync_all()?;
+        fs::remove_file(path)?;
+    }
+    Ok(keystore_path)
+}
+
+fn derive_key(secret: &[u8], salt: &[u8], params: ScryptParams) -> Result<Zeroizing<[u8; KEY_LEN]>, KeystoreError> {
+    let params = scrypt::Params::new(params.log_n, params.r, params.p, KEY_LEN)
+        .map_err(|_| KeystoreError::Invalid("scrypt parameters".to_string()))?;
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    scrypt::scrypt(secret, salt, &params, key.as_mut_slice())
+        .map_err(|_| KeystoreError::Invalid("scrypt output length".to_string()))?;
+    Ok(key)
+}
+
+fn decode_field(name: &str, value: &str) -> Result<Vec<u8>, KeystoreError> {
+    STANDARD.decode(value).map_err(|e| KeystoreError::Invalid(format!("{}: {}", name, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Cheap parameters so tests don't spend seconds in scrypt
+    const TEST_PARAMS: ScryptParams = ScryptParams { log_n: 4, r: 8, p: 1 };
+
+    fn temp_dir() -> PathBuf {
+        let dir = env::temp_dir().join(format!("keystore-test-{}", Keypair::new().pubkey()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_round_trip_and_wrong_secret() {
+        let keypair = Keypair::new();
+        let keystore = Keystore::encrypt(&keypair, &Unlock::passphrase("correct horse"), TEST_PARAMS).unwrap();
+
+        assert_eq!(keystore.public_key, keypair.pubkey().to_string());
+        assert_eq!(keystore.decrypt(&Unlock::passphrase("correct horse")).unwrap().to_bytes(), keypair.to_bytes());
+        assert!(matches!(keystore.decrypt(&Unlock::passphrase("wrong horse")), Err(KeystoreError::Decrypt)));
+
+        // Swapping the address invalidates the authentication tag
+        let mut relabelled = keystore.clone();
+        relabelled.public_key = Keypair::new().pubkey().to_string();
+        assert!(matches!(relabelled.decrypt(&Unlock::passphrase("correct horse")), Err(KeystoreError::Decrypt)));
+    }
+
+    #[test]
+    fn test_migrate_secret_file_with_key_file() {
+        let dir = temp_dir();
+        let key_file = dir.join("wallet.key");
+        fs::write(&key_file, [7u8; 64]).unwrap();
+        let unlock = Unlock::key_file(&key_file);
+        let keypair = Keypair::new();
+        let legacy = dir.join("default-trading-001_secret.json");
+        fs::write(&legacy, serde_json::json!({
+            "secretKey": bs58::encode(keypair.to_bytes()).into_string(),
+            "publicKey": keypair.pubkey().to_string(),
+            "walletType": "trading",
+        }).to_string()).unwrap();
+
+        let keystore_path = migrate_secret_file(&legacy, &unlock, TEST_PARAMS, false).unwrap();
+        assert_eq!(keystore_path, dir.join("default-trading-001_keystore.json"));
+        assert!(!legacy.exists());
+        assert_eq!(Keystore::read(&keystore_path).unwrap().wallet_type.as_deref(), Some("trading"));
+        assert_eq!(load_keypair(&keystore_path, &unlock).unwrap().pubkey(), keypair.pubkey());
+        // The keystore is never overwritten
+        assert!(Keystore::encrypt(&keypair, &unlock, TEST_PARAMS).unwrap().write(&keystore_path).is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,27 +1,35 @@
 use std::{
     fs,
     io::{self},
-    path::Path,
+    path::{Path, PathBuf},
 };
 use rand::Rng;
 use solana_client::rpc_client::RpcClient;
 use solana_sdk::native_token::lamports_to_sol;
 
+use super::keystore::{self, ScryptParams, Unlock, KEYSTORE_SUFFIX, LEGACY_SECRET_SUFFIX};
 use super::types::{Wallet, WalletType};
 
 pub struct WalletManager {
     wallets: Vec<Wallet>,
     pub client: RpcClient,
+    // Unlocks every keystore the manager loads or creates
+    unlock: Unlock,
 }
 
 impl WalletManager {
-    pub fn new(client: RpcClient) -> Self {
+    pub fn new(client: RpcClient, unlock: Unlock) -> Self {
         Self {
             wallets: Vec::new(),
-            client
+            client,
+            unlock,
         }
     }
 
+    pub fn unlock(&self) -> &Unlock {
+        &self.unlock
+    }
+
     pub fn load_wallets_from_directory(&mut self, dir_path: &str) -> io::Result<()> {
         let full_path = Path::new(dir_path);
         println!("Loading wallets from: {}", full_path.display());
@@ -30,16 +38,42 @@ impl WalletManager {
             let entry = entry?;
             let path = entry.path();
             
-            if path.is_file() && path.extension().map_or(false, |ext| ext == "json") {
-                if let Ok(wallet) = Wallet::from_json_file(path.to_str().unwrap()) {
-                    println!("Loaded wallet: {}", wallet.pub_key);
-                    self.wallets.push(wallet);
+            let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+            if !path.is_file() {
+                continue;
+            }
+            if file_name.ends_with(KEYSTORE_SUFFIX) {
+                match Wallet::from_json_file(path.to_str().unwrap(), &self.unlock) {
+                    Ok(wallet) => {
+                        println!("Loaded wallet: {}", wallet.pub_key);
+                        self.wallets.push(wallet);
+                    }
+                    Err(e) => eprintln!("Skipping {}: {}", path.display(), e),
                 }
+            } else if file_name.ends_with(LEGACY_SECRET_SUFFIX) {
+                eprintln!("Skipping plaintext {}: run migrate-keystore to encrypt it", path.display());
             }
         }
         Ok(())
     }
 
+    // Encrypts every plaintext *_secret.json in `dir_path` into a keystore with the manager's unlock
+    pub fn migrate_secret_files(&self, dir_path: &str, keep_plaintext: bool) -> io::Result<Vec<PathBuf>> {
+        let mut migrated = Vec::new();
+        for entry in fs::read_dir(dir_path)? {
+            let path = entry?.path();
+            let is_legacy = path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.ends_with(LEGACY_SECRET_SUFFIX));
+            if path.is_file() && is_legacy {
+                let keystore_path = keystore::migrate_secret_file(&path, &self.unlock, ScryptParams::default(), keep_plaintext)?;
+                println!("Migrated {} -> {}", path.display(), keystore_path.display());
+                migrated.push(keystore_path);
+            }
+        }
+        Ok(migrated)
+    }
+
     pub fn update_all_balances(&mut self) -> io::Result<()> {
         for wallet in &mut self.wallets {
             wallet.update_balance()?;
@@ -63,7 +97,7 @@ impl WalletManager {
         // Generate main wallet first
         println!("\nGenerating main wallet:");
         let main_wallet_name = format!("{}-main-wallet", group_name);
-        if let Err(e) = Wallet::generate(WalletType::Main, &main_wallet_name) {
+        if let Err(e) = Wallet::generate(WalletType::Main, &main_wallet_name, &self.unlock) {
             eprintln!("Error generating main wallet: {}", e);
             return Err(e);
         }
@@ -74,14 +108,14 @@ impl WalletManager {
             
             // Generate intermediate wallet
             let intermediate_name = format!("{}-intermediate-{}", group_name, number);
-            if let Err(e) = Wallet::generate(WalletType::Intermediate, &intermediate_name) {
+            if let Err(e) = Wallet::generate(WalletType::Intermediate, &intermediate_name, &self.unlock) {
                 eprintln!("Error generating intermediate wallet {}: {}", number, e);
                 return Err(e);
             }
             
             // Generate trading wallet
             let trading_name = format!("{}-trading-{}", group_name, number);
-            if let Err(e) = Wallet::generate(WalletType::Trading, &trading_name) {
+            if let Err(e) = Wallet::generate(WalletType::Trading, &trading_name, &self.unlock) {
                 eprintln!("Error generating trading wallet {}: {}", number, e);
                 return Err(e);
             }
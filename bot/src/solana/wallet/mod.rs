@@ -1,7 +1,9 @@
+mod keystore;
 mod types;
 mod manager;
 mod cli;
 
+pub use keystore::{load_keypair, migrate_secret_file, Keystore, KeystoreError, ScryptParams, Unlock};
 pub use types::{Wallet, WalletType, TrackableWallet};
 pub use manager::WalletManager;
 pub use cli::main;
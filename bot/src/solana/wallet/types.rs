@@ -10,15 +10,15 @@ use solana_sdk::{
 use std::{
     io::{self, Write},
     str::FromStr,
-    fs::File,
     fmt,
     path::Path,
 };
-use serde_json::{json, Value};
 use dotenv::dotenv;
 use std::env;
+use zeroize::Zeroizing;
 
 use crate::solana::position_tracker::Position;
+use super::keystore::{Keystore, ScryptParams, Unlock, KEYSTORE_SUFFIX};
 
 pub trait TrackableWallet {
     fn get_pub_key(&self) -> Option<String>;
@@ -29,7 +29,8 @@ pub trait TrackableWallet {
 pub struct Wallet {
     positions: Vec<Position>,
     pub pub_key: String,
-    private_key: String,
+    // Base58 secret key, wiped when the wallet is dropped
+    private_key: Zeroizing<String>,
     pub wallet_type: WalletType,
     pub balance: u64, // in lamports
 }
@@ -52,6 +53,29 @@ pub enum WalletType {
     Trading,
 }
 
+impl WalletType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WalletType::Main => "main",
+            WalletType::Intermediate => "intermediate",
+            WalletType::Trading => "trading",
+        }
+    }
+}
+
+impl FromStr for WalletType {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "main" => Ok(WalletType::Main),
+            "intermediate" => Ok(WalletType::Intermediate),
+            "trading" => Ok(WalletType::Trading),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid wallet type")),
+        }
+    }
+}
+
 impl TrackableWallet for Wallet {
     fn get_pub_key(&self) -> Option<String> {
         Some(self.pub_key.clone())
@@ -71,60 +95,39 @@ impl Wallet {
         Self {
             positions: Vec::new(),
             pub_key,
-            private_key,
+            private_key: Zeroizing::new(private_key),
             wallet_type,
             balance: 0,
         }
     }
 
-    pub fn generate(wallet_type: WalletType, name: &str) -> io::Result<Self> {
-        // Generate a new random keypair
+    // Generates a keypair and saves it encrypted to data/wallets/secrets/<name>_keystore.json
+    pub fn generate(wallet_type: WalletType, name: &str, unlock: &Unlock) -> io::Result<Self> {
         let keypair = Keypair::new();
-        let public_key = keypair.pubkey().to_string();
-        let private_key = bs58::encode(keypair.to_bytes()).into_string();
-        
-        // Create the wallet instance
-        let wallet = Self::new(public_key.clone(), private_key.clone(), wallet_type.clone());
-        
-        // Save to file
-        let wallet_type_str = match wallet_type {
-            WalletType::Main => "main",
-            WalletType::Intermediate => "intermediate",
-            WalletType::Trading => "trading",
-        };
+        let keystore = Keystore::encrypt(&keypair, unlock, ScryptParams::default())?
+            .with_wallet_type(wallet_type.as_str());
 
-        let secret_json = json!({
-            "secretKey": private_key,
-            "publicKey": public_key,
-            "walletType": wallet_type_str
-        });
-        
-        let secret_file_path = Path::new("data").join("wallets").join("secrets")
-            .join(format!("{}_secret.json", name));
-        
-        let mut secret_file = File::create(secret_file_path)?;
-        serde_json::to_writer_pretty(&mut secret_file, &secret_json)?;
-        
-        Ok(wallet)
+        let keystore_path = Path::new("data").join("wallets").join("secrets")
+            .join(format!("{}{}", name, KEYSTORE_SUFFIX));
+        keystore.write(keystore_path)?;
+
+        Ok(Self::from_keypair(&keypair, wallet_type))
     }
 
-    pub fn from_json_file(path: &str) -> io::Result<Self> {
-        let file = File::open(path)?;
-        let json: Value = serde_json::from_reader(file)?;
-        
-        let pub_key = json["publicKey"].as_str()
-            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Public key not found"))?.to_string();
-        let private_key = json["secretKey"].as_str()
-            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Secret key not found"))?.to_string();
-        let wallet_type = match json["walletType"].as_str()
-            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Wallet type not found"))? {
-                "main" => WalletType::Main,
-                "intermediate" => WalletType::Intermediate,
-                "trading" => WalletType::Trading,
-                _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid wallet type")),
-        };
+    // Reads a wallet from a keystore file. Plaintext *_secret.json files must be migrated first.
+    pub fn from_json_file(path: &str, unlock: &Unlock) -> io::Result<Self> {
+        let keystore = Keystore::read(path)?;
+        let wallet_type = keystore.wallet_type.as_deref()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Wallet type not found"))
+            .and_then(WalletType::from_str)?;
+        let keypair = keystore.decrypt(unlock)?;
 
-        Ok(Self::new(pub_key, private_key, wallet_type))
+        Ok(Self::from_keypair(&keypair, wallet_type))
+    }
+
+    fn from_keypair(keypair: &Keypair, wallet_type: WalletType) -> Self {
+        let private_key = Zeroizing::new(keypair.to_bytes());
+        Self::new(keypair.pubkey().to_string(), bs58::encode(private_key.as_slice()).into_string(), wallet_type)
     }
 
     pub fn update_balance(&mut self) -> io::Result<()> {
@@ -168,8 +171,8 @@ impl Wallet {
     }
 
     pub fn get_keypair(&self) -> io::Result<Keypair> {
-        let secret_bytes = bs58::decode(&self.private_key).into_vec()
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let secret_bytes = Zeroizing::new(bs58::decode(self.private_key.as_str()).into_vec()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?);
         
         Keypair::from_bytes(&secret_bytes)
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
@@ -6,6 +6,18 @@ pub mod auth {
     tonic::include_proto!("auth");
 }
 
+pub mod packet {
+    tonic::include_proto!("packet");
+}
+
+pub mod bundle {
+    tonic::include_proto!("bundle");
+}
+
+pub mod searcher {
+    tonic::include_proto!("searcher");
+}
+
 pub mod shredstream {
     tonic::include_proto!("shredstream");
 }
@@ -13,3 +25,5 @@ pub mod shredstream {
 pub mod trace_shred {
     tonic::include_proto!("trace_shred");
 }
+
+pub mod token_authenticator;
@@ -12,6 +12,7 @@ use crossbeam_channel::Receiver;
 use jito_protos::{
     auth::{auth_service_client::AuthServiceClient, Role},
     shredstream::{shredstream_client::ShredstreamClient, Heartbeat},
+    token_authenticator::{create_grpc_channel, ClientInterceptor},
 };
 use log::{info, warn};
 use solana_metrics::{datapoint_info, datapoint_warn};
@@ -19,10 +20,7 @@ use solana_sdk::signature::Keypair;
 use tokio::runtime::Runtime;
 use tonic::{codegen::InterceptedService, transport::Channel, Code};
 
-use crate::{
-    token_authenticator::{create_grpc_channel, ClientInterceptor},
-    ShredstreamProxyError,
-};
+use crate::ShredstreamProxyError;
 
 #[allow(clippy::too_many_arguments)]
 pub fn heartbeat_loop_thread(
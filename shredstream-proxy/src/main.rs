@@ -17,6 +17,7 @@ use std::{
 use arc_swap::ArcSwap;
 use clap::{arg, Parser, ValueEnum};
 use crossbeam_channel::{Receiver, RecvError, Sender};
+use jito_protos::token_authenticator::BlockEngineConnectionError;
 use log::*;
 use signal_hook::consts::{SIGINT, SIGTERM};
 use solana_client::client_error::{reqwest, ClientError};
@@ -29,7 +30,6 @@ use tonic::Status;
 
 use crate::{
     forwarder::{ShredMetrics, start_destination_refresh_thread},
-    token_authenticator::BlockEngineConnectionError,
     logger::LogMode,
 };
 
@@ -37,7 +37,6 @@ pub mod analyser;
 pub mod forwarder;
 mod heartbeat;
 pub mod logger;
-mod token_authenticator;
 
 #[derive(Copy, Clone, Debug, ValueEnum)]
 enum LogModeArg {